@@ -2,17 +2,26 @@ use std::env;
 
 use askama::Template;
 use axum::{
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::{Html, IntoResponse},
     routing::get,
     Json, Router,
 };
 use axum_extra::extract::CookieJar;
+use opentelemetry::{
+    global,
+    trace::{Status, TraceContextExt, Tracer},
+    KeyValue,
+};
+use opentelemetry_http::{HeaderExtractor, HeaderInjector};
+use opentelemetry_sdk::{propagation::TraceContextPropagator, trace::SdkTracerProvider, Resource};
 use serde::Serialize;
 use tower_http::services::ServeDir;
 
 #[tokio::main]
 async fn main() {
+    let tracer_provider = init_tracing();
+
     let app = Router::new()
         .nest_service("/assets", ServeDir::new("assets"))
         .route("/", get(root))
@@ -21,7 +30,65 @@ async fn main() {
     let listener = tokio::net::TcpListener::bind("0.0.0.0:8000").await.unwrap();
 
     println!("listening on {}", listener.local_addr().unwrap());
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(listener, app)
+        .with_graceful_shutdown(termination_signal())
+        .await
+        .unwrap();
+
+    // Export the spans still buffered before exiting
+    if let Some(provider) = tracer_provider {
+        if let Err(e) = provider.shutdown() {
+            eprintln!("Failed to shut down the tracer provider: {e}");
+        }
+    }
+}
+
+async fn termination_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to listen for Ctrl+C");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+// Traces are only exported when OTEL_EXPORTER_OTLP_ENDPOINT is set. Without it
+// incoming trace context is still passed on to the auth service. The provider
+// is returned so it can be shut down on exit.
+fn init_tracing() -> Option<SdkTracerProvider> {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    if env::var("OTEL_EXPORTER_OTLP_ENDPOINT")
+        .unwrap_or_default()
+        .is_empty()
+    {
+        return None;
+    }
+    // The exporter reads the endpoint from the environment itself
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .build()
+        .expect("Failed to build OTLP span exporter");
+    let provider = SdkTracerProvider::builder()
+        .with_resource(Resource::builder().with_service_name("app-service").build())
+        .with_batch_exporter(exporter)
+        .build();
+    global::set_tracer_provider(provider.clone());
+    Some(provider)
 }
 
 #[derive(Template)]
@@ -46,7 +113,7 @@ async fn root() -> impl IntoResponse {
     Html(template.render().unwrap())
 }
 
-async fn protected(jar: CookieJar) -> impl IntoResponse {
+async fn protected(headers: HeaderMap, jar: CookieJar) -> impl IntoResponse {
     let jwt_cookie = match jar.get("jwt") {
         Some(cookie) => cookie,
         None => {
@@ -54,6 +121,17 @@ async fn protected(jar: CookieJar) -> impl IntoResponse {
         }
     };
 
+    // Continue the caller's trace, if any, and pass it on to the auth service
+    let parent = global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(&headers))
+    });
+    let span = global::tracer("app-service").start_with_context("GET /protected", &parent);
+    let cx = parent.with_span(span);
+    let mut trace_headers = HeaderMap::new();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&cx, &mut HeaderInjector(&mut trace_headers))
+    });
+
     let api_client = reqwest::Client::builder().build().unwrap();
 
     let verify_token_body = serde_json::json!({
@@ -63,12 +141,23 @@ async fn protected(jar: CookieJar) -> impl IntoResponse {
     let auth_hostname = env::var("AUTH_SERVICE_HOST_NAME").unwrap_or("0.0.0.0".to_owned());
     let url = format!("http://{}:3000/verify-token", auth_hostname);
 
-    let response = match api_client.post(&url).json(&verify_token_body).send().await {
+    let response = match api_client
+        .post(&url)
+        .headers(trace_headers)
+        .json(&verify_token_body)
+        .send()
+        .await
+    {
         Ok(response) => response,
-        Err(_) => {
+        Err(e) => {
+            cx.span().set_status(Status::error(e.to_string()));
             return StatusCode::INTERNAL_SERVER_ERROR.into_response();
         }
     };
+    cx.span().set_attribute(KeyValue::new(
+        "http.response.status_code",
+        i64::from(response.status().as_u16()),
+    ));
 
     match response.status() {
         reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::BAD_REQUEST => {
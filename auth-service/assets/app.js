@@ -69,7 +69,7 @@ loginButton.addEventListener("click", (e) => {
             alert("You have successfully logged in.");
         } else {
             response.json().then(data => {
-                let error_msg = data.error;
+                let error_msg = data.detail;
                 if (error_msg !== undefined && error_msg !== null && error_msg !== "") {
                     loginErrAlter.innerHTML = `<span><strong>Error: </strong>${error_msg}</span>`;
                     loginErrAlter.style.display = "block";
@@ -104,13 +104,24 @@ signupButton.addEventListener("click", (e) => {
             signupForm.password.value = "";
             signupForm.twoFA.checked = false;
             signupErrAlter.style.display = "none";
-            alert("You have successfully created a user.");
+            response.json().then(data => {
+                let message = "You have successfully created a user.";
+                if (Array.isArray(data.recoveryCodes)) {
+                    message += "\n\nKeep these recovery codes somewhere safe, each one logs you in once without a 2FA code:\n"
+                        + data.recoveryCodes.join("\n");
+                }
+                alert(message);
+            });
             loginSection.style.display = "block";
             twoFASection.style.display = "none";
             signupSection.style.display = "none";
         } else {
             response.json().then(data => {
-                let error_msg = data.error;
+                let error_msg = data.detail;
+                // Invalid input lists what's wrong with each field
+                if (Array.isArray(data.errors) && data.errors.length > 0) {
+                    error_msg = data.errors.map(error => error.message).join("<br>");
+                }
                 if (error_msg !== undefined && error_msg !== null && error_msg !== "") {
                     signupErrAlter.innerHTML = `<span><strong>Error: </strong>${error_msg}</span>`;
                     signupErrAlter.style.display = "block";
@@ -151,7 +162,7 @@ TwoFAButton.addEventListener("click", (e) => {
             signupSection.style.display = "none";
         } else {
             response.json().then(data => {
-                let error_msg = data.error;
+                let error_msg = data.detail;
                 if (error_msg !== undefined && error_msg !== null && error_msg !== "") {
                     TwoFAErrAlter.innerHTML = `<span><strong>Error: </strong>${error_msg}</span>`;
                     TwoFAErrAlter.style.display = "block";
@@ -0,0 +1,90 @@
+use std::sync::Arc;
+
+use auth_service::domain::{Email, Session, UserRoles};
+use auth_service::get_redis_connection_manager;
+use auth_service::prelude::{AppState, Application, RedisBannedTokenStore, RedisSessionStore};
+use auth_service::utils::auth::generate_auth_cookie;
+use auth_service::utils::constants::test;
+use auth_service::utils::settings::settings;
+use chrono::Utc;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use futures::future::join_all;
+use secrecy::SecretString;
+use tokio::runtime::Runtime;
+
+const CONCURRENCY: [usize; 3] = [1, 16, 64];
+
+// Spawns the app on a random port with Redis-backed token and session stores,
+// and returns its address along with a valid token for a stored session
+async fn spawn_app() -> (String, String) {
+    let redis_conn = get_redis_connection_manager(settings().stores.redis_host_name.clone())
+        .await
+        .expect("Failed to get Redis connection");
+
+    let mut app_state = AppState::in_memory(
+        settings().login.unverified_login_policy,
+        settings().login.throttle_policy(),
+    );
+    app_state.banned_token_store = Arc::new(RedisBannedTokenStore::new(redis_conn.clone()));
+    app_state.session_store = Arc::new(RedisSessionStore::new(redis_conn));
+
+    let email = Email::parse(SecretString::new(
+        "bench@example.com".to_owned().into_boxed_str(),
+    ))
+    .unwrap();
+    let session_id = uuid::Uuid::new_v4().to_string();
+    let now = Utc::now().timestamp();
+    app_state
+        .session_store
+        .add_session(Session {
+            id: session_id.clone(),
+            email: email.clone(),
+            user_agent: None,
+            ip: "127.0.0.1".to_owned(),
+            created_at: now,
+            last_seen_at: now,
+        })
+        .await
+        .expect("Failed to add session");
+    let token = generate_auth_cookie(&email, &session_id, &UserRoles::default())
+        .expect("Failed to generate token")
+        .value()
+        .to_owned();
+
+    let app = Application::build(app_state, test::APP_ADDRESS)
+        .await
+        .expect("Failed to build app");
+    let address = format!("http://{}", app.address.clone());
+    tokio::spawn(app.run());
+
+    (address, token)
+}
+
+fn verify_token(c: &mut Criterion) {
+    let runtime = Runtime::new().expect("Failed to build runtime");
+    let (address, token) = runtime.block_on(spawn_app());
+    let client = reqwest::Client::new();
+    let url = format!("{}/verify-token", address);
+    let body = serde_json::json!({ "token": token });
+
+    let mut group = c.benchmark_group("verify_token");
+    for concurrency in CONCURRENCY {
+        group.throughput(Throughput::Elements(concurrency as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(concurrency),
+            &concurrency,
+            |b, &concurrency| {
+                b.to_async(&runtime).iter(|| async {
+                    let requests = (0..concurrency).map(|_| client.post(&url).json(&body).send());
+                    for response in join_all(requests).await {
+                        assert_eq!(response.expect("Request failed").status().as_u16(), 200);
+                    }
+                });
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, verify_token);
+criterion_main!(benches);
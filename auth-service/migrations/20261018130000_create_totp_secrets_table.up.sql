@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS totp_secrets(
+   email TEXT NOT NULL PRIMARY KEY REFERENCES users(email) ON DELETE CASCADE,
+   secret TEXT NOT NULL,
+   confirmed BOOLEAN NOT NULL DEFAULT FALSE,
+   last_used_step BIGINT
+);
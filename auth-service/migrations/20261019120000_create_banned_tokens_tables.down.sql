@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS banned_user_tokens;
+DROP TABLE IF EXISTS banned_tokens;
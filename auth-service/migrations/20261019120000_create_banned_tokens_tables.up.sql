@@ -0,0 +1,13 @@
+-- Expiry times are Unix timestamps; expired rows are ignored and purged periodically
+CREATE TABLE IF NOT EXISTS banned_tokens(
+   token TEXT NOT NULL PRIMARY KEY,
+   expires_at BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS banned_tokens_expires_at_idx ON banned_tokens(expires_at);
+
+CREATE TABLE IF NOT EXISTS banned_user_tokens(
+   email TEXT NOT NULL PRIMARY KEY,
+   issued_before BIGINT NOT NULL,
+   expires_at BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS banned_user_tokens_expires_at_idx ON banned_user_tokens(expires_at);
@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS two_fa_codes(
+   email TEXT NOT NULL PRIMARY KEY REFERENCES users(email) ON DELETE CASCADE,
+   login_attempt_id TEXT NOT NULL,
+   code TEXT NOT NULL,
+   expires_at BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS two_fa_codes_expires_at_idx ON two_fa_codes(expires_at);
@@ -0,0 +1,6 @@
+DROP TABLE IF EXISTS login_attempts;
+DROP TABLE IF EXISTS email_verification_tokens;
+DROP TABLE IF EXISTS password_reset_tokens;
+DROP TABLE IF EXISTS sessions;
+DROP TABLE IF EXISTS revoked_refresh_token_families;
+DROP TABLE IF EXISTS refresh_tokens;
@@ -0,0 +1,52 @@
+-- The rest of what's otherwise kept in Redis, so a deployment can run without it.
+-- Expiry times are Unix timestamps; expired rows are ignored and purged periodically
+CREATE TABLE IF NOT EXISTS refresh_tokens(
+   token TEXT NOT NULL PRIMARY KEY,
+   email TEXT NOT NULL,
+   family_id TEXT NOT NULL,
+   used BOOLEAN NOT NULL DEFAULT FALSE,
+   expires_at BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS refresh_tokens_email_idx ON refresh_tokens(email);
+CREATE INDEX IF NOT EXISTS refresh_tokens_expires_at_idx ON refresh_tokens(expires_at);
+
+CREATE TABLE IF NOT EXISTS revoked_refresh_token_families(
+   family_id TEXT NOT NULL PRIMARY KEY,
+   expires_at BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS revoked_refresh_token_families_expires_at_idx ON revoked_refresh_token_families(expires_at);
+
+CREATE TABLE IF NOT EXISTS sessions(
+   id TEXT NOT NULL PRIMARY KEY,
+   email TEXT NOT NULL,
+   user_agent TEXT,
+   ip TEXT NOT NULL,
+   created_at BIGINT NOT NULL,
+   last_seen_at BIGINT NOT NULL,
+   expires_at BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS sessions_email_idx ON sessions(email);
+CREATE INDEX IF NOT EXISTS sessions_expires_at_idx ON sessions(expires_at);
+
+CREATE TABLE IF NOT EXISTS password_reset_tokens(
+   token TEXT NOT NULL PRIMARY KEY,
+   email TEXT NOT NULL,
+   expires_at BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS password_reset_tokens_expires_at_idx ON password_reset_tokens(expires_at);
+
+CREATE TABLE IF NOT EXISTS email_verification_tokens(
+   token TEXT NOT NULL PRIMARY KEY,
+   email TEXT NOT NULL,
+   expires_at BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS email_verification_tokens_expires_at_idx ON email_verification_tokens(expires_at);
+
+-- Keyed by `account:<email>` or `ip:<address>`
+CREATE TABLE IF NOT EXISTS login_attempts(
+   key TEXT NOT NULL PRIMARY KEY,
+   failures INTEGER NOT NULL,
+   last_failure_at BIGINT NOT NULL,
+   expires_at BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS login_attempts_expires_at_idx ON login_attempts(expires_at);
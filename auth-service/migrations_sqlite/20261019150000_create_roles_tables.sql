@@ -0,0 +1,18 @@
+CREATE TABLE IF NOT EXISTS roles(
+   name TEXT NOT NULL PRIMARY KEY
+);
+CREATE TABLE IF NOT EXISTS role_permissions(
+   role TEXT NOT NULL REFERENCES roles(name) ON DELETE CASCADE,
+   permission TEXT NOT NULL,
+   PRIMARY KEY (role, permission)
+);
+CREATE TABLE IF NOT EXISTS user_roles(
+   email TEXT NOT NULL REFERENCES users(email) ON DELETE CASCADE,
+   role TEXT NOT NULL REFERENCES roles(name) ON DELETE CASCADE,
+   PRIMARY KEY (email, role)
+);
+
+-- Same as `DEFAULT_ROLES`
+INSERT OR IGNORE INTO roles (name) VALUES ('admin');
+INSERT OR IGNORE INTO role_permissions (role, permission)
+VALUES ('admin', 'users:manage');
@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS totp_secrets(
+   email TEXT NOT NULL PRIMARY KEY REFERENCES users(email) ON DELETE CASCADE,
+   secret TEXT NOT NULL,
+   confirmed BOOLEAN NOT NULL DEFAULT FALSE,
+   last_used_step BIGINT
+);
+
+CREATE TABLE IF NOT EXISTS recovery_codes(
+   email TEXT NOT NULL REFERENCES users(email) ON DELETE CASCADE,
+   code_hash TEXT NOT NULL,
+   PRIMARY KEY (email, code_hash)
+);
@@ -1,35 +1,158 @@
+use crate::domain::{
+    BannedTokenStore, Email, EmailClient, EmailVerificationTokenStore, HealthCheck,
+    LoginAttemptStore, PasswordResetTokenStore, RecoveryCodeStore, RefreshTokenStore, SessionStore,
+    TotpStore, TwoFACodeStore, UserStore,
+};
 use crate::prelude::{
-    PostgresUserStore, PostmarkEmailClient, RedisBannedTokenStore, RedisTwoFACodeStore,
+    HashmapEmailVerificationTokenStore, HashmapLoginAttemptStore, HashmapPasswordResetTokenStore,
+    HashmapRecoveryCodeStore, HashmapRefreshTokenStore, HashmapSessionStore, HashmapTotpStore,
+    HashmapTwoFACodeStore, HashmapUserStore, HashsetBannedTokenStore, MockEmailClient,
 };
+use crate::utils::login_throttle::LoginThrottlePolicy;
+use crate::utils::shutdown::Shutdown;
+use serde::Deserialize;
 use std::sync::Arc;
-use tokio::sync::RwLock;
 
 // Using a type alias to improve readability!
-pub type UserStoreType = Arc<RwLock<PostgresUserStore>>;
-pub type BannedTokenType = Arc<RwLock<RedisBannedTokenStore>>;
-pub type TwoFACodeType = Arc<RwLock<RedisTwoFACodeStore>>;
-pub type EmailClientType = Arc<RwLock<PostmarkEmailClient>>;
+// Stores are trait objects so the Postgres/Redis backed ones used in
+// production can be swapped for in-memory ones.
+pub type UserStoreType = Arc<dyn UserStore + Send + Sync>;
+pub type BannedTokenType = Arc<dyn BannedTokenStore + Send + Sync>;
+pub type RefreshTokenType = Arc<dyn RefreshTokenStore + Send + Sync>;
+pub type SessionType = Arc<dyn SessionStore + Send + Sync>;
+pub type PasswordResetTokenType = Arc<dyn PasswordResetTokenStore + Send + Sync>;
+pub type EmailVerificationTokenType = Arc<dyn EmailVerificationTokenStore + Send + Sync>;
+pub type TotpType = Arc<dyn TotpStore + Send + Sync>;
+pub type RecoveryCodeType = Arc<dyn RecoveryCodeStore + Send + Sync>;
+pub type TwoFACodeType = Arc<dyn TwoFACodeStore + Send + Sync>;
+pub type LoginAttemptType = Arc<dyn LoginAttemptStore + Send + Sync>;
+pub type EmailClientType = Arc<dyn EmailClient + Send + Sync>;
+pub type HealthCheckType = Arc<dyn HealthCheck + Send + Sync>;
+
+// Whether users who haven't verified their email address yet may log in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnverifiedLoginPolicy {
+    Allow,
+    Reject,
+}
+
+impl std::str::FromStr for UnverifiedLoginPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "allow" => Ok(Self::Allow),
+            "reject" => Ok(Self::Reject),
+            other => Err(format!("unknown unverified login policy '{other}'")),
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct AppState {
     pub user_store: UserStoreType,
     pub banned_token_store: BannedTokenType,
+    pub refresh_token_store: RefreshTokenType,
+    pub session_store: SessionType,
+    pub password_reset_token_store: PasswordResetTokenType,
+    pub email_verification_token_store: EmailVerificationTokenType,
     pub two_fa_code_store: TwoFACodeType,
+    pub login_attempt_store: LoginAttemptType,
+    pub totp_store: TotpType,
+    pub recovery_code_store: RecoveryCodeType,
     pub email_client: EmailClientType,
+    pub unverified_login_policy: UnverifiedLoginPolicy,
+    pub login_throttle_policy: LoginThrottlePolicy,
+    // Dependencies `/health/ready` checks, none for in-memory stores
+    pub health_checks: Vec<HealthCheckType>,
+    // Flips readiness and stops the server and background tasks
+    pub shutdown: Shutdown,
+    // Whether an account exists must not show in responses or their timing
+    pub enumeration_protection: bool,
+    // Granted the admin role once verified, see `grant_bootstrap_admins`
+    pub bootstrap_admins: Vec<Email>,
 }
 
 impl AppState {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         user_store: UserStoreType,
         banned_token_store: BannedTokenType,
+        refresh_token_store: RefreshTokenType,
+        session_store: SessionType,
+        password_reset_token_store: PasswordResetTokenType,
+        email_verification_token_store: EmailVerificationTokenType,
         two_fa_code_store: TwoFACodeType,
+        login_attempt_store: LoginAttemptType,
+        totp_store: TotpType,
+        recovery_code_store: RecoveryCodeType,
         email_client: EmailClientType,
+        unverified_login_policy: UnverifiedLoginPolicy,
+        login_throttle_policy: LoginThrottlePolicy,
     ) -> Self {
         Self {
             user_store,
             banned_token_store,
+            refresh_token_store,
+            session_store,
+            password_reset_token_store,
+            email_verification_token_store,
             two_fa_code_store,
+            login_attempt_store,
+            totp_store,
+            recovery_code_store,
             email_client,
+            unverified_login_policy,
+            login_throttle_policy,
+            health_checks: Vec::new(),
+            shutdown: Shutdown::new(),
+            enumeration_protection: false,
+            bootstrap_admins: Vec::new(),
         }
     }
+
+    pub fn with_health_checks(mut self, health_checks: Vec<HealthCheckType>) -> Self {
+        self.health_checks = health_checks;
+        self
+    }
+
+    // Background tasks spawned before the state is built need the same handle
+    pub fn with_shutdown(mut self, shutdown: Shutdown) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    pub fn with_enumeration_protection(mut self, enabled: bool) -> Self {
+        self.enumeration_protection = enabled;
+        self
+    }
+
+    pub fn with_bootstrap_admins(mut self, bootstrap_admins: Vec<Email>) -> Self {
+        self.bootstrap_admins = bootstrap_admins;
+        self
+    }
+
+    // Everything is kept in memory and emails are only logged, so the
+    // service can run without Postgres, Redis or Postmark
+    pub fn in_memory(
+        unverified_login_policy: UnverifiedLoginPolicy,
+        login_throttle_policy: LoginThrottlePolicy,
+    ) -> Self {
+        Self::new(
+            Arc::new(HashmapUserStore::new()),
+            Arc::new(HashsetBannedTokenStore::new()),
+            Arc::new(HashmapRefreshTokenStore::new()),
+            Arc::new(HashmapSessionStore::new()),
+            Arc::new(HashmapPasswordResetTokenStore::new()),
+            Arc::new(HashmapEmailVerificationTokenStore::new()),
+            Arc::new(HashmapTwoFACodeStore::new()),
+            Arc::new(HashmapLoginAttemptStore::new()),
+            Arc::new(HashmapTotpStore::new()),
+            Arc::new(HashmapRecoveryCodeStore::new()),
+            Arc::new(MockEmailClient::new()),
+            unverified_login_policy,
+            login_throttle_policy,
+        )
+    }
 }
@@ -1,36 +1,203 @@
 use super::error::BannedTokenStoreError;
-use super::error::{TwoFACodeStoreError, UserStoreError};
-use super::types::{Email, LoginAttemptId, TwoFACode};
-use super::User;
+use super::error::{
+    EmailVerificationTokenStoreError, LoginAttemptStoreError, PasswordResetTokenStoreError,
+    RecoveryCodeStoreError, RefreshTokenStoreError, SessionStoreError, TotpStoreError,
+    TwoFACodeStoreError, UserStoreError,
+};
+use super::types::{
+    Email, EmailVerificationToken, HashedPassword, HashedRecoveryCode, LoginAttemptId,
+    LoginAttemptKey, LoginAttempts, PasswordResetToken, RefreshTokenRecord, Session, TotpRecord,
+    TotpSecret, TwoFACode,
+};
+use super::{User, UserPage, UserQuery, UserRoles};
 use secrecy::SecretString;
 
 #[async_trait::async_trait]
 pub trait UserStore {
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError>;
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError>;
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError>;
     async fn validate_user(
         &self,
         email: Email,
         password: &SecretString,
     ) -> Result<(), UserStoreError>;
+    async fn update_password(
+        &self,
+        email: &Email,
+        password: HashedPassword,
+    ) -> Result<(), UserStoreError>;
+    async fn mark_email_verified(&self, email: &Email) -> Result<(), UserStoreError>;
+    async fn set_requires_2fa(
+        &self,
+        email: &Email,
+        requires_2fa: bool,
+    ) -> Result<(), UserStoreError>;
+    // Roles themselves are defined by the store, see `DEFAULT_ROLES`. Fails
+    // with `RoleNotFound` for any other role, granting one twice is a no-op.
+    async fn add_role(&self, email: &Email, role: &str) -> Result<(), UserStoreError>;
+    async fn remove_role(&self, email: &Email, role: &str) -> Result<(), UserStoreError>;
+    async fn get_roles(&self, email: &Email) -> Result<UserRoles, UserStoreError>;
+    async fn list_users(&self, query: &UserQuery) -> Result<UserPage, UserStoreError>;
+    async fn set_disabled(&self, email: &Email, disabled: bool) -> Result<(), UserStoreError>;
+    // Cleared again by `update_password`
+    async fn set_password_reset_required(
+        &self,
+        email: &Email,
+        required: bool,
+    ) -> Result<(), UserStoreError>;
+    // Also removes everything stored alongside the user, like their roles
+    async fn delete_user(&self, email: &Email) -> Result<(), UserStoreError>;
 }
 
 #[async_trait::async_trait]
 pub trait BannedTokenStore {
-    async fn add_token(&mut self, token: SecretString) -> Result<(), BannedTokenStoreError>;
+    async fn add_token(&self, token: SecretString) -> Result<(), BannedTokenStoreError>;
     async fn token_exists(&self, token: &SecretString) -> Result<bool, BannedTokenStoreError>;
+    // Ban every token issued to `email` at or before `issued_before` (a Unix timestamp)
+    async fn ban_user_tokens(
+        &self,
+        email: &Email,
+        issued_before: i64,
+    ) -> Result<(), BannedTokenStoreError>;
+    async fn user_tokens_banned_before(
+        &self,
+        email: &Email,
+    ) -> Result<Option<i64>, BannedTokenStoreError>;
+}
+
+// Refresh tokens are grouped into families: every token issued by rotating
+// a refresh token belongs to the same family as the token it replaced.
+#[async_trait::async_trait]
+pub trait RefreshTokenStore {
+    async fn add_refresh_token(
+        &self,
+        token: SecretString,
+        record: RefreshTokenRecord,
+    ) -> Result<(), RefreshTokenStoreError>;
+    async fn get_refresh_token(
+        &self,
+        token: &SecretString,
+    ) -> Result<RefreshTokenRecord, RefreshTokenStoreError>;
+    // Fails with `TokenAlreadyUsed` if the token was already marked, so only
+    // one of several concurrent rotations can succeed.
+    async fn mark_refresh_token_used(
+        &self,
+        token: &SecretString,
+    ) -> Result<(), RefreshTokenStoreError>;
+    async fn revoke_family(&self, family_id: &str) -> Result<(), RefreshTokenStoreError>;
+    async fn is_family_revoked(&self, family_id: &str) -> Result<bool, RefreshTokenStoreError>;
+    async fn revoke_user_families(&self, email: &Email) -> Result<(), RefreshTokenStoreError>;
+}
+
+#[async_trait::async_trait]
+pub trait PasswordResetTokenStore {
+    async fn add_token(
+        &self,
+        token: PasswordResetToken,
+        email: Email,
+    ) -> Result<(), PasswordResetTokenStoreError>;
+    // Tokens are single-use, so reading one also removes it
+    async fn take_token(
+        &self,
+        token: &PasswordResetToken,
+    ) -> Result<Email, PasswordResetTokenStoreError>;
+    // Drop every token still pending for the user, so none outlives them
+    async fn remove_user_tokens(&self, email: &Email) -> Result<(), PasswordResetTokenStoreError>;
+}
+
+#[async_trait::async_trait]
+pub trait EmailVerificationTokenStore {
+    async fn add_token(
+        &self,
+        token: EmailVerificationToken,
+        email: Email,
+    ) -> Result<(), EmailVerificationTokenStoreError>;
+    // Tokens are single-use, so reading one also removes it
+    async fn take_token(
+        &self,
+        token: &EmailVerificationToken,
+    ) -> Result<Email, EmailVerificationTokenStoreError>;
+    // Drop every token still pending for the user, so none outlives them
+    async fn remove_user_tokens(
+        &self,
+        email: &Email,
+    ) -> Result<(), EmailVerificationTokenStoreError>;
+}
+
+#[async_trait::async_trait]
+pub trait TotpStore {
+    // Start (or restart) enrollment with a new unconfirmed secret. Fails with
+    // `AlreadyEnabled` once the user has confirmed a secret.
+    async fn add_pending_secret(
+        &self,
+        email: &Email,
+        secret: TotpSecret,
+    ) -> Result<(), TotpStoreError>;
+    async fn get_totp(&self, email: &Email) -> Result<TotpRecord, TotpStoreError>;
+    async fn confirm_secret(&self, email: &Email) -> Result<(), TotpStoreError>;
+    // Remember the time step of an accepted code so it can't be replayed.
+    // Fails with `StepAlreadyUsed` unless `step` is newer than the last one.
+    async fn record_used_step(&self, email: &Email, step: i64) -> Result<(), TotpStoreError>;
+    // Forget the user's secret, confirmed or not. A no-op if there is none.
+    async fn remove_totp(&self, email: &Email) -> Result<(), TotpStoreError>;
+}
+
+#[async_trait::async_trait]
+pub trait RecoveryCodeStore {
+    // Replace all of the user's recovery codes, invalidating the old ones
+    async fn replace_codes(
+        &self,
+        email: &Email,
+        codes: Vec<HashedRecoveryCode>,
+    ) -> Result<(), RecoveryCodeStoreError>;
+    async fn get_codes(
+        &self,
+        email: &Email,
+    ) -> Result<Vec<HashedRecoveryCode>, RecoveryCodeStoreError>;
+    // Burn a used code. Fails with `CodeNotFound` if it was already used.
+    async fn remove_code(
+        &self,
+        email: &Email,
+        code: &HashedRecoveryCode,
+    ) -> Result<(), RecoveryCodeStoreError>;
+}
+
+#[async_trait::async_trait]
+pub trait SessionStore {
+    async fn add_session(&self, session: Session) -> Result<(), SessionStoreError>;
+    async fn get_session(&self, id: &str) -> Result<Session, SessionStoreError>;
+    async fn get_user_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError>;
+    async fn touch_session(&self, id: &str, last_seen_at: i64) -> Result<(), SessionStoreError>;
+    async fn remove_session(&self, id: &str) -> Result<(), SessionStoreError>;
+    async fn remove_user_sessions(&self, email: &Email) -> Result<(), SessionStoreError>;
+}
+
+#[async_trait::async_trait]
+pub trait LoginAttemptStore {
+    // Keys without any recent failures report zero attempts
+    async fn get_attempts(
+        &self,
+        key: &LoginAttemptKey,
+    ) -> Result<LoginAttempts, LoginAttemptStoreError>;
+    // Count a failure now and forget all of them `ttl_seconds` after the last one
+    async fn record_failure(
+        &self,
+        key: &LoginAttemptKey,
+        ttl_seconds: u64,
+    ) -> Result<LoginAttempts, LoginAttemptStoreError>;
+    async fn reset(&self, key: &LoginAttemptKey) -> Result<(), LoginAttemptStoreError>;
 }
 
 #[async_trait::async_trait]
 pub trait TwoFACodeStore {
     async fn add_two_fa_code(
-        &mut self,
+        &self,
         email: Email,
         login_attempt_id: LoginAttemptId,
         two_fa_code: TwoFACode,
     ) -> Result<(), TwoFACodeStoreError>;
     async fn two_fa_code_exists(&self, email: &Email) -> Result<bool, TwoFACodeStoreError>;
-    async fn remove_two_fa_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError>;
+    async fn remove_two_fa_code(&self, email: &Email) -> Result<(), TwoFACodeStoreError>;
     async fn get_two_fa_code(
         &self,
         email: &Email,
@@ -1,24 +1,155 @@
 use color_eyre::eyre::Report;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum AuthAPIError {
     #[error("User not found")]
     UserNotFound,
+    // The user an admin route was called for doesn't exist
+    #[error("Account not found")]
+    AccountNotFound,
     #[error("User already exists")]
     UserAlreadyExists,
     #[error("Invalid credentials")]
     InvalidCredentials,
+    // Fields of the request that broke validation rules, all reported at once
+    #[error("Invalid input")]
+    InvalidInput(Vec<FieldError>),
     #[error("Incorrect credentials")]
     IncorrectCredentials,
     #[error("Missing token")]
     MissingToken,
     #[error("Invalid token")]
     InvalidToken,
+    #[error("Email not verified")]
+    EmailNotVerified,
+    #[error("Account disabled")]
+    AccountDisabled,
+    // An admin requires a new password before the next login
+    #[error("Password reset required")]
+    PasswordResetRequired,
+    #[error("TOTP already enabled")]
+    TotpAlreadyEnabled,
+    #[error("Too many attempts, retry after {0} seconds")]
+    TooManyAttempts(u64),
+    #[error("Session not found")]
+    SessionNotFound,
+    // Authenticated, but the token doesn't grant what the route requires
+    #[error("Forbidden")]
+    Forbidden,
+    // The request body isn't JSON at all, or is cut short
+    #[error("Malformed request body")]
+    MalformedBody,
+    #[error("Unsupported media type")]
+    UnsupportedMediaType,
+    #[error("Payload too large")]
+    PayloadTooLarge,
+    // Valid JSON that doesn't match what the route expects
+    #[error("Invalid request body")]
+    InvalidBody(FieldError),
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
 
+// Stable identifiers for everything the API rejects requests with, so that
+// clients can match on these instead of on messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    UserNotFound,
+    UserAlreadyExists,
+    InvalidCredentials,
+    InvalidInput,
+    IncorrectCredentials,
+    MissingToken,
+    InvalidToken,
+    EmailNotVerified,
+    AccountDisabled,
+    PasswordResetRequired,
+    TotpAlreadyEnabled,
+    RateLimited,
+    SessionNotFound,
+    Forbidden,
+    MalformedBody,
+    UnsupportedMediaType,
+    PayloadTooLarge,
+    InvalidBody,
+    InternalError,
+    // Validation rules of a single field, see `FieldError`
+    PasswordEmpty,
+    PasswordNotAscii,
+    PasswordContainsSpaces,
+    PasswordTooShort,
+    PasswordMissingUppercase,
+    PasswordMissingLowercase,
+    PasswordMissingNumber,
+    PasswordMissingSymbol,
+    EmailEmpty,
+    EmailMissingAtSymbol,
+    EmailInvalidFormat,
+    // Where the request body doesn't match what the route expects
+    MissingField,
+    InvalidType,
+    InvalidValue,
+}
+
+impl AuthAPIError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::UserNotFound | Self::AccountNotFound => ErrorCode::UserNotFound,
+            Self::UserAlreadyExists => ErrorCode::UserAlreadyExists,
+            Self::InvalidCredentials => ErrorCode::InvalidCredentials,
+            Self::InvalidInput(_) => ErrorCode::InvalidInput,
+            Self::IncorrectCredentials => ErrorCode::IncorrectCredentials,
+            Self::MissingToken => ErrorCode::MissingToken,
+            Self::InvalidToken => ErrorCode::InvalidToken,
+            Self::EmailNotVerified => ErrorCode::EmailNotVerified,
+            Self::AccountDisabled => ErrorCode::AccountDisabled,
+            Self::PasswordResetRequired => ErrorCode::PasswordResetRequired,
+            Self::TotpAlreadyEnabled => ErrorCode::TotpAlreadyEnabled,
+            Self::TooManyAttempts(_) => ErrorCode::RateLimited,
+            Self::SessionNotFound => ErrorCode::SessionNotFound,
+            Self::Forbidden => ErrorCode::Forbidden,
+            Self::MalformedBody => ErrorCode::MalformedBody,
+            Self::UnsupportedMediaType => ErrorCode::UnsupportedMediaType,
+            Self::PayloadTooLarge => ErrorCode::PayloadTooLarge,
+            Self::InvalidBody(_) => ErrorCode::InvalidBody,
+            Self::UnexpectedError(_) => ErrorCode::InternalError,
+        }
+    }
+}
+
+// A validation rule one field of the request broke
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldError {
+    pub field: String,
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn email(field: &str, error: &EmailError) -> Self {
+        Self {
+            field: field.to_owned(),
+            code: error.code(),
+            message: error.to_string(),
+        }
+    }
+
+    // `Password::parse` reports a `PasswordError` wrapped in a `Report`
+    pub fn password(field: &str, error: &Report) -> Self {
+        let code = error
+            .downcast_ref::<PasswordError>()
+            .map_or(ErrorCode::InvalidInput, PasswordError::code);
+        Self {
+            field: field.to_owned(),
+            code,
+            message: error.to_string(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Error)]
 pub enum PasswordError {
     #[error("password is too short")]
@@ -46,6 +177,21 @@ pub enum PasswordError {
     IsNotASCII,
 }
 
+impl PasswordError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::ShortLength => ErrorCode::PasswordTooShort,
+            Self::IncludesSpaces => ErrorCode::PasswordContainsSpaces,
+            Self::MissingSymbol => ErrorCode::PasswordMissingSymbol,
+            Self::MissingCapitalLetter => ErrorCode::PasswordMissingUppercase,
+            Self::MissingLowercaseLetter => ErrorCode::PasswordMissingLowercase,
+            Self::MissingNumber => ErrorCode::PasswordMissingNumber,
+            Self::Empty => ErrorCode::PasswordEmpty,
+            Self::IsNotASCII => ErrorCode::PasswordNotAscii,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Error)]
 pub enum EmailError {
     #[error("email is missing '@' symbol")]
@@ -58,6 +204,16 @@ pub enum EmailError {
     Empty,
 }
 
+impl EmailError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::MissingAtSymbol => ErrorCode::EmailMissingAtSymbol,
+            Self::InvalidFormat => ErrorCode::EmailInvalidFormat,
+            Self::Empty => ErrorCode::EmailEmpty,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TokenError {
     Empty,
@@ -69,6 +225,36 @@ pub enum LoginAttemptIdError {
     Empty,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum PasswordResetTokenError {
+    #[error("password reset token is empty")]
+    Empty,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum EmailVerificationTokenError {
+    #[error("email verification token is empty")]
+    Empty,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum RecoveryCodeError {
+    #[error("recovery code is empty")]
+    Empty,
+    #[error("recovery code format is invalid")]
+    InvalidFormat,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum TotpSecretError {
+    #[error("TOTP secret is empty")]
+    Empty,
+    #[error("TOTP secret is not valid base32")]
+    InvalidEncoding,
+    #[error("TOTP secret is shorter than 128 bits")]
+    TooShort,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Error)]
 pub enum TwoFACodeError {
     #[error("two fa code is empty")]
@@ -83,6 +269,8 @@ pub enum UserStoreError {
     UserNotFound,
     #[error("Invalid credentials")]
     InvalidCredentials,
+    #[error("Role not found")]
+    RoleNotFound,
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
@@ -94,6 +282,7 @@ impl PartialEq for UserStoreError {
             (Self::UserAlreadyExists, Self::UserAlreadyExists)
                 | (Self::UserNotFound, Self::UserNotFound)
                 | (Self::InvalidCredentials, Self::InvalidCredentials)
+                | (Self::RoleNotFound, Self::RoleNotFound)
                 | (Self::UnexpectedError(_), Self::UnexpectedError(_))
         )
     }
@@ -132,3 +321,104 @@ pub enum BannedTokenStoreError {
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
+
+#[derive(Debug, Error)]
+pub enum RefreshTokenStoreError {
+    #[error("Refresh token not found")]
+    TokenNotFound,
+    #[error("Refresh token already used")]
+    TokenAlreadyUsed,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+#[derive(Debug, Error)]
+pub enum PasswordResetTokenStoreError {
+    #[error("Password reset token not found")]
+    TokenNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+#[derive(Debug, Error)]
+pub enum EmailVerificationTokenStoreError {
+    #[error("Email verification token not found")]
+    TokenNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+#[derive(Debug, Error)]
+pub enum TotpStoreError {
+    #[error("TOTP secret not found")]
+    SecretNotFound,
+    #[error("TOTP already enabled")]
+    AlreadyEnabled,
+    #[error("TOTP code already used")]
+    StepAlreadyUsed,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for TotpStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::SecretNotFound, Self::SecretNotFound)
+                | (Self::AlreadyEnabled, Self::AlreadyEnabled)
+                | (Self::StepAlreadyUsed, Self::StepAlreadyUsed)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum RecoveryCodeStoreError {
+    #[error("Recovery code not found")]
+    CodeNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for RecoveryCodeStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::CodeNotFound, Self::CodeNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum LoginAttemptStoreError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for LoginAttemptStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum SessionStoreError {
+    #[error("Session not found")]
+    SessionNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for SessionStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::SessionNotFound, Self::SessionNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
@@ -0,0 +1,9 @@
+// A dependency the service needs in order to serve requests, checked by
+// `/health/ready`
+#[async_trait::async_trait]
+pub trait HealthCheck {
+    // Name the dependency is reported under
+    fn name(&self) -> &'static str;
+
+    async fn check(&self) -> color_eyre::eyre::Result<()>;
+}
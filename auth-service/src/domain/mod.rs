@@ -1,14 +1,27 @@
 pub mod data_store;
 pub mod email_client;
 pub mod error;
+pub mod health_check;
 pub mod types;
 pub mod user;
 
-pub use data_store::{TwoFACodeStore, UserStore};
+pub use data_store::{
+    BannedTokenStore, EmailVerificationTokenStore, LoginAttemptStore, PasswordResetTokenStore,
+    RecoveryCodeStore, RefreshTokenStore, SessionStore, TotpStore, TwoFACodeStore, UserStore,
+};
 pub use email_client::*;
 pub use error::{
-    AuthAPIError, BannedTokenStoreError, EmailError, PasswordError, TwoFACodeStoreError,
-    UserStoreError,
+    AuthAPIError, BannedTokenStoreError, EmailError, EmailVerificationTokenStoreError, ErrorCode,
+    FieldError, LoginAttemptStoreError, PasswordError, PasswordResetTokenStoreError,
+    RecoveryCodeStoreError, RefreshTokenStoreError, SessionStoreError, TotpStoreError,
+    TwoFACodeStoreError, UserStoreError,
+};
+pub use health_check::HealthCheck;
+pub use types::{
+    Email, EmailVerificationToken, HashedPassword, HashedRecoveryCode, LoginAttemptKey,
+    LoginAttempts, Password, PasswordResetToken, RecoveryCode, RefreshTokenRecord, Session, Token,
+    TotpRecord, TotpSecret,
+};
+pub use user::{
+    User, UserPage, UserQuery, UserRoles, ADMIN_ROLE, DEFAULT_ROLES, MANAGE_USERS_PERMISSION,
 };
-pub use types::{Email, HashedPassword, Token};
-pub use user::User;
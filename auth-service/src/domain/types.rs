@@ -1,4 +1,8 @@
-use super::error::{EmailError, LoginAttemptIdError, PasswordError, TokenError, TwoFACodeError};
+use super::error::{
+    EmailError, EmailVerificationTokenError, LoginAttemptIdError, PasswordError,
+    PasswordResetTokenError, RecoveryCodeError, TokenError, TotpSecretError, TwoFACodeError,
+};
+use crate::utils::metrics::time_password_hash;
 use argon2::{
     password_hash::{rand_core::OsRng, SaltString},
     Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version,
@@ -6,10 +10,13 @@ use argon2::{
 use color_eyre::eyre::{eyre, Context, Report, Result};
 use secrecy::{ExposeSecret, SecretString};
 use std::hash::Hash;
+use std::net::IpAddr;
+use tokio::sync::OnceCell;
 use validator::ValidateEmail;
 
+// A plaintext password that meets the password rules, see `PasswordError`
 #[derive(Debug, Clone, PartialEq, Eq)]
-struct Password(pub String);
+pub struct Password(String);
 
 impl Password {
     pub fn parse(password: impl AsRef<str>) -> Result<Self> {
@@ -176,6 +183,156 @@ impl AsRef<SecretString> for TwoFACode {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct PasswordResetToken(SecretString);
+
+impl PasswordResetToken {
+    pub fn parse(token: SecretString) -> Result<Self, PasswordResetTokenError> {
+        let token = token.expose_secret().trim();
+        if token.is_empty() {
+            return Err(PasswordResetTokenError::Empty);
+        }
+
+        Ok(PasswordResetToken(SecretString::new(
+            token.to_owned().into_boxed_str(),
+        )))
+    }
+}
+
+impl PartialEq for PasswordResetToken {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl Eq for PasswordResetToken {}
+
+impl AsRef<SecretString> for PasswordResetToken {
+    fn as_ref(&self) -> &SecretString {
+        &self.0
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EmailVerificationToken(SecretString);
+
+impl EmailVerificationToken {
+    pub fn parse(token: SecretString) -> Result<Self, EmailVerificationTokenError> {
+        let token = token.expose_secret().trim();
+        if token.is_empty() {
+            return Err(EmailVerificationTokenError::Empty);
+        }
+
+        Ok(EmailVerificationToken(SecretString::new(
+            token.to_owned().into_boxed_str(),
+        )))
+    }
+}
+
+impl PartialEq for EmailVerificationToken {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl Eq for EmailVerificationToken {}
+
+impl AsRef<SecretString> for EmailVerificationToken {
+    fn as_ref(&self) -> &SecretString {
+        &self.0
+    }
+}
+
+// Base32 encoded (RFC 4648, unpadded) TOTP shared secret
+#[derive(Debug, Clone)]
+pub struct TotpSecret(SecretString);
+
+impl TotpSecret {
+    pub fn parse(secret: SecretString) -> Result<Self, TotpSecretError> {
+        let secret = secret.expose_secret().trim().trim_end_matches('=');
+        if secret.is_empty() {
+            return Err(TotpSecretError::Empty);
+        }
+        if !secret.chars().all(|c| matches!(c, 'A'..='Z' | '2'..='7')) {
+            return Err(TotpSecretError::InvalidEncoding);
+        }
+        // Every base32 character carries 5 bits; RFC 4226 requires at least 128
+        if secret.len() * 5 < 128 {
+            return Err(TotpSecretError::TooShort);
+        }
+
+        Ok(TotpSecret(SecretString::new(
+            secret.to_owned().into_boxed_str(),
+        )))
+    }
+}
+
+impl PartialEq for TotpSecret {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl Eq for TotpSecret {}
+
+impl AsRef<SecretString> for TotpSecret {
+    fn as_ref(&self) -> &SecretString {
+        &self.0
+    }
+}
+
+// A user's TOTP enrollment. The secret only counts as a second factor once
+// the user has confirmed it with a valid code.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TotpRecord {
+    pub secret: TotpSecret,
+    pub confirmed: bool,
+    pub last_used_step: Option<i64>,
+}
+
+// What failed login attempts are counted against
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LoginAttemptKey {
+    Account(Email),
+    Ip(IpAddr),
+}
+
+// Failed login attempts within the current window
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LoginAttempts {
+    pub failures: u32,
+    pub last_failure_at: i64,
+}
+
+// A logged in device. Its id is carried in the `jti` claim of every JWT
+// issued to it and doubles as the id of its refresh token family.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    pub id: String,
+    pub email: Email,
+    pub user_agent: Option<String>,
+    pub ip: String,
+    pub created_at: i64,
+    pub last_seen_at: i64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RefreshTokenRecord {
+    pub email: Email,
+    pub family_id: String,
+    pub used: bool,
+}
+
+impl RefreshTokenRecord {
+    pub fn new(email: Email, family_id: String) -> Self {
+        Self {
+            email,
+            family_id,
+            used: false,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct HashedPassword(SecretString);
 
@@ -214,24 +371,47 @@ impl HashedPassword {
 
     #[tracing::instrument(name = "Verify password hash", skip_all)]
     pub async fn verify_raw_password(&self, password_candidate: &SecretString) -> Result<()> {
-        let current_span = tracing::Span::current();
+        verify_password_hash(&self.0, password_candidate).await
+    }
+
+    // Takes as long as `verify_raw_password` and always fails, for when there
+    // is no user to check the password of but the caller mustn't be able to tell
+    #[tracing::instrument(name = "Verify dummy password hash", skip_all)]
+    pub async fn verify_dummy(password_candidate: &SecretString) -> Result<()> {
+        static DUMMY_HASH: OnceCell<SecretString> = OnceCell::const_new();
+
+        // Hashed with the same parameters as real passwords the first time
+        // it's needed, nothing can ever match it
+        let hash = DUMMY_HASH
+            .get_or_try_init(|| {
+                let password = SecretString::new(uuid::Uuid::new_v4().to_string().into_boxed_str());
+                async move { compute_password_hash(&password).await }
+            })
+            .await?;
+        verify_password_hash(hash, password_candidate).await
+    }
+}
 
-        let password_hash = self.as_ref().expose_secret().to_owned();
-        let password_candidate = password_candidate.expose_secret().to_owned();
+#[tracing::instrument(name = "Verify password hash", skip_all)]
+async fn verify_password_hash(hash: &SecretString, candidate: &SecretString) -> Result<()> {
+    let current_span = tracing::Span::current();
 
-        tokio::task::spawn_blocking(move || {
-            current_span.in_scope(|| {
-                let expected_password_hash =
-                    PasswordHash::new(&password_hash).wrap_err("Failed to parse password hash")?;
+    let password_hash = hash.expose_secret().to_owned();
+    let password_candidate = candidate.expose_secret().to_owned();
 
-                Argon2::default()
-                    .verify_password(password_candidate.as_bytes(), &expected_password_hash)
-                    .wrap_err("failed to verify password hash")
-            })
+    tokio::task::spawn_blocking(move || {
+        current_span.in_scope(|| {
+            let _timer = time_password_hash("verify");
+            let expected_password_hash =
+                PasswordHash::new(&password_hash).wrap_err("Failed to parse password hash")?;
+
+            Argon2::default()
+                .verify_password(password_candidate.as_bytes(), &expected_password_hash)
+                .wrap_err("failed to verify password hash")
         })
-        .await
-        .wrap_err("Password verification task panicked or was cancelled")?
-    }
+    })
+    .await
+    .wrap_err("Password verification task panicked or was cancelled")?
 }
 
 #[tracing::instrument(name = "Computing password hash", skip_all)]
@@ -241,6 +421,7 @@ async fn compute_password_hash(password: &SecretString) -> Result<SecretString>
     let password = password.expose_secret().to_owned();
     let result = tokio::task::spawn_blocking(move || {
         current_span.in_scope(|| {
+            let _timer = time_password_hash("hash");
             let salt: SaltString = SaltString::generate(&mut OsRng);
             let password_hash = Argon2::new(
                 Algorithm::Argon2id,
@@ -264,6 +445,93 @@ impl AsRef<SecretString> for HashedPassword {
     }
 }
 
+// Single-use 2FA fallback code, normalized to 10 upper-case characters.
+// Users see it as `XXXXX-XXXXX`; dashes, spaces and case are ignored on input.
+#[derive(Debug, Clone)]
+pub struct RecoveryCode(SecretString);
+
+// Excludes characters that are easy to mix up, like 0/O and 1/I
+pub const RECOVERY_CODE_ALPHABET: &str = "ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+pub const RECOVERY_CODE_LENGTH: usize = 10;
+
+impl RecoveryCode {
+    pub fn parse(code: SecretString) -> Result<Self, RecoveryCodeError> {
+        let code: String = code
+            .expose_secret()
+            .chars()
+            .filter(|c| *c != '-' && !c.is_whitespace())
+            .map(|c| c.to_ascii_uppercase())
+            .collect();
+        if code.is_empty() {
+            return Err(RecoveryCodeError::Empty);
+        }
+        if code.len() != RECOVERY_CODE_LENGTH
+            || !code.chars().all(|c| RECOVERY_CODE_ALPHABET.contains(c))
+        {
+            return Err(RecoveryCodeError::InvalidFormat);
+        }
+
+        Ok(RecoveryCode(SecretString::new(code.into_boxed_str())))
+    }
+
+    // The code as shown to the user, e.g. `ABCDE-FGHJK`
+    pub fn formatted(&self) -> String {
+        let code = self.0.expose_secret();
+        let (first, second) = code.split_at(RECOVERY_CODE_LENGTH / 2);
+        format!("{first}-{second}")
+    }
+}
+
+impl PartialEq for RecoveryCode {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl Eq for RecoveryCode {}
+
+impl AsRef<SecretString> for RecoveryCode {
+    fn as_ref(&self) -> &SecretString {
+        &self.0
+    }
+}
+
+// Recovery codes are hashed with the same Argon2 settings as passwords
+#[derive(Debug, Clone)]
+pub struct HashedRecoveryCode(SecretString);
+
+impl HashedRecoveryCode {
+    pub async fn parse(code: &RecoveryCode) -> Result<Self> {
+        let hash = compute_password_hash(code.as_ref())
+            .await
+            .wrap_err("Failed to compute recovery code hash")?;
+
+        Self::parse_hash(hash)
+    }
+
+    pub fn parse_hash(hash: SecretString) -> Result<Self> {
+        PasswordHash::new(hash.expose_secret())
+            .map_err(|_| eyre!("Failed to parse string to a HashedRecoveryCode type"))?;
+        Ok(Self(hash))
+    }
+
+    pub async fn verify(&self, code: &RecoveryCode) -> Result<()> {
+        verify_password_hash(&self.0, code.as_ref()).await
+    }
+}
+
+impl PartialEq for HashedRecoveryCode {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl AsRef<SecretString> for HashedRecoveryCode {
+    fn as_ref(&self) -> &SecretString {
+        &self.0
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -303,6 +571,98 @@ mod tests {
         assert!(is_valid_login_attempt_id)
     }
 
+    #[test]
+    fn test_password_reset_token_parsing() {
+        let token =
+            PasswordResetToken::parse(SecretString::new(" 3f2a9c ".to_owned().into_boxed_str()))
+                .unwrap();
+        assert_eq!(token.as_ref().expose_secret(), "3f2a9c");
+
+        let err = PasswordResetToken::parse(SecretString::new("  ".to_owned().into_boxed_str()))
+            .unwrap_err();
+        assert_eq!(err, PasswordResetTokenError::Empty);
+    }
+
+    #[test]
+    fn test_recovery_code_parsing() {
+        let code = RecoveryCode::parse(SecretString::new(
+            " abcde-fghjk ".to_owned().into_boxed_str(),
+        ))
+        .unwrap();
+        assert_eq!(code.as_ref().expose_secret(), "ABCDEFGHJK");
+        assert_eq!(code.formatted(), "ABCDE-FGHJK");
+
+        let cases = [
+            ("", RecoveryCodeError::Empty),
+            ("-", RecoveryCodeError::Empty),
+            ("ABCDE-FGHJ", RecoveryCodeError::InvalidFormat),
+            ("ABCDE-FGHJKL", RecoveryCodeError::InvalidFormat),
+            ("ABCDE-FGHI0", RecoveryCodeError::InvalidFormat),
+        ];
+        for (code, expected) in cases {
+            let err = RecoveryCode::parse(SecretString::new(code.to_owned().into_boxed_str()))
+                .unwrap_err();
+            assert_eq!(err, expected, "Failed for code: {code}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hashed_recovery_code_verification() {
+        let code =
+            RecoveryCode::parse(SecretString::new("ABCDE-FGHJK".to_owned().into_boxed_str()))
+                .unwrap();
+        let other =
+            RecoveryCode::parse(SecretString::new("ABCDE-FGHJL".to_owned().into_boxed_str()))
+                .unwrap();
+
+        let hash = HashedRecoveryCode::parse(&code).await.unwrap();
+
+        assert!(hash.as_ref().expose_secret().starts_with("$argon2id$"));
+        assert!(hash.verify(&code).await.is_ok());
+        assert!(hash.verify(&other).await.is_err());
+    }
+
+    #[test]
+    fn test_totp_secret_parsing() {
+        let secret = TotpSecret::parse(SecretString::new(
+            "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ"
+                .to_owned()
+                .into_boxed_str(),
+        ))
+        .unwrap();
+        assert_eq!(
+            secret.as_ref().expose_secret(),
+            "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ"
+        );
+
+        let cases = [
+            ("", TotpSecretError::Empty),
+            (
+                "gezdgnbvgy3tqojqgezdgnbvgy3tqojq",
+                TotpSecretError::InvalidEncoding,
+            ),
+            ("GEZDGNBVGY3TQOJQ", TotpSecretError::TooShort),
+        ];
+        for (secret, expected) in cases {
+            let err = TotpSecret::parse(SecretString::new(secret.to_owned().into_boxed_str()))
+                .unwrap_err();
+            assert_eq!(err, expected);
+        }
+    }
+
+    #[test]
+    fn test_email_verification_token_parsing() {
+        let token = EmailVerificationToken::parse(SecretString::new(
+            " 9b1e4d ".to_owned().into_boxed_str(),
+        ))
+        .unwrap();
+        assert_eq!(token.as_ref().expose_secret(), "9b1e4d");
+
+        let err = EmailVerificationToken::parse(SecretString::new("".to_owned().into_boxed_str()))
+            .unwrap_err();
+        assert_eq!(err, EmailVerificationTokenError::Empty);
+    }
+
     #[test]
     fn test_valid_email_parsing() {
         let valid_emails = [
@@ -752,6 +1112,15 @@ mod tests {
         hash_password.verify_raw_password(&candidate).await.unwrap();
     }
 
+    #[tokio::test]
+    async fn dummy_verification_always_fails() {
+        let candidate = SecretString::new("TestPassword123".to_owned().into_boxed_str());
+
+        assert!(HashedPassword::verify_dummy(&candidate).await.is_err());
+        // The second call reuses the hash computed by the first
+        assert!(HashedPassword::verify_dummy(&candidate).await.is_err());
+    }
+
     #[derive(Debug, Clone)]
     struct ValidPasswordFixture(pub String);
 
@@ -5,14 +5,54 @@ pub struct User {
     pub email: Email,
     pub password: HashedPassword,
     pub requires_2fa: bool,
+    pub verified: bool,
+    // Set by an admin, a disabled user can't log in
+    pub disabled: bool,
+    // Set by an admin, the user can't log in until they reset their password
+    pub password_reset_required: bool,
 }
 
 impl User {
+    // New users start out unverified until they confirm their email address
     pub fn new(email: Email, password: HashedPassword, requires_2fa: bool) -> Self {
         Self {
             email,
             password,
             requires_2fa,
+            verified: false,
+            disabled: false,
+            password_reset_required: false,
         }
     }
 }
+
+// Role that can manage other users' accounts
+pub const ADMIN_ROLE: &str = "admin";
+pub const MANAGE_USERS_PERMISSION: &str = "users:manage";
+
+// Roles every user store starts out with and the permissions each grants.
+// The SQL migrations seed the same rows.
+pub const DEFAULT_ROLES: &[(&str, &[&str])] = &[(ADMIN_ROLE, &[MANAGE_USERS_PERMISSION])];
+
+// The roles granted to a user and every permission they carry between them,
+// both sorted and without duplicates
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UserRoles {
+    pub roles: Vec<String>,
+    pub permissions: Vec<String>,
+}
+
+// A page of users ordered by email, see `UserStore::list_users`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UserQuery {
+    // Only users whose email contains this, ignoring ASCII case
+    pub email_contains: Option<String>,
+    pub limit: u32,
+    pub offset: u32,
+}
+
+pub struct UserPage {
+    pub users: Vec<User>,
+    // Matching users across all pages
+    pub total: u64,
+}
@@ -5,61 +5,169 @@ pub mod services;
 pub mod utils;
 
 use axum::{
-    http::StatusCode,
+    http::{header, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
-use domain::AuthAPIError;
+use domain::{AuthAPIError, ErrorCode, FieldError};
 use secrecy::{ExposeSecret, SecretString};
 use serde::{Deserialize, Serialize};
 use sqlx::postgres::PgPoolOptions;
 use sqlx::PgPool;
 use std::error::Error;
+use utils::constants::PROBLEM_JSON_CONTENT_TYPE;
 pub use utils::tracing::init_tracing;
 
 pub mod prelude {
-    pub use crate::app_state::{AppState, BannedTokenType};
-    pub use crate::domain::EmailClient;
+    pub use crate::app_state::{
+        AppState, BannedTokenType, EmailClientType, EmailVerificationTokenType, HealthCheckType,
+        LoginAttemptType, PasswordResetTokenType, RecoveryCodeType, RefreshTokenType, SessionType,
+        TotpType, TwoFACodeType, UnverifiedLoginPolicy, UserStoreType,
+    };
+    pub use crate::domain::{EmailClient, HealthCheck};
     pub use crate::routes::Application;
     pub use crate::services::{
+        hashmap_email_verification_token_store::HashmapEmailVerificationTokenStore,
+        hashmap_login_attempt_store::HashmapLoginAttemptStore,
+        hashmap_password_reset_token_store::HashmapPasswordResetTokenStore,
+        hashmap_recovery_code_store::HashmapRecoveryCodeStore,
+        hashmap_refresh_token_store::HashmapRefreshTokenStore,
+        hashmap_session_store::HashmapSessionStore, hashmap_totp_store::HashmapTotpStore,
         hashmap_two_fa_code_store::HashmapTwoFACodeStore, hashmap_user_store::HashmapUserStore,
         hashset_banned_token_store::HashsetBannedTokenStore, mock_email_client::MockEmailClient,
-        postgres_user_store::PostgresUserStore, postmark_email_client::PostmarkEmailClient,
+        postgres_banned_token_store::PostgresBannedTokenStore,
+        postgres_email_verification_token_store::PostgresEmailVerificationTokenStore,
+        postgres_login_attempt_store::PostgresLoginAttemptStore,
+        postgres_password_reset_token_store::PostgresPasswordResetTokenStore,
+        postgres_recovery_code_store::PostgresRecoveryCodeStore,
+        postgres_refresh_token_store::PostgresRefreshTokenStore,
+        postgres_session_store::PostgresSessionStore, postgres_totp_store::PostgresTotpStore,
+        postgres_two_fa_code_store::PostgresTwoFACodeStore, postgres_user_store::PostgresUserStore,
+        postmark_email_client::PostmarkEmailClient,
         redis_banned_token_store::RedisBannedTokenStore,
+        redis_email_verification_token_store::RedisEmailVerificationTokenStore,
+        redis_login_attempt_store::RedisLoginAttemptStore,
+        redis_password_reset_token_store::RedisPasswordResetTokenStore,
+        redis_refresh_token_store::RedisRefreshTokenStore, redis_session_store::RedisSessionStore,
         redis_two_fa_code_store::RedisTwoFACodeStore,
     };
+    #[cfg(feature = "sqlite")]
+    pub use crate::services::{
+        sqlite_banned_token_store::SqliteBannedTokenStore,
+        sqlite_email_verification_token_store::SqliteEmailVerificationTokenStore,
+        sqlite_login_attempt_store::SqliteLoginAttemptStore,
+        sqlite_password_reset_token_store::SqlitePasswordResetTokenStore,
+        sqlite_recovery_code_store::SqliteRecoveryCodeStore,
+        sqlite_refresh_token_store::SqliteRefreshTokenStore,
+        sqlite_session_store::SqliteSessionStore, sqlite_totp_store::SqliteTotpStore,
+        sqlite_two_fa_code_store::SqliteTwoFACodeStore, sqlite_user_store::SqliteUserStore,
+    };
     pub use crate::ErrorResponse;
 }
 
 pub mod dto {
-    pub use crate::routes::{SignupRequest, SignupResponse, TwoFactorAuthResponse};
+    pub use crate::routes::{
+        AdminUserDetailsResponse, AdminUserResponse, AdminUsersResponse, ConfirmTotpResponse,
+        DependencyHealth, HealthResponse, HealthStatus, PasswordResetResponse,
+        RecoveryCodesResponse, SessionResponse, SessionsResponse, SignupRequest, SignupResponse,
+        TotpEnrollmentResponse, TwoFactorAuthResponse, VerifyEmailResponse, VerifyTokenResponse,
+    };
 }
 
-#[derive(Serialize, Deserialize)]
+// RFC 7807 problem details, served as `application/problem+json`. `code`
+// identifies the error, `detail` is only meant for humans.
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ErrorResponse {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    // Deprecated copy of `detail`, kept for clients that read the message
+    // from the error bodies served before problem details
+    #[serde(default)]
     pub error: String,
+    pub code: ErrorCode,
+    // Every validation rule the request broke for `invalid_input`, or where
+    // the body went wrong for `invalid_body`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
 }
 
 impl IntoResponse for AuthAPIError {
     fn into_response(self) -> Response {
         log_error_chain(&self);
-        let (status, error_message) = match self {
+        let code = self.code();
+        let (status, detail) = match &self {
             AuthAPIError::UserAlreadyExists => (StatusCode::CONFLICT, "User already exists"),
             AuthAPIError::InvalidCredentials => (StatusCode::BAD_REQUEST, "Invalid credentials"),
+            AuthAPIError::InvalidInput(_) => (StatusCode::BAD_REQUEST, "Invalid input"),
             AuthAPIError::UnexpectedError(_) => {
                 (StatusCode::INTERNAL_SERVER_ERROR, "Unexpected error")
             }
             AuthAPIError::UserNotFound => (StatusCode::UNAUTHORIZED, "User doesn't exist"),
+            AuthAPIError::AccountNotFound => (StatusCode::NOT_FOUND, "User doesn't exist"),
             AuthAPIError::IncorrectCredentials => {
-                (StatusCode::UNAUTHORIZED, "User crendetials do not match")
+                (StatusCode::UNAUTHORIZED, "User credentials do not match")
             }
             AuthAPIError::MissingToken => (StatusCode::BAD_REQUEST, "Missing token"),
             AuthAPIError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid token"),
+            AuthAPIError::EmailNotVerified => (StatusCode::FORBIDDEN, "Email not verified"),
+            AuthAPIError::AccountDisabled => (StatusCode::FORBIDDEN, "Account is disabled"),
+            AuthAPIError::PasswordResetRequired => {
+                (StatusCode::FORBIDDEN, "Password reset required")
+            }
+            AuthAPIError::TotpAlreadyEnabled => (StatusCode::CONFLICT, "TOTP already enabled"),
+            AuthAPIError::SessionNotFound => (StatusCode::NOT_FOUND, "Session not found"),
+            AuthAPIError::Forbidden => (StatusCode::FORBIDDEN, "Missing permission"),
+            AuthAPIError::TooManyAttempts(_) => {
+                (StatusCode::TOO_MANY_REQUESTS, "Too many attempts")
+            }
+            AuthAPIError::MalformedBody => (StatusCode::BAD_REQUEST, "Malformed JSON body"),
+            AuthAPIError::UnsupportedMediaType => (
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "Expected a request with Content-Type: application/json",
+            ),
+            AuthAPIError::PayloadTooLarge => {
+                (StatusCode::PAYLOAD_TOO_LARGE, "Request body is too large")
+            }
+            AuthAPIError::InvalidBody(_) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "Request body doesn't match the expected schema",
+            ),
+        };
+        let retry_after = match &self {
+            AuthAPIError::TooManyAttempts(seconds) => Some(*seconds),
+            _ => None,
+        };
+        let errors = match self {
+            AuthAPIError::InvalidInput(errors) => errors,
+            AuthAPIError::InvalidBody(error) => vec![error],
+            _ => Vec::new(),
         };
+
         let body = Json(ErrorResponse {
-            error: error_message.to_string(),
+            // The `code` is what tells errors apart, there are no pages
+            // documenting each type
+            problem_type: "about:blank".to_owned(),
+            title: status.canonical_reason().unwrap_or_default().to_owned(),
+            status: status.as_u16(),
+            detail: detail.to_owned(),
+            error: detail.to_owned(),
+            code,
+            errors,
         });
-        (status, body).into_response()
+        let mut response = (status, body).into_response();
+        response.headers_mut().insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static(PROBLEM_JSON_CONTENT_TYPE),
+        );
+        if let Some(seconds) = retry_after {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, seconds.into());
+        }
+        response
     }
 }
 
@@ -71,11 +179,35 @@ pub async fn get_postgres_pool(url: &SecretString) -> Result<PgPool, sqlx::Error
         .await
 }
 
+#[cfg(feature = "sqlite")]
+pub async fn get_sqlite_pool(url: &SecretString) -> Result<sqlx::SqlitePool, sqlx::Error> {
+    use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+    use std::str::FromStr;
+
+    let options = SqliteConnectOptions::from_str(url.expose_secret())?
+        .create_if_missing(true)
+        .foreign_keys(true);
+    SqlitePoolOptions::new()
+        .max_connections(5)
+        .connect_with(options)
+        .await
+}
+
 pub fn get_redis_client(redis_hostname: String) -> redis::RedisResult<redis::Client> {
     let redis_url = format!("redis://{}/", redis_hostname);
     redis::Client::open(redis_url)
 }
 
+pub async fn get_redis_connection_manager(
+    redis_hostname: String,
+) -> redis::RedisResult<redis::aio::ConnectionManager> {
+    // The manager multiplexes one connection and reconnects on failure, so
+    // clones can be handed to every Redis store and used concurrently
+    get_redis_client(redis_hostname)?
+        .get_connection_manager()
+        .await
+}
+
 fn log_error_chain(e: &(dyn Error + 'static)) {
     let separator =
         "\n-----------------------------------------------------------------------------------\n";
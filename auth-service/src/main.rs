@@ -1,48 +1,234 @@
 use auth_service::domain::Email;
 use auth_service::get_postgres_pool;
-use auth_service::get_redis_client;
+use auth_service::get_redis_connection_manager;
 use auth_service::init_tracing;
 use auth_service::prelude::{
-    AppState, Application, PostgresUserStore, PostmarkEmailClient, RedisBannedTokenStore,
-    RedisTwoFACodeStore,
+    AppState, Application, BannedTokenType, EmailClientType, EmailVerificationTokenType,
+    HealthCheckType, LoginAttemptType, MockEmailClient, PasswordResetTokenType,
+    PostgresRecoveryCodeStore, PostgresTotpStore, PostgresUserStore, PostmarkEmailClient,
+    RecoveryCodeType, RedisBannedTokenStore, RedisEmailVerificationTokenStore,
+    RedisLoginAttemptStore, RedisPasswordResetTokenStore, RedisRefreshTokenStore,
+    RedisSessionStore, RedisTwoFACodeStore, RefreshTokenType, SessionType, TotpType, TwoFACodeType,
+    UserStoreType,
 };
-use auth_service::utils::constants::prod;
-use auth_service::utils::constants::POSTMARK_AUTH_TOKEN;
-use auth_service::utils::constants::{DATABASE_URL, REDIS_HOST_NAME};
+use auth_service::routes::grant_bootstrap_admins;
+use auth_service::services::health_checks::{PostgresHealthCheck, RedisHealthCheck};
+use auth_service::services::purge_task::{spawn_purge_task, PostgresTokenStores};
+use auth_service::utils::settings::{init_settings, EmailProvider, Settings};
+use auth_service::utils::shutdown::{termination_signal, Shutdown};
+use color_eyre::eyre::Result;
 use reqwest::Client;
 use secrecy::SecretString;
 use sqlx::PgPool;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::Duration;
 
 #[tokio::main]
-async fn main() {
-    color_eyre::install().expect("Failed to install color_eyre");
-    init_tracing().expect("Failed to initialize tracing");
-    let pg_pool = configure_postgresql().await;
-    let redis_conn = Arc::new(RwLock::new(configure_redis()));
-    let user_store = PostgresUserStore::new(pg_pool);
-    let banned_token_store = RedisBannedTokenStore::new(redis_conn.clone());
-    let two_fa_code_store = RedisTwoFACodeStore::new(redis_conn);
-    let email_client = configure_postmark_email_client();
-    let app_state = AppState::new(
-        Arc::new(RwLock::new(user_store)),
-        Arc::new(RwLock::new(banned_token_store)),
-        Arc::new(RwLock::new(two_fa_code_store)),
-        Arc::new(RwLock::new(email_client)),
-    );
+async fn main() -> Result<()> {
+    color_eyre::install()?;
+    let settings = init_settings()?;
+    let _tracing_guard = init_tracing(&settings.tracing)?;
+
+    let app_state = if settings.stores.in_memory {
+        tracing::warn!(
+            "In-memory stores are enabled, nothing will be persisted and emails are only logged"
+        );
+        AppState::in_memory(
+            settings.login.unverified_login_policy,
+            settings.login.throttle_policy(),
+        )
+    } else {
+        configure_app_state(settings).await
+    }
+    .with_enumeration_protection(settings.login.enumeration_protection)
+    .with_bootstrap_admins(settings.admin.bootstrap_admins());
+    grant_bootstrap_admins(&app_state).await?;
 
-    let app = Application::build(app_state, prod::APP_ADDRESS)
+    let app = Application::build(app_state, &settings.application.address)
         .await
         .expect("Failed to build app");
 
-    app.run().await.expect("Failed to run app");
+    // Spans still buffered are flushed when `_tracing_guard` is dropped
+    app.run_until_stopped(termination_signal(), &settings.shutdown)
+        .await?;
+    Ok(())
+}
+
+async fn configure_app_state(settings: &Settings) -> AppState {
+    let shutdown = Shutdown::new();
+    let mut health_checks: Vec<HealthCheckType> = Vec::new();
+    let (user_store, totp_store, recovery_code_store, token_stores) =
+        match settings.stores.sqlite_database_url {
+            Some(_) => configure_sqlite_stores(settings, &shutdown, &mut health_checks).await,
+            None => {
+                let pg_pool = configure_postgresql(settings).await;
+                health_checks.push(Arc::new(PostgresHealthCheck::new(pg_pool.clone())));
+                let (user_store, totp_store, recovery_code_store) =
+                    configure_postgres_stores(pg_pool.clone());
+                let token_stores = if settings.stores.postgres_token_stores {
+                    configure_postgres_token_stores(
+                        pg_pool,
+                        settings.stores.purge_interval_seconds,
+                        &shutdown,
+                    )
+                } else {
+                    configure_redis_token_stores(settings, &mut health_checks).await
+                };
+                (user_store, totp_store, recovery_code_store, token_stores)
+            }
+        };
+    let email_client = configure_email_client(settings, &mut health_checks);
+    AppState::new(
+        user_store,
+        token_stores.banned_token_store,
+        token_stores.refresh_token_store,
+        token_stores.session_store,
+        token_stores.password_reset_token_store,
+        token_stores.email_verification_token_store,
+        token_stores.two_fa_code_store,
+        token_stores.login_attempt_store,
+        totp_store,
+        recovery_code_store,
+        email_client,
+        settings.login.unverified_login_policy,
+        settings.login.throttle_policy(),
+    )
+    .with_health_checks(health_checks)
+    .with_shutdown(shutdown)
+}
+
+// Everything that expires: tokens, sessions, 2FA codes and login attempts
+struct TokenStores {
+    banned_token_store: BannedTokenType,
+    refresh_token_store: RefreshTokenType,
+    session_store: SessionType,
+    password_reset_token_store: PasswordResetTokenType,
+    email_verification_token_store: EmailVerificationTokenType,
+    two_fa_code_store: TwoFACodeType,
+    login_attempt_store: LoginAttemptType,
+}
+
+async fn configure_redis_token_stores(
+    settings: &Settings,
+    health_checks: &mut Vec<HealthCheckType>,
+) -> TokenStores {
+    let redis_conn = configure_redis(settings).await;
+    health_checks.push(Arc::new(RedisHealthCheck::new(redis_conn.clone())));
+    TokenStores {
+        banned_token_store: Arc::new(RedisBannedTokenStore::new(redis_conn.clone())),
+        refresh_token_store: Arc::new(RedisRefreshTokenStore::new(redis_conn.clone())),
+        session_store: Arc::new(RedisSessionStore::new(redis_conn.clone())),
+        password_reset_token_store: Arc::new(RedisPasswordResetTokenStore::new(redis_conn.clone())),
+        email_verification_token_store: Arc::new(RedisEmailVerificationTokenStore::new(
+            redis_conn.clone(),
+        )),
+        two_fa_code_store: Arc::new(RedisTwoFACodeStore::new(redis_conn.clone())),
+        login_attempt_store: Arc::new(RedisLoginAttemptStore::new(redis_conn)),
+    }
+}
+
+fn configure_postgres_stores(pg_pool: PgPool) -> (UserStoreType, TotpType, RecoveryCodeType) {
+    (
+        Arc::new(PostgresUserStore::new(pg_pool.clone())),
+        Arc::new(PostgresTotpStore::new(pg_pool.clone())),
+        Arc::new(PostgresRecoveryCodeStore::new(pg_pool)),
+    )
+}
+
+// Everything is kept in a single SQLite database, so neither Postgres nor
+// Redis is used at all, with a background task deleting the expired rows
+#[cfg(feature = "sqlite")]
+async fn configure_sqlite_stores(
+    settings: &Settings,
+    shutdown: &Shutdown,
+    health_checks: &mut Vec<HealthCheckType>,
+) -> (UserStoreType, TotpType, RecoveryCodeType, TokenStores) {
+    use auth_service::get_sqlite_pool;
+    use auth_service::prelude::{SqliteRecoveryCodeStore, SqliteTotpStore, SqliteUserStore};
+    use auth_service::services::health_checks::SqliteHealthCheck;
+    use auth_service::services::purge_task::SqliteTokenStores;
+
+    let url = settings
+        .stores
+        .sqlite_database_url
+        .as_ref()
+        .expect("SQLITE_DATABASE_URL must be set.");
+    let pool = get_sqlite_pool(url)
+        .await
+        .expect("Failed to create SQLite connection pool!");
+
+    sqlx::migrate!("./migrations_sqlite")
+        .run(&pool)
+        .await
+        .expect("Failed to run SQLite migrations");
+    health_checks.push(Arc::new(SqliteHealthCheck::new(pool.clone())));
+
+    let stores = SqliteTokenStores::new(pool.clone());
+    spawn_purge_task(
+        stores.clone(),
+        Duration::from_secs(settings.stores.purge_interval_seconds),
+        shutdown,
+    );
+    let token_stores = TokenStores {
+        banned_token_store: stores.banned_token_store,
+        refresh_token_store: stores.refresh_token_store,
+        session_store: stores.session_store,
+        password_reset_token_store: stores.password_reset_token_store,
+        email_verification_token_store: stores.email_verification_token_store,
+        two_fa_code_store: stores.two_fa_code_store,
+        login_attempt_store: stores.login_attempt_store,
+    };
+
+    (
+        Arc::new(SqliteUserStore::new(pool.clone())),
+        Arc::new(SqliteTotpStore::new(pool.clone())),
+        Arc::new(SqliteRecoveryCodeStore::new(pool)),
+        token_stores,
+    )
 }
 
-async fn configure_postgresql() -> PgPool {
+#[cfg(not(feature = "sqlite"))]
+async fn configure_sqlite_stores(
+    _settings: &Settings,
+    _shutdown: &Shutdown,
+    _health_checks: &mut Vec<HealthCheckType>,
+) -> (UserStoreType, TotpType, RecoveryCodeType, TokenStores) {
+    unreachable!("validation rejects SQLITE_DATABASE_URL without the `sqlite` feature");
+}
+
+// Everything is kept in Postgres instead of Redis, which isn't connected to
+// at all, with a background task deleting the expired rows
+fn configure_postgres_token_stores(
+    pg_pool: PgPool,
+    purge_interval_seconds: u64,
+    shutdown: &Shutdown,
+) -> TokenStores {
+    let stores = PostgresTokenStores::new(pg_pool);
+    spawn_purge_task(
+        stores.clone(),
+        Duration::from_secs(purge_interval_seconds),
+        shutdown,
+    );
+    TokenStores {
+        banned_token_store: stores.banned_token_store,
+        refresh_token_store: stores.refresh_token_store,
+        session_store: stores.session_store,
+        password_reset_token_store: stores.password_reset_token_store,
+        email_verification_token_store: stores.email_verification_token_store,
+        two_fa_code_store: stores.two_fa_code_store,
+        login_attempt_store: stores.login_attempt_store,
+    }
+}
+
+async fn configure_postgresql(settings: &Settings) -> PgPool {
     // Create a new database connection pool
+    let database_url = settings
+        .stores
+        .database_url
+        .as_ref()
+        .expect("DATABASE_URL must be set.");
 
-    let pg_pool = get_postgres_pool(&DATABASE_URL)
+    let pg_pool = get_postgres_pool(database_url)
         .await
         .expect("Failed to create Postgres connection pool!");
 
@@ -55,26 +241,40 @@ async fn configure_postgresql() -> PgPool {
     pg_pool
 }
 
-fn configure_redis() -> redis::Connection {
-    get_redis_client(REDIS_HOST_NAME.to_owned())
-        .expect("Failed to get Redis client")
-        .get_connection()
+async fn configure_redis(settings: &Settings) -> redis::aio::ConnectionManager {
+    get_redis_connection_manager(settings.stores.redis_host_name.clone())
+        .await
         .expect("Failed to get Redis connection")
 }
 
-fn configure_postmark_email_client() -> PostmarkEmailClient {
+fn configure_email_client(
+    settings: &Settings,
+    health_checks: &mut Vec<HealthCheckType>,
+) -> EmailClientType {
+    let email = &settings.email;
+    if email.provider == EmailProvider::Log {
+        tracing::warn!("The email provider is set to log, emails are only logged");
+        return Arc::new(MockEmailClient::new());
+    }
+
     let http_client = Client::builder()
-        .timeout(prod::email_client::TIMEOUT)
+        .timeout(email.timeout())
         .build()
         .expect("Failed to build HTTP client");
 
-    PostmarkEmailClient::new(
-        prod::email_client::BASE_URL.to_owned(),
-        Email::parse(SecretString::new(
-            prod::email_client::SENDER.to_owned().into_boxed_str(),
-        ))
-        .unwrap(),
-        POSTMARK_AUTH_TOKEN.to_owned(),
+    // Validation has already checked the sender and the auth token
+    let sender = email.sender.clone().expect("email.sender must be set.");
+    let email_client = Arc::new(PostmarkEmailClient::new(
+        email.base_url.clone(),
+        Email::parse(SecretString::from(sender)).unwrap(),
+        email
+            .postmark_auth_token
+            .clone()
+            .expect("POSTMARK_AUTH_TOKEN must be set."),
         http_client,
-    )
+    ));
+    if settings.health.check_email_provider {
+        health_checks.push(email_client.clone());
+    }
+    email_client
 }
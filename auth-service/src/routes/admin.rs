@@ -0,0 +1,434 @@
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuthAPIError, Email, ErrorCode, FieldError, TwoFACodeStoreError, User, UserQuery,
+        UserStoreError,
+    },
+    routes::{
+        authorization::{ManageUsers, RequirePermission},
+        helpers::log_out_everywhere,
+        password_reset::send_password_reset_email,
+    },
+    utils::{
+        audit::{audit_admin_action, AdminAction},
+        json::JsonBody,
+        metrics::{record_revocation, RevocationReason},
+    },
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use color_eyre::eyre::Report;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_PAGE_SIZE: u32 = 50;
+const MAX_PAGE_SIZE: u32 = 100;
+
+// Users ordered by email, optionally only those whose email contains `email`
+#[tracing::instrument(skip_all)]
+pub async fn admin_list_users(
+    State(state): State<AppState>,
+    admin: RequirePermission<ManageUsers>,
+    Query(params): Query<ListUsersParams>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let result = list_users(&state, params).await;
+    audit_admin_action(&admin.claims, AdminAction::ListUsers, None, &result);
+    result.map(Json)
+}
+
+async fn list_users(
+    state: &AppState,
+    params: ListUsersParams,
+) -> Result<AdminUsersResponse, AuthAPIError> {
+    let query = params.parse()?;
+    let page = state
+        .user_store
+        .list_users(&query)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(Report::new(e)))?;
+
+    Ok(AdminUsersResponse {
+        users: page.users.iter().map(AdminUserResponse::from).collect(),
+        total: page.total,
+        limit: query.limit,
+        offset: query.offset,
+    })
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn admin_get_user(
+    State(state): State<AppState>,
+    admin: RequirePermission<ManageUsers>,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = parse_email(email)?;
+    let result = user_details(&state, &email).await;
+    audit_admin_action(&admin.claims, AdminAction::GetUser, Some(&email), &result);
+    result.map(Json)
+}
+
+// Logs the user out everywhere and keeps them from logging in again until
+// they're enabled
+#[tracing::instrument(skip_all)]
+pub async fn admin_disable_user(
+    State(state): State<AppState>,
+    admin: RequirePermission<ManageUsers>,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = parse_email(email)?;
+    let result = disable_user(&state, &email).await;
+    audit_admin_action(
+        &admin.claims,
+        AdminAction::DisableUser,
+        Some(&email),
+        &result,
+    );
+    result.map(Json)
+}
+
+async fn disable_user(
+    state: &AppState,
+    email: &Email,
+) -> Result<AdminUserDetailsResponse, AuthAPIError> {
+    state
+        .user_store
+        .set_disabled(email, true)
+        .await
+        .map_err(user_store_error)?;
+    revoke_access(state, email).await?;
+    user_details(state, email).await
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn admin_enable_user(
+    State(state): State<AppState>,
+    admin: RequirePermission<ManageUsers>,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = parse_email(email)?;
+    let result = enable_user(&state, &email).await;
+    audit_admin_action(
+        &admin.claims,
+        AdminAction::EnableUser,
+        Some(&email),
+        &result,
+    );
+    result.map(Json)
+}
+
+async fn enable_user(
+    state: &AppState,
+    email: &Email,
+) -> Result<AdminUserDetailsResponse, AuthAPIError> {
+    state
+        .user_store
+        .set_disabled(email, false)
+        .await
+        .map_err(user_store_error)?;
+    user_details(state, email).await
+}
+
+// Turn 2FA on or off for the user's next logins
+#[tracing::instrument(skip_all)]
+pub async fn admin_set_user_2fa(
+    State(state): State<AppState>,
+    admin: RequirePermission<ManageUsers>,
+    Path(email): Path<String>,
+    JsonBody(request): JsonBody<SetUser2FARequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = parse_email(email)?;
+    let action = match request.required {
+        true => AdminAction::Require2fa,
+        false => AdminAction::Waive2fa,
+    };
+    let result = set_user_2fa(&state, &email, request.required).await;
+    audit_admin_action(&admin.claims, action, Some(&email), &result);
+    result.map(Json)
+}
+
+async fn set_user_2fa(
+    state: &AppState,
+    email: &Email,
+    required: bool,
+) -> Result<AdminUserDetailsResponse, AuthAPIError> {
+    state
+        .user_store
+        .set_requires_2fa(email, required)
+        .await
+        .map_err(user_store_error)?;
+    user_details(state, email).await
+}
+
+// Logs the user out everywhere and emails them a password reset token.
+// They can't log in again until they've used it.
+#[tracing::instrument(skip_all)]
+pub async fn admin_force_password_reset(
+    State(state): State<AppState>,
+    admin: RequirePermission<ManageUsers>,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = parse_email(email)?;
+    let result = force_password_reset(&state, &email).await;
+    audit_admin_action(
+        &admin.claims,
+        AdminAction::ForcePasswordReset,
+        Some(&email),
+        &result,
+    );
+    result.map(Json)
+}
+
+async fn force_password_reset(
+    state: &AppState,
+    email: &Email,
+) -> Result<AdminUserDetailsResponse, AuthAPIError> {
+    state
+        .user_store
+        .set_password_reset_required(email, true)
+        .await
+        .map_err(user_store_error)?;
+    revoke_access(state, email).await?;
+    send_password_reset_email(state, email).await?;
+    user_details(state, email).await
+}
+
+// Logs the user out everywhere and removes the account along with its 2FA
+// secrets, so that signing up again with the email starts from scratch
+#[tracing::instrument(skip_all)]
+pub async fn admin_delete_user(
+    State(state): State<AppState>,
+    admin: RequirePermission<ManageUsers>,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = parse_email(email)?;
+    let result = delete_user(&state, &email).await;
+    audit_admin_action(
+        &admin.claims,
+        AdminAction::DeleteUser,
+        Some(&email),
+        &result,
+    );
+    result.map(|()| StatusCode::NO_CONTENT)
+}
+
+async fn delete_user(state: &AppState, email: &Email) -> Result<(), AuthAPIError> {
+    // Fails with `AccountNotFound` before anything is revoked
+    state
+        .user_store
+        .get_user(email)
+        .await
+        .map_err(user_store_error)?;
+    revoke_access(state, email).await?;
+
+    state
+        .totp_store
+        .remove_totp(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(Report::new(e)))?;
+    state
+        .recovery_code_store
+        .replace_codes(email, Vec::new())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(Report::new(e)))?;
+    // A token emailed before the deletion mustn't work for whoever signs
+    // up with the same email next
+    state
+        .password_reset_token_store
+        .remove_user_tokens(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(Report::new(e)))?;
+    state
+        .email_verification_token_store
+        .remove_user_tokens(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(Report::new(e)))?;
+
+    state
+        .user_store
+        .delete_user(email)
+        .await
+        .map_err(user_store_error)
+}
+
+// End the user's sessions, including a login waiting on its 2FA code
+async fn revoke_access(state: &AppState, email: &Email) -> Result<(), AuthAPIError> {
+    log_out_everywhere(state, email).await?;
+    match state.two_fa_code_store.remove_two_fa_code(email).await {
+        Ok(()) => {}
+        Err(TwoFACodeStoreError::LoginAttemptIdNotFound | TwoFACodeStoreError::EmailNotFound) => {}
+        Err(e) => return Err(AuthAPIError::UnexpectedError(Report::new(e))),
+    }
+    record_revocation(RevocationReason::Admin);
+    Ok(())
+}
+
+async fn user_details(
+    state: &AppState,
+    email: &Email,
+) -> Result<AdminUserDetailsResponse, AuthAPIError> {
+    let user = state
+        .user_store
+        .get_user(email)
+        .await
+        .map_err(user_store_error)?;
+    let roles = state
+        .user_store
+        .get_roles(email)
+        .await
+        .map_err(user_store_error)?;
+
+    Ok(AdminUserDetailsResponse {
+        user: AdminUserResponse::from(&user),
+        roles: roles.roles,
+        permissions: roles.permissions,
+    })
+}
+
+fn parse_email(email: String) -> Result<Email, AuthAPIError> {
+    Email::parse(SecretString::new(email.into_boxed_str()))
+        .map_err(|e| AuthAPIError::InvalidInput(vec![FieldError::email("email", &e)]))
+}
+
+fn user_store_error(e: UserStoreError) -> AuthAPIError {
+    match e {
+        UserStoreError::UserNotFound => AuthAPIError::AccountNotFound,
+        e => AuthAPIError::UnexpectedError(Report::new(e)),
+    }
+}
+
+// Everything is taken as a string so that a bad value is reported like any
+// other invalid input, rather than as axum's plain text rejection
+#[derive(Debug, Default, Deserialize)]
+pub struct ListUsersParams {
+    pub email: Option<String>,
+    pub limit: Option<String>,
+    pub offset: Option<String>,
+}
+
+impl ListUsersParams {
+    fn parse(self) -> Result<UserQuery, AuthAPIError> {
+        let mut errors = Vec::new();
+
+        let limit = match self.limit.as_deref().map(str::parse::<u32>) {
+            None => DEFAULT_PAGE_SIZE,
+            Some(Ok(limit)) if (1..=MAX_PAGE_SIZE).contains(&limit) => limit,
+            Some(_) => {
+                errors.push(FieldError {
+                    field: "limit".to_owned(),
+                    code: ErrorCode::InvalidValue,
+                    message: format!("limit must be between 1 and {MAX_PAGE_SIZE}"),
+                });
+                DEFAULT_PAGE_SIZE
+            }
+        };
+        let offset = match self.offset.as_deref().map(str::parse::<u32>) {
+            None => 0,
+            Some(Ok(offset)) => offset,
+            Some(Err(_)) => {
+                errors.push(FieldError {
+                    field: "offset".to_owned(),
+                    code: ErrorCode::InvalidValue,
+                    message: "offset must be a non-negative integer".to_owned(),
+                });
+                0
+            }
+        };
+
+        if !errors.is_empty() {
+            return Err(AuthAPIError::InvalidInput(errors));
+        }
+
+        Ok(UserQuery {
+            email_contains: self.email.filter(|email| !email.is_empty()),
+            limit,
+            offset,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SetUser2FARequest {
+    pub required: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct AdminUsersResponse {
+    pub users: Vec<AdminUserResponse>,
+    // Matching users across all pages
+    pub total: u64,
+    pub limit: u32,
+    pub offset: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct AdminUserResponse {
+    pub email: String,
+    pub verified: bool,
+    #[serde(rename = "requires2FA")]
+    pub requires_2fa: bool,
+    pub disabled: bool,
+    #[serde(rename = "passwordResetRequired")]
+    pub password_reset_required: bool,
+}
+
+impl From<&User> for AdminUserResponse {
+    fn from(user: &User) -> Self {
+        Self {
+            email: user.email.as_ref().expose_secret().to_owned(),
+            verified: user.verified,
+            requires_2fa: user.requires_2fa,
+            disabled: user.disabled,
+            password_reset_required: user.password_reset_required,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct AdminUserDetailsResponse {
+    #[serde(flatten)]
+    pub user: AdminUserResponse,
+    pub roles: Vec<String>,
+    pub permissions: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(limit: Option<&str>, offset: Option<&str>) -> ListUsersParams {
+        ListUsersParams {
+            email: None,
+            limit: limit.map(str::to_owned),
+            offset: offset.map(str::to_owned),
+        }
+    }
+
+    #[test]
+    fn test_list_users_params() {
+        let query = ListUsersParams::default().parse().unwrap();
+        assert_eq!(query.limit, DEFAULT_PAGE_SIZE);
+        assert_eq!(query.offset, 0);
+        assert_eq!(query.email_contains, None);
+
+        let query = params(Some("100"), Some("200")).parse().unwrap();
+        assert_eq!((query.limit, query.offset), (100, 200));
+
+        for (limit, offset, fields) in [
+            (Some("0"), None, vec!["limit"]),
+            (Some("101"), None, vec!["limit"]),
+            (Some("ten"), Some("-1"), vec!["limit", "offset"]),
+        ] {
+            match params(limit, offset).parse() {
+                Err(AuthAPIError::InvalidInput(errors)) => {
+                    let invalid: Vec<_> = errors.iter().map(|e| e.field.as_str()).collect();
+                    assert_eq!(invalid, fields);
+                }
+                other => panic!("Expected invalid input, got {other:?}"),
+            }
+        }
+    }
+}
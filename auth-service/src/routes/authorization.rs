@@ -0,0 +1,180 @@
+use super::helpers::authenticated_claims;
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, UserStoreError, ADMIN_ROLE, MANAGE_USERS_PERMISSION},
+    utils::auth::Claims,
+};
+use axum::{extract::FromRequestParts, http::request::Parts};
+use axum_extra::extract::CookieJar;
+use std::marker::PhantomData;
+
+// A permission a route can require with `RequirePermission`
+pub trait Permission {
+    const NAME: &'static str;
+}
+
+pub struct ManageUsers;
+
+impl Permission for ManageUsers {
+    const NAME: &'static str = MANAGE_USERS_PERMISSION;
+}
+
+// Rejects the request unless it carries a valid JWT cookie whose claims
+// grant `P`, with `Forbidden` when only the permission is missing
+pub struct RequirePermission<P> {
+    pub claims: Claims,
+    permission: PhantomData<P>,
+}
+
+impl<P: Permission> FromRequestParts<AppState> for RequirePermission<P> {
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let jar = CookieJar::from_headers(&parts.headers);
+        let claims = authenticated_claims(&jar, state).await?;
+
+        if !claims.permissions.iter().any(|p| p == P::NAME) {
+            return Err(AuthAPIError::Forbidden);
+        }
+
+        Ok(Self {
+            claims,
+            permission: PhantomData,
+        })
+    }
+}
+
+// Run at startup so that a fresh deployment has someone who can manage users.
+// Bootstrap admins who haven't signed up or verified their email yet are
+// granted the role by `verify_email` once they do.
+pub async fn grant_bootstrap_admins(state: &AppState) -> Result<(), UserStoreError> {
+    for email in &state.bootstrap_admins {
+        match state.user_store.get_user(email).await {
+            Ok(user) if user.verified => {
+                state.user_store.add_role(email, ADMIN_ROLE).await?;
+                tracing::info!("Granted the admin role to a bootstrap admin");
+            }
+            Ok(_) | Err(UserStoreError::UserNotFound) => {
+                tracing::warn!("A bootstrap admin hasn't signed up and verified their email yet")
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app_state::UnverifiedLoginPolicy;
+    use crate::domain::{Email, HashedPassword, User, ADMIN_ROLE};
+    use crate::routes::helpers::update_cookie_jar;
+    use crate::utils::settings::settings;
+    use axum::http::{header::COOKIE, Request};
+    use secrecy::SecretString;
+
+    async fn state_with_user(roles: &[&str]) -> (AppState, Email) {
+        let state = AppState::in_memory(
+            UnverifiedLoginPolicy::Allow,
+            settings().login.throttle_policy(),
+        );
+        let email = Email::parse(SecretString::from("admin@example.com")).unwrap();
+        let password = HashedPassword::parse(SecretString::from("123DSDFdasd@@456789"))
+            .await
+            .unwrap();
+        state
+            .user_store
+            .add_user(User::new(email.clone(), password, false))
+            .await
+            .unwrap();
+        for role in roles {
+            state.user_store.add_role(&email, role).await.unwrap();
+        }
+        (state, email)
+    }
+
+    async fn require_manage_users(
+        state: &AppState,
+        cookie: Option<String>,
+    ) -> Result<RequirePermission<ManageUsers>, AuthAPIError> {
+        let mut request = Request::builder();
+        if let Some(cookie) = cookie {
+            request = request.header(COOKIE, cookie);
+        }
+        let (mut parts, _) = request.body(()).unwrap().into_parts();
+        RequirePermission::<ManageUsers>::from_request_parts(&mut parts, state).await
+    }
+
+    async fn login_cookie(state: &AppState, email: &Email) -> String {
+        let jar = update_cookie_jar(
+            CookieJar::new(),
+            email,
+            state,
+            None,
+            "127.0.0.1".parse().unwrap(),
+        )
+        .await
+        .unwrap();
+        let cookie = jar.get(&settings().cookies.jwt_name).unwrap();
+        format!("{}={}", cookie.name(), cookie.value())
+    }
+
+    #[tokio::test]
+    async fn test_require_permission() {
+        let (state, email) = state_with_user(&[ADMIN_ROLE]).await;
+        let cookie = login_cookie(&state, &email).await;
+
+        let guard = require_manage_users(&state, Some(cookie)).await.unwrap();
+        assert_eq!(guard.claims.roles, vec![ADMIN_ROLE.to_owned()]);
+    }
+
+    #[tokio::test]
+    async fn test_require_permission_rejects_missing_permission() {
+        let (state, email) = state_with_user(&[]).await;
+        let cookie = login_cookie(&state, &email).await;
+
+        assert!(matches!(
+            require_manage_users(&state, Some(cookie)).await,
+            Err(AuthAPIError::Forbidden)
+        ));
+        assert!(matches!(
+            require_manage_users(&state, None).await,
+            Err(AuthAPIError::MissingToken)
+        ));
+        assert!(matches!(
+            require_manage_users(
+                &state,
+                Some(format!("{}=invalid", settings().cookies.jwt_name))
+            )
+            .await,
+            Err(AuthAPIError::InvalidToken)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_grant_bootstrap_admins_once_verified() {
+        let (state, email) = state_with_user(&[]).await;
+        let unknown = Email::parse(SecretString::from("unknown@example.com")).unwrap();
+        let state = state.with_bootstrap_admins(vec![email.clone(), unknown]);
+
+        grant_bootstrap_admins(&state).await.unwrap();
+        assert!(state
+            .user_store
+            .get_roles(&email)
+            .await
+            .unwrap()
+            .roles
+            .is_empty());
+
+        state.user_store.mark_email_verified(&email).await.unwrap();
+        grant_bootstrap_admins(&state).await.unwrap();
+        grant_bootstrap_admins(&state).await.unwrap();
+        assert_eq!(
+            state.user_store.get_roles(&email).await.unwrap().roles,
+            vec![ADMIN_ROLE.to_owned()]
+        );
+    }
+}
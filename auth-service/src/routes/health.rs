@@ -0,0 +1,118 @@
+use crate::{app_state::AppState, utils::settings::settings};
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+// The process is up and serving requests, whatever state its dependencies
+// are in. Orchestrators restart the instance when this fails.
+#[tracing::instrument(skip_all)]
+pub async fn health_live() -> Json<HealthResponse> {
+    Json(HealthResponse {
+        status: HealthStatus::Up,
+        checks: BTreeMap::new(),
+    })
+}
+
+// Every dependency answered in time, so the instance can be sent traffic.
+// The checks run concurrently, each bounded by `health.timeout_milliseconds`.
+// Once shutting down this fails straight away, so no new traffic is routed
+// here while in-flight requests finish.
+#[tracing::instrument(skip_all)]
+pub async fn health_ready(State(state): State<AppState>) -> impl IntoResponse {
+    if state.shutdown.is_draining() {
+        let status = HealthStatus::Draining;
+        let checks = BTreeMap::new();
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(HealthResponse { status, checks }),
+        );
+    }
+
+    let timeout = settings().health.timeout();
+    let handles: Vec<_> = state
+        .health_checks
+        .iter()
+        .map(|check| {
+            let name = check.name();
+            let check = check.clone();
+            let handle = tokio::spawn(async move {
+                let start = Instant::now();
+                let result = tokio::time::timeout(timeout, check.check()).await;
+                let latency_ms = start.elapsed().as_millis() as u64;
+                let error = match result {
+                    Ok(Ok(())) => None,
+                    Ok(Err(e)) => {
+                        tracing::warn!("{} health check failed: {:?}", check.name(), e);
+                        Some(e.to_string())
+                    }
+                    Err(_) => {
+                        tracing::warn!("{} health check timed out", check.name());
+                        Some(format!("timed out after {}ms", timeout.as_millis()))
+                    }
+                };
+                DependencyHealth::new(latency_ms, error)
+            });
+            (name, handle)
+        })
+        .collect();
+
+    let mut checks = BTreeMap::new();
+    for (name, handle) in handles {
+        let health = handle.await.unwrap_or_else(|e| {
+            tracing::error!("{} health check panicked: {:?}", name, e);
+            DependencyHealth::new(0, Some("health check panicked".to_owned()))
+        });
+        checks.insert(name.to_owned(), health);
+    }
+
+    let status = if checks.values().all(|c| c.status == HealthStatus::Up) {
+        HealthStatus::Up
+    } else {
+        HealthStatus::Down
+    };
+    let status_code = match status {
+        HealthStatus::Up => StatusCode::OK,
+        HealthStatus::Down | HealthStatus::Draining => StatusCode::SERVICE_UNAVAILABLE,
+    };
+    (status_code, Json(HealthResponse { status, checks }))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Up,
+    Down,
+    // Shutting down, only reported by `/health/ready`
+    Draining,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HealthResponse {
+    pub status: HealthStatus,
+    // Keyed by dependency name, only reported by `/health/ready`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub checks: BTreeMap<String, DependencyHealth>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DependencyHealth {
+    pub status: HealthStatus,
+    pub latency_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl DependencyHealth {
+    fn new(latency_ms: u64, error: Option<String>) -> Self {
+        let status = match error {
+            None => HealthStatus::Up,
+            Some(_) => HealthStatus::Down,
+        };
+        Self {
+            status,
+            latency_ms,
+            error,
+        }
+    }
+}
@@ -1,13 +1,225 @@
 use crate::{
-    domain::{AuthAPIError, Email},
-    utils::auth::generate_auth_cookie,
+    app_state::AppState,
+    domain::{AuthAPIError, Email, LoginAttemptKey, Session},
+    utils::{
+        auth::{generate_auth_cookie, generate_refresh_cookie, validate_token, Claims},
+        settings::settings,
+    },
 };
+use axum::http::{header::USER_AGENT, HeaderMap};
 use axum_extra::extract::CookieJar;
+use chrono::Utc;
+use color_eyre::eyre::Report;
+use secrecy::SecretString;
+use std::future::Future;
+use std::net::IpAddr;
+use tracing::Instrument;
 
-pub fn update_cookie_jar(jar: CookieJar, email: &Email) -> Result<CookieJar, AuthAPIError> {
-    let auth_cookie = generate_auth_cookie(email).map_err(AuthAPIError::UnexpectedError)?;
+// Claims of the valid JWT cookie sent with the request
+pub async fn authenticated_claims(
+    jar: &CookieJar,
+    state: &AppState,
+) -> Result<Claims, AuthAPIError> {
+    let cookie = jar
+        .get(&settings().cookies.jwt_name)
+        .ok_or(AuthAPIError::MissingToken)?;
 
-    let updated_jar = jar.add(auth_cookie);
+    validate_token(
+        cookie.value(),
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+    )
+    .await
+    .map_err(|_| AuthAPIError::InvalidToken)
+}
+
+// Email of the user the JWT cookie was issued to
+pub async fn authenticated_email(jar: &CookieJar, state: &AppState) -> Result<Email, AuthAPIError> {
+    let claims = authenticated_claims(jar, state).await?;
+
+    Email::parse(SecretString::new(claims.sub.into_boxed_str()))
+        .map_err(|_| AuthAPIError::InvalidToken)
+}
+
+pub fn user_agent(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned)
+}
+
+// Start a new session and issue its auth cookie together with a refresh
+// token whose family shares the session's id
+pub async fn update_cookie_jar(
+    jar: CookieJar,
+    email: &Email,
+    state: &AppState,
+    user_agent: Option<String>,
+    ip: IpAddr,
+) -> Result<CookieJar, AuthAPIError> {
+    // Roles are read once per token, so changes apply from the next refresh
+    let roles = state
+        .user_store
+        .get_roles(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(Report::new(e)))?;
+
+    let now = Utc::now().timestamp();
+    let session = Session {
+        id: uuid::Uuid::new_v4().to_string(),
+        email: email.clone(),
+        user_agent,
+        ip: ip.to_string(),
+        created_at: now,
+        last_seen_at: now,
+    };
+    let session_id = session.id.clone();
+
+    state
+        .session_store
+        .add_session(session)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(Report::new(e)))?;
+
+    let auth_cookie =
+        generate_auth_cookie(email, &session_id, &roles).map_err(AuthAPIError::UnexpectedError)?;
+    let refresh_cookie =
+        generate_refresh_cookie(email, Some(session_id), state.refresh_token_store.clone())
+            .await
+            .map_err(AuthAPIError::UnexpectedError)?;
+
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
 
     Ok(updated_jar)
 }
+
+// End every session of the user and invalidate the tokens issued to them
+pub async fn log_out_everywhere(state: &AppState, email: &Email) -> Result<(), AuthAPIError> {
+    state
+        .banned_token_store
+        .ban_user_tokens(email, Utc::now().timestamp())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(Report::new(e)))?;
+
+    state
+        .refresh_token_store
+        .revoke_user_families(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(Report::new(e)))?;
+
+    state
+        .session_store
+        .remove_user_sessions(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(Report::new(e)))
+}
+
+// Reject the attempt with `TooManyAttempts` while either the account or the
+// client IP is throttled after earlier failures
+pub async fn check_login_throttle(
+    state: &AppState,
+    email: &Email,
+    ip: IpAddr,
+) -> Result<(), AuthAPIError> {
+    let now = Utc::now().timestamp();
+
+    let mut retry_after = None;
+    for key in [
+        LoginAttemptKey::Account(email.clone()),
+        LoginAttemptKey::Ip(ip),
+    ] {
+        let attempts = state
+            .login_attempt_store
+            .get_attempts(&key)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(Report::new(e)))?;
+        retry_after = retry_after.max(
+            state
+                .login_throttle_policy
+                .retry_after(&key, &attempts, now),
+        );
+    }
+
+    match retry_after {
+        Some(seconds) => Err(AuthAPIError::TooManyAttempts(seconds)),
+        None => Ok(()),
+    }
+}
+
+pub async fn record_login_failure(
+    state: &AppState,
+    email: &Email,
+    ip: IpAddr,
+) -> Result<(), AuthAPIError> {
+    for key in [
+        LoginAttemptKey::Account(email.clone()),
+        LoginAttemptKey::Ip(ip),
+    ] {
+        state
+            .login_attempt_store
+            .record_failure(&key, state.login_throttle_policy.lockout_seconds)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(Report::new(e)))?;
+    }
+    Ok(())
+}
+
+// Whether an email was sent shows in how long the response takes, so with
+// enumeration protection it's sent after responding and failures are only
+// logged
+pub async fn send_unobserved<F>(state: &AppState, send: F) -> Result<(), AuthAPIError>
+where
+    F: Future<Output = Result<(), AuthAPIError>> + Send + 'static,
+{
+    if !state.enumeration_protection {
+        return send.await;
+    }
+
+    state.shutdown.spawn(
+        async move {
+            if let Err(e) = send.await {
+                tracing::error!("Failed to send email: {:?}", e);
+            }
+        }
+        .in_current_span(),
+    );
+    Ok(())
+}
+
+// Only the account is cleared once the user is fully logged in. Clearing the
+// IP would let an attacker reset it by logging into an account of their own.
+pub async fn reset_login_failures(state: &AppState, email: &Email) -> Result<(), AuthAPIError> {
+    state
+        .login_attempt_store
+        .reset(&LoginAttemptKey::Account(email.clone()))
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(Report::new(e)))
+}
+
+// A token lifetime for emails, e.g. "15 minutes" or "24 hours"
+pub fn describe_ttl(seconds: u64) -> String {
+    let (count, unit) = match seconds {
+        s if s >= 3600 && s % 3600 == 0 => (s / 3600, "hour"),
+        s if s >= 60 && s % 60 == 0 => (s / 60, "minute"),
+        s => (s, "second"),
+    };
+    match count {
+        1 => format!("1 {unit}"),
+        count => format!("{count} {unit}s"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_ttl() {
+        assert_eq!(describe_ttl(900), "15 minutes");
+        assert_eq!(describe_ttl(86_400), "24 hours");
+        assert_eq!(describe_ttl(3600), "1 hour");
+        assert_eq!(describe_ttl(5400), "90 minutes");
+        assert_eq!(describe_ttl(90), "90 seconds");
+        assert_eq!(describe_ttl(1), "1 second");
+    }
+}
@@ -0,0 +1,10 @@
+use crate::utils::constants::JWT_KEYRING;
+use axum::Json;
+use jsonwebtoken::jwk::JwkSet;
+
+// Publish the public half of every key tokens may be signed with, so other
+// services can verify tokens locally instead of calling `/verify-token`.
+#[tracing::instrument(skip_all)]
+pub async fn jwks() -> Json<JwkSet> {
+    Json(JWT_KEYRING.jwks().clone())
+}
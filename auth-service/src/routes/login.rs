@@ -1,22 +1,35 @@
 use crate::{
-    app_state::AppState,
-    domain::data_store::TwoFACodeStore,
+    app_state::{AppState, UnverifiedLoginPolicy},
     domain::types::{LoginAttemptId, TwoFACode},
-    domain::{AuthAPIError, Email, EmailClient, HashedPassword, User, UserStore},
-    routes::helpers::update_cookie_jar,
+    domain::{AuthAPIError, Email, HashedPassword, TotpStoreError, User},
+    routes::helpers::{
+        check_login_throttle, record_login_failure, reset_login_failures, update_cookie_jar,
+        user_agent,
+    },
     utils::auth::generate_6_digit_code,
+    utils::client_ip::ClientIp,
+    utils::json::JsonBody,
+    utils::metrics::{record_login, LoginOutcome},
+};
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
 };
-use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 use axum_extra::extract::CookieJar;
 use color_eyre::eyre::Report;
 use secrecy::{ExposeSecret, SecretString};
 use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
 
 #[tracing::instrument(skip_all)]
 pub async fn login(
     State(state): State<AppState>,
+    ClientIp(client_ip): ClientIp,
+    headers: HeaderMap,
     jar: CookieJar,
-    Json(request): Json<LoginRequest>,
+    JsonBody(request): JsonBody<LoginRequest>,
 ) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
     let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
     let raw_password = request.password;
@@ -24,16 +37,58 @@ pub async fn login(
         .await
         .map_err(|_| AuthAPIError::InvalidCredentials)?;
 
-    let user_store = state.user_store.read().await;
+    check_login_throttle(&state, &email, client_ip)
+        .await
+        .inspect_err(|e| {
+            if matches!(e, AuthAPIError::TooManyAttempts(_)) {
+                record_login(LoginOutcome::Throttled)
+            }
+        })?;
+
+    let Ok(user) = state.user_store.get_user(&email).await else {
+        record_login(LoginOutcome::UserNotFound);
+        let error = if state.enumeration_protection {
+            // Exactly what a wrong password gets, after as long a wait
+            let _ = HashedPassword::verify_dummy(&raw_password).await;
+            AuthAPIError::IncorrectCredentials
+        } else {
+            AuthAPIError::UserNotFound
+        };
+        record_login_failure(&state, &email, client_ip).await?;
+        return Err(error);
+    };
 
-    let user = user_store
-        .get_user(&email)
+    // The password must be checked before either branch so that a 2FA code
+    // is never generated or emailed for a caller who doesn't know it.
+    if user
+        .password
+        .verify_raw_password(&raw_password)
         .await
-        .map_err(|_| AuthAPIError::UserNotFound)?;
+        .is_err()
+    {
+        record_login(LoginOutcome::IncorrectCredentials);
+        record_login_failure(&state, &email, client_ip).await?;
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+
+    if !user.verified && state.unverified_login_policy == UnverifiedLoginPolicy::Reject {
+        record_login(LoginOutcome::EmailNotVerified);
+        return Err(AuthAPIError::EmailNotVerified);
+    }
+
+    if user.disabled {
+        record_login(LoginOutcome::AccountDisabled);
+        return Err(AuthAPIError::AccountDisabled);
+    }
+
+    if user.password_reset_required {
+        record_login(LoginOutcome::PasswordResetRequired);
+        return Err(AuthAPIError::PasswordResetRequired);
+    }
 
     match user.requires_2fa {
         true => handle_2fa(&user, &state, jar).await,
-        false => handle_no_2fa(&user, &raw_password, jar).await,
+        false => handle_no_2fa(&user, &state, jar, &headers, client_ip).await,
     }
 }
 
@@ -53,8 +108,8 @@ async fn handle_2fa(
     ))
     .map_err(|_| AuthAPIError::InvalidCredentials)?;
 
-    let mut two_fa_code_store = state.two_fa_code_store.write().await;
-    two_fa_code_store
+    state
+        .two_fa_code_store
         .add_two_fa_code(
             user.email.clone(),
             login_attempt_id.clone(),
@@ -63,16 +118,27 @@ async fn handle_2fa(
         .await
         .map_err(|e| AuthAPIError::UnexpectedError(Report::new(e)))?;
 
-    let email_client = state.email_client.read().await;
-    email_client
-        .send_email(
-            &user.email,
-            "Your 2FA Code",
-            &format!("Your 2FA Code is {}.", two_fa_code.as_ref().expose_secret()),
-        )
-        .await
-        .map_err(|e| AuthAPIError::UnexpectedError(Report::msg(e)))?;
+    // Users with an authenticator app enter its code instead, so they don't
+    // depend on email delivery to log in.
+    let totp_enabled = match state.totp_store.get_totp(&user.email).await {
+        Ok(record) => record.confirmed,
+        Err(TotpStoreError::SecretNotFound) => false,
+        Err(e) => return Err(AuthAPIError::UnexpectedError(Report::new(e))),
+    };
+
+    if !totp_enabled {
+        state
+            .email_client
+            .send_email(
+                &user.email,
+                "Your 2FA Code",
+                &format!("Your 2FA Code is {}.", two_fa_code.as_ref().expose_secret()),
+            )
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(Report::msg(e)))?;
+    }
 
+    record_login(LoginOutcome::TwoFactorRequired);
     Ok((
         jar,
         (
@@ -89,15 +155,16 @@ async fn handle_2fa(
 #[tracing::instrument(skip_all)]
 async fn handle_no_2fa(
     user: &User,
-    password: &SecretString,
+    state: &AppState,
     jar: CookieJar,
+    headers: &HeaderMap,
+    client_ip: IpAddr,
 ) -> Result<(CookieJar, (StatusCode, Json<LoginResponse>)), AuthAPIError> {
-    if user.password.verify_raw_password(password).await.is_err() {
-        return Err(AuthAPIError::IncorrectCredentials);
-    }
-
-    let updated_jar = update_cookie_jar(jar, &user.email)?;
+    reset_login_failures(state, &user.email).await?;
+    let updated_jar =
+        update_cookie_jar(jar, &user.email, state, user_agent(headers), client_ip).await?;
 
+    record_login(LoginOutcome::Success);
     Ok((
         updated_jar,
         (StatusCode::OK, Json(LoginResponse::RegularAuth)),
@@ -1,8 +1,11 @@
 use crate::{
     app_state::AppState,
-    domain::data_store::BannedTokenStore,
-    domain::AuthAPIError,
-    utils::{auth::validate_token, constants::JWT_COOKIE_NAME},
+    domain::{AuthAPIError, RefreshTokenStoreError, SessionStoreError},
+    utils::{
+        auth::{remove_auth_cookies, validate_token},
+        metrics::LOGOUTS,
+        settings::settings,
+    },
 };
 use axum::{extract::State, http::StatusCode, response::IntoResponse};
 use axum_extra::extract::CookieJar;
@@ -16,21 +19,52 @@ pub async fn logout(
 ) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
     // Retrieve JWT cookie from the `CookieJar`
     // Return AuthAPIError::MissingToken is the cookie is not found
-    let cookie = jar.get(JWT_COOKIE_NAME).ok_or(AuthAPIError::MissingToken)?;
+    let cookie = jar
+        .get(&settings().cookies.jwt_name)
+        .ok_or(AuthAPIError::MissingToken)?;
 
     let token = cookie.value().to_owned();
 
-    validate_token(&token, state.banned_token_store.clone())
-        .await
-        .map_err(|_| AuthAPIError::InvalidToken)?;
+    let claims = validate_token(
+        &token,
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+    )
+    .await
+    .map_err(|_| AuthAPIError::InvalidToken)?;
 
-    let mut banned_token_store = state.banned_token_store.write().await;
-    banned_token_store
+    state
+        .banned_token_store
         .add_token(SecretString::new(token.into_boxed_str()))
         .await
         .map_err(|e| AuthAPIError::UnexpectedError(Report::new(e)))?;
 
-    let jar = jar.remove(JWT_COOKIE_NAME);
+    match state.session_store.remove_session(&claims.jti).await {
+        Ok(()) | Err(SessionStoreError::SessionNotFound) => {}
+        Err(e) => return Err(AuthAPIError::UnexpectedError(Report::new(e))),
+    }
+
+    // Revoke the refresh token family so the session can't be silently resumed
+    if let Some(refresh_cookie) = jar.get(&settings().cookies.refresh_name) {
+        let refresh_token = SecretString::new(refresh_cookie.value().to_owned().into_boxed_str());
+
+        match state
+            .refresh_token_store
+            .get_refresh_token(&refresh_token)
+            .await
+        {
+            Ok(record) => state
+                .refresh_token_store
+                .revoke_family(&record.family_id)
+                .await
+                .map_err(|e| AuthAPIError::UnexpectedError(Report::new(e)))?,
+            Err(RefreshTokenStoreError::TokenNotFound) => {}
+            Err(e) => return Err(AuthAPIError::UnexpectedError(Report::new(e))),
+        }
+    }
+
+    let jar = remove_auth_cookies(jar);
+    LOGOUTS.inc();
 
     Ok((jar, StatusCode::OK))
 }
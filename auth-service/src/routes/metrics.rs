@@ -0,0 +1,10 @@
+use crate::{domain::AuthAPIError, utils::metrics::render};
+use axum::{http::header, response::IntoResponse};
+use color_eyre::eyre::Report;
+
+// Prometheus scrape endpoint
+#[tracing::instrument(skip_all)]
+pub async fn metrics() -> Result<impl IntoResponse, AuthAPIError> {
+    let body = render().map_err(|e| AuthAPIError::UnexpectedError(Report::new(e)))?;
+    Ok(([(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)], body))
+}
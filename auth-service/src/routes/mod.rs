@@ -1,42 +1,105 @@
+mod admin;
+mod authorization;
+mod health;
 mod helpers;
+mod jwks;
 mod login;
 mod logout;
+mod metrics;
+mod password_reset;
+mod recovery_codes;
+mod refresh;
+mod sessions;
 mod signup;
+mod totp;
 mod verify_2fa;
+mod verify_email;
 mod verify_token;
 
+pub use admin::*;
+pub use authorization::*;
+pub use health::*;
+pub use jwks::*;
 pub use login::*;
 pub use logout::*;
+pub use metrics::*;
+pub use password_reset::*;
+pub use recovery_codes::*;
+pub use refresh::*;
+pub use sessions::*;
 pub use signup::*;
+pub use totp::*;
 pub use verify_2fa::*;
+pub use verify_email::*;
 pub use verify_token::*;
 
 use crate::prelude::AppState;
+use crate::utils::constants::MAX_REQUEST_BODY_BYTES;
+use crate::utils::https::{redirect_to_https, secure_cookies, HttpsPolicy};
+use crate::utils::settings::{settings, ShutdownSettings, TlsSettings};
+use crate::utils::shutdown::Shutdown;
+use crate::utils::tls::{
+    server_config, spawn_reload_task, ReloadingCertResolver, ServerIo, ServerListener, TlsListener,
+};
 use crate::utils::tracing::{make_span_with_request_id, on_request, on_response};
-use axum::{routing::post, serve::Serve, Router};
+use axum::{
+    extract::{connect_info::IntoMakeServiceWithConnectInfo, ConnectInfo, DefaultBodyLimit},
+    http::HeaderValue,
+    middleware::{self, AddExtension},
+    routing::{delete, get, post},
+    serve::{ListenerExt, Serve, TapIo},
+    Router,
+};
 use reqwest::Method;
 use std::error::Error;
+use std::future::{Future, IntoFuture};
+use std::net::SocketAddr;
+use std::sync::Arc;
 use tokio::net::TcpListener;
 use tower_http::{cors::CorsLayer, services::ServeDir, trace::TraceLayer};
 
+// axum only provides `ConnectInfo<SocketAddr>` for a `TcpListener` or a
+// listener wrapped by `tap_io`, so `ServerListener` is tapped with a no-op
+type Tap = fn(&mut ServerIo);
+type TappedListener = TapIo<ServerListener, Tap>;
+
 // This struct encapsulates our application-related logic.
 pub struct Application {
-    server: Serve<TcpListener, Router, Router>,
+    server: Serve<
+        TappedListener,
+        IntoMakeServiceWithConnectInfo<Router, SocketAddr>,
+        AddExtension<Router, ConnectInfo<SocketAddr>>,
+    >,
+    // Plain HTTP listener redirecting to HTTPS, see `TlsSettings`
+    redirect: Option<Serve<TcpListener, Router, Router>>,
     // address is exposed as a public field
     // so we have access to it in tests.
     pub address: String,
+    pub redirect_address: Option<String>,
+    shutdown: Shutdown,
 }
 
 impl Application {
     pub async fn build(app_state: AppState, address: &str) -> Result<Self, Box<dyn Error>> {
-        let allowed_origins = [
-            "http://localhost:8000".parse()?,
-            "http://157.230.191.28:8000".parse()?,
-        ];
+        Self::build_with_tls(app_state, address, &settings().tls).await
+    }
+
+    // Serves HTTPS when `tls` has a certificate, plain HTTP otherwise
+    pub async fn build_with_tls(
+        app_state: AppState,
+        address: &str,
+        tls: &TlsSettings,
+    ) -> Result<Self, Box<dyn Error>> {
+        let allowed_origins = settings()
+            .application
+            .allowed_origins
+            .iter()
+            .map(|origin| origin.parse())
+            .collect::<Result<Vec<HeaderValue>, _>>()?;
 
         let cors = CorsLayer::new()
-            // Allow GET and POST requests
-            .allow_methods([Method::GET, Method::POST])
+            // Allow GET, POST and DELETE requests
+            .allow_methods([Method::GET, Method::POST, Method::DELETE])
             // Allow cookies to be included in requests
             .allow_credentials(true)
             .allow_origin(allowed_origins);
@@ -44,14 +107,47 @@ impl Application {
         // Also, remove the `hello` route.
         // We don't need it at this point!
         let asset_dir = ServeDir::new("assets");
+        let shutdown = app_state.shutdown.clone();
         let router = Router::new()
             .fallback_service(asset_dir)
             .route("/signup", post(signup))
             .route("/login", post(login))
             .route("/logout", post(logout))
+            .route("/refresh", post(refresh))
+            .route("/password-reset/request", post(request_password_reset))
+            .route("/password-reset/confirm", post(confirm_password_reset))
+            .route("/verify-email", post(verify_email))
+            .route("/verify-email/resend", post(resend_verification_email))
+            .route("/2fa/totp/enroll", post(enroll_totp))
+            .route("/2fa/totp/confirm", post(confirm_totp))
+            .route("/2fa/recovery-codes", post(regenerate_recovery_codes))
+            .route("/sessions", get(list_sessions).delete(revoke_all_sessions))
+            .route("/sessions/{id}", delete(revoke_session))
             .route("/verify-2fa", post(verify_2fa))
             .route("/verify-token", post(verify_token))
+            .route("/admin/users", get(admin_list_users))
+            .route(
+                "/admin/users/{email}",
+                get(admin_get_user).delete(admin_delete_user),
+            )
+            .route("/admin/users/{email}/disable", post(admin_disable_user))
+            .route("/admin/users/{email}/enable", post(admin_enable_user))
+            .route("/admin/users/{email}/2fa", post(admin_set_user_2fa))
+            .route(
+                "/admin/users/{email}/password-reset",
+                post(admin_force_password_reset),
+            )
+            .route("/.well-known/jwks.json", get(jwks))
+            .route("/health/live", get(health_live))
+            .route("/health/ready", get(health_ready))
+            .route("/metrics", get(metrics))
             .with_state(app_state)
+            // `JsonBody` answers `413` beyond this
+            .layer(DefaultBodyLimit::max(MAX_REQUEST_BODY_BYTES))
+            .layer(middleware::from_fn_with_state(
+                HttpsPolicy::new(tls.enabled(), &settings().application.trusted_proxies),
+                secure_cookies,
+            ))
             .layer(cors)
             .layer(
                 // New!
@@ -65,15 +161,110 @@ impl Application {
             );
 
         let listener = tokio::net::TcpListener::bind(address).await?;
-        let address = listener.local_addr()?.to_string();
-        let server = axum::serve(listener, router);
+        let local_addr = listener.local_addr()?;
+        let address = local_addr.to_string();
+        let listener = match tls.certificate() {
+            Some((cert_path, key_path)) => {
+                let resolver = Arc::new(ReloadingCertResolver::new(cert_path, key_path)?);
+                spawn_reload_task(resolver.clone(), tls.reload_interval(), &shutdown);
+                ServerListener::Tls(TlsListener::new(listener, server_config(resolver)?)?)
+            }
+            None => ServerListener::Plain(listener),
+        };
+        // Login throttling needs the client's address
+        let server = axum::serve(
+            listener.tap_io((|_| {}) as Tap),
+            router.into_make_service_with_connect_info::<SocketAddr>(),
+        );
+
+        let (redirect, redirect_address) = match &tls.redirect_address {
+            Some(redirect_address) if tls.enabled() => {
+                let listener = tokio::net::TcpListener::bind(redirect_address).await?;
+                let redirect_address = listener.local_addr()?.to_string();
+                let router = Router::new()
+                    .fallback(redirect_to_https)
+                    .with_state(local_addr.port());
+                (Some(axum::serve(listener, router)), Some(redirect_address))
+            }
+            _ => (None, None),
+        };
 
         // Create a new Application instance and return it
-        Ok(Self { server, address })
+        Ok(Self {
+            server,
+            redirect,
+            address,
+            redirect_address,
+            shutdown,
+        })
     }
 
+    // Serves until `AppState::shutdown` is stopped, then waits for the open
+    // connections to finish their requests
     pub async fn run(self) -> Result<(), std::io::Error> {
         tracing::info!("listening on {}", &self.address);
-        self.server.await
+        let shutdown = self.shutdown.clone();
+        let server = self
+            .server
+            .with_graceful_shutdown(async move { shutdown.stopped().await });
+        let Some(redirect) = self.redirect else {
+            return server.await;
+        };
+
+        if let Some(redirect_address) = &self.redirect_address {
+            tracing::info!("redirecting HTTP on {} to HTTPS", redirect_address);
+        }
+        let shutdown = self.shutdown;
+        let redirect = redirect.with_graceful_shutdown(async move { shutdown.stopped().await });
+        tokio::try_join!(server.into_future(), redirect.into_future())?;
+        Ok(())
+    }
+
+    // Serves until `signal` resolves, then shuts down in stages: readiness
+    // fails for `readiness_delay` while requests are still served, the
+    // listener closes, and in-flight requests and background tasks get up to
+    // `drain_timeout` to finish. Anything still running after that is dropped.
+    pub async fn run_until_stopped(
+        self,
+        signal: impl Future<Output = ()>,
+        settings: &ShutdownSettings,
+    ) -> Result<(), std::io::Error> {
+        let shutdown = self.shutdown.clone();
+        let mut server = tokio::spawn(self.run());
+
+        tokio::select! {
+            result = &mut server => return result.map_err(std::io::Error::other)?,
+            _ = signal => {}
+        }
+
+        tracing::info!(
+            "Shutting down, failing readiness for {}s",
+            settings.readiness_delay_seconds
+        );
+        shutdown.start_draining();
+        tokio::time::sleep(settings.readiness_delay()).await;
+
+        tracing::info!(
+            "Closing the listener, draining for up to {}s",
+            settings.drain_timeout_seconds
+        );
+        shutdown.stop();
+        let deadline = tokio::time::Instant::now() + settings.drain_timeout();
+        match tokio::time::timeout_at(deadline, &mut server).await {
+            Ok(result) => result.map_err(std::io::Error::other)??,
+            Err(_) => {
+                tracing::warn!("Drain timeout elapsed, dropping the remaining connections");
+                server.abort();
+            }
+        }
+        if tokio::time::timeout_at(deadline, shutdown.wait_for_tasks())
+            .await
+            .is_err()
+        {
+            tracing::warn!("Drain timeout elapsed, dropping the remaining background tasks");
+        }
+
+        tracing::info!("Shutdown complete");
+        Ok(())
     }
 }
@@ -0,0 +1,138 @@
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuthAPIError, Email, FieldError, HashedPassword, Password, PasswordResetToken,
+        PasswordResetTokenStoreError,
+    },
+    routes::helpers::{describe_ttl, log_out_everywhere, send_unobserved},
+    utils::{
+        auth::generate_random_token,
+        json::JsonBody,
+        metrics::{record_revocation, RevocationReason},
+        settings::settings,
+    },
+};
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use color_eyre::eyre::Report;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+
+#[tracing::instrument(skip_all)]
+pub async fn request_password_reset(
+    State(state): State<AppState>,
+    JsonBody(request): JsonBody<PasswordResetRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    // The response is the same whether or not the account exists, so this
+    // route can't be used to find out which emails are registered.
+    let response = (
+        StatusCode::OK,
+        Json(PasswordResetResponse {
+            message: "If the account exists, a password reset email has been sent".to_owned(),
+        }),
+    );
+
+    if state.user_store.get_user(&email).await.is_err() {
+        return Ok(response);
+    }
+
+    let send = {
+        let state = state.clone();
+        async move { send_password_reset_email(&state, &email).await }
+    };
+    send_unobserved(&state, send).await?;
+
+    Ok(response)
+}
+
+pub(super) async fn send_password_reset_email(
+    state: &AppState,
+    email: &Email,
+) -> Result<(), AuthAPIError> {
+    let token =
+        PasswordResetToken::parse(SecretString::new(generate_random_token().into_boxed_str()))
+            .map_err(|e| AuthAPIError::UnexpectedError(Report::new(e)))?;
+
+    state
+        .password_reset_token_store
+        .add_token(token.clone(), email.clone())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(Report::new(e)))?;
+
+    state
+        .email_client
+        .send_email(
+            email,
+            "Reset your password",
+            &format!(
+                "Your password reset token is {}. It expires in {}.",
+                token.as_ref().expose_secret(),
+                describe_ttl(settings().tokens.password_reset_token_ttl_seconds)
+            ),
+        )
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(Report::msg(e)))
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn confirm_password_reset(
+    State(state): State<AppState>,
+    JsonBody(request): JsonBody<PasswordResetConfirmRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let token = PasswordResetToken::parse(request.token).map_err(|_| AuthAPIError::MissingToken)?;
+
+    // Validate the new password before redeeming the token so a rejected
+    // password doesn't use it up.
+    if let Err(e) = Password::parse(request.new_password.expose_secret()) {
+        let error = FieldError::password("newPassword", &e);
+        return Err(AuthAPIError::InvalidInput(vec![error]));
+    }
+    let password = HashedPassword::parse(request.new_password)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    let email = state
+        .password_reset_token_store
+        .take_token(&token)
+        .await
+        .map_err(|e| match e {
+            PasswordResetTokenStoreError::TokenNotFound => AuthAPIError::InvalidToken,
+            PasswordResetTokenStoreError::UnexpectedError(r) => AuthAPIError::UnexpectedError(r),
+        })?;
+
+    state
+        .user_store
+        .update_password(&email, password)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    // Log the user out everywhere: existing JWTs and refresh tokens were
+    // obtained with the old password.
+    log_out_everywhere(&state, &email).await?;
+    record_revocation(RevocationReason::PasswordReset);
+
+    Ok((
+        StatusCode::OK,
+        Json(PasswordResetResponse {
+            message: "Password updated successfully".to_owned(),
+        }),
+    ))
+}
+
+#[derive(Deserialize)]
+pub struct PasswordResetRequest {
+    pub email: SecretString,
+}
+
+#[derive(Deserialize)]
+pub struct PasswordResetConfirmRequest {
+    pub token: SecretString,
+    #[serde(rename = "newPassword")]
+    pub new_password: SecretString,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct PasswordResetResponse {
+    pub message: String,
+}
@@ -0,0 +1,107 @@
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email, HashedRecoveryCode, RecoveryCode, RecoveryCodeStoreError},
+    routes::helpers::authenticated_email,
+    utils::auth::generate_recovery_codes,
+};
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum_extra::extract::CookieJar;
+use color_eyre::eyre::Report;
+use serde::{Deserialize, Serialize};
+
+// Replace the logged in user's recovery codes with a fresh set
+#[tracing::instrument(skip_all)]
+pub async fn regenerate_recovery_codes(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = authenticated_email(&jar, &state).await?;
+
+    let recovery_codes = issue_recovery_codes(&state, &email).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(RecoveryCodesResponse { recovery_codes }),
+    ))
+}
+
+// Generate and store a new set of recovery codes for `email`, returning them
+// formatted for display. This is the only time the plain codes are available.
+#[tracing::instrument(skip_all)]
+pub(crate) async fn issue_recovery_codes(
+    state: &AppState,
+    email: &Email,
+) -> Result<Vec<String>, AuthAPIError> {
+    let codes = generate_recovery_codes();
+    let hashed_codes = hash_recovery_codes(&codes).await?;
+
+    state
+        .recovery_code_store
+        .replace_codes(email, hashed_codes)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(Report::new(e)))?;
+
+    Ok(codes.iter().map(RecoveryCode::formatted).collect())
+}
+
+// Codes that look like and take as long as `issue_recovery_codes`, but are
+// never stored, for responses that mustn't give away that nothing was issued
+#[tracing::instrument(skip_all)]
+pub(crate) async fn decoy_recovery_codes() -> Result<Vec<String>, AuthAPIError> {
+    let codes = generate_recovery_codes();
+    hash_recovery_codes(&codes).await?;
+
+    Ok(codes.iter().map(RecoveryCode::formatted).collect())
+}
+
+async fn hash_recovery_codes(
+    codes: &[RecoveryCode],
+) -> Result<Vec<HashedRecoveryCode>, AuthAPIError> {
+    let mut hashed_codes = Vec::with_capacity(codes.len());
+    for code in codes {
+        hashed_codes.push(
+            HashedRecoveryCode::parse(code)
+                .await
+                .map_err(AuthAPIError::UnexpectedError)?,
+        );
+    }
+    Ok(hashed_codes)
+}
+
+// Check `code` against the user's unused recovery codes, burning it on success
+#[tracing::instrument(skip_all)]
+pub(crate) async fn redeem_recovery_code(
+    state: &AppState,
+    email: &Email,
+    code: &RecoveryCode,
+) -> Result<bool, AuthAPIError> {
+    let hashed_codes = state
+        .recovery_code_store
+        .get_codes(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(Report::new(e)))?;
+
+    for hashed_code in hashed_codes {
+        if hashed_code.verify(code).await.is_err() {
+            continue;
+        }
+
+        return match state
+            .recovery_code_store
+            .remove_code(email, &hashed_code)
+            .await
+        {
+            Ok(()) => Ok(true),
+            Err(RecoveryCodeStoreError::CodeNotFound) => Ok(false),
+            Err(e) => Err(AuthAPIError::UnexpectedError(Report::new(e))),
+        };
+    }
+
+    Ok(false)
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct RecoveryCodesResponse {
+    #[serde(rename = "recoveryCodes")]
+    pub recovery_codes: Vec<String>,
+}
@@ -0,0 +1,112 @@
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, RefreshTokenStoreError, SessionStoreError, UserStoreError},
+    utils::{
+        auth::{generate_auth_cookie, generate_refresh_cookie},
+        metrics::{record_revocation, RevocationReason},
+        settings::settings,
+    },
+};
+use axum::{extract::State, http::StatusCode, response::IntoResponse};
+use axum_extra::extract::CookieJar;
+use chrono::Utc;
+use color_eyre::eyre::Report;
+use secrecy::SecretString;
+
+#[tracing::instrument(skip_all)]
+pub async fn refresh(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
+    let cookie = jar
+        .get(&settings().cookies.refresh_name)
+        .ok_or(AuthAPIError::MissingToken)?;
+
+    let token = SecretString::new(cookie.value().to_owned().into_boxed_str());
+
+    let record = state
+        .refresh_token_store
+        .get_refresh_token(&token)
+        .await
+        .map_err(|e| match e {
+            RefreshTokenStoreError::TokenNotFound => AuthAPIError::InvalidToken,
+            e => AuthAPIError::UnexpectedError(Report::new(e)),
+        })?;
+
+    let is_revoked = state
+        .refresh_token_store
+        .is_family_revoked(&record.family_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(Report::new(e)))?;
+
+    if is_revoked {
+        return Err(AuthAPIError::InvalidToken);
+    }
+
+    // A refresh token that has already been rotated is being replayed, so
+    // the whole family is treated as compromised.
+    if record.used {
+        return Err(revoke_reused_family(&state, &record.family_id).await);
+    }
+
+    // The family belongs to a session that may have been revoked since
+    state
+        .session_store
+        .touch_session(&record.family_id, Utc::now().timestamp())
+        .await
+        .map_err(|e| match e {
+            SessionStoreError::SessionNotFound => AuthAPIError::InvalidToken,
+            SessionStoreError::UnexpectedError(r) => AuthAPIError::UnexpectedError(r),
+        })?;
+
+    // Marking the token used is atomic, so when the same token is redeemed
+    // twice concurrently only one request gets through and the other counts
+    // as a replay.
+    match state
+        .refresh_token_store
+        .mark_refresh_token_used(&token)
+        .await
+    {
+        Ok(()) => {}
+        Err(RefreshTokenStoreError::TokenAlreadyUsed) => {
+            return Err(revoke_reused_family(&state, &record.family_id).await)
+        }
+        Err(RefreshTokenStoreError::TokenNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(Report::new(e))),
+    }
+
+    // Picks up roles granted or removed since the last token was issued
+    let roles = state
+        .user_store
+        .get_roles(&record.email)
+        .await
+        .map_err(|e| match e {
+            UserStoreError::UserNotFound => AuthAPIError::InvalidToken,
+            e => AuthAPIError::UnexpectedError(Report::new(e)),
+        })?;
+
+    let auth_cookie = generate_auth_cookie(&record.email, &record.family_id, &roles)
+        .map_err(AuthAPIError::UnexpectedError)?;
+    let refresh_cookie = generate_refresh_cookie(
+        &record.email,
+        Some(record.family_id),
+        state.refresh_token_store.clone(),
+    )
+    .await
+    .map_err(AuthAPIError::UnexpectedError)?;
+
+    let jar = jar.add(auth_cookie).add(refresh_cookie);
+
+    Ok((jar, StatusCode::OK))
+}
+
+async fn revoke_reused_family(state: &AppState, family_id: &str) -> AuthAPIError {
+    tracing::warn!("refresh token reuse detected, revoking token family");
+    match state.refresh_token_store.revoke_family(family_id).await {
+        Ok(()) => {
+            record_revocation(RevocationReason::RefreshTokenReuse);
+            AuthAPIError::InvalidToken
+        }
+        Err(e) => AuthAPIError::UnexpectedError(Report::new(e)),
+    }
+}
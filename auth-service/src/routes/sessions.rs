@@ -0,0 +1,146 @@
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email, SessionStoreError},
+    routes::helpers::authenticated_claims,
+    utils::{
+        auth::remove_auth_cookies,
+        metrics::{record_revocation, RevocationReason},
+    },
+};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::CookieJar;
+use color_eyre::eyre::Report;
+use secrecy::SecretString;
+use serde::{Deserialize, Serialize};
+
+// List the logged in user's sessions, most recently used first
+#[tracing::instrument(skip_all)]
+pub async fn list_sessions(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let claims = authenticated_claims(&jar, &state).await?;
+    let email = claims_email(&claims.sub)?;
+
+    let mut sessions = state
+        .session_store
+        .get_user_sessions(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(Report::new(e)))?;
+    sessions.sort_by_key(|session| std::cmp::Reverse(session.last_seen_at));
+
+    let sessions = sessions
+        .into_iter()
+        .map(|session| SessionResponse {
+            current: session.id == claims.jti,
+            id: session.id,
+            user_agent: session.user_agent,
+            ip: session.ip,
+            created_at: session.created_at,
+            last_seen_at: session.last_seen_at,
+        })
+        .collect();
+
+    Ok((StatusCode::OK, Json(SessionsResponse { sessions })))
+}
+
+// Log out a single session. Revoking the current one also clears its cookies.
+#[tracing::instrument(skip_all)]
+pub async fn revoke_session(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(id): Path<String>,
+) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
+    let claims = authenticated_claims(&jar, &state).await?;
+    let email = claims_email(&claims.sub)?;
+
+    // Other users' sessions are reported as missing so their ids can't be probed
+    let session = state
+        .session_store
+        .get_session(&id)
+        .await
+        .map_err(|e| match e {
+            SessionStoreError::SessionNotFound => AuthAPIError::SessionNotFound,
+            SessionStoreError::UnexpectedError(r) => AuthAPIError::UnexpectedError(r),
+        })?;
+    if session.email != email {
+        return Err(AuthAPIError::SessionNotFound);
+    }
+
+    state
+        .session_store
+        .remove_session(&id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(Report::new(e)))?;
+
+    state
+        .refresh_token_store
+        .revoke_family(&id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(Report::new(e)))?;
+    record_revocation(RevocationReason::Session);
+
+    let jar = if id == claims.jti {
+        remove_auth_cookies(jar)
+    } else {
+        jar
+    };
+
+    Ok((jar, StatusCode::OK))
+}
+
+// Log out everywhere, including the current session
+#[tracing::instrument(skip_all)]
+pub async fn revoke_all_sessions(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
+    let claims = authenticated_claims(&jar, &state).await?;
+    let email = claims_email(&claims.sub)?;
+
+    state
+        .session_store
+        .remove_user_sessions(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(Report::new(e)))?;
+
+    state
+        .refresh_token_store
+        .revoke_user_families(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(Report::new(e)))?;
+    record_revocation(RevocationReason::AllSessions);
+
+    let jar = remove_auth_cookies(jar);
+
+    Ok((jar, StatusCode::OK))
+}
+
+fn claims_email(sub: &str) -> Result<Email, AuthAPIError> {
+    Email::parse(SecretString::new(sub.to_owned().into_boxed_str()))
+        .map_err(|_| AuthAPIError::InvalidToken)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionsResponse {
+    pub sessions: Vec<SessionResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionResponse {
+    pub id: String,
+    #[serde(rename = "userAgent")]
+    pub user_agent: Option<String>,
+    pub ip: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+    #[serde(rename = "lastSeenAt")]
+    pub last_seen_at: i64,
+    // Whether this is the session making the request
+    pub current: bool,
+}
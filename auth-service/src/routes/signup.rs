@@ -1,39 +1,110 @@
-use crate::domain::{Email, HashedPassword};
+use crate::domain::{Email, FieldError, HashedPassword, Password, UserStoreError};
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
-use secrecy::SecretString;
+use color_eyre::eyre::Report;
+use secrecy::{ExposeSecret, SecretString};
 use serde::{Deserialize, Serialize};
 
-use crate::{app_state::AppState, domain::User, domain::UserStore, AuthAPIError};
+use super::{
+    helpers::send_unobserved,
+    recovery_codes::{decoy_recovery_codes, issue_recovery_codes},
+    send_verification_email,
+};
+use crate::{
+    app_state::AppState,
+    domain::User,
+    utils::{json::JsonBody, metrics::SIGNUPS},
+    AuthAPIError,
+};
 
 #[tracing::instrument(name = "Signup", skip_all)]
 pub async fn signup(
     State(state): State<AppState>,
-    Json(request): Json<SignupRequest>,
+    JsonBody(request): JsonBody<SignupRequest>,
 ) -> Result<impl IntoResponse, AuthAPIError> {
-    let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    // Both fields are checked before failing, so the UI can show every
+    // broken rule at once
+    let email = Email::parse(request.email);
+    let password = Password::parse(request.password.expose_secret());
+    let email = match (email, password) {
+        (Ok(email), Ok(_)) => email,
+        (email, password) => {
+            let mut errors = Vec::new();
+            if let Err(e) = &email {
+                errors.push(FieldError::email("email", e));
+            }
+            if let Err(e) = &password {
+                errors.push(FieldError::password("password", e));
+            }
+            return Err(AuthAPIError::InvalidInput(errors));
+        }
+    };
+    // Already validated, only hashing can fail
     let password = HashedPassword::parse(request.password)
         .await
-        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+        .map_err(AuthAPIError::UnexpectedError)?;
     let requires_2fa = request.requires_2fa;
 
-    let user = User::new(email, password, requires_2fa);
-    let mut user_store = state.user_store.write().await;
+    let user = User::new(email.clone(), password, requires_2fa);
+    // The store rejects duplicates atomically, so there's no need to look the
+    // user up first and concurrent signups don't have to wait on each other
+    let created = match state.user_store.add_user(user).await {
+        Ok(()) => true,
+        // Answered like a new account, only the owner of the address is told
+        Err(UserStoreError::UserAlreadyExists) if state.enumeration_protection => false,
+        Err(UserStoreError::UserAlreadyExists) => return Err(AuthAPIError::UserAlreadyExists),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
 
-    if user_store.get_user(&user.email).await.is_ok() {
-        return Err(AuthAPIError::UserAlreadyExists);
+    if created {
+        SIGNUPS.inc();
     }
 
-    if let Err(e) = user_store.add_user(user).await {
-        return Err(AuthAPIError::UnexpectedError(e.into()));
+    // Email 2FA users need a way in if they lose access to their mailbox too.
+    // A taken email gets codes that were never stored, so the response is the
+    // same as for a new account.
+    let recovery_codes = match (requires_2fa, created) {
+        (false, _) => None,
+        (true, true) => Some(issue_recovery_codes(&state, &email).await?),
+        (true, false) => Some(decoy_recovery_codes().await?),
+    };
+
+    // The account already exists at this point, so a failed email must not
+    // fail the signup. The user can ask for a new one via /verify-email/resend.
+    let send = {
+        let (state, email) = (state.clone(), email.clone());
+        async move {
+            match created {
+                true => send_verification_email(&state, &email).await,
+                false => send_account_exists_email(&state, &email).await,
+            }
+        }
+    };
+    if let Err(e) = send_unobserved(&state, send).await {
+        tracing::error!("Failed to send signup email: {:?}", e);
     }
 
     let response = Json(SignupResponse {
         message: "User created successfully!".to_string(),
+        recovery_codes,
     });
 
     Ok((StatusCode::CREATED, response))
 }
 
+// Sent instead of failing a signup with a taken email address
+async fn send_account_exists_email(state: &AppState, email: &Email) -> Result<(), AuthAPIError> {
+    state
+        .email_client
+        .send_email(
+            email,
+            "Someone tried to sign up with your email address",
+            "Someone tried to create an account with this email address, which already has one. \
+             If it was you, log in or reset your password instead. Otherwise you can ignore this email.",
+        )
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(Report::msg(e)))
+}
+
 #[derive(Deserialize)]
 pub struct SignupRequest {
     pub email: SecretString,
@@ -45,4 +116,11 @@ pub struct SignupRequest {
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct SignupResponse {
     pub message: String,
+    // Only when signing up with 2FA, shown this once
+    #[serde(
+        rename = "recoveryCodes",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub recovery_codes: Option<Vec<String>>,
 }
@@ -0,0 +1,134 @@
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, TotpStoreError},
+    routes::{helpers::authenticated_email, recovery_codes::issue_recovery_codes},
+    utils::{
+        json::JsonBody,
+        settings::settings,
+        totp::{generate_totp_secret, is_totp_code, provisioning_uri, verify_totp_code},
+    },
+};
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum_extra::extract::CookieJar;
+use chrono::Utc;
+use color_eyre::eyre::Report;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+
+// Generate a new TOTP secret for the logged in user. It isn't used as a
+// second factor until the user proves they've set it up via /2fa/totp/confirm.
+#[tracing::instrument(skip_all)]
+pub async fn enroll_totp(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = authenticated_email(&jar, &state).await?;
+
+    let secret = generate_totp_secret();
+    let otpauth_uri = provisioning_uri(&secret, &email).map_err(AuthAPIError::UnexpectedError)?;
+
+    state
+        .totp_store
+        .add_pending_secret(&email, secret.clone())
+        .await
+        .map_err(|e| match e {
+            TotpStoreError::AlreadyEnabled => AuthAPIError::TotpAlreadyEnabled,
+            e => AuthAPIError::UnexpectedError(Report::new(e)),
+        })?;
+
+    Ok((
+        StatusCode::OK,
+        Json(TotpEnrollmentResponse {
+            secret: secret.as_ref().expose_secret().to_owned(),
+            otpauth_uri,
+        }),
+    ))
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn confirm_totp(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    JsonBody(request): JsonBody<ConfirmTotpRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = authenticated_email(&jar, &state).await?;
+
+    let code = request.code.expose_secret().trim();
+    if !is_totp_code(code) {
+        return Err(AuthAPIError::InvalidCredentials);
+    }
+
+    let record = state
+        .totp_store
+        .get_totp(&email)
+        .await
+        .map_err(|e| match e {
+            TotpStoreError::SecretNotFound => AuthAPIError::InvalidToken,
+            e => AuthAPIError::UnexpectedError(Report::new(e)),
+        })?;
+
+    if record.confirmed {
+        return Err(AuthAPIError::TotpAlreadyEnabled);
+    }
+
+    let step = verify_totp_code(
+        &record.secret,
+        code,
+        Utc::now().timestamp() as u64,
+        settings().login.totp_drift_steps,
+    )
+    .map_err(AuthAPIError::UnexpectedError)?
+    .ok_or(AuthAPIError::InvalidToken)?;
+
+    // The confirmation code can't be reused to log in
+    state
+        .totp_store
+        .record_used_step(&email, step as i64)
+        .await
+        .map_err(|e| match e {
+            TotpStoreError::StepAlreadyUsed => AuthAPIError::InvalidToken,
+            e => AuthAPIError::UnexpectedError(Report::new(e)),
+        })?;
+
+    state
+        .totp_store
+        .confirm_secret(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(Report::new(e)))?;
+
+    state
+        .user_store
+        .set_requires_2fa(&email, true)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    // Hand out recovery codes in case the user loses their authenticator app
+    let recovery_codes = issue_recovery_codes(&state, &email).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(ConfirmTotpResponse {
+            message: "TOTP enabled".to_owned(),
+            recovery_codes,
+        }),
+    ))
+}
+
+#[derive(Deserialize)]
+pub struct ConfirmTotpRequest {
+    pub code: SecretString,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct TotpEnrollmentResponse {
+    pub secret: String,
+    #[serde(rename = "otpauthUri")]
+    pub otpauth_uri: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ConfirmTotpResponse {
+    pub message: String,
+    #[serde(rename = "recoveryCodes")]
+    pub recovery_codes: Vec<String>,
+}
@@ -1,11 +1,27 @@
 use crate::app_state::AppState;
 use crate::domain::error::TwoFACodeStoreError;
-use crate::domain::types::{Email, LoginAttemptId, TwoFACode};
-use crate::domain::TwoFACodeStore;
-use crate::routes::helpers::update_cookie_jar;
+use crate::domain::types::{Email, LoginAttemptId, RecoveryCode, TwoFACode};
+use crate::domain::{TotpRecord, TotpStoreError};
+use crate::routes::{
+    helpers::{
+        check_login_throttle, record_login_failure, reset_login_failures, update_cookie_jar,
+        user_agent,
+    },
+    recovery_codes::redeem_recovery_code,
+};
+use crate::utils::client_ip::ClientIp;
+use crate::utils::json::JsonBody;
+use crate::utils::metrics::record_two_fa_verification;
+use crate::utils::settings::settings;
+use crate::utils::totp::{is_totp_code, verify_totp_code};
 use crate::AuthAPIError;
-use axum::{extract::State, http::StatusCode, Json};
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    Json,
+};
 use axum_extra::extract::CookieJar;
+use chrono::Utc;
 use color_eyre::eyre::Report;
 use secrecy::SecretString;
 use serde::{Deserialize, Serialize};
@@ -13,20 +29,30 @@ use serde::{Deserialize, Serialize};
 #[tracing::instrument(skip_all)]
 pub async fn verify_2fa(
     State(state): State<AppState>,
+    ClientIp(client_ip): ClientIp,
+    headers: HeaderMap,
     jar: CookieJar,
-    Json(request): Json<Verify2FARequest>,
+    JsonBody(request): JsonBody<Verify2FARequest>,
 ) -> Result<(CookieJar, (StatusCode, Json<Verify2FAResponse>)), AuthAPIError> {
     let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
     let login_attempt_id = LoginAttemptId::parse(SecretString::new(
         request.login_attempt_id.to_owned().into_boxed_str(),
     ))
     .map_err(|_| AuthAPIError::InvalidCredentials)?;
-    let two_fa_code = TwoFACode::parse(SecretString::new(request.two_fa_code.into_boxed_str()))
-        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+    // The code is either the one emailed by login, one from an authenticator
+    // app or a recovery code. TOTP codes may start with a zero, which
+    // `TwoFACode` doesn't allow.
+    let code = request.two_fa_code.trim().to_owned();
+    let two_fa_code = TwoFACode::parse(SecretString::new(code.clone().into_boxed_str())).ok();
+    let recovery_code = RecoveryCode::parse(SecretString::new(code.clone().into_boxed_str())).ok();
+    if two_fa_code.is_none() && recovery_code.is_none() && !is_totp_code(&code) {
+        return Err(AuthAPIError::InvalidCredentials);
+    }
 
-    let mut two_fa_code_store = state.two_fa_code_store.write().await;
+    check_login_throttle(&state, &email, client_ip).await?;
 
-    let (stored_login_attempt_id, stored_two_fa_code) = two_fa_code_store
+    let (stored_login_attempt_id, stored_two_fa_code) = state
+        .two_fa_code_store
         .get_two_fa_code(&email)
         .await
         .map_err(|e| match e {
@@ -35,21 +61,46 @@ pub async fn verify_2fa(
             _ => AuthAPIError::UnexpectedError(Report::msg(e.to_string())),
         })?;
 
-    if stored_two_fa_code != two_fa_code || stored_login_attempt_id != login_attempt_id {
+    if stored_login_attempt_id != login_attempt_id {
+        record_two_fa_verification(false);
+        return Err(AuthAPIError::InvalidToken);
+    }
+
+    let verified = match &recovery_code {
+        Some(recovery_code) => redeem_recovery_code(&state, &email, recovery_code).await?,
+        // Users with an authenticator app are never emailed the code stored
+        // with the login attempt, so only their app's code is accepted
+        None => match confirmed_totp(&state, &email).await? {
+            Some(record) => verify_totp(&state, &email, &record, &code).await?,
+            None => two_fa_code.as_ref() == Some(&stored_two_fa_code),
+        },
+    };
+
+    if !verified {
+        record_two_fa_verification(false);
+        record_login_failure(&state, &email, client_ip).await?;
         return Err(AuthAPIError::InvalidToken);
     }
 
-    let updated_jar = update_cookie_jar(jar, &email)?;
+    reset_login_failures(&state, &email).await?;
 
-    match two_fa_code_store.remove_two_fa_code(&email).await {
+    // Removing the code is what completes the login attempt, so when the same
+    // attempt is verified twice concurrently only one request gets a session
+    match state.two_fa_code_store.remove_two_fa_code(&email).await {
         Ok(()) => {}
-        Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => {}
+        Err(TwoFACodeStoreError::LoginAttemptIdNotFound | TwoFACodeStoreError::EmailNotFound) => {
+            return Err(AuthAPIError::InvalidToken)
+        }
         Err(TwoFACodeStoreError::UnexpectedError(r)) => {
             return Err(AuthAPIError::UnexpectedError(r))
         }
         Err(e) => return Err(AuthAPIError::UnexpectedError(Report::msg(e.to_string()))),
     }
 
+    let updated_jar =
+        update_cookie_jar(jar, &email, &state, user_agent(&headers), client_ip).await?;
+
+    record_two_fa_verification(true);
     let response = Json(Verify2FAResponse {
         message: "Token verified successfully".to_string(),
     });
@@ -57,6 +108,43 @@ pub async fn verify_2fa(
     Ok((updated_jar, (StatusCode::OK, response)))
 }
 
+async fn confirmed_totp(
+    state: &AppState,
+    email: &Email,
+) -> Result<Option<TotpRecord>, AuthAPIError> {
+    match state.totp_store.get_totp(email).await {
+        Ok(record) if record.confirmed => Ok(Some(record)),
+        Ok(_) | Err(TotpStoreError::SecretNotFound) => Ok(None),
+        Err(e) => Err(AuthAPIError::UnexpectedError(Report::new(e))),
+    }
+}
+
+// Check `code` against the user's confirmed TOTP secret, burning it on success
+async fn verify_totp(
+    state: &AppState,
+    email: &Email,
+    record: &TotpRecord,
+    code: &str,
+) -> Result<bool, AuthAPIError> {
+    let step = verify_totp_code(
+        &record.secret,
+        code,
+        Utc::now().timestamp() as u64,
+        settings().login.totp_drift_steps,
+    )
+    .map_err(AuthAPIError::UnexpectedError)?;
+
+    let Some(step) = step else {
+        return Ok(false);
+    };
+
+    match state.totp_store.record_used_step(email, step as i64).await {
+        Ok(()) => Ok(true),
+        Err(TotpStoreError::StepAlreadyUsed) => Ok(false),
+        Err(e) => Err(AuthAPIError::UnexpectedError(Report::new(e))),
+    }
+}
+
 #[derive(Deserialize)]
 pub struct Verify2FARequest {
     pub email: SecretString,
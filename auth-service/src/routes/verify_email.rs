@@ -0,0 +1,131 @@
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuthAPIError, Email, EmailVerificationToken, EmailVerificationTokenStoreError, ADMIN_ROLE,
+    },
+    routes::helpers::{describe_ttl, send_unobserved},
+    utils::{auth::generate_random_token, json::JsonBody, settings::settings},
+};
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use color_eyre::eyre::Report;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+
+#[tracing::instrument(skip_all)]
+pub async fn verify_email(
+    State(state): State<AppState>,
+    JsonBody(request): JsonBody<VerifyEmailRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let token =
+        EmailVerificationToken::parse(request.token).map_err(|_| AuthAPIError::MissingToken)?;
+
+    let email = state
+        .email_verification_token_store
+        .take_token(&token)
+        .await
+        .map_err(|e| match e {
+            EmailVerificationTokenStoreError::TokenNotFound => AuthAPIError::InvalidToken,
+            EmailVerificationTokenStoreError::UnexpectedError(r) => {
+                AuthAPIError::UnexpectedError(r)
+            }
+        })?;
+
+    state
+        .user_store
+        .mark_email_verified(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    if state.bootstrap_admins.contains(&email) {
+        state
+            .user_store
+            .add_role(&email, ADMIN_ROLE)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+        tracing::info!("Granted the admin role to a bootstrap admin");
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(VerifyEmailResponse {
+            message: "Email verified successfully".to_owned(),
+        }),
+    ))
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn resend_verification_email(
+    State(state): State<AppState>,
+    JsonBody(request): JsonBody<ResendVerificationEmailRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    // Same response whether the account doesn't exist, is already verified
+    // or was just sent a new email.
+    let response = (
+        StatusCode::OK,
+        Json(VerifyEmailResponse {
+            message: "If the account exists and is unverified, a verification email has been sent"
+                .to_owned(),
+        }),
+    );
+
+    match state.user_store.get_user(&email).await {
+        Ok(user) if !user.verified => {}
+        _ => return Ok(response),
+    }
+
+    let send = {
+        let state = state.clone();
+        async move { send_verification_email(&state, &email).await }
+    };
+    send_unobserved(&state, send).await?;
+
+    Ok(response)
+}
+
+// Store a new single-use verification token for `email` and send it to that address
+#[tracing::instrument(skip_all)]
+pub(crate) async fn send_verification_email(
+    state: &AppState,
+    email: &Email,
+) -> Result<(), AuthAPIError> {
+    let token =
+        EmailVerificationToken::parse(SecretString::new(generate_random_token().into_boxed_str()))
+            .map_err(|e| AuthAPIError::UnexpectedError(Report::new(e)))?;
+
+    state
+        .email_verification_token_store
+        .add_token(token.clone(), email.clone())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(Report::new(e)))?;
+
+    state
+        .email_client
+        .send_email(
+            email,
+            "Verify your email address",
+            &format!(
+                "Your email verification token is {}. It expires in {}.",
+                token.as_ref().expose_secret(),
+                describe_ttl(settings().tokens.email_verification_token_ttl_seconds)
+            ),
+        )
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(Report::msg(e)))
+}
+
+#[derive(Deserialize)]
+pub struct VerifyEmailRequest {
+    pub token: SecretString,
+}
+
+#[derive(Deserialize)]
+pub struct ResendVerificationEmailRequest {
+    pub email: SecretString,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct VerifyEmailResponse {
+    pub message: String,
+}
@@ -1,27 +1,44 @@
-use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
-use serde::Deserialize;
+use axum::{extract::State, response::IntoResponse, Json};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     app_state::AppState,
     domain::{AuthAPIError, Token},
-    utils::auth::validate_token,
+    utils::{auth::validate_token, json::JsonBody, metrics::record_token_verification},
 };
 
 #[tracing::instrument(skip_all)]
 pub async fn verify_token(
     State(state): State<AppState>,
-    Json(request): Json<VerifyTokenRequest>,
+    JsonBody(request): JsonBody<VerifyTokenRequest>,
 ) -> Result<impl IntoResponse, AuthAPIError> {
     Token::parse(&request.token).map_err(|_| AuthAPIError::MissingToken)?;
 
-    validate_token(&request.token, state.banned_token_store.clone())
-        .await
-        .map_err(|_| AuthAPIError::InvalidToken)?;
+    let result = validate_token(
+        &request.token,
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+    )
+    .await;
+    record_token_verification(result.is_ok());
+    let claims = result.map_err(|_| AuthAPIError::InvalidToken)?;
 
-    Ok(StatusCode::OK)
+    Ok(Json(VerifyTokenResponse {
+        email: claims.sub,
+        roles: claims.roles,
+        permissions: claims.permissions,
+    }))
 }
 
 #[derive(Deserialize)]
 pub struct VerifyTokenRequest {
     pub token: String,
 }
+
+// What the token grants, as of when it was issued
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct VerifyTokenResponse {
+    pub email: String,
+    pub roles: Vec<String>,
+    pub permissions: Vec<String>,
+}
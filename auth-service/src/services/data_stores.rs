@@ -1,6 +1,49 @@
+pub mod hashmap_email_verification_token_store;
+pub mod hashmap_login_attempt_store;
+pub mod hashmap_password_reset_token_store;
+pub mod hashmap_recovery_code_store;
+pub mod hashmap_refresh_token_store;
+pub mod hashmap_session_store;
+pub mod hashmap_totp_store;
 pub mod hashmap_two_fa_code_store;
 pub mod hashmap_user_store;
 pub mod hashset_banned_token_store;
+pub mod postgres_banned_token_store;
+pub mod postgres_email_verification_token_store;
+pub mod postgres_login_attempt_store;
+pub mod postgres_password_reset_token_store;
+pub mod postgres_recovery_code_store;
+pub mod postgres_refresh_token_store;
+pub mod postgres_session_store;
+pub mod postgres_totp_store;
+pub mod postgres_two_fa_code_store;
 pub mod postgres_user_store;
 pub mod redis_banned_token_store;
+pub mod redis_email_verification_token_store;
+pub mod redis_login_attempt_store;
+pub mod redis_password_reset_token_store;
+pub mod redis_refresh_token_store;
+pub mod redis_session_store;
 pub mod redis_two_fa_code_store;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_banned_token_store;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_email_verification_token_store;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_login_attempt_store;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_password_reset_token_store;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_recovery_code_store;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_refresh_token_store;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_session_store;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_totp_store;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_two_fa_code_store;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_user_store;
+#[cfg(test)]
+mod user_store_tests;
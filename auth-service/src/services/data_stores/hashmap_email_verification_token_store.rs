@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+use crate::domain::data_store::EmailVerificationTokenStore;
+use crate::domain::error::EmailVerificationTokenStoreError;
+use crate::domain::types::{Email, EmailVerificationToken};
+use secrecy::ExposeSecret;
+use tokio::sync::RwLock;
+
+#[derive(Default)]
+pub struct HashmapEmailVerificationTokenStore {
+    tokens: RwLock<HashMap<String, Email>>,
+}
+
+#[async_trait::async_trait]
+impl EmailVerificationTokenStore for HashmapEmailVerificationTokenStore {
+    async fn add_token(
+        &self,
+        token: EmailVerificationToken,
+        email: Email,
+    ) -> Result<(), EmailVerificationTokenStoreError> {
+        self.tokens
+            .write()
+            .await
+            .insert(token.as_ref().expose_secret().to_owned(), email);
+        Ok(())
+    }
+
+    async fn take_token(
+        &self,
+        token: &EmailVerificationToken,
+    ) -> Result<Email, EmailVerificationTokenStoreError> {
+        self.tokens
+            .write()
+            .await
+            .remove(token.as_ref().expose_secret())
+            .ok_or(EmailVerificationTokenStoreError::TokenNotFound)
+    }
+
+    async fn remove_user_tokens(
+        &self,
+        email: &Email,
+    ) -> Result<(), EmailVerificationTokenStoreError> {
+        self.tokens
+            .write()
+            .await
+            .retain(|_, token_email| token_email != email);
+        Ok(())
+    }
+}
+
+impl HashmapEmailVerificationTokenStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::SecretString;
+
+    fn token() -> EmailVerificationToken {
+        EmailVerificationToken::parse(SecretString::new(
+            "verify1234567890".to_owned().into_boxed_str(),
+        ))
+        .unwrap()
+    }
+
+    fn other_token() -> EmailVerificationToken {
+        EmailVerificationToken::parse(SecretString::from("verify0987654321")).unwrap()
+    }
+
+    fn email() -> Email {
+        Email::parse(SecretString::new(
+            "test@example.com".to_owned().into_boxed_str(),
+        ))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_take_token_returns_email() {
+        let store = HashmapEmailVerificationTokenStore::new();
+
+        store.add_token(token(), email()).await.unwrap();
+
+        assert_eq!(store.take_token(&token()).await.unwrap(), email());
+    }
+
+    #[tokio::test]
+    async fn test_take_token_is_single_use() {
+        let store = HashmapEmailVerificationTokenStore::new();
+
+        store.add_token(token(), email()).await.unwrap();
+        store.take_token(&token()).await.unwrap();
+
+        let err = store.take_token(&token()).await.unwrap_err();
+        assert!(matches!(
+            err,
+            EmailVerificationTokenStoreError::TokenNotFound
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_remove_user_tokens() {
+        let store = HashmapEmailVerificationTokenStore::new();
+        let other = Email::parse(SecretString::from("other@example.com")).unwrap();
+
+        store.add_token(token(), email()).await.unwrap();
+        store.add_token(other_token(), other.clone()).await.unwrap();
+        store.remove_user_tokens(&email()).await.unwrap();
+
+        let err = store.take_token(&token()).await.unwrap_err();
+        assert!(matches!(
+            err,
+            EmailVerificationTokenStoreError::TokenNotFound
+        ));
+        assert_eq!(store.take_token(&other_token()).await.unwrap(), other);
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+
+use crate::domain::data_store::LoginAttemptStore;
+use crate::domain::error::LoginAttemptStoreError;
+use crate::domain::types::{LoginAttemptKey, LoginAttempts};
+use chrono::Utc;
+use tokio::sync::RwLock;
+
+// Attempts are kept together with the time they expire at
+#[derive(Default)]
+pub struct HashmapLoginAttemptStore {
+    attempts: RwLock<HashMap<LoginAttemptKey, (LoginAttempts, i64)>>,
+}
+
+impl HashmapLoginAttemptStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait::async_trait]
+impl LoginAttemptStore for HashmapLoginAttemptStore {
+    async fn get_attempts(
+        &self,
+        key: &LoginAttemptKey,
+    ) -> Result<LoginAttempts, LoginAttemptStoreError> {
+        let now = Utc::now().timestamp();
+        Ok(self
+            .attempts
+            .read()
+            .await
+            .get(key)
+            .filter(|(_, expires_at)| *expires_at > now)
+            .map(|(attempts, _)| *attempts)
+            .unwrap_or_default())
+    }
+
+    async fn record_failure(
+        &self,
+        key: &LoginAttemptKey,
+        ttl_seconds: u64,
+    ) -> Result<LoginAttempts, LoginAttemptStoreError> {
+        let now = Utc::now().timestamp();
+        // Count under one write lock so concurrent failures aren't lost
+        let mut attempts = self.attempts.write().await;
+        let mut current = attempts
+            .get(key)
+            .filter(|(_, expires_at)| *expires_at > now)
+            .map(|(attempts, _)| *attempts)
+            .unwrap_or_default();
+        current.failures += 1;
+        current.last_failure_at = now;
+        attempts.insert(key.clone(), (current, now + ttl_seconds as i64));
+        Ok(current)
+    }
+
+    async fn reset(&self, key: &LoginAttemptKey) -> Result<(), LoginAttemptStoreError> {
+        self.attempts.write().await.remove(key);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::types::Email;
+    use secrecy::SecretString;
+
+    fn account_key() -> LoginAttemptKey {
+        LoginAttemptKey::Account(
+            Email::parse(SecretString::new(
+                "test@example.com".to_owned().into_boxed_str(),
+            ))
+            .unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_record_failure_counts_per_key() {
+        let store = HashmapLoginAttemptStore::new();
+        let ip_key = LoginAttemptKey::Ip("127.0.0.1".parse().unwrap());
+
+        store.record_failure(&account_key(), 60).await.unwrap();
+        let attempts = store.record_failure(&account_key(), 60).await.unwrap();
+
+        assert_eq!(attempts.failures, 2);
+        assert_eq!(store.get_attempts(&account_key()).await.unwrap(), attempts);
+        assert_eq!(store.get_attempts(&ip_key).await.unwrap().failures, 0);
+    }
+
+    #[tokio::test]
+    async fn test_failures_expire() {
+        let store = HashmapLoginAttemptStore::new();
+
+        store.record_failure(&account_key(), 0).await.unwrap();
+
+        assert_eq!(
+            store.get_attempts(&account_key()).await.unwrap(),
+            LoginAttempts::default()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reset() {
+        let store = HashmapLoginAttemptStore::new();
+
+        store.record_failure(&account_key(), 60).await.unwrap();
+        store.reset(&account_key()).await.unwrap();
+
+        assert_eq!(
+            store.get_attempts(&account_key()).await.unwrap().failures,
+            0
+        );
+    }
+}
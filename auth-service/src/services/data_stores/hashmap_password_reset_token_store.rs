@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+
+use crate::domain::data_store::PasswordResetTokenStore;
+use crate::domain::error::PasswordResetTokenStoreError;
+use crate::domain::types::{Email, PasswordResetToken};
+use secrecy::ExposeSecret;
+use tokio::sync::RwLock;
+
+#[derive(Default)]
+pub struct HashmapPasswordResetTokenStore {
+    tokens: RwLock<HashMap<String, Email>>,
+}
+
+#[async_trait::async_trait]
+impl PasswordResetTokenStore for HashmapPasswordResetTokenStore {
+    async fn add_token(
+        &self,
+        token: PasswordResetToken,
+        email: Email,
+    ) -> Result<(), PasswordResetTokenStoreError> {
+        self.tokens
+            .write()
+            .await
+            .insert(token.as_ref().expose_secret().to_owned(), email);
+        Ok(())
+    }
+
+    async fn take_token(
+        &self,
+        token: &PasswordResetToken,
+    ) -> Result<Email, PasswordResetTokenStoreError> {
+        self.tokens
+            .write()
+            .await
+            .remove(token.as_ref().expose_secret())
+            .ok_or(PasswordResetTokenStoreError::TokenNotFound)
+    }
+
+    async fn remove_user_tokens(&self, email: &Email) -> Result<(), PasswordResetTokenStoreError> {
+        self.tokens
+            .write()
+            .await
+            .retain(|_, token_email| token_email != email);
+        Ok(())
+    }
+}
+
+impl HashmapPasswordResetTokenStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::SecretString;
+
+    fn token() -> PasswordResetToken {
+        PasswordResetToken::parse(SecretString::new(
+            "reset1234567890".to_owned().into_boxed_str(),
+        ))
+        .unwrap()
+    }
+
+    fn other_token() -> PasswordResetToken {
+        PasswordResetToken::parse(SecretString::from("reset0987654321")).unwrap()
+    }
+
+    fn email() -> Email {
+        Email::parse(SecretString::new(
+            "test@example.com".to_owned().into_boxed_str(),
+        ))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_take_token_returns_email() {
+        let store = HashmapPasswordResetTokenStore::new();
+
+        store.add_token(token(), email()).await.unwrap();
+
+        assert_eq!(store.take_token(&token()).await.unwrap(), email());
+    }
+
+    #[tokio::test]
+    async fn test_take_token_is_single_use() {
+        let store = HashmapPasswordResetTokenStore::new();
+
+        store.add_token(token(), email()).await.unwrap();
+        store.take_token(&token()).await.unwrap();
+
+        let err = store.take_token(&token()).await.unwrap_err();
+        assert!(matches!(err, PasswordResetTokenStoreError::TokenNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_remove_user_tokens() {
+        let store = HashmapPasswordResetTokenStore::new();
+        let other = Email::parse(SecretString::from("other@example.com")).unwrap();
+
+        store.add_token(token(), email()).await.unwrap();
+        store.add_token(other_token(), other.clone()).await.unwrap();
+        store.remove_user_tokens(&email()).await.unwrap();
+
+        let err = store.take_token(&token()).await.unwrap_err();
+        assert!(matches!(err, PasswordResetTokenStoreError::TokenNotFound));
+        assert_eq!(store.take_token(&other_token()).await.unwrap(), other);
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+
+use crate::domain::data_store::RecoveryCodeStore;
+use crate::domain::error::RecoveryCodeStoreError;
+use crate::domain::types::{Email, HashedRecoveryCode};
+use tokio::sync::RwLock;
+
+#[derive(Default)]
+pub struct HashmapRecoveryCodeStore {
+    codes: RwLock<HashMap<Email, Vec<HashedRecoveryCode>>>,
+}
+
+#[async_trait::async_trait]
+impl RecoveryCodeStore for HashmapRecoveryCodeStore {
+    async fn replace_codes(
+        &self,
+        email: &Email,
+        codes: Vec<HashedRecoveryCode>,
+    ) -> Result<(), RecoveryCodeStoreError> {
+        self.codes.write().await.insert(email.clone(), codes);
+        Ok(())
+    }
+
+    async fn get_codes(
+        &self,
+        email: &Email,
+    ) -> Result<Vec<HashedRecoveryCode>, RecoveryCodeStoreError> {
+        Ok(self
+            .codes
+            .read()
+            .await
+            .get(email)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn remove_code(
+        &self,
+        email: &Email,
+        code: &HashedRecoveryCode,
+    ) -> Result<(), RecoveryCodeStoreError> {
+        let mut codes = self.codes.write().await;
+        let codes = codes
+            .get_mut(email)
+            .ok_or(RecoveryCodeStoreError::CodeNotFound)?;
+        let index = codes
+            .iter()
+            .position(|stored| stored == code)
+            .ok_or(RecoveryCodeStoreError::CodeNotFound)?;
+        codes.remove(index);
+        Ok(())
+    }
+}
+
+impl HashmapRecoveryCodeStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::types::RecoveryCode;
+    use secrecy::SecretString;
+
+    fn email() -> Email {
+        Email::parse(SecretString::new(
+            "test@example.com".to_owned().into_boxed_str(),
+        ))
+        .unwrap()
+    }
+
+    async fn hashed_code(code: &str) -> HashedRecoveryCode {
+        let code =
+            RecoveryCode::parse(SecretString::new(code.to_owned().into_boxed_str())).unwrap();
+        HashedRecoveryCode::parse(&code).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_replace_codes_invalidates_old_codes() {
+        let store = HashmapRecoveryCodeStore::new();
+        let old = hashed_code("AAAAA-AAAAA").await;
+        let new = hashed_code("BBBBB-BBBBB").await;
+
+        store.replace_codes(&email(), vec![old]).await.unwrap();
+        store
+            .replace_codes(&email(), vec![new.clone()])
+            .await
+            .unwrap();
+
+        assert_eq!(store.get_codes(&email()).await.unwrap(), vec![new]);
+    }
+
+    #[tokio::test]
+    async fn test_remove_code_is_single_use() {
+        let store = HashmapRecoveryCodeStore::new();
+        let first = hashed_code("AAAAA-AAAAA").await;
+        let second = hashed_code("BBBBB-BBBBB").await;
+
+        store
+            .replace_codes(&email(), vec![first.clone(), second.clone()])
+            .await
+            .unwrap();
+        store.remove_code(&email(), &first).await.unwrap();
+
+        assert_eq!(
+            store.remove_code(&email(), &first).await,
+            Err(RecoveryCodeStoreError::CodeNotFound)
+        );
+        assert_eq!(store.get_codes(&email()).await.unwrap(), vec![second]);
+    }
+
+    #[tokio::test]
+    async fn test_get_codes_returns_empty_if_none_generated() {
+        let store = HashmapRecoveryCodeStore::new();
+
+        assert!(store.get_codes(&email()).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::domain::data_store::RefreshTokenStore;
+use crate::domain::error::RefreshTokenStoreError;
+use crate::domain::types::{Email, RefreshTokenRecord};
+use secrecy::{ExposeSecret, SecretString};
+use tokio::sync::RwLock;
+
+#[derive(Default)]
+pub struct HashmapRefreshTokenStore {
+    tokens: RwLock<HashMap<String, RefreshTokenRecord>>,
+    revoked_families: RwLock<HashSet<String>>,
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for HashmapRefreshTokenStore {
+    async fn add_refresh_token(
+        &self,
+        token: SecretString,
+        record: RefreshTokenRecord,
+    ) -> Result<(), RefreshTokenStoreError> {
+        self.tokens
+            .write()
+            .await
+            .insert(token.expose_secret().to_owned(), record);
+        Ok(())
+    }
+
+    async fn get_refresh_token(
+        &self,
+        token: &SecretString,
+    ) -> Result<RefreshTokenRecord, RefreshTokenStoreError> {
+        self.tokens
+            .read()
+            .await
+            .get(token.expose_secret())
+            .cloned()
+            .ok_or(RefreshTokenStoreError::TokenNotFound)
+    }
+
+    async fn mark_refresh_token_used(
+        &self,
+        token: &SecretString,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let mut tokens = self.tokens.write().await;
+        let record = tokens
+            .get_mut(token.expose_secret())
+            .ok_or(RefreshTokenStoreError::TokenNotFound)?;
+        if record.used {
+            return Err(RefreshTokenStoreError::TokenAlreadyUsed);
+        }
+        record.used = true;
+        Ok(())
+    }
+
+    async fn revoke_family(&self, family_id: &str) -> Result<(), RefreshTokenStoreError> {
+        self.revoked_families
+            .write()
+            .await
+            .insert(family_id.to_owned());
+        Ok(())
+    }
+
+    async fn is_family_revoked(&self, family_id: &str) -> Result<bool, RefreshTokenStoreError> {
+        Ok(self.revoked_families.read().await.contains(family_id))
+    }
+
+    async fn revoke_user_families(&self, email: &Email) -> Result<(), RefreshTokenStoreError> {
+        let families: Vec<String> = self
+            .tokens
+            .read()
+            .await
+            .values()
+            .filter(|record| &record.email == email)
+            .map(|record| record.family_id.clone())
+            .collect();
+        self.revoked_families.write().await.extend(families);
+        Ok(())
+    }
+}
+
+impl HashmapRefreshTokenStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(family_id: &str) -> RefreshTokenRecord {
+        let email = Email::parse(SecretString::new(
+            "test@example.com".to_owned().into_boxed_str(),
+        ))
+        .unwrap();
+        RefreshTokenRecord::new(email, family_id.to_owned())
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_refresh_token() {
+        let store = HashmapRefreshTokenStore::new();
+        let token = SecretString::new("refresh1234567890".to_owned().into_boxed_str());
+
+        store
+            .add_refresh_token(token.clone(), record("family-1"))
+            .await
+            .unwrap();
+
+        let stored = store.get_refresh_token(&token).await.unwrap();
+        assert_eq!(stored, record("family-1"));
+        assert!(!stored.used);
+    }
+
+    #[tokio::test]
+    async fn test_get_refresh_token_returns_err_if_missing() {
+        let store = HashmapRefreshTokenStore::new();
+        let token = SecretString::new("missing".to_owned().into_boxed_str());
+
+        let err = store.get_refresh_token(&token).await.unwrap_err();
+        assert!(matches!(err, RefreshTokenStoreError::TokenNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_mark_refresh_token_used() {
+        let store = HashmapRefreshTokenStore::new();
+        let token = SecretString::new("refresh1234567890".to_owned().into_boxed_str());
+
+        store
+            .add_refresh_token(token.clone(), record("family-1"))
+            .await
+            .unwrap();
+        store.mark_refresh_token_used(&token).await.unwrap();
+
+        assert!(store.get_refresh_token(&token).await.unwrap().used);
+
+        let err = store.mark_refresh_token_used(&token).await.unwrap_err();
+        assert!(matches!(err, RefreshTokenStoreError::TokenAlreadyUsed));
+    }
+
+    #[tokio::test]
+    async fn test_revoke_family() {
+        let store = HashmapRefreshTokenStore::new();
+
+        store.revoke_family("family-1").await.unwrap();
+
+        assert!(store.is_family_revoked("family-1").await.unwrap());
+        assert!(!store.is_family_revoked("family-2").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_revoke_user_families() {
+        let store = HashmapRefreshTokenStore::new();
+        let email = record("family-1").email;
+
+        store
+            .add_refresh_token(
+                SecretString::new("refresh1".to_owned().into_boxed_str()),
+                record("family-1"),
+            )
+            .await
+            .unwrap();
+        store
+            .add_refresh_token(
+                SecretString::new("refresh2".to_owned().into_boxed_str()),
+                record("family-2"),
+            )
+            .await
+            .unwrap();
+
+        store.revoke_user_families(&email).await.unwrap();
+
+        assert!(store.is_family_revoked("family-1").await.unwrap());
+        assert!(store.is_family_revoked("family-2").await.unwrap());
+    }
+}
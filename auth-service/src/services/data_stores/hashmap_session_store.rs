@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+
+use crate::domain::data_store::SessionStore;
+use crate::domain::error::SessionStoreError;
+use crate::domain::types::{Email, Session};
+use tokio::sync::RwLock;
+
+#[derive(Default)]
+pub struct HashmapSessionStore {
+    sessions: RwLock<HashMap<String, Session>>,
+}
+
+impl HashmapSessionStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait::async_trait]
+impl SessionStore for HashmapSessionStore {
+    async fn add_session(&self, session: Session) -> Result<(), SessionStoreError> {
+        self.sessions
+            .write()
+            .await
+            .insert(session.id.clone(), session);
+        Ok(())
+    }
+
+    async fn get_session(&self, id: &str) -> Result<Session, SessionStoreError> {
+        self.sessions
+            .read()
+            .await
+            .get(id)
+            .cloned()
+            .ok_or(SessionStoreError::SessionNotFound)
+    }
+
+    async fn get_user_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError> {
+        Ok(self
+            .sessions
+            .read()
+            .await
+            .values()
+            .filter(|session| &session.email == email)
+            .cloned()
+            .collect())
+    }
+
+    async fn touch_session(&self, id: &str, last_seen_at: i64) -> Result<(), SessionStoreError> {
+        let mut sessions = self.sessions.write().await;
+        let session = sessions
+            .get_mut(id)
+            .ok_or(SessionStoreError::SessionNotFound)?;
+        session.last_seen_at = last_seen_at;
+        Ok(())
+    }
+
+    async fn remove_session(&self, id: &str) -> Result<(), SessionStoreError> {
+        self.sessions
+            .write()
+            .await
+            .remove(id)
+            .map(|_| ())
+            .ok_or(SessionStoreError::SessionNotFound)
+    }
+
+    async fn remove_user_sessions(&self, email: &Email) -> Result<(), SessionStoreError> {
+        self.sessions
+            .write()
+            .await
+            .retain(|_, session| &session.email != email);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::SecretString;
+
+    fn email(address: &str) -> Email {
+        Email::parse(SecretString::new(address.to_owned().into_boxed_str())).unwrap()
+    }
+
+    fn session(id: &str, address: &str) -> Session {
+        Session {
+            id: id.to_owned(),
+            email: email(address),
+            user_agent: Some("test".to_owned()),
+            ip: "127.0.0.1".to_owned(),
+            created_at: 1_000,
+            last_seen_at: 1_000,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_and_touch_session() {
+        let store = HashmapSessionStore::new();
+        store
+            .add_session(session("a", "test@example.com"))
+            .await
+            .unwrap();
+
+        store.touch_session("a", 2_000).await.unwrap();
+
+        let stored = store.get_session("a").await.unwrap();
+        assert_eq!(stored.last_seen_at, 2_000);
+        assert_eq!(stored.created_at, 1_000);
+    }
+
+    #[tokio::test]
+    async fn test_remove_session() {
+        let store = HashmapSessionStore::new();
+        store
+            .add_session(session("a", "test@example.com"))
+            .await
+            .unwrap();
+
+        store.remove_session("a").await.unwrap();
+
+        assert_eq!(
+            store.get_session("a").await,
+            Err(SessionStoreError::SessionNotFound)
+        );
+        assert_eq!(
+            store.remove_session("a").await,
+            Err(SessionStoreError::SessionNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_remove_user_sessions_leaves_other_users() {
+        let store = HashmapSessionStore::new();
+        store
+            .add_session(session("a", "test@example.com"))
+            .await
+            .unwrap();
+        store
+            .add_session(session("b", "test@example.com"))
+            .await
+            .unwrap();
+        store
+            .add_session(session("c", "other@example.com"))
+            .await
+            .unwrap();
+
+        store
+            .remove_user_sessions(&email("test@example.com"))
+            .await
+            .unwrap();
+
+        assert!(store
+            .get_user_sessions(&email("test@example.com"))
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            store
+                .get_user_sessions(&email("other@example.com"))
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+
+use crate::domain::data_store::TotpStore;
+use crate::domain::error::TotpStoreError;
+use crate::domain::types::{Email, TotpRecord, TotpSecret};
+use tokio::sync::RwLock;
+
+#[derive(Default)]
+pub struct HashmapTotpStore {
+    records: RwLock<HashMap<Email, TotpRecord>>,
+}
+
+#[async_trait::async_trait]
+impl TotpStore for HashmapTotpStore {
+    async fn add_pending_secret(
+        &self,
+        email: &Email,
+        secret: TotpSecret,
+    ) -> Result<(), TotpStoreError> {
+        let mut records = self.records.write().await;
+        if let Some(record) = records.get(email) {
+            if record.confirmed {
+                return Err(TotpStoreError::AlreadyEnabled);
+            }
+        }
+        records.insert(
+            email.clone(),
+            TotpRecord {
+                secret,
+                confirmed: false,
+                last_used_step: None,
+            },
+        );
+        Ok(())
+    }
+
+    async fn get_totp(&self, email: &Email) -> Result<TotpRecord, TotpStoreError> {
+        self.records
+            .read()
+            .await
+            .get(email)
+            .cloned()
+            .ok_or(TotpStoreError::SecretNotFound)
+    }
+
+    async fn confirm_secret(&self, email: &Email) -> Result<(), TotpStoreError> {
+        let mut records = self.records.write().await;
+        let record = records
+            .get_mut(email)
+            .ok_or(TotpStoreError::SecretNotFound)?;
+        record.confirmed = true;
+        Ok(())
+    }
+
+    async fn record_used_step(&self, email: &Email, step: i64) -> Result<(), TotpStoreError> {
+        let mut records = self.records.write().await;
+        let record = records
+            .get_mut(email)
+            .ok_or(TotpStoreError::SecretNotFound)?;
+        if record.last_used_step.is_some_and(|last| last >= step) {
+            return Err(TotpStoreError::StepAlreadyUsed);
+        }
+        record.last_used_step = Some(step);
+        Ok(())
+    }
+
+    async fn remove_totp(&self, email: &Email) -> Result<(), TotpStoreError> {
+        self.records.write().await.remove(email);
+        Ok(())
+    }
+}
+
+impl HashmapTotpStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::totp::generate_totp_secret;
+    use secrecy::SecretString;
+
+    fn email() -> Email {
+        Email::parse(SecretString::new(
+            "test@example.com".to_owned().into_boxed_str(),
+        ))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_add_pending_secret_replaces_unconfirmed_secret() {
+        let store = HashmapTotpStore::new();
+        let secret = generate_totp_secret();
+
+        store
+            .add_pending_secret(&email(), generate_totp_secret())
+            .await
+            .unwrap();
+        store
+            .add_pending_secret(&email(), secret.clone())
+            .await
+            .unwrap();
+
+        let record = store.get_totp(&email()).await.unwrap();
+        assert_eq!(record.secret, secret);
+        assert!(!record.confirmed);
+    }
+
+    #[tokio::test]
+    async fn test_add_pending_secret_fails_once_confirmed() {
+        let store = HashmapTotpStore::new();
+
+        store
+            .add_pending_secret(&email(), generate_totp_secret())
+            .await
+            .unwrap();
+        store.confirm_secret(&email()).await.unwrap();
+
+        let result = store
+            .add_pending_secret(&email(), generate_totp_secret())
+            .await;
+        assert_eq!(result, Err(TotpStoreError::AlreadyEnabled));
+        assert!(store.get_totp(&email()).await.unwrap().confirmed);
+    }
+
+    #[tokio::test]
+    async fn test_record_used_step_rejects_replays() {
+        let store = HashmapTotpStore::new();
+
+        store
+            .add_pending_secret(&email(), generate_totp_secret())
+            .await
+            .unwrap();
+        store.record_used_step(&email(), 10).await.unwrap();
+
+        assert_eq!(
+            store.record_used_step(&email(), 10).await,
+            Err(TotpStoreError::StepAlreadyUsed)
+        );
+        assert_eq!(
+            store.record_used_step(&email(), 9).await,
+            Err(TotpStoreError::StepAlreadyUsed)
+        );
+        store.record_used_step(&email(), 11).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_get_totp_returns_err_if_not_enrolled() {
+        let store = HashmapTotpStore::new();
+
+        assert_eq!(
+            store.get_totp(&email()).await,
+            Err(TotpStoreError::SecretNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_remove_totp() {
+        let store = HashmapTotpStore::new();
+        store
+            .add_pending_secret(&email(), generate_totp_secret())
+            .await
+            .unwrap();
+        store.confirm_secret(&email()).await.unwrap();
+
+        store.remove_totp(&email()).await.unwrap();
+        store.remove_totp(&email()).await.unwrap();
+
+        assert_eq!(
+            store.get_totp(&email()).await,
+            Err(TotpStoreError::SecretNotFound)
+        );
+    }
+}
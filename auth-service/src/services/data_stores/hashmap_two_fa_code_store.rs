@@ -3,40 +3,45 @@ use std::collections::HashMap;
 use crate::domain::data_store::TwoFACodeStore;
 use crate::domain::error::TwoFACodeStoreError;
 use crate::domain::types::{Email, LoginAttemptId, TwoFACode};
+use tokio::sync::RwLock;
 
 pub struct HashmapTwoFACodeStore {
-    codes: HashMap<Email, (LoginAttemptId, TwoFACode)>,
+    codes: RwLock<HashMap<Email, (LoginAttemptId, TwoFACode)>>,
 }
 
 #[async_trait::async_trait]
 impl TwoFACodeStore for HashmapTwoFACodeStore {
     async fn add_two_fa_code(
-        &mut self,
+        &self,
         email: Email,
         login_attempt_id: LoginAttemptId,
         two_fa_code: TwoFACode,
     ) -> Result<(), TwoFACodeStoreError> {
-        if let Ok(true) = self.two_fa_code_exists(&email).await {
+        let mut codes = self.codes.write().await;
+        if codes.contains_key(&email) {
             return Err(TwoFACodeStoreError::EmailAlreadyExists);
         }
-        self.codes.insert(email, (login_attempt_id, two_fa_code));
+        codes.insert(email, (login_attempt_id, two_fa_code));
         Ok(())
     }
     async fn two_fa_code_exists(&self, email: &Email) -> Result<bool, TwoFACodeStoreError> {
-        Ok(self.codes.contains_key(email))
+        Ok(self.codes.read().await.contains_key(email))
     }
-    async fn remove_two_fa_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
-        if let Ok(false) = self.two_fa_code_exists(email).await {
-            return Err(TwoFACodeStoreError::EmailNotFound);
-        }
-        self.codes.remove(email);
-        Ok(())
+    async fn remove_two_fa_code(&self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+        self.codes
+            .write()
+            .await
+            .remove(email)
+            .map(|_| ())
+            .ok_or(TwoFACodeStoreError::EmailNotFound)
     }
     async fn get_two_fa_code(
         &self,
         email: &Email,
     ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
         self.codes
+            .read()
+            .await
             .get(email)
             .map(|(_login_attempt_id, two_fa_code)| {
                 (_login_attempt_id.clone(), two_fa_code.clone())
@@ -48,7 +53,7 @@ impl TwoFACodeStore for HashmapTwoFACodeStore {
 impl HashmapTwoFACodeStore {
     pub fn new() -> Self {
         Self {
-            codes: HashMap::new(),
+            codes: RwLock::new(HashMap::new()),
         }
     }
 }
@@ -67,7 +72,7 @@ mod tests {
 
     async fn setup_store_and_add_code(
     ) -> Result<(HashmapTwoFACodeStore, Email), TwoFACodeStoreError> {
-        let mut store = HashmapTwoFACodeStore::default();
+        let store = HashmapTwoFACodeStore::default();
 
         let email = Email::parse(SecretString::new(
             "test@example.com".to_owned().into_boxed_str(),
@@ -98,7 +103,7 @@ mod tests {
     #[tokio::test]
     async fn test_add_two_fa_code_returns_err_if_email_already_exists(
     ) -> Result<(), TwoFACodeStoreError> {
-        let (mut store, email) = setup_store_and_add_code().await?;
+        let (store, email) = setup_store_and_add_code().await?;
 
         let login_attempt_id = LoginAttemptId::parse(SecretString::new(
             Uuid::new_v4().to_string().into_boxed_str(),
@@ -118,7 +123,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_remove_two_fa_code() -> Result<(), TwoFACodeStoreError> {
-        let (mut store, email) = setup_store_and_add_code().await?;
+        let (store, email) = setup_store_and_add_code().await?;
 
         store.remove_two_fa_code(&email).await?;
 
@@ -129,7 +134,7 @@ mod tests {
     #[tokio::test]
     async fn test_remove_two_fa_code_returns_err_if_email_not_found(
     ) -> Result<(), TwoFACodeStoreError> {
-        let mut store = HashmapTwoFACodeStore::default();
+        let store = HashmapTwoFACodeStore::default();
 
         let email = Email::parse(SecretString::new(
             "missing@example.com".to_owned().into_boxed_str(),
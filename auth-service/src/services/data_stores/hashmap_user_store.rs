@@ -1,9 +1,14 @@
-use crate::domain::{Email, User, UserStore, UserStoreError};
-use secrecy::SecretString;
-use std::collections::HashMap;
+use crate::domain::{
+    Email, HashedPassword, User, UserPage, UserQuery, UserRoles, UserStore, UserStoreError,
+    DEFAULT_ROLES,
+};
+use secrecy::{ExposeSecret, SecretString};
+use std::collections::{BTreeSet, HashMap};
+use tokio::sync::RwLock;
 
 pub struct HashmapUserStore {
-    users: HashMap<Email, User>,
+    users: RwLock<HashMap<Email, User>>,
+    user_roles: RwLock<HashMap<Email, BTreeSet<String>>>,
 }
 
 impl Default for HashmapUserStore {
@@ -15,25 +20,29 @@ impl Default for HashmapUserStore {
 impl HashmapUserStore {
     pub fn new() -> Self {
         Self {
-            users: HashMap::new(),
+            users: RwLock::new(HashMap::new()),
+            user_roles: RwLock::new(HashMap::new()),
         }
     }
 }
 
 #[async_trait::async_trait]
 impl UserStore for HashmapUserStore {
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError> {
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError> {
         // Return `UserStoreError::UserAlreadyExists` if the user already exists,
         // otherwise insert the user into the hashmap and return `Ok(())`.
-        if self.users.contains_key(&user.email) {
+        let mut users = self.users.write().await;
+        if users.contains_key(&user.email) {
             return Err(UserStoreError::UserAlreadyExists);
         }
-        self.users.insert(user.email.clone(), user);
+        users.insert(user.email.clone(), user);
         Ok(())
     }
 
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
         self.users
+            .read()
+            .await
             .get(email)
             .cloned()
             .ok_or(UserStoreError::UserNotFound)
@@ -50,85 +59,151 @@ impl UserStore for HashmapUserStore {
         }
         Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::domain::HashedPassword;
-    use secrecy::SecretString;
-
-    async fn setup_store_and_get_user() -> Result<(HashmapUserStore, User), UserStoreError> {
-        let mut store = HashmapUserStore::new();
-
-        let password = SecretString::new("123DSDFdasd@@456789".to_owned().into_boxed_str());
-
-        store
-            .add_user(User::new(
-                Email::parse(SecretString::new(
-                    "test@example.com".to_owned().into_boxed_str(),
-                ))
-                .unwrap(),
-                HashedPassword::parse(password).await.unwrap(),
-                false,
-            ))
-            .await?;
-
-        let user = store
-            .get_user(
-                &Email::parse(SecretString::new(
-                    "test@example.com".to_owned().into_boxed_str(),
-                ))
-                .unwrap(),
-            )
-            .await?;
-
-        Ok((store, user))
+    async fn update_password(
+        &self,
+        email: &Email,
+        password: HashedPassword,
+    ) -> Result<(), UserStoreError> {
+        let mut users = self.users.write().await;
+        let user = users.get_mut(email).ok_or(UserStoreError::UserNotFound)?;
+        user.password = password;
+        user.password_reset_required = false;
+        Ok(())
     }
 
-    #[tokio::test]
-    async fn test_add_user() -> Result<(), UserStoreError> {
-        let (store, store_user) = setup_store_and_get_user().await?;
-
-        let password = SecretString::new("123DSDFdasd@@456789".to_owned().into_boxed_str());
-
-        store
-            .validate_user(
-                Email::parse(SecretString::new(
-                    "test@example.com".to_owned().into_boxed_str(),
-                ))
-                .unwrap(),
-                &password,
-            )
-            .await?;
+    async fn mark_email_verified(&self, email: &Email) -> Result<(), UserStoreError> {
+        let mut users = self.users.write().await;
+        let user = users.get_mut(email).ok_or(UserStoreError::UserNotFound)?;
+        user.verified = true;
+        Ok(())
+    }
 
-        assert!(!store_user.requires_2fa);
+    async fn set_requires_2fa(
+        &self,
+        email: &Email,
+        requires_2fa: bool,
+    ) -> Result<(), UserStoreError> {
+        let mut users = self.users.write().await;
+        let user = users.get_mut(email).ok_or(UserStoreError::UserNotFound)?;
+        user.requires_2fa = requires_2fa;
+        Ok(())
+    }
 
+    async fn add_role(&self, email: &Email, role: &str) -> Result<(), UserStoreError> {
+        if !DEFAULT_ROLES.iter().any(|(name, _)| *name == role) {
+            return Err(UserStoreError::RoleNotFound);
+        }
+        if !self.users.read().await.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        self.user_roles
+            .write()
+            .await
+            .entry(email.clone())
+            .or_default()
+            .insert(role.to_owned());
         Ok(())
     }
 
-    #[tokio::test]
-    async fn test_get_user() -> Result<(), UserStoreError> {
-        setup_store_and_get_user().await?;
+    async fn remove_role(&self, email: &Email, role: &str) -> Result<(), UserStoreError> {
+        if !self.users.read().await.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        if let Some(roles) = self.user_roles.write().await.get_mut(email) {
+            roles.remove(role);
+        }
         Ok(())
     }
 
-    #[tokio::test]
-    async fn test_validate_user() -> Result<(), UserStoreError> {
-        let (store, _) = setup_store_and_get_user().await?;
+    async fn get_roles(&self, email: &Email) -> Result<UserRoles, UserStoreError> {
+        if !self.users.read().await.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        let roles = self
+            .user_roles
+            .read()
+            .await
+            .get(email)
+            .cloned()
+            .unwrap_or_default();
+        let permissions: BTreeSet<String> = DEFAULT_ROLES
+            .iter()
+            .filter(|(name, _)| roles.contains(*name))
+            .flat_map(|(_, permissions)| permissions.iter().map(|p| (*p).to_owned()))
+            .collect();
+        Ok(UserRoles {
+            roles: roles.into_iter().collect(),
+            permissions: permissions.into_iter().collect(),
+        })
+    }
 
-        let password = SecretString::new("123DSDFdasd@@456789".to_owned().into_boxed_str());
+    async fn list_users(&self, query: &UserQuery) -> Result<UserPage, UserStoreError> {
+        let needle = query.email_contains.as_deref().map(str::to_ascii_lowercase);
+        let users = self.users.read().await;
+        let mut matching: Vec<&User> = users
+            .values()
+            .filter(|user| {
+                needle.as_deref().is_none_or(|needle| {
+                    user.email
+                        .as_ref()
+                        .expose_secret()
+                        .to_ascii_lowercase()
+                        .contains(needle)
+                })
+            })
+            .collect();
+        matching.sort_by(|a, b| {
+            a.email
+                .as_ref()
+                .expose_secret()
+                .cmp(b.email.as_ref().expose_secret())
+        });
+
+        Ok(UserPage {
+            total: matching.len() as u64,
+            users: matching
+                .into_iter()
+                .skip(query.offset as usize)
+                .take(query.limit as usize)
+                .cloned()
+                .collect(),
+        })
+    }
 
-        store
-            .validate_user(
-                Email::parse(SecretString::new(
-                    "test@example.com".to_owned().into_boxed_str(),
-                ))
-                .unwrap(),
-                &password,
-            )
-            .await?;
+    async fn set_disabled(&self, email: &Email, disabled: bool) -> Result<(), UserStoreError> {
+        let mut users = self.users.write().await;
+        let user = users.get_mut(email).ok_or(UserStoreError::UserNotFound)?;
+        user.disabled = disabled;
+        Ok(())
+    }
+
+    async fn set_password_reset_required(
+        &self,
+        email: &Email,
+        required: bool,
+    ) -> Result<(), UserStoreError> {
+        let mut users = self.users.write().await;
+        let user = users.get_mut(email).ok_or(UserStoreError::UserNotFound)?;
+        user.password_reset_required = required;
+        Ok(())
+    }
 
+    async fn delete_user(&self, email: &Email) -> Result<(), UserStoreError> {
+        self.users
+            .write()
+            .await
+            .remove(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        self.user_roles.write().await.remove(email);
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::HashmapUserStore;
+    use crate::services::data_stores::user_store_tests::user_store_tests;
+
+    user_store_tests!(async { HashmapUserStore::new() });
+}
@@ -1,30 +1,55 @@
 use crate::domain::data_store::BannedTokenStore;
 use crate::domain::error::BannedTokenStoreError;
+use crate::domain::Email;
 use secrecy::{ExposeSecret, SecretString};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use tokio::sync::RwLock;
 
-#[derive(Clone)]
 pub struct HashsetBannedTokenStore {
-    tokens: HashSet<String>,
+    tokens: RwLock<HashSet<String>>,
+    user_bans: RwLock<HashMap<Email, i64>>,
 }
 
 #[async_trait::async_trait]
 impl BannedTokenStore for HashsetBannedTokenStore {
-    async fn add_token(&mut self, token: SecretString) -> Result<(), BannedTokenStoreError> {
-        self.tokens.insert(token.expose_secret().to_owned());
+    async fn add_token(&self, token: SecretString) -> Result<(), BannedTokenStoreError> {
+        self.tokens
+            .write()
+            .await
+            .insert(token.expose_secret().to_owned());
         Ok(())
     }
 
     async fn token_exists(&self, token: &SecretString) -> Result<bool, BannedTokenStoreError> {
-        let exists = self.tokens.contains(token.expose_secret());
+        let exists = self.tokens.read().await.contains(token.expose_secret());
         Ok(exists)
     }
+
+    async fn ban_user_tokens(
+        &self,
+        email: &Email,
+        issued_before: i64,
+    ) -> Result<(), BannedTokenStoreError> {
+        self.user_bans
+            .write()
+            .await
+            .insert(email.clone(), issued_before);
+        Ok(())
+    }
+
+    async fn user_tokens_banned_before(
+        &self,
+        email: &Email,
+    ) -> Result<Option<i64>, BannedTokenStoreError> {
+        Ok(self.user_bans.read().await.get(email).copied())
+    }
 }
 
 impl HashsetBannedTokenStore {
     pub fn new() -> Self {
         Self {
-            tokens: HashSet::new(),
+            tokens: RwLock::new(HashSet::new()),
+            user_bans: RwLock::new(HashMap::new()),
         }
     }
 }
@@ -42,7 +67,7 @@ mod tests {
 
     #[tokio::test]
     async fn adds_token_correctly() {
-        let mut banned_token_store = HashsetBannedTokenStore::new();
+        let banned_token_store = HashsetBannedTokenStore::new();
 
         let token = SecretString::new("token1234567890".to_owned().into_boxed_str());
 
@@ -53,7 +78,7 @@ mod tests {
 
     #[tokio::test]
     async fn returns_false_for_nonexisting_token() {
-        let mut banned_token_store = HashsetBannedTokenStore::new();
+        let banned_token_store = HashsetBannedTokenStore::new();
 
         let tokens = [
             "token123456790",
@@ -73,4 +98,34 @@ mod tests {
 
         assert!(!banned_token_store.token_exists(&missing).await.unwrap());
     }
+
+    #[tokio::test]
+    async fn bans_user_tokens() {
+        let banned_token_store = HashsetBannedTokenStore::new();
+        let email = Email::parse(SecretString::new(
+            "test@example.com".to_owned().into_boxed_str(),
+        ))
+        .unwrap();
+
+        assert_eq!(
+            banned_token_store
+                .user_tokens_banned_before(&email)
+                .await
+                .unwrap(),
+            None
+        );
+
+        banned_token_store
+            .ban_user_tokens(&email, 1_700_000_000)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            banned_token_store
+                .user_tokens_banned_before(&email)
+                .await
+                .unwrap(),
+            Some(1_700_000_000)
+        );
+    }
 }
@@ -0,0 +1,139 @@
+use crate::domain::data_store::BannedTokenStore;
+use crate::domain::error::BannedTokenStoreError;
+use crate::domain::Email;
+use crate::utils::auth::token_ttl_seconds;
+use crate::utils::metrics::time_store;
+use chrono::Utc;
+use secrecy::{ExposeSecret, SecretString};
+use sqlx::PgPool;
+
+pub struct PostgresBannedTokenStore {
+    pool: PgPool,
+}
+
+impl PostgresBannedTokenStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // Delete bans that expired at or before `now`, returning how many were removed
+    #[tracing::instrument(name = "Purging expired banned tokens from PostgreSQL", skip_all)]
+    pub async fn purge_expired(&self, now: i64) -> Result<u64, BannedTokenStoreError> {
+        let _timer = time_store("postgres", "banned_token", "purge_expired");
+        let tokens = sqlx::query!(
+            r#"
+            DELETE FROM banned_tokens
+            WHERE expires_at <= $1
+            "#,
+            now,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        let user_tokens = sqlx::query!(
+            r#"
+            DELETE FROM banned_user_tokens
+            WHERE expires_at <= $1
+            "#,
+            now,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(tokens.rows_affected() + user_tokens.rows_affected())
+    }
+}
+
+#[async_trait::async_trait]
+impl BannedTokenStore for PostgresBannedTokenStore {
+    #[tracing::instrument(name = "Adding banned token to PostgreSQL", skip_all)]
+    async fn add_token(&self, token: SecretString) -> Result<(), BannedTokenStoreError> {
+        let _timer = time_store("postgres", "banned_token", "add_token");
+        // A banned token only has to be remembered until it would have expired
+        sqlx::query!(
+            r#"
+            INSERT INTO banned_tokens (token, expires_at)
+            VALUES ($1, $2)
+            ON CONFLICT (token) DO UPDATE
+            SET expires_at = EXCLUDED.expires_at
+            "#,
+            token.expose_secret(),
+            Utc::now().timestamp() + token_ttl_seconds(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Checking banned token in PostgreSQL", skip_all)]
+    async fn token_exists(&self, token: &SecretString) -> Result<bool, BannedTokenStoreError> {
+        let _timer = time_store("postgres", "banned_token", "token_exists");
+        let row = sqlx::query!(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM banned_tokens
+                WHERE token = $1 AND expires_at > $2
+            ) AS "exists!"
+            "#,
+            token.expose_secret(),
+            Utc::now().timestamp(),
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(row.exists)
+    }
+
+    #[tracing::instrument(name = "Banning user tokens in PostgreSQL", skip_all)]
+    async fn ban_user_tokens(
+        &self,
+        email: &Email,
+        issued_before: i64,
+    ) -> Result<(), BannedTokenStoreError> {
+        let _timer = time_store("postgres", "banned_token", "ban_user_tokens");
+        // Once the token TTL has passed every affected token has expired anyway
+        sqlx::query!(
+            r#"
+            INSERT INTO banned_user_tokens (email, issued_before, expires_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (email) DO UPDATE
+            SET issued_before = EXCLUDED.issued_before, expires_at = EXCLUDED.expires_at
+            "#,
+            email.as_ref().expose_secret(),
+            issued_before,
+            Utc::now().timestamp() + token_ttl_seconds(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving user token ban from PostgreSQL", skip_all)]
+    async fn user_tokens_banned_before(
+        &self,
+        email: &Email,
+    ) -> Result<Option<i64>, BannedTokenStoreError> {
+        let _timer = time_store("postgres", "banned_token", "user_tokens_banned_before");
+        let row = sqlx::query!(
+            r#"
+            SELECT issued_before
+            FROM banned_user_tokens
+            WHERE email = $1 AND expires_at > $2
+            "#,
+            email.as_ref().expose_secret(),
+            Utc::now().timestamp(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(row.map(|row| row.issued_before))
+    }
+}
@@ -0,0 +1,128 @@
+use crate::domain::data_store::LoginAttemptStore;
+use crate::domain::error::LoginAttemptStoreError;
+use crate::domain::types::{LoginAttemptKey, LoginAttempts};
+use crate::utils::metrics::time_store;
+use chrono::Utc;
+use secrecy::ExposeSecret;
+use sqlx::PgPool;
+
+pub struct PostgresLoginAttemptStore {
+    pool: PgPool,
+}
+
+impl PostgresLoginAttemptStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // Delete failures that expired at or before `now`, returning how many
+    // keys were removed
+    #[tracing::instrument(name = "Purging expired login attempts from PostgreSQL", skip_all)]
+    pub async fn purge_expired(&self, now: i64) -> Result<u64, LoginAttemptStoreError> {
+        let _timer = time_store("postgres", "login_attempt", "purge_expired");
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM login_attempts
+            WHERE expires_at <= $1
+            "#,
+            now,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| LoginAttemptStoreError::UnexpectedError(e.into()))?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait::async_trait]
+impl LoginAttemptStore for PostgresLoginAttemptStore {
+    #[tracing::instrument(name = "Retrieving login attempts from PostgreSQL", skip_all)]
+    async fn get_attempts(
+        &self,
+        key: &LoginAttemptKey,
+    ) -> Result<LoginAttempts, LoginAttemptStoreError> {
+        let _timer = time_store("postgres", "login_attempt", "get_attempts");
+        let row = sqlx::query!(
+            r#"
+            SELECT failures, last_failure_at
+            FROM login_attempts
+            WHERE key = $1 AND expires_at > $2
+            "#,
+            get_key(key),
+            Utc::now().timestamp(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| LoginAttemptStoreError::UnexpectedError(e.into()))?;
+
+        Ok(row
+            .map(|row| LoginAttempts {
+                failures: row.failures as u32,
+                last_failure_at: row.last_failure_at,
+            })
+            .unwrap_or_default())
+    }
+
+    #[tracing::instrument(name = "Recording failed login attempt in PostgreSQL", skip_all)]
+    async fn record_failure(
+        &self,
+        key: &LoginAttemptKey,
+        ttl_seconds: u64,
+    ) -> Result<LoginAttempts, LoginAttemptStoreError> {
+        let _timer = time_store("postgres", "login_attempt", "record_failure");
+        let now = Utc::now().timestamp();
+        // Counting in a single upsert keeps the count right across several
+        // instances. Failures from an expired window start over.
+        let row = sqlx::query!(
+            r#"
+            INSERT INTO login_attempts (key, failures, last_failure_at, expires_at)
+            VALUES ($1, 1, $2, $3)
+            ON CONFLICT (key) DO UPDATE
+            SET failures = CASE
+                    WHEN login_attempts.expires_at > $2 THEN login_attempts.failures + 1
+                    ELSE 1
+                END,
+                last_failure_at = EXCLUDED.last_failure_at,
+                expires_at = EXCLUDED.expires_at
+            RETURNING failures
+            "#,
+            get_key(key),
+            now,
+            now + ttl_seconds as i64,
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| LoginAttemptStoreError::UnexpectedError(e.into()))?;
+
+        Ok(LoginAttempts {
+            failures: row.failures as u32,
+            last_failure_at: now,
+        })
+    }
+
+    #[tracing::instrument(name = "Resetting login attempts in PostgreSQL", skip_all)]
+    async fn reset(&self, key: &LoginAttemptKey) -> Result<(), LoginAttemptStoreError> {
+        let _timer = time_store("postgres", "login_attempt", "reset");
+        sqlx::query!(
+            r#"
+            DELETE FROM login_attempts
+            WHERE key = $1
+            "#,
+            get_key(key),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| LoginAttemptStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
+
+// The same keys as `RedisLoginAttemptStore`, without the prefix
+fn get_key(key: &LoginAttemptKey) -> String {
+    match key {
+        LoginAttemptKey::Account(email) => format!("account:{}", email.as_ref().expose_secret()),
+        LoginAttemptKey::Ip(ip) => format!("ip:{}", ip),
+    }
+}
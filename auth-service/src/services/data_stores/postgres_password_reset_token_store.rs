@@ -0,0 +1,108 @@
+use crate::domain::data_store::PasswordResetTokenStore;
+use crate::domain::error::PasswordResetTokenStoreError;
+use crate::domain::types::{Email, PasswordResetToken};
+use crate::utils::metrics::time_store;
+use crate::utils::settings::settings;
+use chrono::Utc;
+use color_eyre::eyre::Report;
+use secrecy::{ExposeSecret, SecretString};
+use sqlx::PgPool;
+
+pub struct PostgresPasswordResetTokenStore {
+    pool: PgPool,
+}
+
+impl PostgresPasswordResetTokenStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // Delete tokens that expired at or before `now`, returning how many were removed
+    #[tracing::instrument(
+        name = "Purging expired password reset tokens from PostgreSQL",
+        skip_all
+    )]
+    pub async fn purge_expired(&self, now: i64) -> Result<u64, PasswordResetTokenStoreError> {
+        let _timer = time_store("postgres", "password_reset_token", "purge_expired");
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM password_reset_tokens
+            WHERE expires_at <= $1
+            "#,
+            now,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| PasswordResetTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait::async_trait]
+impl PasswordResetTokenStore for PostgresPasswordResetTokenStore {
+    #[tracing::instrument(name = "Adding password reset token to PostgreSQL", skip_all)]
+    async fn add_token(
+        &self,
+        token: PasswordResetToken,
+        email: Email,
+    ) -> Result<(), PasswordResetTokenStoreError> {
+        let _timer = time_store("postgres", "password_reset_token", "add_token");
+        sqlx::query!(
+            r#"
+            INSERT INTO password_reset_tokens (token, email, expires_at)
+            VALUES ($1, $2, $3)
+            "#,
+            token.as_ref().expose_secret(),
+            email.as_ref().expose_secret(),
+            Utc::now().timestamp() + settings().tokens.password_reset_token_ttl_seconds as i64,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| PasswordResetTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Taking password reset token from PostgreSQL", skip_all)]
+    async fn take_token(
+        &self,
+        token: &PasswordResetToken,
+    ) -> Result<Email, PasswordResetTokenStoreError> {
+        let _timer = time_store("postgres", "password_reset_token", "take_token");
+        // Deleting the row is what redeems the token, so it can only be used once
+        let row = sqlx::query!(
+            r#"
+            DELETE FROM password_reset_tokens
+            WHERE token = $1 AND expires_at > $2
+            RETURNING email
+            "#,
+            token.as_ref().expose_secret(),
+            Utc::now().timestamp(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| PasswordResetTokenStoreError::UnexpectedError(e.into()))?
+        .ok_or(PasswordResetTokenStoreError::TokenNotFound)?;
+
+        Email::parse(SecretString::new(row.email.into_boxed_str()))
+            .map_err(|e| PasswordResetTokenStoreError::UnexpectedError(Report::new(e)))
+    }
+
+    #[tracing::instrument(name = "Removing user password reset tokens from PostgreSQL", skip_all)]
+    async fn remove_user_tokens(&self, email: &Email) -> Result<(), PasswordResetTokenStoreError> {
+        let _timer = time_store("postgres", "password_reset_token", "remove_user_tokens");
+        sqlx::query!(
+            r#"
+            DELETE FROM password_reset_tokens
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| PasswordResetTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
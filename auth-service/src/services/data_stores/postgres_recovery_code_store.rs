@@ -0,0 +1,118 @@
+use crate::domain::data_store::RecoveryCodeStore;
+use crate::domain::error::RecoveryCodeStoreError;
+use crate::domain::types::{Email, HashedRecoveryCode};
+use crate::utils::metrics::time_store;
+use color_eyre::eyre::eyre;
+use secrecy::{ExposeSecret, SecretString};
+use sqlx::PgPool;
+
+pub struct PostgresRecoveryCodeStore {
+    pool: PgPool,
+}
+
+impl PostgresRecoveryCodeStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl RecoveryCodeStore for PostgresRecoveryCodeStore {
+    #[tracing::instrument(name = "Replacing recovery codes in PostgreSQL", skip_all)]
+    async fn replace_codes(
+        &self,
+        email: &Email,
+        codes: Vec<HashedRecoveryCode>,
+    ) -> Result<(), RecoveryCodeStoreError> {
+        let _timer = time_store("postgres", "recovery_code", "replace_codes");
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| RecoveryCodeStoreError::UnexpectedError(e.into()))?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM recovery_codes
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| RecoveryCodeStoreError::UnexpectedError(e.into()))?;
+
+        let code_hashes: Vec<String> = codes
+            .iter()
+            .map(|code| code.as_ref().expose_secret().to_owned())
+            .collect();
+
+        sqlx::query!(
+            r#"
+            INSERT INTO recovery_codes (email, code_hash)
+            SELECT $1, code_hash FROM UNNEST($2::TEXT[]) AS code_hash
+            "#,
+            email.as_ref().expose_secret(),
+            &code_hashes,
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| RecoveryCodeStoreError::UnexpectedError(e.into()))?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| RecoveryCodeStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Retrieving recovery codes from PostgreSQL", skip_all)]
+    async fn get_codes(
+        &self,
+        email: &Email,
+    ) -> Result<Vec<HashedRecoveryCode>, RecoveryCodeStoreError> {
+        let _timer = time_store("postgres", "recovery_code", "get_codes");
+        sqlx::query!(
+            r#"
+            SELECT code_hash
+            FROM recovery_codes
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RecoveryCodeStoreError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(|row| {
+            HashedRecoveryCode::parse_hash(SecretString::new(row.code_hash.into_boxed_str()))
+                .map_err(|e| RecoveryCodeStoreError::UnexpectedError(eyre!(e)))
+        })
+        .collect()
+    }
+
+    #[tracing::instrument(name = "Removing recovery code from PostgreSQL", skip_all)]
+    async fn remove_code(
+        &self,
+        email: &Email,
+        code: &HashedRecoveryCode,
+    ) -> Result<(), RecoveryCodeStoreError> {
+        let _timer = time_store("postgres", "recovery_code", "remove_code");
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM recovery_codes
+            WHERE email = $1 AND code_hash = $2
+            "#,
+            email.as_ref().expose_secret(),
+            code.as_ref().expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RecoveryCodeStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(RecoveryCodeStoreError::CodeNotFound);
+        }
+
+        Ok(())
+    }
+}
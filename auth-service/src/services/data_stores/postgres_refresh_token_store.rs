@@ -0,0 +1,206 @@
+use crate::domain::data_store::RefreshTokenStore;
+use crate::domain::error::RefreshTokenStoreError;
+use crate::domain::types::{Email, RefreshTokenRecord};
+use crate::utils::auth::refresh_token_ttl_seconds;
+use crate::utils::metrics::time_store;
+use chrono::Utc;
+use color_eyre::eyre::Report;
+use secrecy::{ExposeSecret, SecretString};
+use sqlx::PgPool;
+
+pub struct PostgresRefreshTokenStore {
+    pool: PgPool,
+}
+
+impl PostgresRefreshTokenStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // Delete tokens and revocations that expired at or before `now`,
+    // returning how many were removed
+    #[tracing::instrument(name = "Purging expired refresh tokens from PostgreSQL", skip_all)]
+    pub async fn purge_expired(&self, now: i64) -> Result<u64, RefreshTokenStoreError> {
+        let _timer = time_store("postgres", "refresh_token", "purge_expired");
+        let tokens = sqlx::query!(
+            r#"
+            DELETE FROM refresh_tokens
+            WHERE expires_at <= $1
+            "#,
+            now,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        let families = sqlx::query!(
+            r#"
+            DELETE FROM revoked_refresh_token_families
+            WHERE expires_at <= $1
+            "#,
+            now,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(tokens.rows_affected() + families.rows_affected())
+    }
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for PostgresRefreshTokenStore {
+    #[tracing::instrument(name = "Adding refresh token to PostgreSQL", skip_all)]
+    async fn add_refresh_token(
+        &self,
+        token: SecretString,
+        record: RefreshTokenRecord,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let _timer = time_store("postgres", "refresh_token", "add_refresh_token");
+        sqlx::query!(
+            r#"
+            INSERT INTO refresh_tokens (token, email, family_id, used, expires_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (token) DO UPDATE
+            SET email = EXCLUDED.email,
+                family_id = EXCLUDED.family_id,
+                used = EXCLUDED.used,
+                expires_at = EXCLUDED.expires_at
+            "#,
+            token.expose_secret(),
+            record.email.as_ref().expose_secret(),
+            record.family_id,
+            record.used,
+            Utc::now().timestamp() + refresh_token_ttl_seconds(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving refresh token from PostgreSQL", skip_all)]
+    async fn get_refresh_token(
+        &self,
+        token: &SecretString,
+    ) -> Result<RefreshTokenRecord, RefreshTokenStoreError> {
+        let _timer = time_store("postgres", "refresh_token", "get_refresh_token");
+        let row = sqlx::query!(
+            r#"
+            SELECT email, family_id, used
+            FROM refresh_tokens
+            WHERE token = $1 AND expires_at > $2
+            "#,
+            token.expose_secret(),
+            Utc::now().timestamp(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?
+        .ok_or(RefreshTokenStoreError::TokenNotFound)?;
+
+        let email = Email::parse(SecretString::new(row.email.into_boxed_str()))
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(Report::new(e)))?;
+        Ok(RefreshTokenRecord {
+            email,
+            family_id: row.family_id,
+            used: row.used,
+        })
+    }
+
+    #[tracing::instrument(name = "Marking refresh token used in PostgreSQL", skip_all)]
+    async fn mark_refresh_token_used(
+        &self,
+        token: &SecretString,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let _timer = time_store("postgres", "refresh_token", "mark_refresh_token_used");
+        self.get_refresh_token(token).await?;
+
+        // A single conditional UPDATE, so of several concurrent rotations
+        // only one succeeds
+        let result = sqlx::query!(
+            r#"
+            UPDATE refresh_tokens
+            SET used = TRUE
+            WHERE token = $1 AND used = FALSE
+            "#,
+            token.expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(RefreshTokenStoreError::TokenAlreadyUsed);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Revoking refresh token family in PostgreSQL", skip_all)]
+    async fn revoke_family(&self, family_id: &str) -> Result<(), RefreshTokenStoreError> {
+        let _timer = time_store("postgres", "refresh_token", "revoke_family");
+        // Once the refresh token TTL has passed every token of the family has
+        // expired anyway
+        sqlx::query!(
+            r#"
+            INSERT INTO revoked_refresh_token_families (family_id, expires_at)
+            VALUES ($1, $2)
+            ON CONFLICT (family_id) DO UPDATE
+            SET expires_at = EXCLUDED.expires_at
+            "#,
+            family_id,
+            Utc::now().timestamp() + refresh_token_ttl_seconds(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Checking refresh token family in PostgreSQL", skip_all)]
+    async fn is_family_revoked(&self, family_id: &str) -> Result<bool, RefreshTokenStoreError> {
+        let _timer = time_store("postgres", "refresh_token", "is_family_revoked");
+        let row = sqlx::query!(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM revoked_refresh_token_families
+                WHERE family_id = $1 AND expires_at > $2
+            ) AS "exists!"
+            "#,
+            family_id,
+            Utc::now().timestamp(),
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(row.exists)
+    }
+
+    #[tracing::instrument(name = "Revoking user refresh token families in PostgreSQL", skip_all)]
+    async fn revoke_user_families(&self, email: &Email) -> Result<(), RefreshTokenStoreError> {
+        let _timer = time_store("postgres", "refresh_token", "revoke_user_families");
+        let now = Utc::now().timestamp();
+        sqlx::query!(
+            r#"
+            INSERT INTO revoked_refresh_token_families (family_id, expires_at)
+            SELECT DISTINCT family_id, $3::BIGINT
+            FROM refresh_tokens
+            WHERE email = $1 AND expires_at > $2
+            ON CONFLICT (family_id) DO UPDATE
+            SET expires_at = EXCLUDED.expires_at
+            "#,
+            email.as_ref().expose_secret(),
+            now,
+            now + refresh_token_ttl_seconds(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
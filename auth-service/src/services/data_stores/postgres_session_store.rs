@@ -0,0 +1,194 @@
+use crate::domain::data_store::SessionStore;
+use crate::domain::error::SessionStoreError;
+use crate::domain::types::{Email, Session};
+use crate::utils::auth::refresh_token_ttl_seconds;
+use crate::utils::metrics::time_store;
+use chrono::Utc;
+use color_eyre::eyre::Report;
+use secrecy::{ExposeSecret, SecretString};
+use sqlx::PgPool;
+
+pub struct PostgresSessionStore {
+    pool: PgPool,
+}
+
+impl PostgresSessionStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // Delete sessions that expired at or before `now`, returning how many
+    // were removed
+    #[tracing::instrument(name = "Purging expired sessions from PostgreSQL", skip_all)]
+    pub async fn purge_expired(&self, now: i64) -> Result<u64, SessionStoreError> {
+        let _timer = time_store("postgres", "session", "purge_expired");
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM sessions
+            WHERE expires_at <= $1
+            "#,
+            now,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait::async_trait]
+impl SessionStore for PostgresSessionStore {
+    #[tracing::instrument(name = "Adding session to PostgreSQL", skip_all)]
+    async fn add_session(&self, session: Session) -> Result<(), SessionStoreError> {
+        let _timer = time_store("postgres", "session", "add_session");
+        // A session lasts as long as its refresh token family can be used
+        sqlx::query!(
+            r#"
+            INSERT INTO sessions (id, email, user_agent, ip, created_at, last_seen_at, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (id) DO UPDATE
+            SET email = EXCLUDED.email,
+                user_agent = EXCLUDED.user_agent,
+                ip = EXCLUDED.ip,
+                created_at = EXCLUDED.created_at,
+                last_seen_at = EXCLUDED.last_seen_at,
+                expires_at = EXCLUDED.expires_at
+            "#,
+            session.id,
+            session.email.as_ref().expose_secret(),
+            session.user_agent,
+            session.ip,
+            session.created_at,
+            session.last_seen_at,
+            Utc::now().timestamp() + refresh_token_ttl_seconds(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving session from PostgreSQL", skip_all)]
+    async fn get_session(&self, id: &str) -> Result<Session, SessionStoreError> {
+        let _timer = time_store("postgres", "session", "get_session");
+        let row = sqlx::query!(
+            r#"
+            SELECT id, email, user_agent, ip, created_at, last_seen_at
+            FROM sessions
+            WHERE id = $1 AND expires_at > $2
+            "#,
+            id,
+            Utc::now().timestamp(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?
+        .ok_or(SessionStoreError::SessionNotFound)?;
+
+        Ok(Session {
+            id: row.id,
+            email: Email::parse(SecretString::new(row.email.into_boxed_str()))
+                .map_err(|e| SessionStoreError::UnexpectedError(Report::new(e)))?,
+            user_agent: row.user_agent,
+            ip: row.ip,
+            created_at: row.created_at,
+            last_seen_at: row.last_seen_at,
+        })
+    }
+
+    #[tracing::instrument(name = "Retrieving user sessions from PostgreSQL", skip_all)]
+    async fn get_user_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError> {
+        let _timer = time_store("postgres", "session", "get_user_sessions");
+        sqlx::query!(
+            r#"
+            SELECT id, user_agent, ip, created_at, last_seen_at
+            FROM sessions
+            WHERE email = $1 AND expires_at > $2
+            "#,
+            email.as_ref().expose_secret(),
+            Utc::now().timestamp(),
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))
+        .map(|rows| {
+            rows.into_iter()
+                .map(|row| Session {
+                    id: row.id,
+                    email: email.clone(),
+                    user_agent: row.user_agent,
+                    ip: row.ip,
+                    created_at: row.created_at,
+                    last_seen_at: row.last_seen_at,
+                })
+                .collect()
+        })
+    }
+
+    #[tracing::instrument(name = "Touching session in PostgreSQL", skip_all)]
+    async fn touch_session(&self, id: &str, last_seen_at: i64) -> Result<(), SessionStoreError> {
+        let _timer = time_store("postgres", "session", "touch_session");
+        let now = Utc::now().timestamp();
+        let result = sqlx::query!(
+            r#"
+            UPDATE sessions
+            SET last_seen_at = $2, expires_at = $4
+            WHERE id = $1 AND expires_at > $3
+            "#,
+            id,
+            last_seen_at,
+            now,
+            now + refresh_token_ttl_seconds(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(SessionStoreError::SessionNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Removing session from PostgreSQL", skip_all)]
+    async fn remove_session(&self, id: &str) -> Result<(), SessionStoreError> {
+        let _timer = time_store("postgres", "session", "remove_session");
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM sessions
+            WHERE id = $1 AND expires_at > $2
+            "#,
+            id,
+            Utc::now().timestamp(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(SessionStoreError::SessionNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Removing user sessions from PostgreSQL", skip_all)]
+    async fn remove_user_sessions(&self, email: &Email) -> Result<(), SessionStoreError> {
+        let _timer = time_store("postgres", "session", "remove_user_sessions");
+        sqlx::query!(
+            r#"
+            DELETE FROM sessions
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
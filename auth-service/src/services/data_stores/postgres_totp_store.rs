@@ -0,0 +1,140 @@
+use crate::domain::data_store::TotpStore;
+use crate::domain::error::TotpStoreError;
+use crate::domain::types::{Email, TotpRecord, TotpSecret};
+use crate::utils::metrics::time_store;
+use color_eyre::eyre::eyre;
+use secrecy::{ExposeSecret, SecretString};
+use sqlx::PgPool;
+
+pub struct PostgresTotpStore {
+    pool: PgPool,
+}
+
+impl PostgresTotpStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl TotpStore for PostgresTotpStore {
+    #[tracing::instrument(name = "Adding pending TOTP secret to PostgreSQL", skip_all)]
+    async fn add_pending_secret(
+        &self,
+        email: &Email,
+        secret: TotpSecret,
+    ) -> Result<(), TotpStoreError> {
+        let _timer = time_store("postgres", "totp", "add_pending_secret");
+        // The conditional upsert leaves a confirmed secret untouched, in which
+        // case no row is affected.
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO totp_secrets (email, secret, confirmed, last_used_step)
+            VALUES ($1, $2, FALSE, NULL)
+            ON CONFLICT (email) DO UPDATE
+            SET secret = EXCLUDED.secret, last_used_step = NULL
+            WHERE totp_secrets.confirmed = FALSE
+            "#,
+            email.as_ref().expose_secret(),
+            secret.as_ref().expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TotpStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(TotpStoreError::AlreadyEnabled);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving TOTP secret from PostgreSQL", skip_all)]
+    async fn get_totp(&self, email: &Email) -> Result<TotpRecord, TotpStoreError> {
+        let _timer = time_store("postgres", "totp", "get_totp");
+        sqlx::query!(
+            r#"
+            SELECT secret, confirmed, last_used_step
+            FROM totp_secrets
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| TotpStoreError::UnexpectedError(e.into()))?
+        .map(|row| {
+            Ok(TotpRecord {
+                secret: TotpSecret::parse(SecretString::new(row.secret.into_boxed_str()))
+                    .map_err(|e| TotpStoreError::UnexpectedError(eyre!(e)))?,
+                confirmed: row.confirmed,
+                last_used_step: row.last_used_step,
+            })
+        })
+        .ok_or(TotpStoreError::SecretNotFound)?
+    }
+
+    #[tracing::instrument(name = "Confirming TOTP secret in PostgreSQL", skip_all)]
+    async fn confirm_secret(&self, email: &Email) -> Result<(), TotpStoreError> {
+        let _timer = time_store("postgres", "totp", "confirm_secret");
+        let result = sqlx::query!(
+            r#"
+            UPDATE totp_secrets
+            SET confirmed = TRUE
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TotpStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(TotpStoreError::SecretNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Recording used TOTP step in PostgreSQL", skip_all)]
+    async fn record_used_step(&self, email: &Email, step: i64) -> Result<(), TotpStoreError> {
+        let _timer = time_store("postgres", "totp", "record_used_step");
+        // A single conditional UPDATE, so two concurrent requests can't both
+        // redeem the same code.
+        let result = sqlx::query!(
+            r#"
+            UPDATE totp_secrets
+            SET last_used_step = $2
+            WHERE email = $1 AND (last_used_step IS NULL OR last_used_step < $2)
+            "#,
+            email.as_ref().expose_secret(),
+            step,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TotpStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(TotpStoreError::StepAlreadyUsed);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Removing TOTP secret from PostgreSQL", skip_all)]
+    async fn remove_totp(&self, email: &Email) -> Result<(), TotpStoreError> {
+        let _timer = time_store("postgres", "totp", "remove_totp");
+        sqlx::query!(
+            r#"
+            DELETE FROM totp_secrets
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TotpStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
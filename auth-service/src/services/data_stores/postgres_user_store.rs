@@ -1,7 +1,11 @@
-use crate::domain::{Email, HashedPassword, User, UserStore, UserStoreError};
+use crate::domain::{
+    Email, HashedPassword, User, UserPage, UserQuery, UserRoles, UserStore, UserStoreError,
+};
+use crate::utils::metrics::time_store;
 use color_eyre::eyre::{eyre, Result};
 use secrecy::{ExposeSecret, SecretString};
 use sqlx::PgPool;
+use std::collections::BTreeSet;
 
 pub struct PostgresUserStore {
     pool: PgPool,
@@ -16,34 +20,52 @@ impl PostgresUserStore {
 #[async_trait::async_trait]
 impl UserStore for PostgresUserStore {
     #[tracing::instrument(name = "Adding user to PostgreSQL", skip_all)]
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError> {
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError> {
+        let _timer = time_store("postgres", "user", "add_user");
         let User {
             email,
             password: password_hash,
             requires_2fa,
+            verified,
+            disabled,
+            password_reset_required,
         } = user;
 
         sqlx::query!(
             r#"
-                INSERT INTO users (email, password_hash, requires_2fa)
-                VALUES ($1, $2, $3)
+                INSERT INTO users (
+                    email, password_hash, requires_2fa, verified, disabled,
+                    password_reset_required
+                )
+                VALUES ($1, $2, $3, $4, $5, $6)
             "#,
             email.as_ref().expose_secret(),
             password_hash.as_ref().expose_secret(),
             requires_2fa,
+            verified,
+            disabled,
+            password_reset_required,
         )
         .execute(&self.pool)
         .await
-        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        .map_err(|e| match e {
+            // The email is the primary key, so a duplicate signup violates it
+            sqlx::Error::Database(e) if e.is_unique_violation() => {
+                UserStoreError::UserAlreadyExists
+            }
+            e => UserStoreError::UnexpectedError(e.into()),
+        })?;
 
         Ok(())
     }
 
     #[tracing::instrument(name = "Retrieving user from PostgreSQL", skip_all)]
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
-        sqlx::query!(
+        let _timer = time_store("postgres", "user", "get_user");
+        sqlx::query_as!(
+            UserRow,
             r#"
-            SELECT email, password_hash, requires_2fa
+            SELECT email, password_hash, requires_2fa, verified, disabled, password_reset_required
             FROM users
             WHERE email = $1
             "#,
@@ -52,17 +74,7 @@ impl UserStore for PostgresUserStore {
         .fetch_optional(&self.pool)
         .await
         .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
-        .map(|row| {
-            Ok(User {
-                email: Email::parse(SecretString::new(row.email.to_owned().into_boxed_str()))
-                    .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
-                password: HashedPassword::parse_password_hash(SecretString::new(
-                    row.password_hash.to_owned().into_boxed_str(),
-                ))
-                .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
-                requires_2fa: row.requires_2fa,
-            })
-        })
+        .map(User::try_from)
         .ok_or(UserStoreError::UserNotFound)?
     }
 
@@ -78,4 +90,331 @@ impl UserStore for PostgresUserStore {
         }
         Ok(())
     }
+
+    #[tracing::instrument(name = "Updating user password in PostgreSQL", skip_all)]
+    async fn update_password(
+        &self,
+        email: &Email,
+        password: HashedPassword,
+    ) -> Result<(), UserStoreError> {
+        let _timer = time_store("postgres", "user", "update_password");
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET password_hash = $2, password_reset_required = FALSE
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+            password.as_ref().expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Marking user email as verified in PostgreSQL", skip_all)]
+    async fn mark_email_verified(&self, email: &Email) -> Result<(), UserStoreError> {
+        let _timer = time_store("postgres", "user", "mark_email_verified");
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET verified = TRUE
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Updating user 2FA requirement in PostgreSQL", skip_all)]
+    async fn set_requires_2fa(
+        &self,
+        email: &Email,
+        requires_2fa: bool,
+    ) -> Result<(), UserStoreError> {
+        let _timer = time_store("postgres", "user", "set_requires_2fa");
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET requires_2fa = $2
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+            requires_2fa,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Adding user role in PostgreSQL", skip_all)]
+    async fn add_role(&self, email: &Email, role: &str) -> Result<(), UserStoreError> {
+        let _timer = time_store("postgres", "user", "add_role");
+        sqlx::query!(
+            r#"
+            INSERT INTO user_roles (email, role)
+            VALUES ($1, $2)
+            ON CONFLICT DO NOTHING
+            "#,
+            email.as_ref().expose_secret(),
+            role,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match e {
+            // Either the user or the role doesn't exist
+            sqlx::Error::Database(e) if e.is_foreign_key_violation() => match e.constraint() {
+                Some("user_roles_role_fkey") => UserStoreError::RoleNotFound,
+                _ => UserStoreError::UserNotFound,
+            },
+            e => UserStoreError::UnexpectedError(e.into()),
+        })?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Removing user role in PostgreSQL", skip_all)]
+    async fn remove_role(&self, email: &Email, role: &str) -> Result<(), UserStoreError> {
+        let _timer = time_store("postgres", "user", "remove_role");
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM user_roles
+            WHERE email = $1 AND role = $2
+            "#,
+            email.as_ref().expose_secret(),
+            role,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        // Tell apart an unknown user from one without the role
+        if result.rows_affected() == 0 {
+            self.get_user(email).await?;
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving user roles from PostgreSQL", skip_all)]
+    async fn get_roles(&self, email: &Email) -> Result<UserRoles, UserStoreError> {
+        let _timer = time_store("postgres", "user", "get_roles");
+        // One row per permission, or a single row without a role when the
+        // user has none
+        let rows = sqlx::query!(
+            r#"
+            SELECT ur.role AS "role?", rp.permission AS "permission?"
+            FROM users u
+            LEFT JOIN user_roles ur ON ur.email = u.email
+            LEFT JOIN role_permissions rp ON rp.role = ur.role
+            WHERE u.email = $1
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if rows.is_empty() {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        let mut roles = BTreeSet::new();
+        let mut permissions = BTreeSet::new();
+        for row in rows {
+            roles.extend(row.role);
+            permissions.extend(row.permission);
+        }
+        Ok(UserRoles {
+            roles: roles.into_iter().collect(),
+            permissions: permissions.into_iter().collect(),
+        })
+    }
+
+    #[tracing::instrument(name = "Listing users in PostgreSQL", skip_all)]
+    async fn list_users(&self, query: &UserQuery) -> Result<UserPage, UserStoreError> {
+        let _timer = time_store("postgres", "user", "list_users");
+        let pattern = email_like_pattern(query.email_contains.as_deref());
+
+        let total = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "total!"
+            FROM users
+            WHERE email ILIKE $1 ESCAPE '\'
+            "#,
+            pattern,
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        let users = sqlx::query_as!(
+            UserRow,
+            r#"
+            SELECT email, password_hash, requires_2fa, verified, disabled, password_reset_required
+            FROM users
+            WHERE email ILIKE $1 ESCAPE '\'
+            ORDER BY email
+            LIMIT $2 OFFSET $3
+            "#,
+            pattern,
+            i64::from(query.limit),
+            i64::from(query.offset),
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(User::try_from)
+        .collect::<Result<_, _>>()?;
+
+        Ok(UserPage {
+            users,
+            total: total as u64,
+        })
+    }
+
+    #[tracing::instrument(name = "Updating user disabled flag in PostgreSQL", skip_all)]
+    async fn set_disabled(&self, email: &Email, disabled: bool) -> Result<(), UserStoreError> {
+        let _timer = time_store("postgres", "user", "set_disabled");
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET disabled = $2
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+            disabled,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Updating user password reset flag in PostgreSQL", skip_all)]
+    async fn set_password_reset_required(
+        &self,
+        email: &Email,
+        required: bool,
+    ) -> Result<(), UserStoreError> {
+        let _timer = time_store("postgres", "user", "set_password_reset_required");
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET password_reset_required = $2
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+            required,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Deleting user from PostgreSQL", skip_all)]
+    async fn delete_user(&self, email: &Email) -> Result<(), UserStoreError> {
+        let _timer = time_store("postgres", "user", "delete_user");
+        // Roles, TOTP secrets and recovery codes go with it, see the
+        // `ON DELETE CASCADE` foreign keys
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM users
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+}
+
+struct UserRow {
+    email: String,
+    password_hash: String,
+    requires_2fa: bool,
+    verified: bool,
+    disabled: bool,
+    password_reset_required: bool,
+}
+
+impl TryFrom<UserRow> for User {
+    type Error = UserStoreError;
+
+    fn try_from(row: UserRow) -> Result<Self, Self::Error> {
+        Ok(User {
+            email: Email::parse(SecretString::new(row.email.into_boxed_str()))
+                .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
+            password: HashedPassword::parse_password_hash(SecretString::new(
+                row.password_hash.into_boxed_str(),
+            ))
+            .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
+            requires_2fa: row.requires_2fa,
+            verified: row.verified,
+            disabled: row.disabled,
+            password_reset_required: row.password_reset_required,
+        })
+    }
+}
+
+// `LIKE` pattern matching emails that contain `needle`, or every email.
+// Wildcards in `needle` are escaped with `\`.
+pub(crate) fn email_like_pattern(needle: Option<&str>) -> String {
+    let escaped = needle
+        .unwrap_or_default()
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{escaped}%")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::email_like_pattern;
+
+    #[test]
+    fn test_email_like_pattern() {
+        assert_eq!(email_like_pattern(None), "%%");
+        assert_eq!(email_like_pattern(Some("ann")), "%ann%");
+        assert_eq!(email_like_pattern(Some("a_b%c\\d")), "%a\\_b\\%c\\\\d%");
+    }
 }
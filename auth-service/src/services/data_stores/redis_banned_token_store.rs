@@ -1,19 +1,18 @@
-use std::sync::Arc;
-
 use crate::domain::data_store::BannedTokenStore;
 use crate::domain::error::BannedTokenStoreError;
-use crate::utils::auth::TOKEN_TTL_SECONDS;
+use crate::domain::Email;
+use crate::utils::metrics::time_store;
+use crate::utils::settings::settings;
 use color_eyre::eyre::Report;
-use redis::{Commands, Connection};
+use redis::{aio::ConnectionManager, AsyncCommands};
 use secrecy::{ExposeSecret, SecretString};
-use tokio::sync::RwLock;
 
 pub struct RedisBannedTokenStore {
-    conn: Arc<RwLock<Connection>>,
+    conn: ConnectionManager,
 }
 
 impl RedisBannedTokenStore {
-    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+    pub fn new(conn: ConnectionManager) -> Self {
         Self { conn }
     }
 }
@@ -21,38 +20,88 @@ impl RedisBannedTokenStore {
 #[async_trait::async_trait]
 impl BannedTokenStore for RedisBannedTokenStore {
     #[tracing::instrument(skip_all)]
-    async fn add_token(&mut self, token: SecretString) -> Result<(), BannedTokenStoreError> {
-        let ttl: u64 = TOKEN_TTL_SECONDS
-            .try_into()
-            .map_err(|e| BannedTokenStoreError::UnexpectedError(Report::new(e)))?;
+    async fn add_token(&self, token: SecretString) -> Result<(), BannedTokenStoreError> {
+        let _timer = time_store("redis", "banned_token", "add_token");
+        let ttl = settings().tokens.access_token_ttl_seconds;
 
         let key = get_key(token.expose_secret());
 
-        let mut connection = self.conn.write().await;
+        let mut connection = self.conn.clone();
 
         connection
             .set_ex::<_, _, ()>(key, true, ttl)
+            .await
             .map_err(|e| BannedTokenStoreError::UnexpectedError(Report::new(e)))?;
         Ok(())
     }
 
     #[tracing::instrument(skip_all)]
     async fn token_exists(&self, token: &SecretString) -> Result<bool, BannedTokenStoreError> {
+        let _timer = time_store("redis", "banned_token", "token_exists");
         let key = get_key(token.expose_secret());
 
-        let mut connection = self.conn.write().await;
+        let mut connection = self.conn.clone();
 
         let exists: bool = connection
             .exists(key)
+            .await
             .map_err(|e| BannedTokenStoreError::UnexpectedError(Report::new(e)))?;
 
         Ok(exists)
     }
+
+    #[tracing::instrument(skip_all)]
+    async fn ban_user_tokens(
+        &self,
+        email: &Email,
+        issued_before: i64,
+    ) -> Result<(), BannedTokenStoreError> {
+        let _timer = time_store("redis", "banned_token", "ban_user_tokens");
+        // Once the token TTL has passed every affected token has expired anyway
+        let ttl = settings().tokens.access_token_ttl_seconds;
+
+        let key = get_user_key(email);
+
+        let mut connection = self.conn.clone();
+
+        connection
+            .set_ex::<_, _, ()>(key, issued_before, ttl)
+            .await
+            .map_err(|e| BannedTokenStoreError::UnexpectedError(Report::new(e)))?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn user_tokens_banned_before(
+        &self,
+        email: &Email,
+    ) -> Result<Option<i64>, BannedTokenStoreError> {
+        let _timer = time_store("redis", "banned_token", "user_tokens_banned_before");
+        let key = get_user_key(email);
+
+        let mut connection = self.conn.clone();
+
+        let issued_before: Option<i64> = connection
+            .get(key)
+            .await
+            .map_err(|e| BannedTokenStoreError::UnexpectedError(Report::new(e)))?;
+
+        Ok(issued_before)
+    }
 }
 
 // We are using a key prefix to prevent collisions and organize data!
 const BANNED_TOKEN_KEY_PREFIX: &str = "banned_token:";
+const BANNED_USER_TOKENS_KEY_PREFIX: &str = "banned_user_tokens:";
 
 fn get_key(token: &str) -> String {
     format!("{}{}", BANNED_TOKEN_KEY_PREFIX, token)
 }
+
+fn get_user_key(email: &Email) -> String {
+    format!(
+        "{}{}",
+        BANNED_USER_TOKENS_KEY_PREFIX,
+        email.as_ref().expose_secret()
+    )
+}
@@ -0,0 +1,99 @@
+use crate::domain::data_store::LoginAttemptStore;
+use crate::domain::error::LoginAttemptStoreError;
+use crate::domain::types::{LoginAttemptKey, LoginAttempts};
+use crate::utils::metrics::time_store;
+use chrono::Utc;
+use color_eyre::eyre::Report;
+use redis::{aio::ConnectionManager, AsyncCommands};
+use secrecy::ExposeSecret;
+use std::collections::HashMap;
+
+pub struct RedisLoginAttemptStore {
+    conn: ConnectionManager,
+}
+
+impl RedisLoginAttemptStore {
+    pub fn new(conn: ConnectionManager) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl LoginAttemptStore for RedisLoginAttemptStore {
+    #[tracing::instrument(skip_all)]
+    async fn get_attempts(
+        &self,
+        key: &LoginAttemptKey,
+    ) -> Result<LoginAttempts, LoginAttemptStoreError> {
+        let _timer = time_store("redis", "login_attempt", "get_attempts");
+        let key = get_key(key);
+
+        let mut connection = self.conn.clone();
+        let fields: HashMap<String, i64> = connection
+            .hgetall(key)
+            .await
+            .map_err(|e| LoginAttemptStoreError::UnexpectedError(Report::new(e)))?;
+
+        Ok(LoginAttempts {
+            failures: fields.get(FAILURES_FIELD).copied().unwrap_or(0) as u32,
+            last_failure_at: fields.get(LAST_FAILURE_AT_FIELD).copied().unwrap_or(0),
+        })
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn record_failure(
+        &self,
+        key: &LoginAttemptKey,
+        ttl_seconds: u64,
+    ) -> Result<LoginAttempts, LoginAttemptStoreError> {
+        let _timer = time_store("redis", "login_attempt", "record_failure");
+        let key = get_key(key);
+        let now = Utc::now().timestamp();
+
+        // Incrementing in Redis keeps the count right across several instances
+        let mut connection = self.conn.clone();
+        let (failures,): (u32,) = redis::pipe()
+            .atomic()
+            .hincr(&key, FAILURES_FIELD, 1)
+            .hset(&key, LAST_FAILURE_AT_FIELD, now)
+            .ignore()
+            .expire(&key, ttl_seconds as i64)
+            .ignore()
+            .query_async(&mut connection)
+            .await
+            .map_err(|e| LoginAttemptStoreError::UnexpectedError(Report::new(e)))?;
+
+        Ok(LoginAttempts {
+            failures,
+            last_failure_at: now,
+        })
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn reset(&self, key: &LoginAttemptKey) -> Result<(), LoginAttemptStoreError> {
+        let _timer = time_store("redis", "login_attempt", "reset");
+        let key = get_key(key);
+
+        let mut connection = self.conn.clone();
+        connection
+            .del::<_, ()>(key)
+            .await
+            .map_err(|e| LoginAttemptStoreError::UnexpectedError(Report::new(e)))?;
+        Ok(())
+    }
+}
+
+const LOGIN_ATTEMPTS_PREFIX: &str = "login_attempts:";
+const FAILURES_FIELD: &str = "failures";
+const LAST_FAILURE_AT_FIELD: &str = "last_failure_at";
+
+fn get_key(key: &LoginAttemptKey) -> String {
+    match key {
+        LoginAttemptKey::Account(email) => format!(
+            "{}account:{}",
+            LOGIN_ATTEMPTS_PREFIX,
+            email.as_ref().expose_secret()
+        ),
+        LoginAttemptKey::Ip(ip) => format!("{}ip:{}", LOGIN_ATTEMPTS_PREFIX, ip),
+    }
+}
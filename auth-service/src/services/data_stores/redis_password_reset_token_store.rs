@@ -0,0 +1,116 @@
+use crate::domain::data_store::PasswordResetTokenStore;
+use crate::domain::error::PasswordResetTokenStoreError;
+use crate::domain::types::{Email, PasswordResetToken};
+use crate::utils::metrics::time_store;
+use crate::utils::settings::settings;
+use color_eyre::eyre::Report;
+use redis::{aio::ConnectionManager, AsyncCommands};
+use secrecy::{ExposeSecret, SecretString};
+
+pub struct RedisPasswordResetTokenStore {
+    conn: ConnectionManager,
+}
+
+impl RedisPasswordResetTokenStore {
+    pub fn new(conn: ConnectionManager) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl PasswordResetTokenStore for RedisPasswordResetTokenStore {
+    #[tracing::instrument(skip_all)]
+    async fn add_token(
+        &self,
+        token: PasswordResetToken,
+        email: Email,
+    ) -> Result<(), PasswordResetTokenStoreError> {
+        let _timer = time_store("redis", "password_reset_token", "add_token");
+        let key = get_key(&token);
+        let user_key = get_user_tokens_key(&email);
+        let ttl_seconds = settings().tokens.password_reset_token_ttl_seconds;
+
+        // The user's tokens are also tracked in a set, so they can all be
+        // removed at once
+        let mut connection = self.conn.clone();
+        connection
+            .set_ex::<_, _, ()>(key, email.as_ref().expose_secret(), ttl_seconds)
+            .await
+            .map_err(|e| PasswordResetTokenStoreError::UnexpectedError(Report::new(e)))?;
+        connection
+            .sadd::<_, _, ()>(&user_key, token.as_ref().expose_secret())
+            .await
+            .map_err(|e| PasswordResetTokenStoreError::UnexpectedError(Report::new(e)))?;
+        connection
+            .expire::<_, ()>(&user_key, ttl_seconds as i64)
+            .await
+            .map_err(|e| PasswordResetTokenStoreError::UnexpectedError(Report::new(e)))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn take_token(
+        &self,
+        token: &PasswordResetToken,
+    ) -> Result<Email, PasswordResetTokenStoreError> {
+        let _timer = time_store("redis", "password_reset_token", "take_token");
+        let key = get_key(token);
+
+        // GETDEL makes redeeming the token atomic, so it can only be used once
+        let mut connection = self.conn.clone();
+        let email: Option<String> = connection
+            .get_del(key)
+            .await
+            .map_err(|e| PasswordResetTokenStoreError::UnexpectedError(Report::new(e)))?;
+        let email = email.ok_or(PasswordResetTokenStoreError::TokenNotFound)?;
+
+        Email::parse(SecretString::new(email.into_boxed_str()))
+            .map_err(|e| PasswordResetTokenStoreError::UnexpectedError(Report::new(e)))
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn remove_user_tokens(&self, email: &Email) -> Result<(), PasswordResetTokenStoreError> {
+        let _timer = time_store("redis", "password_reset_token", "remove_user_tokens");
+        let user_key = get_user_tokens_key(email);
+
+        let mut connection = self.conn.clone();
+        let tokens: Vec<String> = connection
+            .smembers(&user_key)
+            .await
+            .map_err(|e| PasswordResetTokenStoreError::UnexpectedError(Report::new(e)))?;
+
+        for token in tokens {
+            connection
+                .del::<_, ()>(format!("{}{}", PASSWORD_RESET_TOKEN_PREFIX, token))
+                .await
+                .map_err(|e| PasswordResetTokenStoreError::UnexpectedError(Report::new(e)))?;
+        }
+        connection
+            .del::<_, ()>(&user_key)
+            .await
+            .map_err(|e| PasswordResetTokenStoreError::UnexpectedError(Report::new(e)))?;
+
+        Ok(())
+    }
+}
+
+const PASSWORD_RESET_TOKEN_PREFIX: &str = "password_reset_token:";
+
+fn get_key(token: &PasswordResetToken) -> String {
+    format!(
+        "{}{}",
+        PASSWORD_RESET_TOKEN_PREFIX,
+        token.as_ref().expose_secret()
+    )
+}
+
+const USER_PASSWORD_RESET_TOKENS_PREFIX: &str = "user_password_reset_tokens:";
+
+fn get_user_tokens_key(email: &Email) -> String {
+    format!(
+        "{}{}",
+        USER_PASSWORD_RESET_TOKENS_PREFIX,
+        email.as_ref().expose_secret()
+    )
+}
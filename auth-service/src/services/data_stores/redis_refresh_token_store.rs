@@ -0,0 +1,231 @@
+use crate::domain::data_store::RefreshTokenStore;
+use crate::domain::error::RefreshTokenStoreError;
+use crate::domain::types::{Email, RefreshTokenRecord};
+use crate::utils::auth::refresh_token_ttl_seconds;
+use crate::utils::metrics::time_store;
+use crate::utils::settings::settings;
+use color_eyre::eyre::Report;
+use redis::{aio::ConnectionManager, AsyncCommands, ExistenceCheck, SetExpiry, SetOptions};
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+
+pub struct RedisRefreshTokenStore {
+    conn: ConnectionManager,
+}
+
+impl RedisRefreshTokenStore {
+    pub fn new(conn: ConnectionManager) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for RedisRefreshTokenStore {
+    #[tracing::instrument(skip_all)]
+    async fn add_refresh_token(
+        &self,
+        token: SecretString,
+        record: RefreshTokenRecord,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let _timer = time_store("redis", "refresh_token", "add_refresh_token");
+        let key = get_token_key(token.expose_secret());
+        let value = serde_json::to_string(&StoredRefreshToken::from(&record))
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(Report::new(e)))?;
+
+        let user_key = get_user_families_key(&record.email);
+
+        let mut connection = self.conn.clone();
+        connection
+            .set_ex::<_, _, ()>(key, value, ttl())
+            .await
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(Report::new(e)))?;
+
+        // Track each user's families so all of their sessions can be revoked at once
+        connection
+            .sadd::<_, _, ()>(&user_key, &record.family_id)
+            .await
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(Report::new(e)))?;
+        connection
+            .expire::<_, ()>(&user_key, refresh_token_ttl_seconds())
+            .await
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(Report::new(e)))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn get_refresh_token(
+        &self,
+        token: &SecretString,
+    ) -> Result<RefreshTokenRecord, RefreshTokenStoreError> {
+        let _timer = time_store("redis", "refresh_token", "get_refresh_token");
+        let key = get_token_key(token.expose_secret());
+
+        let mut connection = self.conn.clone();
+        let value: Option<String> = connection
+            .get(key)
+            .await
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(Report::new(e)))?;
+        let value = value.ok_or(RefreshTokenStoreError::TokenNotFound)?;
+
+        let stored: StoredRefreshToken = serde_json::from_str(&value)
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(Report::new(e)))?;
+
+        stored.try_into()
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn mark_refresh_token_used(
+        &self,
+        token: &SecretString,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let _timer = time_store("redis", "refresh_token", "mark_refresh_token_used");
+        let mut record = self.get_refresh_token(token).await?;
+        if record.used {
+            return Err(RefreshTokenStoreError::TokenAlreadyUsed);
+        }
+
+        // Claim the token with SET NX first, so of several concurrent
+        // rotations only the one that creates the marker succeeds.
+        let mut connection = self.conn.clone();
+        let claimed: Option<String> = connection
+            .set_options(
+                get_used_key(token.expose_secret()),
+                true,
+                SetOptions::default()
+                    .conditional_set(ExistenceCheck::NX)
+                    .with_expiration(SetExpiry::EX(ttl())),
+            )
+            .await
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(Report::new(e)))?;
+        if claimed.is_none() {
+            return Err(RefreshTokenStoreError::TokenAlreadyUsed);
+        }
+
+        record.used = true;
+        let key = get_token_key(token.expose_secret());
+        let value = serde_json::to_string(&StoredRefreshToken::from(&record))
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(Report::new(e)))?;
+
+        // Keep the original expiry so a rotated token is remembered exactly as
+        // long as it could otherwise have been used.
+        connection
+            .set_options::<_, _, ()>(
+                key,
+                value,
+                SetOptions::default().with_expiration(SetExpiry::KEEPTTL),
+            )
+            .await
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(Report::new(e)))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn revoke_family(&self, family_id: &str) -> Result<(), RefreshTokenStoreError> {
+        let _timer = time_store("redis", "refresh_token", "revoke_family");
+        let key = get_family_key(family_id);
+
+        let mut connection = self.conn.clone();
+        connection
+            .set_ex::<_, _, ()>(key, true, ttl())
+            .await
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(Report::new(e)))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn is_family_revoked(&self, family_id: &str) -> Result<bool, RefreshTokenStoreError> {
+        let _timer = time_store("redis", "refresh_token", "is_family_revoked");
+        let key = get_family_key(family_id);
+
+        let mut connection = self.conn.clone();
+        let exists: bool = connection
+            .exists(key)
+            .await
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(Report::new(e)))?;
+
+        Ok(exists)
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn revoke_user_families(&self, email: &Email) -> Result<(), RefreshTokenStoreError> {
+        let _timer = time_store("redis", "refresh_token", "revoke_user_families");
+        let user_key = get_user_families_key(email);
+
+        let families: Vec<String> = {
+            let mut connection = self.conn.clone();
+            connection
+                .smembers(&user_key)
+                .await
+                .map_err(|e| RefreshTokenStoreError::UnexpectedError(Report::new(e)))?
+        };
+
+        for family_id in families {
+            self.revoke_family(&family_id).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredRefreshToken {
+    email: String,
+    family_id: String,
+    used: bool,
+}
+
+impl From<&RefreshTokenRecord> for StoredRefreshToken {
+    fn from(record: &RefreshTokenRecord) -> Self {
+        Self {
+            email: record.email.as_ref().expose_secret().to_owned(),
+            family_id: record.family_id.clone(),
+            used: record.used,
+        }
+    }
+}
+
+impl TryFrom<StoredRefreshToken> for RefreshTokenRecord {
+    type Error = RefreshTokenStoreError;
+
+    fn try_from(stored: StoredRefreshToken) -> Result<Self, Self::Error> {
+        let email = Email::parse(SecretString::new(stored.email.into_boxed_str()))
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(Report::new(e)))?;
+        Ok(Self {
+            email,
+            family_id: stored.family_id,
+            used: stored.used,
+        })
+    }
+}
+
+fn ttl() -> u64 {
+    settings().tokens.refresh_token_ttl_seconds
+}
+
+const REFRESH_TOKEN_KEY_PREFIX: &str = "refresh_token:";
+const USED_REFRESH_TOKEN_KEY_PREFIX: &str = "used_refresh_token:";
+const REVOKED_FAMILY_KEY_PREFIX: &str = "revoked_refresh_token_family:";
+const USER_FAMILIES_KEY_PREFIX: &str = "refresh_token_families:";
+
+fn get_token_key(token: &str) -> String {
+    format!("{}{}", REFRESH_TOKEN_KEY_PREFIX, token)
+}
+
+fn get_used_key(token: &str) -> String {
+    format!("{}{}", USED_REFRESH_TOKEN_KEY_PREFIX, token)
+}
+
+fn get_family_key(family_id: &str) -> String {
+    format!("{}{}", REVOKED_FAMILY_KEY_PREFIX, family_id)
+}
+
+fn get_user_families_key(email: &Email) -> String {
+    format!(
+        "{}{}",
+        USER_FAMILIES_KEY_PREFIX,
+        email.as_ref().expose_secret()
+    )
+}
@@ -0,0 +1,205 @@
+use crate::domain::data_store::SessionStore;
+use crate::domain::error::SessionStoreError;
+use crate::domain::types::{Email, Session};
+use crate::utils::auth::refresh_token_ttl_seconds;
+use crate::utils::metrics::time_store;
+use crate::utils::settings::settings;
+use color_eyre::eyre::Report;
+use redis::{aio::ConnectionManager, AsyncCommands};
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+
+pub struct RedisSessionStore {
+    conn: ConnectionManager,
+}
+
+impl RedisSessionStore {
+    pub fn new(conn: ConnectionManager) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl SessionStore for RedisSessionStore {
+    #[tracing::instrument(skip_all)]
+    async fn add_session(&self, session: Session) -> Result<(), SessionStoreError> {
+        let _timer = time_store("redis", "session", "add_session");
+        let key = get_session_key(&session.id);
+        let user_key = get_user_sessions_key(&session.email);
+        let value = serde_json::to_string(&StoredSession::from(&session))
+            .map_err(|e| SessionStoreError::UnexpectedError(Report::new(e)))?;
+
+        // A session lasts as long as its refresh token family can be used
+        let mut connection = self.conn.clone();
+        connection
+            .set_ex::<_, _, ()>(key, value, settings().tokens.refresh_token_ttl_seconds)
+            .await
+            .map_err(|e| SessionStoreError::UnexpectedError(Report::new(e)))?;
+        connection
+            .sadd::<_, _, ()>(&user_key, &session.id)
+            .await
+            .map_err(|e| SessionStoreError::UnexpectedError(Report::new(e)))?;
+        connection
+            .expire::<_, ()>(&user_key, refresh_token_ttl_seconds())
+            .await
+            .map_err(|e| SessionStoreError::UnexpectedError(Report::new(e)))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn get_session(&self, id: &str) -> Result<Session, SessionStoreError> {
+        let _timer = time_store("redis", "session", "get_session");
+        let key = get_session_key(id);
+
+        let mut connection = self.conn.clone();
+        let value: Option<String> = connection
+            .get(key)
+            .await
+            .map_err(|e| SessionStoreError::UnexpectedError(Report::new(e)))?;
+        let value = value.ok_or(SessionStoreError::SessionNotFound)?;
+
+        let stored: StoredSession = serde_json::from_str(&value)
+            .map_err(|e| SessionStoreError::UnexpectedError(Report::new(e)))?;
+
+        stored.try_into()
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn get_user_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError> {
+        let _timer = time_store("redis", "session", "get_user_sessions");
+        let user_key = get_user_sessions_key(email);
+
+        let ids: Vec<String> = {
+            let mut connection = self.conn.clone();
+            connection
+                .smembers(&user_key)
+                .await
+                .map_err(|e| SessionStoreError::UnexpectedError(Report::new(e)))?
+        };
+
+        // Ids of sessions that have expired are left behind in the set
+        let mut sessions = Vec::with_capacity(ids.len());
+        for id in ids {
+            match self.get_session(&id).await {
+                Ok(session) => sessions.push(session),
+                Err(SessionStoreError::SessionNotFound) => {
+                    let mut connection = self.conn.clone();
+                    connection
+                        .srem::<_, _, ()>(&user_key, &id)
+                        .await
+                        .map_err(|e| SessionStoreError::UnexpectedError(Report::new(e)))?;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(sessions)
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn touch_session(&self, id: &str, last_seen_at: i64) -> Result<(), SessionStoreError> {
+        let _timer = time_store("redis", "session", "touch_session");
+        let mut session = self.get_session(id).await?;
+        session.last_seen_at = last_seen_at;
+        self.add_session(session).await
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn remove_session(&self, id: &str) -> Result<(), SessionStoreError> {
+        let _timer = time_store("redis", "session", "remove_session");
+        let session = self.get_session(id).await?;
+
+        let mut connection = self.conn.clone();
+        connection
+            .del::<_, ()>(get_session_key(id))
+            .await
+            .map_err(|e| SessionStoreError::UnexpectedError(Report::new(e)))?;
+        connection
+            .srem::<_, _, ()>(get_user_sessions_key(&session.email), id)
+            .await
+            .map_err(|e| SessionStoreError::UnexpectedError(Report::new(e)))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn remove_user_sessions(&self, email: &Email) -> Result<(), SessionStoreError> {
+        let _timer = time_store("redis", "session", "remove_user_sessions");
+        let user_key = get_user_sessions_key(email);
+
+        let mut connection = self.conn.clone();
+        let ids: Vec<String> = connection
+            .smembers(&user_key)
+            .await
+            .map_err(|e| SessionStoreError::UnexpectedError(Report::new(e)))?;
+
+        for id in ids {
+            connection
+                .del::<_, ()>(get_session_key(&id))
+                .await
+                .map_err(|e| SessionStoreError::UnexpectedError(Report::new(e)))?;
+        }
+        connection
+            .del::<_, ()>(&user_key)
+            .await
+            .map_err(|e| SessionStoreError::UnexpectedError(Report::new(e)))?;
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredSession {
+    id: String,
+    email: String,
+    user_agent: Option<String>,
+    ip: String,
+    created_at: i64,
+    last_seen_at: i64,
+}
+
+impl From<&Session> for StoredSession {
+    fn from(session: &Session) -> Self {
+        Self {
+            id: session.id.clone(),
+            email: session.email.as_ref().expose_secret().to_owned(),
+            user_agent: session.user_agent.clone(),
+            ip: session.ip.clone(),
+            created_at: session.created_at,
+            last_seen_at: session.last_seen_at,
+        }
+    }
+}
+
+impl TryFrom<StoredSession> for Session {
+    type Error = SessionStoreError;
+
+    fn try_from(stored: StoredSession) -> Result<Self, Self::Error> {
+        let email = Email::parse(SecretString::new(stored.email.into_boxed_str()))
+            .map_err(|e| SessionStoreError::UnexpectedError(Report::new(e)))?;
+        Ok(Self {
+            id: stored.id,
+            email,
+            user_agent: stored.user_agent,
+            ip: stored.ip,
+            created_at: stored.created_at,
+            last_seen_at: stored.last_seen_at,
+        })
+    }
+}
+
+const SESSION_KEY_PREFIX: &str = "session:";
+const USER_SESSIONS_KEY_PREFIX: &str = "user_sessions:";
+
+fn get_session_key(id: &str) -> String {
+    format!("{}{}", SESSION_KEY_PREFIX, id)
+}
+
+fn get_user_sessions_key(email: &Email) -> String {
+    format!(
+        "{}{}",
+        USER_SESSIONS_KEY_PREFIX,
+        email.as_ref().expose_secret()
+    )
+}
@@ -2,20 +2,20 @@ use crate::domain::{
     types::{LoginAttemptId, TwoFACode},
     Email, TwoFACodeStore, TwoFACodeStoreError,
 };
+use crate::utils::metrics::time_store;
+use crate::utils::settings::settings;
 use color_eyre::eyre::Report;
-use redis::{Commands, Connection};
+use redis::{aio::ConnectionManager, AsyncCommands};
 use secrecy::{ExposeSecret, SecretString};
 use serde::{Deserialize, Serialize};
 use serde_json::to_string;
-use std::sync::Arc;
-use tokio::sync::RwLock;
 
 pub struct RedisTwoFACodeStore {
-    conn: Arc<RwLock<Connection>>,
+    conn: ConnectionManager,
 }
 
 impl RedisTwoFACodeStore {
-    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+    pub fn new(conn: ConnectionManager) -> Self {
         Self { conn }
     }
 }
@@ -24,11 +24,12 @@ impl RedisTwoFACodeStore {
 impl TwoFACodeStore for RedisTwoFACodeStore {
     #[tracing::instrument(skip_all)]
     async fn add_two_fa_code(
-        &mut self,
+        &self,
         email: Email,
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
     ) -> Result<(), TwoFACodeStoreError> {
+        let _timer = time_store("redis", "two_fa_code", "add_two_fa_code");
         let key = get_key(&email);
         let two_fa_code = TwoFATuple(
             login_attempt_id.as_ref().expose_secret().to_string(),
@@ -37,22 +38,34 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
         let two_fa_code_json = to_string(&two_fa_code)
             .map_err(|e| TwoFACodeStoreError::UnexpectedError(Report::new(e)))?;
 
-        let mut connection = self.conn.write().await;
+        let mut connection = self.conn.clone();
         connection
-            .set_ex::<_, _, ()>(key, two_fa_code_json, TEN_MINUTES_IN_SECONDS)
+            .set_ex::<_, _, ()>(
+                key,
+                two_fa_code_json,
+                settings().tokens.two_fa_code_ttl_seconds,
+            )
+            .await
             .map_err(|e| TwoFACodeStoreError::UnexpectedError(Report::new(e)))?;
 
         Ok(())
     }
 
     #[tracing::instrument(skip_all)]
-    async fn remove_two_fa_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+    async fn remove_two_fa_code(&self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+        let _timer = time_store("redis", "two_fa_code", "remove_two_fa_code");
         let key = get_key(email);
 
-        let mut connection = self.conn.write().await;
-        connection
-            .del::<_, ()>(key)
+        // DEL reports whether the key existed, so only one of several
+        // concurrent removals succeeds
+        let mut connection = self.conn.clone();
+        let removed: u64 = connection
+            .del(key)
+            .await
             .map_err(|e| TwoFACodeStoreError::UnexpectedError(Report::new(e)))?;
+        if removed == 0 {
+            return Err(TwoFACodeStoreError::LoginAttemptIdNotFound);
+        }
         Ok(())
     }
 
@@ -61,12 +74,14 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
         &self,
         email: &Email,
     ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
+        let _timer = time_store("redis", "two_fa_code", "get_two_fa_code");
         let key = get_key(email);
 
-        let mut connection = self.conn.write().await;
+        let mut connection = self.conn.clone();
 
         let value: String = connection
             .get::<_, String>(key)
+            .await
             .map_err(|_| TwoFACodeStoreError::LoginAttemptIdNotFound)?;
 
         let tuple: TwoFATuple = serde_json::from_str(&value)
@@ -82,12 +97,14 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
 
     #[tracing::instrument(skip_all)]
     async fn two_fa_code_exists(&self, email: &Email) -> Result<bool, TwoFACodeStoreError> {
+        let _timer = time_store("redis", "two_fa_code", "two_fa_code_exists");
         let key = get_key(email);
 
-        let mut connection = self.conn.write().await;
+        let mut connection = self.conn.clone();
 
         let exists: bool = connection
             .exists::<_, bool>(key)
+            .await
             .map_err(|e| TwoFACodeStoreError::UnexpectedError(Report::new(e)))?;
 
         Ok(exists)
@@ -97,7 +114,6 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
 #[derive(Serialize, Deserialize)]
 struct TwoFATuple(pub String, pub String);
 
-const TEN_MINUTES_IN_SECONDS: u64 = 600;
 const TWO_FA_CODE_PREFIX: &str = "two_fa_code:";
 
 fn get_key(email: &Email) -> String {
@@ -0,0 +1,140 @@
+use crate::domain::data_store::BannedTokenStore;
+use crate::domain::error::BannedTokenStoreError;
+use crate::domain::Email;
+use crate::utils::auth::token_ttl_seconds;
+use crate::utils::metrics::time_store;
+use chrono::Utc;
+use secrecy::{ExposeSecret, SecretString};
+use sqlx::{Row, SqlitePool};
+
+// Same schema and semantics as `PostgresBannedTokenStore`
+pub struct SqliteBannedTokenStore {
+    pool: SqlitePool,
+}
+
+impl SqliteBannedTokenStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    // Delete bans that expired at or before `now`, returning how many were removed
+    #[tracing::instrument(name = "Purging expired banned tokens from SQLite", skip_all)]
+    pub async fn purge_expired(&self, now: i64) -> Result<u64, BannedTokenStoreError> {
+        let _timer = time_store("sqlite", "banned_token", "purge_expired");
+        let tokens = sqlx::query(
+            r#"
+            DELETE FROM banned_tokens
+            WHERE expires_at <= ?1
+            "#,
+        )
+        .bind(now)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        let user_tokens = sqlx::query(
+            r#"
+            DELETE FROM banned_user_tokens
+            WHERE expires_at <= ?1
+            "#,
+        )
+        .bind(now)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(tokens.rows_affected() + user_tokens.rows_affected())
+    }
+}
+
+#[async_trait::async_trait]
+impl BannedTokenStore for SqliteBannedTokenStore {
+    #[tracing::instrument(name = "Adding banned token to SQLite", skip_all)]
+    async fn add_token(&self, token: SecretString) -> Result<(), BannedTokenStoreError> {
+        let _timer = time_store("sqlite", "banned_token", "add_token");
+        sqlx::query(
+            r#"
+            INSERT INTO banned_tokens (token, expires_at)
+            VALUES (?1, ?2)
+            ON CONFLICT (token) DO UPDATE
+            SET expires_at = excluded.expires_at
+            "#,
+        )
+        .bind(token.expose_secret())
+        .bind(Utc::now().timestamp() + token_ttl_seconds())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Checking banned token in SQLite", skip_all)]
+    async fn token_exists(&self, token: &SecretString) -> Result<bool, BannedTokenStoreError> {
+        let _timer = time_store("sqlite", "banned_token", "token_exists");
+        let row = sqlx::query(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM banned_tokens
+                WHERE token = ?1 AND expires_at > ?2
+            ) AS "exists"
+            "#,
+        )
+        .bind(token.expose_secret())
+        .bind(Utc::now().timestamp())
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        row.try_get("exists")
+            .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Banning user tokens in SQLite", skip_all)]
+    async fn ban_user_tokens(
+        &self,
+        email: &Email,
+        issued_before: i64,
+    ) -> Result<(), BannedTokenStoreError> {
+        let _timer = time_store("sqlite", "banned_token", "ban_user_tokens");
+        sqlx::query(
+            r#"
+            INSERT INTO banned_user_tokens (email, issued_before, expires_at)
+            VALUES (?1, ?2, ?3)
+            ON CONFLICT (email) DO UPDATE
+            SET issued_before = excluded.issued_before, expires_at = excluded.expires_at
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .bind(issued_before)
+        .bind(Utc::now().timestamp() + token_ttl_seconds())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving user token ban from SQLite", skip_all)]
+    async fn user_tokens_banned_before(
+        &self,
+        email: &Email,
+    ) -> Result<Option<i64>, BannedTokenStoreError> {
+        let _timer = time_store("sqlite", "banned_token", "user_tokens_banned_before");
+        sqlx::query(
+            r#"
+            SELECT issued_before
+            FROM banned_user_tokens
+            WHERE email = ?1 AND expires_at > ?2
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .bind(Utc::now().timestamp())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?
+        .map(|row| row.try_get("issued_before"))
+        .transpose()
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))
+    }
+}
@@ -0,0 +1,117 @@
+use crate::domain::data_store::EmailVerificationTokenStore;
+use crate::domain::error::EmailVerificationTokenStoreError;
+use crate::domain::types::{Email, EmailVerificationToken};
+use crate::utils::metrics::time_store;
+use crate::utils::settings::settings;
+use chrono::Utc;
+use color_eyre::eyre::Report;
+use secrecy::{ExposeSecret, SecretString};
+use sqlx::{Row, SqlitePool};
+
+// Same schema and semantics as `PostgresEmailVerificationTokenStore`
+pub struct SqliteEmailVerificationTokenStore {
+    pool: SqlitePool,
+}
+
+impl SqliteEmailVerificationTokenStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    // Delete tokens that expired at or before `now`, returning how many were removed
+    #[tracing::instrument(
+        name = "Purging expired email verification tokens from SQLite",
+        skip_all
+    )]
+    pub async fn purge_expired(&self, now: i64) -> Result<u64, EmailVerificationTokenStoreError> {
+        let _timer = time_store("sqlite", "email_verification_token", "purge_expired");
+        let result = sqlx::query(
+            r#"
+            DELETE FROM email_verification_tokens
+            WHERE expires_at <= ?1
+            "#,
+        )
+        .bind(now)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailVerificationTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailVerificationTokenStore for SqliteEmailVerificationTokenStore {
+    #[tracing::instrument(name = "Adding email verification token to SQLite", skip_all)]
+    async fn add_token(
+        &self,
+        token: EmailVerificationToken,
+        email: Email,
+    ) -> Result<(), EmailVerificationTokenStoreError> {
+        let _timer = time_store("sqlite", "email_verification_token", "add_token");
+        sqlx::query(
+            r#"
+            INSERT INTO email_verification_tokens (token, email, expires_at)
+            VALUES (?1, ?2, ?3)
+            "#,
+        )
+        .bind(token.as_ref().expose_secret())
+        .bind(email.as_ref().expose_secret())
+        .bind(
+            Utc::now().timestamp() + settings().tokens.email_verification_token_ttl_seconds as i64,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailVerificationTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Taking email verification token from SQLite", skip_all)]
+    async fn take_token(
+        &self,
+        token: &EmailVerificationToken,
+    ) -> Result<Email, EmailVerificationTokenStoreError> {
+        let _timer = time_store("sqlite", "email_verification_token", "take_token");
+        // Deleting the row is what redeems the token, so it can only be used once
+        let row = sqlx::query(
+            r#"
+            DELETE FROM email_verification_tokens
+            WHERE token = ?1 AND expires_at > ?2
+            RETURNING email
+            "#,
+        )
+        .bind(token.as_ref().expose_secret())
+        .bind(Utc::now().timestamp())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| EmailVerificationTokenStoreError::UnexpectedError(e.into()))?
+        .ok_or(EmailVerificationTokenStoreError::TokenNotFound)?;
+
+        let email: String = row
+            .try_get("email")
+            .map_err(|e| EmailVerificationTokenStoreError::UnexpectedError(e.into()))?;
+        Email::parse(SecretString::new(email.into_boxed_str()))
+            .map_err(|e| EmailVerificationTokenStoreError::UnexpectedError(Report::new(e)))
+    }
+
+    #[tracing::instrument(name = "Removing user email verification tokens from SQLite", skip_all)]
+    async fn remove_user_tokens(
+        &self,
+        email: &Email,
+    ) -> Result<(), EmailVerificationTokenStoreError> {
+        let _timer = time_store("sqlite", "email_verification_token", "remove_user_tokens");
+        sqlx::query(
+            r#"
+            DELETE FROM email_verification_tokens
+            WHERE email = ?1
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailVerificationTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
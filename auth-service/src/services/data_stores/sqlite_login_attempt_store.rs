@@ -0,0 +1,197 @@
+use crate::domain::data_store::LoginAttemptStore;
+use crate::domain::error::LoginAttemptStoreError;
+use crate::domain::types::{LoginAttemptKey, LoginAttempts};
+use crate::utils::metrics::time_store;
+use chrono::Utc;
+use secrecy::ExposeSecret;
+use sqlx::{Row, SqlitePool};
+
+// Same schema and semantics as `PostgresLoginAttemptStore`
+pub struct SqliteLoginAttemptStore {
+    pool: SqlitePool,
+}
+
+impl SqliteLoginAttemptStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    // Delete failures that expired at or before `now`, returning how many
+    // keys were removed
+    #[tracing::instrument(name = "Purging expired login attempts from SQLite", skip_all)]
+    pub async fn purge_expired(&self, now: i64) -> Result<u64, LoginAttemptStoreError> {
+        let _timer = time_store("sqlite", "login_attempt", "purge_expired");
+        let result = sqlx::query(
+            r#"
+            DELETE FROM login_attempts
+            WHERE expires_at <= ?1
+            "#,
+        )
+        .bind(now)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| LoginAttemptStoreError::UnexpectedError(e.into()))?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait::async_trait]
+impl LoginAttemptStore for SqliteLoginAttemptStore {
+    #[tracing::instrument(name = "Retrieving login attempts from SQLite", skip_all)]
+    async fn get_attempts(
+        &self,
+        key: &LoginAttemptKey,
+    ) -> Result<LoginAttempts, LoginAttemptStoreError> {
+        let _timer = time_store("sqlite", "login_attempt", "get_attempts");
+        let Some(row) = sqlx::query(
+            r#"
+            SELECT failures, last_failure_at
+            FROM login_attempts
+            WHERE key = ?1 AND expires_at > ?2
+            "#,
+        )
+        .bind(get_key(key))
+        .bind(Utc::now().timestamp())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| LoginAttemptStoreError::UnexpectedError(e.into()))?
+        else {
+            return Ok(LoginAttempts::default());
+        };
+
+        Ok(LoginAttempts {
+            failures: row
+                .try_get("failures")
+                .map_err(|e| LoginAttemptStoreError::UnexpectedError(e.into()))?,
+            last_failure_at: row
+                .try_get("last_failure_at")
+                .map_err(|e| LoginAttemptStoreError::UnexpectedError(e.into()))?,
+        })
+    }
+
+    #[tracing::instrument(name = "Recording failed login attempt in SQLite", skip_all)]
+    async fn record_failure(
+        &self,
+        key: &LoginAttemptKey,
+        ttl_seconds: u64,
+    ) -> Result<LoginAttempts, LoginAttemptStoreError> {
+        let _timer = time_store("sqlite", "login_attempt", "record_failure");
+        let now = Utc::now().timestamp();
+        // Failures from an expired window start over
+        let row = sqlx::query(
+            r#"
+            INSERT INTO login_attempts (key, failures, last_failure_at, expires_at)
+            VALUES (?1, 1, ?2, ?3)
+            ON CONFLICT (key) DO UPDATE
+            SET failures = CASE
+                    WHEN login_attempts.expires_at > ?2 THEN login_attempts.failures + 1
+                    ELSE 1
+                END,
+                last_failure_at = excluded.last_failure_at,
+                expires_at = excluded.expires_at
+            RETURNING failures
+            "#,
+        )
+        .bind(get_key(key))
+        .bind(now)
+        .bind(now + ttl_seconds as i64)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| LoginAttemptStoreError::UnexpectedError(e.into()))?;
+
+        Ok(LoginAttempts {
+            failures: row
+                .try_get("failures")
+                .map_err(|e| LoginAttemptStoreError::UnexpectedError(e.into()))?,
+            last_failure_at: now,
+        })
+    }
+
+    #[tracing::instrument(name = "Resetting login attempts in SQLite", skip_all)]
+    async fn reset(&self, key: &LoginAttemptKey) -> Result<(), LoginAttemptStoreError> {
+        let _timer = time_store("sqlite", "login_attempt", "reset");
+        sqlx::query(
+            r#"
+            DELETE FROM login_attempts
+            WHERE key = ?1
+            "#,
+        )
+        .bind(get_key(key))
+        .execute(&self.pool)
+        .await
+        .map_err(|e| LoginAttemptStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
+
+// The same keys as `PostgresLoginAttemptStore`
+fn get_key(key: &LoginAttemptKey) -> String {
+    match key {
+        LoginAttemptKey::Account(email) => format!("account:{}", email.as_ref().expose_secret()),
+        LoginAttemptKey::Ip(ip) => format!("ip:{}", ip),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Email;
+    use crate::get_sqlite_pool;
+    use secrecy::SecretString;
+
+    async fn new_store() -> SqliteLoginAttemptStore {
+        let pool = get_sqlite_pool(&SecretString::from("sqlite::memory:"))
+            .await
+            .expect("Failed to create SQLite pool");
+        sqlx::migrate!("./migrations_sqlite")
+            .run(&pool)
+            .await
+            .expect("Failed to run SQLite migrations");
+        SqliteLoginAttemptStore::new(pool)
+    }
+
+    fn account() -> LoginAttemptKey {
+        LoginAttemptKey::Account(Email::parse(SecretString::from("test@example.com")).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_record_failure_counts_until_reset() {
+        let store = new_store().await;
+        let ip = LoginAttemptKey::Ip("127.0.0.1".parse().unwrap());
+
+        assert_eq!(
+            store.record_failure(&account(), 60).await.unwrap().failures,
+            1
+        );
+        assert_eq!(
+            store.record_failure(&account(), 60).await.unwrap().failures,
+            2
+        );
+        assert_eq!(store.record_failure(&ip, 60).await.unwrap().failures, 1);
+        assert_eq!(store.get_attempts(&account()).await.unwrap().failures, 2);
+
+        store.reset(&account()).await.unwrap();
+        assert_eq!(
+            store.get_attempts(&account()).await.unwrap(),
+            LoginAttempts::default()
+        );
+        assert_eq!(store.get_attempts(&ip).await.unwrap().failures, 1);
+    }
+
+    #[tokio::test]
+    async fn test_purge_expired() {
+        let store = new_store().await;
+
+        store.record_failure(&account(), 60).await.unwrap();
+
+        let now = Utc::now().timestamp();
+        assert_eq!(store.purge_expired(now).await.unwrap(), 0);
+        assert_eq!(store.purge_expired(now + 60).await.unwrap(), 1);
+        assert_eq!(
+            store.get_attempts(&account()).await.unwrap(),
+            LoginAttempts::default()
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use crate::domain::data_store::PasswordResetTokenStore;
+use crate::domain::error::PasswordResetTokenStoreError;
+use crate::domain::types::{Email, PasswordResetToken};
+use crate::utils::metrics::time_store;
+use crate::utils::settings::settings;
+use chrono::Utc;
+use color_eyre::eyre::Report;
+use secrecy::{ExposeSecret, SecretString};
+use sqlx::{Row, SqlitePool};
+
+// Same schema and semantics as `PostgresPasswordResetTokenStore`
+pub struct SqlitePasswordResetTokenStore {
+    pool: SqlitePool,
+}
+
+impl SqlitePasswordResetTokenStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    // Delete tokens that expired at or before `now`, returning how many were removed
+    #[tracing::instrument(name = "Purging expired password reset tokens from SQLite", skip_all)]
+    pub async fn purge_expired(&self, now: i64) -> Result<u64, PasswordResetTokenStoreError> {
+        let _timer = time_store("sqlite", "password_reset_token", "purge_expired");
+        let result = sqlx::query(
+            r#"
+            DELETE FROM password_reset_tokens
+            WHERE expires_at <= ?1
+            "#,
+        )
+        .bind(now)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| PasswordResetTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait::async_trait]
+impl PasswordResetTokenStore for SqlitePasswordResetTokenStore {
+    #[tracing::instrument(name = "Adding password reset token to SQLite", skip_all)]
+    async fn add_token(
+        &self,
+        token: PasswordResetToken,
+        email: Email,
+    ) -> Result<(), PasswordResetTokenStoreError> {
+        let _timer = time_store("sqlite", "password_reset_token", "add_token");
+        sqlx::query(
+            r#"
+            INSERT INTO password_reset_tokens (token, email, expires_at)
+            VALUES (?1, ?2, ?3)
+            "#,
+        )
+        .bind(token.as_ref().expose_secret())
+        .bind(email.as_ref().expose_secret())
+        .bind(Utc::now().timestamp() + settings().tokens.password_reset_token_ttl_seconds as i64)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| PasswordResetTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Taking password reset token from SQLite", skip_all)]
+    async fn take_token(
+        &self,
+        token: &PasswordResetToken,
+    ) -> Result<Email, PasswordResetTokenStoreError> {
+        let _timer = time_store("sqlite", "password_reset_token", "take_token");
+        // Deleting the row is what redeems the token, so it can only be used once
+        let row = sqlx::query(
+            r#"
+            DELETE FROM password_reset_tokens
+            WHERE token = ?1 AND expires_at > ?2
+            RETURNING email
+            "#,
+        )
+        .bind(token.as_ref().expose_secret())
+        .bind(Utc::now().timestamp())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| PasswordResetTokenStoreError::UnexpectedError(e.into()))?
+        .ok_or(PasswordResetTokenStoreError::TokenNotFound)?;
+
+        let email: String = row
+            .try_get("email")
+            .map_err(|e| PasswordResetTokenStoreError::UnexpectedError(e.into()))?;
+        Email::parse(SecretString::new(email.into_boxed_str()))
+            .map_err(|e| PasswordResetTokenStoreError::UnexpectedError(Report::new(e)))
+    }
+
+    #[tracing::instrument(name = "Removing user password reset tokens from SQLite", skip_all)]
+    async fn remove_user_tokens(&self, email: &Email) -> Result<(), PasswordResetTokenStoreError> {
+        let _timer = time_store("sqlite", "password_reset_token", "remove_user_tokens");
+        sqlx::query(
+            r#"
+            DELETE FROM password_reset_tokens
+            WHERE email = ?1
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| PasswordResetTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_sqlite_pool;
+
+    #[tokio::test]
+    async fn test_take_token_only_once() {
+        let pool = get_sqlite_pool(&SecretString::from("sqlite::memory:"))
+            .await
+            .expect("Failed to create SQLite pool");
+        sqlx::migrate!("./migrations_sqlite")
+            .run(&pool)
+            .await
+            .expect("Failed to run SQLite migrations");
+        let store = SqlitePasswordResetTokenStore::new(pool);
+        let token = PasswordResetToken::parse(SecretString::from("reset1234567890")).unwrap();
+        let email = Email::parse(SecretString::from("test@example.com")).unwrap();
+
+        store.add_token(token.clone(), email.clone()).await.unwrap();
+
+        assert_eq!(store.take_token(&token).await.unwrap(), email);
+        let err = store.take_token(&token).await.unwrap_err();
+        assert!(matches!(err, PasswordResetTokenStoreError::TokenNotFound));
+    }
+}
@@ -0,0 +1,192 @@
+use crate::domain::data_store::RecoveryCodeStore;
+use crate::domain::error::RecoveryCodeStoreError;
+use crate::domain::types::{Email, HashedRecoveryCode};
+use crate::utils::metrics::time_store;
+use color_eyre::eyre::eyre;
+use secrecy::{ExposeSecret, SecretString};
+use sqlx::{Row, SqlitePool};
+
+// Same schema and semantics as `PostgresRecoveryCodeStore`, kept next to the
+// users of a `SqliteUserStore`
+pub struct SqliteRecoveryCodeStore {
+    pool: SqlitePool,
+}
+
+impl SqliteRecoveryCodeStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl RecoveryCodeStore for SqliteRecoveryCodeStore {
+    #[tracing::instrument(name = "Replacing recovery codes in SQLite", skip_all)]
+    async fn replace_codes(
+        &self,
+        email: &Email,
+        codes: Vec<HashedRecoveryCode>,
+    ) -> Result<(), RecoveryCodeStoreError> {
+        let _timer = time_store("sqlite", "recovery_code", "replace_codes");
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| RecoveryCodeStoreError::UnexpectedError(e.into()))?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM recovery_codes
+            WHERE email = ?1
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| RecoveryCodeStoreError::UnexpectedError(e.into()))?;
+
+        for code in &codes {
+            sqlx::query(
+                r#"
+                INSERT INTO recovery_codes (email, code_hash)
+                VALUES (?1, ?2)
+                "#,
+            )
+            .bind(email.as_ref().expose_secret())
+            .bind(code.as_ref().expose_secret())
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| RecoveryCodeStoreError::UnexpectedError(e.into()))?;
+        }
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| RecoveryCodeStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Retrieving recovery codes from SQLite", skip_all)]
+    async fn get_codes(
+        &self,
+        email: &Email,
+    ) -> Result<Vec<HashedRecoveryCode>, RecoveryCodeStoreError> {
+        let _timer = time_store("sqlite", "recovery_code", "get_codes");
+        sqlx::query(
+            r#"
+            SELECT code_hash
+            FROM recovery_codes
+            WHERE email = ?1
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RecoveryCodeStoreError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(|row| {
+            let code_hash: String = row
+                .try_get("code_hash")
+                .map_err(|e| RecoveryCodeStoreError::UnexpectedError(e.into()))?;
+            HashedRecoveryCode::parse_hash(SecretString::new(code_hash.into_boxed_str()))
+                .map_err(|e| RecoveryCodeStoreError::UnexpectedError(eyre!(e)))
+        })
+        .collect()
+    }
+
+    #[tracing::instrument(name = "Removing recovery code from SQLite", skip_all)]
+    async fn remove_code(
+        &self,
+        email: &Email,
+        code: &HashedRecoveryCode,
+    ) -> Result<(), RecoveryCodeStoreError> {
+        let _timer = time_store("sqlite", "recovery_code", "remove_code");
+        let result = sqlx::query(
+            r#"
+            DELETE FROM recovery_codes
+            WHERE email = ?1 AND code_hash = ?2
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .bind(code.as_ref().expose_secret())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RecoveryCodeStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(RecoveryCodeStoreError::CodeNotFound);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::types::RecoveryCode;
+    use crate::domain::{HashedPassword, User, UserStore};
+    use crate::get_sqlite_pool;
+    use crate::services::data_stores::sqlite_user_store::SqliteUserStore;
+
+    // Codes belong to a user, so the store comes with one signed up
+    async fn new_store() -> (SqliteRecoveryCodeStore, Email) {
+        let pool = get_sqlite_pool(&SecretString::from("sqlite::memory:"))
+            .await
+            .expect("Failed to create SQLite pool");
+        sqlx::migrate!("./migrations_sqlite")
+            .run(&pool)
+            .await
+            .expect("Failed to run SQLite migrations");
+
+        let email = Email::parse(SecretString::from("test@example.com")).unwrap();
+        let password = HashedPassword::parse(SecretString::from("123DSDFdasd@@456789"))
+            .await
+            .unwrap();
+        SqliteUserStore::new(pool.clone())
+            .add_user(User::new(email.clone(), password, true))
+            .await
+            .unwrap();
+
+        (SqliteRecoveryCodeStore::new(pool), email)
+    }
+
+    async fn hashed_code(code: &str) -> HashedRecoveryCode {
+        let code =
+            RecoveryCode::parse(SecretString::new(code.to_owned().into_boxed_str())).unwrap();
+        HashedRecoveryCode::parse(&code).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_replace_codes_invalidates_old_codes() {
+        let (store, email) = new_store().await;
+        let old = hashed_code("AAAAA-AAAAA").await;
+        let new = hashed_code("BBBBB-BBBBB").await;
+
+        assert!(store.get_codes(&email).await.unwrap().is_empty());
+        store.replace_codes(&email, vec![old]).await.unwrap();
+        store
+            .replace_codes(&email, vec![new.clone()])
+            .await
+            .unwrap();
+
+        assert_eq!(store.get_codes(&email).await.unwrap(), vec![new]);
+    }
+
+    #[tokio::test]
+    async fn test_remove_code_is_single_use() {
+        let (store, email) = new_store().await;
+        let first = hashed_code("AAAAA-AAAAA").await;
+        let second = hashed_code("BBBBB-BBBBB").await;
+
+        store
+            .replace_codes(&email, vec![first.clone(), second.clone()])
+            .await
+            .unwrap();
+        store.remove_code(&email, &first).await.unwrap();
+
+        assert_eq!(
+            store.remove_code(&email, &first).await,
+            Err(RecoveryCodeStoreError::CodeNotFound)
+        );
+        assert_eq!(store.get_codes(&email).await.unwrap(), vec![second]);
+    }
+}
@@ -0,0 +1,310 @@
+use crate::domain::data_store::RefreshTokenStore;
+use crate::domain::error::RefreshTokenStoreError;
+use crate::domain::types::{Email, RefreshTokenRecord};
+use crate::utils::auth::refresh_token_ttl_seconds;
+use crate::utils::metrics::time_store;
+use chrono::Utc;
+use color_eyre::eyre::Report;
+use secrecy::{ExposeSecret, SecretString};
+use sqlx::{Row, SqlitePool};
+
+// Same schema and semantics as `PostgresRefreshTokenStore`
+pub struct SqliteRefreshTokenStore {
+    pool: SqlitePool,
+}
+
+impl SqliteRefreshTokenStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    // Delete tokens and revocations that expired at or before `now`,
+    // returning how many were removed
+    #[tracing::instrument(name = "Purging expired refresh tokens from SQLite", skip_all)]
+    pub async fn purge_expired(&self, now: i64) -> Result<u64, RefreshTokenStoreError> {
+        let _timer = time_store("sqlite", "refresh_token", "purge_expired");
+        let tokens = sqlx::query(
+            r#"
+            DELETE FROM refresh_tokens
+            WHERE expires_at <= ?1
+            "#,
+        )
+        .bind(now)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        let families = sqlx::query(
+            r#"
+            DELETE FROM revoked_refresh_token_families
+            WHERE expires_at <= ?1
+            "#,
+        )
+        .bind(now)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(tokens.rows_affected() + families.rows_affected())
+    }
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for SqliteRefreshTokenStore {
+    #[tracing::instrument(name = "Adding refresh token to SQLite", skip_all)]
+    async fn add_refresh_token(
+        &self,
+        token: SecretString,
+        record: RefreshTokenRecord,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let _timer = time_store("sqlite", "refresh_token", "add_refresh_token");
+        sqlx::query(
+            r#"
+            INSERT INTO refresh_tokens (token, email, family_id, used, expires_at)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            ON CONFLICT (token) DO UPDATE
+            SET email = excluded.email,
+                family_id = excluded.family_id,
+                used = excluded.used,
+                expires_at = excluded.expires_at
+            "#,
+        )
+        .bind(token.expose_secret())
+        .bind(record.email.as_ref().expose_secret())
+        .bind(&record.family_id)
+        .bind(record.used)
+        .bind(Utc::now().timestamp() + refresh_token_ttl_seconds())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving refresh token from SQLite", skip_all)]
+    async fn get_refresh_token(
+        &self,
+        token: &SecretString,
+    ) -> Result<RefreshTokenRecord, RefreshTokenStoreError> {
+        let _timer = time_store("sqlite", "refresh_token", "get_refresh_token");
+        let row = sqlx::query(
+            r#"
+            SELECT email, family_id, used
+            FROM refresh_tokens
+            WHERE token = ?1 AND expires_at > ?2
+            "#,
+        )
+        .bind(token.expose_secret())
+        .bind(Utc::now().timestamp())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?
+        .ok_or(RefreshTokenStoreError::TokenNotFound)?;
+
+        let email: String = row
+            .try_get("email")
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+        Ok(RefreshTokenRecord {
+            email: Email::parse(SecretString::new(email.into_boxed_str()))
+                .map_err(|e| RefreshTokenStoreError::UnexpectedError(Report::new(e)))?,
+            family_id: row
+                .try_get("family_id")
+                .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?,
+            used: row
+                .try_get("used")
+                .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?,
+        })
+    }
+
+    #[tracing::instrument(name = "Marking refresh token used in SQLite", skip_all)]
+    async fn mark_refresh_token_used(
+        &self,
+        token: &SecretString,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let _timer = time_store("sqlite", "refresh_token", "mark_refresh_token_used");
+        self.get_refresh_token(token).await?;
+
+        // A single conditional UPDATE, so of several concurrent rotations
+        // only one succeeds
+        let result = sqlx::query(
+            r#"
+            UPDATE refresh_tokens
+            SET used = TRUE
+            WHERE token = ?1 AND used = FALSE
+            "#,
+        )
+        .bind(token.expose_secret())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(RefreshTokenStoreError::TokenAlreadyUsed);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Revoking refresh token family in SQLite", skip_all)]
+    async fn revoke_family(&self, family_id: &str) -> Result<(), RefreshTokenStoreError> {
+        let _timer = time_store("sqlite", "refresh_token", "revoke_family");
+        sqlx::query(
+            r#"
+            INSERT INTO revoked_refresh_token_families (family_id, expires_at)
+            VALUES (?1, ?2)
+            ON CONFLICT (family_id) DO UPDATE
+            SET expires_at = excluded.expires_at
+            "#,
+        )
+        .bind(family_id)
+        .bind(Utc::now().timestamp() + refresh_token_ttl_seconds())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Checking refresh token family in SQLite", skip_all)]
+    async fn is_family_revoked(&self, family_id: &str) -> Result<bool, RefreshTokenStoreError> {
+        let _timer = time_store("sqlite", "refresh_token", "is_family_revoked");
+        let row = sqlx::query(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM revoked_refresh_token_families
+                WHERE family_id = ?1 AND expires_at > ?2
+            ) AS "exists"
+            "#,
+        )
+        .bind(family_id)
+        .bind(Utc::now().timestamp())
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        row.try_get("exists")
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Revoking user refresh token families in SQLite", skip_all)]
+    async fn revoke_user_families(&self, email: &Email) -> Result<(), RefreshTokenStoreError> {
+        let _timer = time_store("sqlite", "refresh_token", "revoke_user_families");
+        let now = Utc::now().timestamp();
+        sqlx::query(
+            r#"
+            INSERT INTO revoked_refresh_token_families (family_id, expires_at)
+            SELECT DISTINCT family_id, ?3
+            FROM refresh_tokens
+            WHERE email = ?1 AND expires_at > ?2
+            ON CONFLICT (family_id) DO UPDATE
+            SET expires_at = excluded.expires_at
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .bind(now)
+        .bind(now + refresh_token_ttl_seconds())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_sqlite_pool;
+
+    async fn new_store() -> SqliteRefreshTokenStore {
+        let pool = get_sqlite_pool(&SecretString::from("sqlite::memory:"))
+            .await
+            .expect("Failed to create SQLite pool");
+        sqlx::migrate!("./migrations_sqlite")
+            .run(&pool)
+            .await
+            .expect("Failed to run SQLite migrations");
+        SqliteRefreshTokenStore::new(pool)
+    }
+
+    fn email() -> Email {
+        Email::parse(SecretString::from("test@example.com")).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_mark_refresh_token_used_only_once() {
+        let store = new_store().await;
+        let token = SecretString::from("refresh-token");
+        let record = RefreshTokenRecord::new(email(), "family".to_owned());
+
+        store
+            .add_refresh_token(token.clone(), record.clone())
+            .await
+            .unwrap();
+        assert_eq!(store.get_refresh_token(&token).await.unwrap(), record);
+
+        store.mark_refresh_token_used(&token).await.unwrap();
+        let err = store.mark_refresh_token_used(&token).await.unwrap_err();
+        assert!(matches!(err, RefreshTokenStoreError::TokenAlreadyUsed));
+        assert!(store.get_refresh_token(&token).await.unwrap().used);
+        let err = store
+            .mark_refresh_token_used(&SecretString::from("unknown"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, RefreshTokenStoreError::TokenNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_revoke_user_families() {
+        let store = new_store().await;
+        let other = Email::parse(SecretString::from("other@example.com")).unwrap();
+
+        store
+            .add_refresh_token(
+                SecretString::from("first"),
+                RefreshTokenRecord::new(email(), "first-family".to_owned()),
+            )
+            .await
+            .unwrap();
+        store
+            .add_refresh_token(
+                SecretString::from("second"),
+                RefreshTokenRecord::new(email(), "second-family".to_owned()),
+            )
+            .await
+            .unwrap();
+        store
+            .add_refresh_token(
+                SecretString::from("other"),
+                RefreshTokenRecord::new(other, "other-family".to_owned()),
+            )
+            .await
+            .unwrap();
+
+        store.revoke_user_families(&email()).await.unwrap();
+
+        assert!(store.is_family_revoked("first-family").await.unwrap());
+        assert!(store.is_family_revoked("second-family").await.unwrap());
+        assert!(!store.is_family_revoked("other-family").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_purge_expired() {
+        let store = new_store().await;
+        let token = SecretString::from("refresh-token");
+
+        store
+            .add_refresh_token(
+                token.clone(),
+                RefreshTokenRecord::new(email(), "family".to_owned()),
+            )
+            .await
+            .unwrap();
+        store.revoke_family("family").await.unwrap();
+
+        let now = Utc::now().timestamp();
+        assert_eq!(store.purge_expired(now).await.unwrap(), 0);
+        let expired = now + refresh_token_ttl_seconds() + 1;
+        assert_eq!(store.purge_expired(expired).await.unwrap(), 2);
+    }
+}
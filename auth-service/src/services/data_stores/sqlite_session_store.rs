@@ -0,0 +1,275 @@
+use crate::domain::data_store::SessionStore;
+use crate::domain::error::SessionStoreError;
+use crate::domain::types::{Email, Session};
+use crate::utils::auth::refresh_token_ttl_seconds;
+use crate::utils::metrics::time_store;
+use chrono::Utc;
+use color_eyre::eyre::Report;
+use secrecy::{ExposeSecret, SecretString};
+use sqlx::sqlite::SqliteRow;
+use sqlx::{Row, SqlitePool};
+
+// Same schema and semantics as `PostgresSessionStore`
+pub struct SqliteSessionStore {
+    pool: SqlitePool,
+}
+
+impl SqliteSessionStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    // Delete sessions that expired at or before `now`, returning how many
+    // were removed
+    #[tracing::instrument(name = "Purging expired sessions from SQLite", skip_all)]
+    pub async fn purge_expired(&self, now: i64) -> Result<u64, SessionStoreError> {
+        let _timer = time_store("sqlite", "session", "purge_expired");
+        let result = sqlx::query(
+            r#"
+            DELETE FROM sessions
+            WHERE expires_at <= ?1
+            "#,
+        )
+        .bind(now)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait::async_trait]
+impl SessionStore for SqliteSessionStore {
+    #[tracing::instrument(name = "Adding session to SQLite", skip_all)]
+    async fn add_session(&self, session: Session) -> Result<(), SessionStoreError> {
+        let _timer = time_store("sqlite", "session", "add_session");
+        // A session lasts as long as its refresh token family can be used
+        sqlx::query(
+            r#"
+            INSERT INTO sessions (id, email, user_agent, ip, created_at, last_seen_at, expires_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            ON CONFLICT (id) DO UPDATE
+            SET email = excluded.email,
+                user_agent = excluded.user_agent,
+                ip = excluded.ip,
+                created_at = excluded.created_at,
+                last_seen_at = excluded.last_seen_at,
+                expires_at = excluded.expires_at
+            "#,
+        )
+        .bind(&session.id)
+        .bind(session.email.as_ref().expose_secret())
+        .bind(&session.user_agent)
+        .bind(&session.ip)
+        .bind(session.created_at)
+        .bind(session.last_seen_at)
+        .bind(Utc::now().timestamp() + refresh_token_ttl_seconds())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving session from SQLite", skip_all)]
+    async fn get_session(&self, id: &str) -> Result<Session, SessionStoreError> {
+        let _timer = time_store("sqlite", "session", "get_session");
+        let row = sqlx::query(
+            r#"
+            SELECT id, email, user_agent, ip, created_at, last_seen_at
+            FROM sessions
+            WHERE id = ?1 AND expires_at > ?2
+            "#,
+        )
+        .bind(id)
+        .bind(Utc::now().timestamp())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?
+        .ok_or(SessionStoreError::SessionNotFound)?;
+
+        let email: String = row
+            .try_get("email")
+            .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+        let email = Email::parse(SecretString::new(email.into_boxed_str()))
+            .map_err(|e| SessionStoreError::UnexpectedError(Report::new(e)))?;
+        parse_session(row, email)
+    }
+
+    #[tracing::instrument(name = "Retrieving user sessions from SQLite", skip_all)]
+    async fn get_user_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError> {
+        let _timer = time_store("sqlite", "session", "get_user_sessions");
+        sqlx::query(
+            r#"
+            SELECT id, user_agent, ip, created_at, last_seen_at
+            FROM sessions
+            WHERE email = ?1 AND expires_at > ?2
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .bind(Utc::now().timestamp())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(|row| parse_session(row, email.clone()))
+        .collect()
+    }
+
+    #[tracing::instrument(name = "Touching session in SQLite", skip_all)]
+    async fn touch_session(&self, id: &str, last_seen_at: i64) -> Result<(), SessionStoreError> {
+        let _timer = time_store("sqlite", "session", "touch_session");
+        let now = Utc::now().timestamp();
+        let result = sqlx::query(
+            r#"
+            UPDATE sessions
+            SET last_seen_at = ?2, expires_at = ?4
+            WHERE id = ?1 AND expires_at > ?3
+            "#,
+        )
+        .bind(id)
+        .bind(last_seen_at)
+        .bind(now)
+        .bind(now + refresh_token_ttl_seconds())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(SessionStoreError::SessionNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Removing session from SQLite", skip_all)]
+    async fn remove_session(&self, id: &str) -> Result<(), SessionStoreError> {
+        let _timer = time_store("sqlite", "session", "remove_session");
+        let result = sqlx::query(
+            r#"
+            DELETE FROM sessions
+            WHERE id = ?1 AND expires_at > ?2
+            "#,
+        )
+        .bind(id)
+        .bind(Utc::now().timestamp())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(SessionStoreError::SessionNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Removing user sessions from SQLite", skip_all)]
+    async fn remove_user_sessions(&self, email: &Email) -> Result<(), SessionStoreError> {
+        let _timer = time_store("sqlite", "session", "remove_user_sessions");
+        sqlx::query(
+            r#"
+            DELETE FROM sessions
+            WHERE email = ?1
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
+
+fn parse_session(row: SqliteRow, email: Email) -> Result<Session, SessionStoreError> {
+    Ok(Session {
+        id: row
+            .try_get("id")
+            .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?,
+        email,
+        user_agent: row
+            .try_get("user_agent")
+            .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?,
+        ip: row
+            .try_get("ip")
+            .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?,
+        created_at: row
+            .try_get("created_at")
+            .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?,
+        last_seen_at: row
+            .try_get("last_seen_at")
+            .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_sqlite_pool;
+
+    async fn new_store() -> SqliteSessionStore {
+        let pool = get_sqlite_pool(&SecretString::from("sqlite::memory:"))
+            .await
+            .expect("Failed to create SQLite pool");
+        sqlx::migrate!("./migrations_sqlite")
+            .run(&pool)
+            .await
+            .expect("Failed to run SQLite migrations");
+        SqliteSessionStore::new(pool)
+    }
+
+    fn session(id: &str) -> Session {
+        Session {
+            id: id.to_owned(),
+            email: Email::parse(SecretString::from("test@example.com")).unwrap(),
+            user_agent: Some("curl/8.0".to_owned()),
+            ip: "127.0.0.1".to_owned(),
+            created_at: 1,
+            last_seen_at: 1,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_touch_and_remove_session() {
+        let store = new_store().await;
+        let session = session("session");
+
+        store.add_session(session.clone()).await.unwrap();
+        store.touch_session(&session.id, 2).await.unwrap();
+        assert_eq!(
+            store.get_session(&session.id).await.unwrap().last_seen_at,
+            2
+        );
+
+        store.remove_session(&session.id).await.unwrap();
+        assert_eq!(
+            store.remove_session(&session.id).await,
+            Err(SessionStoreError::SessionNotFound)
+        );
+        assert_eq!(
+            store.touch_session(&session.id, 3).await,
+            Err(SessionStoreError::SessionNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_remove_user_sessions() {
+        let store = new_store().await;
+        let first = session("first");
+
+        store.add_session(first.clone()).await.unwrap();
+        store.add_session(session("second")).await.unwrap();
+        let mut sessions = store.get_user_sessions(&first.email).await.unwrap();
+        sessions.sort_by(|a, b| a.id.cmp(&b.id));
+        assert_eq!(sessions, vec![first.clone(), session("second")]);
+
+        store.remove_user_sessions(&first.email).await.unwrap();
+        assert!(store
+            .get_user_sessions(&first.email)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
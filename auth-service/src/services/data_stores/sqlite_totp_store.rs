@@ -0,0 +1,264 @@
+use crate::domain::data_store::TotpStore;
+use crate::domain::error::TotpStoreError;
+use crate::domain::types::{Email, TotpRecord, TotpSecret};
+use crate::utils::metrics::time_store;
+use color_eyre::eyre::eyre;
+use secrecy::{ExposeSecret, SecretString};
+use sqlx::{Row, SqlitePool};
+
+// Same schema and semantics as `PostgresTotpStore`, kept next to the users
+// of a `SqliteUserStore`
+pub struct SqliteTotpStore {
+    pool: SqlitePool,
+}
+
+impl SqliteTotpStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl TotpStore for SqliteTotpStore {
+    #[tracing::instrument(name = "Adding pending TOTP secret to SQLite", skip_all)]
+    async fn add_pending_secret(
+        &self,
+        email: &Email,
+        secret: TotpSecret,
+    ) -> Result<(), TotpStoreError> {
+        let _timer = time_store("sqlite", "totp", "add_pending_secret");
+        // The conditional upsert leaves a confirmed secret untouched, in which
+        // case no row is affected.
+        let result = sqlx::query(
+            r#"
+            INSERT INTO totp_secrets (email, secret, confirmed, last_used_step)
+            VALUES (?1, ?2, FALSE, NULL)
+            ON CONFLICT (email) DO UPDATE
+            SET secret = excluded.secret, last_used_step = NULL
+            WHERE totp_secrets.confirmed = FALSE
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .bind(secret.as_ref().expose_secret())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TotpStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(TotpStoreError::AlreadyEnabled);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving TOTP secret from SQLite", skip_all)]
+    async fn get_totp(&self, email: &Email) -> Result<TotpRecord, TotpStoreError> {
+        let _timer = time_store("sqlite", "totp", "get_totp");
+        let row = sqlx::query(
+            r#"
+            SELECT secret, confirmed, last_used_step
+            FROM totp_secrets
+            WHERE email = ?1
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| TotpStoreError::UnexpectedError(e.into()))?
+        .ok_or(TotpStoreError::SecretNotFound)?;
+
+        let secret: String = row
+            .try_get("secret")
+            .map_err(|e| TotpStoreError::UnexpectedError(e.into()))?;
+
+        Ok(TotpRecord {
+            secret: TotpSecret::parse(SecretString::new(secret.into_boxed_str()))
+                .map_err(|e| TotpStoreError::UnexpectedError(eyre!(e)))?,
+            confirmed: row
+                .try_get("confirmed")
+                .map_err(|e| TotpStoreError::UnexpectedError(e.into()))?,
+            last_used_step: row
+                .try_get("last_used_step")
+                .map_err(|e| TotpStoreError::UnexpectedError(e.into()))?,
+        })
+    }
+
+    #[tracing::instrument(name = "Confirming TOTP secret in SQLite", skip_all)]
+    async fn confirm_secret(&self, email: &Email) -> Result<(), TotpStoreError> {
+        let _timer = time_store("sqlite", "totp", "confirm_secret");
+        let result = sqlx::query(
+            r#"
+            UPDATE totp_secrets
+            SET confirmed = TRUE
+            WHERE email = ?1
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TotpStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(TotpStoreError::SecretNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Recording used TOTP step in SQLite", skip_all)]
+    async fn record_used_step(&self, email: &Email, step: i64) -> Result<(), TotpStoreError> {
+        let _timer = time_store("sqlite", "totp", "record_used_step");
+        // A single conditional UPDATE, so two concurrent requests can't both
+        // redeem the same code.
+        let result = sqlx::query(
+            r#"
+            UPDATE totp_secrets
+            SET last_used_step = ?2
+            WHERE email = ?1 AND (last_used_step IS NULL OR last_used_step < ?2)
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .bind(step)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TotpStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(TotpStoreError::StepAlreadyUsed);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Removing TOTP secret from SQLite", skip_all)]
+    async fn remove_totp(&self, email: &Email) -> Result<(), TotpStoreError> {
+        let _timer = time_store("sqlite", "totp", "remove_totp");
+        sqlx::query(
+            r#"
+            DELETE FROM totp_secrets
+            WHERE email = ?1
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TotpStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{HashedPassword, User, UserStore};
+    use crate::get_sqlite_pool;
+    use crate::services::data_stores::sqlite_user_store::SqliteUserStore;
+    use crate::utils::totp::generate_totp_secret;
+
+    // Secrets belong to a user, so the store comes with one signed up
+    async fn new_store() -> (SqliteTotpStore, Email) {
+        let pool = get_sqlite_pool(&SecretString::from("sqlite::memory:"))
+            .await
+            .expect("Failed to create SQLite pool");
+        sqlx::migrate!("./migrations_sqlite")
+            .run(&pool)
+            .await
+            .expect("Failed to run SQLite migrations");
+
+        let email = Email::parse(SecretString::from("test@example.com")).unwrap();
+        let password = HashedPassword::parse(SecretString::from("123DSDFdasd@@456789"))
+            .await
+            .unwrap();
+        SqliteUserStore::new(pool.clone())
+            .add_user(User::new(email.clone(), password, true))
+            .await
+            .unwrap();
+
+        (SqliteTotpStore::new(pool), email)
+    }
+
+    #[tokio::test]
+    async fn test_add_pending_secret_replaces_unconfirmed_secret() {
+        let (store, email) = new_store().await;
+        let secret = generate_totp_secret();
+
+        store
+            .add_pending_secret(&email, generate_totp_secret())
+            .await
+            .unwrap();
+        store
+            .add_pending_secret(&email, secret.clone())
+            .await
+            .unwrap();
+
+        let record = store.get_totp(&email).await.unwrap();
+        assert_eq!(record.secret, secret);
+        assert!(!record.confirmed);
+    }
+
+    #[tokio::test]
+    async fn test_add_pending_secret_fails_once_confirmed() {
+        let (store, email) = new_store().await;
+
+        store
+            .add_pending_secret(&email, generate_totp_secret())
+            .await
+            .unwrap();
+        store.confirm_secret(&email).await.unwrap();
+
+        let result = store
+            .add_pending_secret(&email, generate_totp_secret())
+            .await;
+        assert_eq!(result, Err(TotpStoreError::AlreadyEnabled));
+        assert!(store.get_totp(&email).await.unwrap().confirmed);
+    }
+
+    #[tokio::test]
+    async fn test_record_used_step_rejects_replays() {
+        let (store, email) = new_store().await;
+
+        store
+            .add_pending_secret(&email, generate_totp_secret())
+            .await
+            .unwrap();
+        store.record_used_step(&email, 10).await.unwrap();
+
+        assert_eq!(
+            store.record_used_step(&email, 10).await,
+            Err(TotpStoreError::StepAlreadyUsed)
+        );
+        assert_eq!(
+            store.record_used_step(&email, 9).await,
+            Err(TotpStoreError::StepAlreadyUsed)
+        );
+        store.record_used_step(&email, 11).await.unwrap();
+        assert_eq!(
+            store.get_totp(&email).await.unwrap().last_used_step,
+            Some(11)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_remove_totp() {
+        let (store, email) = new_store().await;
+        assert_eq!(
+            store.get_totp(&email).await,
+            Err(TotpStoreError::SecretNotFound)
+        );
+
+        store
+            .add_pending_secret(&email, generate_totp_secret())
+            .await
+            .unwrap();
+        store.confirm_secret(&email).await.unwrap();
+
+        store.remove_totp(&email).await.unwrap();
+        store.remove_totp(&email).await.unwrap();
+
+        assert_eq!(
+            store.get_totp(&email).await,
+            Err(TotpStoreError::SecretNotFound)
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use crate::domain::{
+    types::{LoginAttemptId, TwoFACode},
+    Email, TwoFACodeStore, TwoFACodeStoreError,
+};
+use crate::utils::metrics::time_store;
+use crate::utils::settings::settings;
+use chrono::Utc;
+use secrecy::{ExposeSecret, SecretString};
+use sqlx::{Row, SqlitePool};
+
+// Same schema and semantics as `PostgresTwoFACodeStore`
+pub struct SqliteTwoFACodeStore {
+    pool: SqlitePool,
+}
+
+impl SqliteTwoFACodeStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    // Delete codes that expired at or before `now`, returning how many were removed
+    #[tracing::instrument(name = "Purging expired 2FA codes from SQLite", skip_all)]
+    pub async fn purge_expired(&self, now: i64) -> Result<u64, TwoFACodeStoreError> {
+        let _timer = time_store("sqlite", "two_fa_code", "purge_expired");
+        let result = sqlx::query(
+            r#"
+            DELETE FROM two_fa_codes
+            WHERE expires_at <= ?1
+            "#,
+        )
+        .bind(now)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait::async_trait]
+impl TwoFACodeStore for SqliteTwoFACodeStore {
+    #[tracing::instrument(name = "Adding 2FA code to SQLite", skip_all)]
+    async fn add_two_fa_code(
+        &self,
+        email: Email,
+        login_attempt_id: LoginAttemptId,
+        code: TwoFACode,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let _timer = time_store("sqlite", "two_fa_code", "add_two_fa_code");
+        // A new login attempt replaces any earlier one
+        sqlx::query(
+            r#"
+            INSERT INTO two_fa_codes (email, login_attempt_id, code, expires_at)
+            VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT (email) DO UPDATE
+            SET login_attempt_id = excluded.login_attempt_id,
+                code = excluded.code,
+                expires_at = excluded.expires_at
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .bind(login_attempt_id.as_ref().expose_secret())
+        .bind(code.as_ref().expose_secret())
+        .bind(Utc::now().timestamp() + settings().tokens.two_fa_code_ttl_seconds as i64)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Removing 2FA code from SQLite", skip_all)]
+    async fn remove_two_fa_code(&self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+        let _timer = time_store("sqlite", "two_fa_code", "remove_two_fa_code");
+        // Only one of several concurrent removals deletes the row
+        let result = sqlx::query(
+            r#"
+            DELETE FROM two_fa_codes
+            WHERE email = ?1 AND expires_at > ?2
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .bind(Utc::now().timestamp())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(TwoFACodeStoreError::LoginAttemptIdNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving 2FA code from SQLite", skip_all)]
+    async fn get_two_fa_code(
+        &self,
+        email: &Email,
+    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
+        let _timer = time_store("sqlite", "two_fa_code", "get_two_fa_code");
+        let row = sqlx::query(
+            r#"
+            SELECT login_attempt_id, code
+            FROM two_fa_codes
+            WHERE email = ?1 AND expires_at > ?2
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .bind(Utc::now().timestamp())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?
+        .ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+
+        let login_attempt_id: String = row
+            .try_get("login_attempt_id")
+            .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+        let code: String = row
+            .try_get("code")
+            .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+
+        let login_attempt_id =
+            LoginAttemptId::parse(SecretString::new(login_attempt_id.into_boxed_str()))
+                .map_err(TwoFACodeStoreError::UnexpectedError)?;
+        let code = TwoFACode::parse(SecretString::new(code.into_boxed_str()))
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+        Ok((login_attempt_id, code))
+    }
+
+    #[tracing::instrument(name = "Checking 2FA code in SQLite", skip_all)]
+    async fn two_fa_code_exists(&self, email: &Email) -> Result<bool, TwoFACodeStoreError> {
+        let _timer = time_store("sqlite", "two_fa_code", "two_fa_code_exists");
+        let row = sqlx::query(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM two_fa_codes
+                WHERE email = ?1 AND expires_at > ?2
+            ) AS "exists"
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .bind(Utc::now().timestamp())
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+
+        row.try_get("exists")
+            .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))
+    }
+}
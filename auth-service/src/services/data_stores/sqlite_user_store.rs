@@ -0,0 +1,431 @@
+use crate::domain::{
+    Email, HashedPassword, User, UserPage, UserQuery, UserRoles, UserStore, UserStoreError,
+};
+use crate::services::data_stores::postgres_user_store::email_like_pattern;
+use crate::utils::metrics::time_store;
+use color_eyre::eyre::eyre;
+use secrecy::{ExposeSecret, SecretString};
+use sqlx::sqlite::SqliteRow;
+use sqlx::{Row, SqlitePool};
+use std::collections::BTreeSet;
+
+// Same schema and semantics as `PostgresUserStore`, for deployments that
+// don't want to run Postgres. Queries are checked at runtime since the
+// compile-time checked macros can only target one database.
+pub struct SqliteUserStore {
+    pool: SqlitePool,
+}
+
+impl SqliteUserStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl UserStore for SqliteUserStore {
+    #[tracing::instrument(name = "Adding user to SQLite", skip_all)]
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError> {
+        let _timer = time_store("sqlite", "user", "add_user");
+        let User {
+            email,
+            password: password_hash,
+            requires_2fa,
+            verified,
+            disabled,
+            password_reset_required,
+        } = user;
+
+        sqlx::query(
+            r#"
+            INSERT INTO users (
+                email, password_hash, requires_2fa, verified, disabled, password_reset_required
+            )
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .bind(password_hash.as_ref().expose_secret())
+        .bind(requires_2fa)
+        .bind(verified)
+        .bind(disabled)
+        .bind(password_reset_required)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match e {
+            // The email is the primary key, so a duplicate signup violates it
+            sqlx::Error::Database(e) if e.is_unique_violation() => {
+                UserStoreError::UserAlreadyExists
+            }
+            e => UserStoreError::UnexpectedError(e.into()),
+        })?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving user from SQLite", skip_all)]
+    async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
+        let _timer = time_store("sqlite", "user", "get_user");
+        sqlx::query(
+            r#"
+            SELECT email, password_hash, requires_2fa, verified, disabled, password_reset_required
+            FROM users
+            WHERE email = ?1
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .map(parse_user)
+        .ok_or(UserStoreError::UserNotFound)?
+    }
+
+    #[tracing::instrument(name = "Validating user credentials in SQLite", skip_all)]
+    async fn validate_user(
+        &self,
+        email: Email,
+        password: &SecretString,
+    ) -> Result<(), UserStoreError> {
+        let user = self.get_user(&email).await?;
+        if user.password.verify_raw_password(password).await.is_err() {
+            return Err(UserStoreError::InvalidCredentials);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Updating user password in SQLite", skip_all)]
+    async fn update_password(
+        &self,
+        email: &Email,
+        password: HashedPassword,
+    ) -> Result<(), UserStoreError> {
+        let _timer = time_store("sqlite", "user", "update_password");
+        let result = sqlx::query(
+            r#"
+            UPDATE users
+            SET password_hash = ?2, password_reset_required = FALSE
+            WHERE email = ?1
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .bind(password.as_ref().expose_secret())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Marking user email as verified in SQLite", skip_all)]
+    async fn mark_email_verified(&self, email: &Email) -> Result<(), UserStoreError> {
+        let _timer = time_store("sqlite", "user", "mark_email_verified");
+        let result = sqlx::query(
+            r#"
+            UPDATE users
+            SET verified = TRUE
+            WHERE email = ?1
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Updating user 2FA requirement in SQLite", skip_all)]
+    async fn set_requires_2fa(
+        &self,
+        email: &Email,
+        requires_2fa: bool,
+    ) -> Result<(), UserStoreError> {
+        let _timer = time_store("sqlite", "user", "set_requires_2fa");
+        let result = sqlx::query(
+            r#"
+            UPDATE users
+            SET requires_2fa = ?2
+            WHERE email = ?1
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .bind(requires_2fa)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Adding user role in SQLite", skip_all)]
+    async fn add_role(&self, email: &Email, role: &str) -> Result<(), UserStoreError> {
+        let _timer = time_store("sqlite", "user", "add_role");
+        // SQLite doesn't say which foreign key was violated
+        let role_exists = sqlx::query("SELECT 1 FROM roles WHERE name = ?1")
+            .bind(role)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+            .is_some();
+        if !role_exists {
+            return Err(UserStoreError::RoleNotFound);
+        }
+
+        sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO user_roles (email, role)
+            VALUES (?1, ?2)
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .bind(role)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(e) if e.is_foreign_key_violation() => {
+                UserStoreError::UserNotFound
+            }
+            e => UserStoreError::UnexpectedError(e.into()),
+        })?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Removing user role in SQLite", skip_all)]
+    async fn remove_role(&self, email: &Email, role: &str) -> Result<(), UserStoreError> {
+        let _timer = time_store("sqlite", "user", "remove_role");
+        let result = sqlx::query("DELETE FROM user_roles WHERE email = ?1 AND role = ?2")
+            .bind(email.as_ref().expose_secret())
+            .bind(role)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        // Tell apart an unknown user from one without the role
+        if result.rows_affected() == 0 {
+            self.get_user(email).await?;
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving user roles from SQLite", skip_all)]
+    async fn get_roles(&self, email: &Email) -> Result<UserRoles, UserStoreError> {
+        let _timer = time_store("sqlite", "user", "get_roles");
+        // One row per permission, or a single row without a role when the
+        // user has none
+        let rows = sqlx::query(
+            r#"
+            SELECT ur.role AS role, rp.permission AS permission
+            FROM users u
+            LEFT JOIN user_roles ur ON ur.email = u.email
+            LEFT JOIN role_permissions rp ON rp.role = ur.role
+            WHERE u.email = ?1
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if rows.is_empty() {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        let mut roles = BTreeSet::new();
+        let mut permissions = BTreeSet::new();
+        for row in rows {
+            let role: Option<String> = row
+                .try_get("role")
+                .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+            let permission: Option<String> = row
+                .try_get("permission")
+                .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+            roles.extend(role);
+            permissions.extend(permission);
+        }
+        Ok(UserRoles {
+            roles: roles.into_iter().collect(),
+            permissions: permissions.into_iter().collect(),
+        })
+    }
+
+    #[tracing::instrument(name = "Listing users in SQLite", skip_all)]
+    async fn list_users(&self, query: &UserQuery) -> Result<UserPage, UserStoreError> {
+        let _timer = time_store("sqlite", "user", "list_users");
+        // `LIKE` ignores ASCII case in SQLite
+        let pattern = email_like_pattern(query.email_contains.as_deref());
+
+        let total: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*)
+            FROM users
+            WHERE email LIKE ?1 ESCAPE '\'
+            "#,
+        )
+        .bind(&pattern)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        let users = sqlx::query(
+            r#"
+            SELECT email, password_hash, requires_2fa, verified, disabled, password_reset_required
+            FROM users
+            WHERE email LIKE ?1 ESCAPE '\'
+            ORDER BY email
+            LIMIT ?2 OFFSET ?3
+            "#,
+        )
+        .bind(&pattern)
+        .bind(i64::from(query.limit))
+        .bind(i64::from(query.offset))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(parse_user)
+        .collect::<Result<_, _>>()?;
+
+        Ok(UserPage {
+            users,
+            total: total as u64,
+        })
+    }
+
+    #[tracing::instrument(name = "Updating user disabled flag in SQLite", skip_all)]
+    async fn set_disabled(&self, email: &Email, disabled: bool) -> Result<(), UserStoreError> {
+        let _timer = time_store("sqlite", "user", "set_disabled");
+        let result = sqlx::query(
+            r#"
+            UPDATE users
+            SET disabled = ?2
+            WHERE email = ?1
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .bind(disabled)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Updating user password reset flag in SQLite", skip_all)]
+    async fn set_password_reset_required(
+        &self,
+        email: &Email,
+        required: bool,
+    ) -> Result<(), UserStoreError> {
+        let _timer = time_store("sqlite", "user", "set_password_reset_required");
+        let result = sqlx::query(
+            r#"
+            UPDATE users
+            SET password_reset_required = ?2
+            WHERE email = ?1
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .bind(required)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Deleting user from SQLite", skip_all)]
+    async fn delete_user(&self, email: &Email) -> Result<(), UserStoreError> {
+        let _timer = time_store("sqlite", "user", "delete_user");
+        // Roles go with it, see the `ON DELETE CASCADE` foreign key
+        let result = sqlx::query(
+            r#"
+            DELETE FROM users
+            WHERE email = ?1
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_user(row: SqliteRow) -> Result<User, UserStoreError> {
+    let email: String = row
+        .try_get("email")
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+    let password_hash: String = row
+        .try_get("password_hash")
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+    Ok(User {
+        email: Email::parse(SecretString::new(email.into_boxed_str()))
+            .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
+        password: HashedPassword::parse_password_hash(SecretString::new(
+            password_hash.into_boxed_str(),
+        ))
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
+        requires_2fa: row
+            .try_get("requires_2fa")
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?,
+        verified: row
+            .try_get("verified")
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?,
+        disabled: row
+            .try_get("disabled")
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?,
+        password_reset_required: row
+            .try_get("password_reset_required")
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SqliteUserStore;
+    use crate::get_sqlite_pool;
+    use crate::services::data_stores::user_store_tests::user_store_tests;
+
+    async fn new_store() -> SqliteUserStore {
+        // Each test gets its own private in-memory database
+        let pool = get_sqlite_pool(&SecretString::from("sqlite::memory:"))
+            .await
+            .expect("Failed to create SQLite pool");
+        sqlx::migrate!("./migrations_sqlite")
+            .run(&pool)
+            .await
+            .expect("Failed to run SQLite migrations");
+        SqliteUserStore::new(pool)
+    }
+
+    user_store_tests!(new_store());
+}
@@ -0,0 +1,365 @@
+// Tests shared by every `UserStore` implementation. `$new_store` is a future
+// resolving to an empty store.
+macro_rules! user_store_tests {
+    ($new_store:expr) => {
+        use crate::domain::{
+            Email, HashedPassword, User, UserQuery, UserStore, UserStoreError, ADMIN_ROLE,
+            MANAGE_USERS_PERMISSION,
+        };
+        use secrecy::{ExposeSecret, SecretString};
+
+        async fn setup_store_and_get_user() -> Result<(impl UserStore, User), UserStoreError> {
+            let store = $new_store.await;
+
+            let password = SecretString::new("123DSDFdasd@@456789".to_owned().into_boxed_str());
+
+            store
+                .add_user(User::new(
+                    Email::parse(SecretString::new(
+                        "test@example.com".to_owned().into_boxed_str(),
+                    ))
+                    .unwrap(),
+                    HashedPassword::parse(password).await.unwrap(),
+                    false,
+                ))
+                .await?;
+
+            let user = store
+                .get_user(
+                    &Email::parse(SecretString::new(
+                        "test@example.com".to_owned().into_boxed_str(),
+                    ))
+                    .unwrap(),
+                )
+                .await?;
+
+            Ok((store, user))
+        }
+
+        #[tokio::test]
+        async fn test_add_user() -> Result<(), UserStoreError> {
+            let (store, store_user) = setup_store_and_get_user().await?;
+
+            let password = SecretString::new("123DSDFdasd@@456789".to_owned().into_boxed_str());
+
+            store
+                .validate_user(
+                    Email::parse(SecretString::new(
+                        "test@example.com".to_owned().into_boxed_str(),
+                    ))
+                    .unwrap(),
+                    &password,
+                )
+                .await?;
+
+            assert!(!store_user.requires_2fa);
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_add_user_returns_err_if_user_exists() -> Result<(), UserStoreError> {
+            let (store, user) = setup_store_and_get_user().await?;
+
+            assert_eq!(
+                store.add_user(user).await,
+                Err(UserStoreError::UserAlreadyExists)
+            );
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_get_user() -> Result<(), UserStoreError> {
+            setup_store_and_get_user().await?;
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_get_user_returns_err_if_user_not_found() {
+            let store = $new_store.await;
+            let email = Email::parse(SecretString::new(
+                "missing@example.com".to_owned().into_boxed_str(),
+            ))
+            .unwrap();
+
+            assert_eq!(
+                store.get_user(&email).await.map(|_| ()),
+                Err(UserStoreError::UserNotFound)
+            );
+        }
+
+        #[tokio::test]
+        async fn test_validate_user() -> Result<(), UserStoreError> {
+            let (store, _) = setup_store_and_get_user().await?;
+
+            let password = SecretString::new("123DSDFdasd@@456789".to_owned().into_boxed_str());
+
+            store
+                .validate_user(
+                    Email::parse(SecretString::new(
+                        "test@example.com".to_owned().into_boxed_str(),
+                    ))
+                    .unwrap(),
+                    &password,
+                )
+                .await?;
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_update_password() -> Result<(), UserStoreError> {
+            let (store, user) = setup_store_and_get_user().await?;
+
+            let new_password = SecretString::new("New123DSDFdasd@@456".to_owned().into_boxed_str());
+            store
+                .update_password(
+                    &user.email,
+                    HashedPassword::parse(new_password.clone()).await.unwrap(),
+                )
+                .await?;
+
+            store
+                .validate_user(user.email.clone(), &new_password)
+                .await?;
+
+            let old_password = SecretString::new("123DSDFdasd@@456789".to_owned().into_boxed_str());
+            assert_eq!(
+                store.validate_user(user.email, &old_password).await,
+                Err(UserStoreError::InvalidCredentials)
+            );
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_update_password_returns_err_if_user_not_found() {
+            let store = $new_store.await;
+            let email = Email::parse(SecretString::new(
+                "missing@example.com".to_owned().into_boxed_str(),
+            ))
+            .unwrap();
+            let password = SecretString::new("123DSDFdasd@@456789".to_owned().into_boxed_str());
+
+            let result = store
+                .update_password(&email, HashedPassword::parse(password).await.unwrap())
+                .await;
+
+            assert_eq!(result, Err(UserStoreError::UserNotFound));
+        }
+
+        #[tokio::test]
+        async fn test_mark_email_verified() -> Result<(), UserStoreError> {
+            let (store, user) = setup_store_and_get_user().await?;
+            assert!(!user.verified);
+
+            store.mark_email_verified(&user.email).await?;
+
+            assert!(store.get_user(&user.email).await?.verified);
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_set_requires_2fa() -> Result<(), UserStoreError> {
+            let (store, user) = setup_store_and_get_user().await?;
+
+            store.set_requires_2fa(&user.email, true).await?;
+
+            assert!(store.get_user(&user.email).await?.requires_2fa);
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_roles() -> Result<(), UserStoreError> {
+            let (store, user) = setup_store_and_get_user().await?;
+            let roles = store.get_roles(&user.email).await?;
+            assert!(roles.roles.is_empty());
+            assert!(roles.permissions.is_empty());
+
+            // Granting a role twice is a no-op
+            store.add_role(&user.email, ADMIN_ROLE).await?;
+            store.add_role(&user.email, ADMIN_ROLE).await?;
+            let roles = store.get_roles(&user.email).await?;
+            assert_eq!(roles.roles, vec![ADMIN_ROLE.to_owned()]);
+            assert_eq!(roles.permissions, vec![MANAGE_USERS_PERMISSION.to_owned()]);
+
+            store.remove_role(&user.email, ADMIN_ROLE).await?;
+            store.remove_role(&user.email, ADMIN_ROLE).await?;
+            assert!(store.get_roles(&user.email).await?.roles.is_empty());
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_roles_return_err_if_user_or_role_not_found() -> Result<(), UserStoreError> {
+            let (store, user) = setup_store_and_get_user().await?;
+            let missing = Email::parse(SecretString::new(
+                "missing@example.com".to_owned().into_boxed_str(),
+            ))
+            .unwrap();
+
+            assert_eq!(
+                store.add_role(&user.email, "superuser").await,
+                Err(UserStoreError::RoleNotFound)
+            );
+            assert_eq!(
+                store.add_role(&missing, ADMIN_ROLE).await,
+                Err(UserStoreError::UserNotFound)
+            );
+            assert_eq!(
+                store.remove_role(&missing, ADMIN_ROLE).await,
+                Err(UserStoreError::UserNotFound)
+            );
+            assert_eq!(
+                store.get_roles(&missing).await,
+                Err(UserStoreError::UserNotFound)
+            );
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_list_users() -> Result<(), UserStoreError> {
+            let store = $new_store.await;
+            let password = HashedPassword::parse(SecretString::from("123DSDFdasd@@456789"))
+                .await
+                .unwrap();
+            for address in ["carol@example.com", "alice@example.com", "bob@other.org"] {
+                let email = Email::parse(SecretString::from(address)).unwrap();
+                store
+                    .add_user(User::new(email, password.clone(), false))
+                    .await?;
+            }
+            let emails = |users: &[User]| -> Vec<String> {
+                users
+                    .iter()
+                    .map(|user| user.email.as_ref().expose_secret().to_owned())
+                    .collect()
+            };
+
+            let page = store
+                .list_users(&UserQuery {
+                    email_contains: None,
+                    limit: 2,
+                    offset: 0,
+                })
+                .await?;
+            assert_eq!(page.total, 3);
+            assert_eq!(
+                emails(&page.users),
+                vec!["alice@example.com", "bob@other.org"]
+            );
+
+            let page = store
+                .list_users(&UserQuery {
+                    email_contains: None,
+                    limit: 2,
+                    offset: 2,
+                })
+                .await?;
+            assert_eq!(page.total, 3);
+            assert_eq!(emails(&page.users), vec!["carol@example.com"]);
+
+            let page = store
+                .list_users(&UserQuery {
+                    email_contains: Some("EXAMPLE".to_owned()),
+                    limit: 10,
+                    offset: 0,
+                })
+                .await?;
+            assert_eq!(page.total, 2);
+            assert_eq!(
+                emails(&page.users),
+                vec!["alice@example.com", "carol@example.com"]
+            );
+
+            // Wildcards are matched literally
+            let page = store
+                .list_users(&UserQuery {
+                    email_contains: Some("%".to_owned()),
+                    limit: 10,
+                    offset: 0,
+                })
+                .await?;
+            assert_eq!(page.total, 0);
+            assert!(page.users.is_empty());
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_set_disabled() -> Result<(), UserStoreError> {
+            let (store, user) = setup_store_and_get_user().await?;
+            assert!(!user.disabled);
+
+            store.set_disabled(&user.email, true).await?;
+            assert!(store.get_user(&user.email).await?.disabled);
+
+            store.set_disabled(&user.email, false).await?;
+            assert!(!store.get_user(&user.email).await?.disabled);
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_password_reset_required_is_cleared_by_update_password(
+        ) -> Result<(), UserStoreError> {
+            let (store, user) = setup_store_and_get_user().await?;
+            assert!(!user.password_reset_required);
+
+            store.set_password_reset_required(&user.email, true).await?;
+            assert!(store.get_user(&user.email).await?.password_reset_required);
+
+            let password = SecretString::from("New123DSDFdasd@@456");
+            store
+                .update_password(&user.email, HashedPassword::parse(password).await.unwrap())
+                .await?;
+            assert!(!store.get_user(&user.email).await?.password_reset_required);
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_delete_user() -> Result<(), UserStoreError> {
+            let (store, user) = setup_store_and_get_user().await?;
+            store.add_role(&user.email, ADMIN_ROLE).await?;
+
+            store.delete_user(&user.email).await?;
+
+            assert_eq!(
+                store.get_user(&user.email).await.err(),
+                Some(UserStoreError::UserNotFound)
+            );
+            assert_eq!(
+                store.delete_user(&user.email).await,
+                Err(UserStoreError::UserNotFound)
+            );
+
+            // Signing up again starts without the old roles
+            store.add_user(user.clone()).await?;
+            assert!(store.get_roles(&user.email).await?.roles.is_empty());
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_admin_flags_return_err_if_user_not_found() {
+            let store = $new_store.await;
+            let email = Email::parse(SecretString::from("missing@example.com")).unwrap();
+
+            assert_eq!(
+                store.set_disabled(&email, true).await,
+                Err(UserStoreError::UserNotFound)
+            );
+            assert_eq!(
+                store.set_password_reset_required(&email, true).await,
+                Err(UserStoreError::UserNotFound)
+            );
+        }
+    };
+}
+
+pub(crate) use user_store_tests;
@@ -0,0 +1,86 @@
+use crate::domain::HealthCheck;
+use color_eyre::eyre::{Result, WrapErr};
+use redis::aio::ConnectionManager;
+use sqlx::PgPool;
+
+pub struct PostgresHealthCheck {
+    pool: PgPool,
+}
+
+impl PostgresHealthCheck {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl HealthCheck for PostgresHealthCheck {
+    fn name(&self) -> &'static str {
+        "postgres"
+    }
+
+    #[tracing::instrument(name = "Checking PostgreSQL health", skip_all)]
+    async fn check(&self) -> Result<()> {
+        sqlx::query("SELECT 1")
+            .execute(&self.pool)
+            .await
+            .wrap_err("Failed to query PostgreSQL")?;
+        Ok(())
+    }
+}
+
+pub struct RedisHealthCheck {
+    conn: ConnectionManager,
+}
+
+impl RedisHealthCheck {
+    pub fn new(conn: ConnectionManager) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl HealthCheck for RedisHealthCheck {
+    fn name(&self) -> &'static str {
+        "redis"
+    }
+
+    #[tracing::instrument(name = "Checking Redis health", skip_all)]
+    async fn check(&self) -> Result<()> {
+        let mut conn = self.conn.clone();
+        redis::cmd("PING")
+            .query_async::<()>(&mut conn)
+            .await
+            .wrap_err("Failed to ping Redis")?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "sqlite")]
+pub struct SqliteHealthCheck {
+    pool: sqlx::SqlitePool,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteHealthCheck {
+    pub fn new(pool: sqlx::SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[cfg(feature = "sqlite")]
+#[async_trait::async_trait]
+impl HealthCheck for SqliteHealthCheck {
+    fn name(&self) -> &'static str {
+        "sqlite"
+    }
+
+    #[tracing::instrument(name = "Checking SQLite health", skip_all)]
+    async fn check(&self) -> Result<()> {
+        sqlx::query("SELECT 1")
+            .execute(&self.pool)
+            .await
+            .wrap_err("Failed to query SQLite")?;
+        Ok(())
+    }
+}
@@ -1,4 +1,6 @@
 pub mod data_stores;
 pub mod mock_email_client;
 pub use data_stores::*;
+pub mod health_checks;
 pub mod postmark_email_client;
+pub mod purge_task;
@@ -1,7 +1,10 @@
-use crate::domain::{Email, EmailClient};
+use crate::domain::{Email, EmailClient, HealthCheck};
+use crate::utils::metrics::observe_email_send;
+use crate::utils::tracing::trace_context_headers;
 use color_eyre::eyre::{eyre, Result, WrapErr}; // For improved error handling and reporting
 use reqwest::{Client, Url}; // For making HTTP requests
 use secrecy::{ExposeSecret, SecretString}; // For securely handling sensitive data // Import domain-specific modules
+use std::time::Instant;
 
 // Define the PostmarkEmailClient struct
 pub struct PostmarkEmailClient {
@@ -26,12 +29,9 @@ impl PostmarkEmailClient {
             authorization_token,
         }
     }
-}
 
-#[async_trait::async_trait]
-impl EmailClient for PostmarkEmailClient {
-    #[tracing::instrument(name = "Sending email", skip_all)] // Trace this function, skipping logging its parameters
-    async fn send_email(&self, recipient: &Email, subject: &str, content: &str) -> Result<()> {
+    // Send without recording metrics, see `send_email`
+    async fn post_email(&self, recipient: &Email, subject: &str, content: &str) -> Result<()> {
         // Parse the base URL and join it with the email endpoint
         let base = Url::parse(&self.base_url)?;
         let url = base.join("/email")?;
@@ -50,6 +50,7 @@ impl EmailClient for PostmarkEmailClient {
         let request = self
             .http_client
             .post(url)
+            .headers(trace_context_headers())
             .header(
                 POSTMARK_AUTH_HEADER,
                 self.authorization_token.expose_secret(), // Securely expose the authorization token
@@ -74,6 +75,49 @@ impl EmailClient for PostmarkEmailClient {
     }
 }
 
+#[async_trait::async_trait]
+impl EmailClient for PostmarkEmailClient {
+    #[tracing::instrument(name = "Sending email", skip_all)] // Trace this function, skipping logging its parameters
+    async fn send_email(&self, recipient: &Email, subject: &str, content: &str) -> Result<()> {
+        let start = Instant::now();
+        let result = self.post_email(recipient, subject, content).await;
+        observe_email_send("postmark", result.is_ok(), start.elapsed().as_secs_f64());
+        result
+    }
+}
+
+#[async_trait::async_trait]
+impl HealthCheck for PostmarkEmailClient {
+    fn name(&self) -> &'static str {
+        "email"
+    }
+
+    // Fetching the server's details checks both that Postmark is reachable
+    // and that the token is still accepted, without sending anything
+    #[tracing::instrument(name = "Checking Postmark health", skip_all)]
+    async fn check(&self) -> Result<()> {
+        let url = Url::parse(&self.base_url)?.join("/server")?;
+        let resp = self
+            .http_client
+            .get(url)
+            .headers(trace_context_headers())
+            .header(
+                POSTMARK_AUTH_HEADER,
+                self.authorization_token.expose_secret(),
+            )
+            .header("Accept", "application/json")
+            .send()
+            .await
+            .wrap_err("Failed to send request to Postmark")?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            return Err(eyre!("Postmark returned {}", status));
+        }
+        Ok(())
+    }
+}
+
 // Constants for message stream and authorization header
 const MESSAGE_STREAM: &str = "outbound";
 const POSTMARK_AUTH_HEADER: &str = "X-Postmark-Server-Token";
@@ -222,4 +266,36 @@ mod tests {
 
         assert!(outcome.is_err());
     }
+
+    // Test that the health check authenticates against the server endpoint
+    #[tokio::test]
+    async fn check_requests_the_server_details() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(header_exists(POSTMARK_AUTH_HEADER))
+            .and(path("/server"))
+            .and(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        assert!(email_client.check().await.is_ok());
+    }
+
+    // Test that a rejected token fails the health check
+    #[tokio::test]
+    async fn check_fails_if_the_server_returns_401() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(401))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        assert!(email_client.check().await.is_err());
+    }
 }
@@ -0,0 +1,187 @@
+use crate::services::data_stores::{
+    postgres_banned_token_store::PostgresBannedTokenStore,
+    postgres_email_verification_token_store::PostgresEmailVerificationTokenStore,
+    postgres_login_attempt_store::PostgresLoginAttemptStore,
+    postgres_password_reset_token_store::PostgresPasswordResetTokenStore,
+    postgres_refresh_token_store::PostgresRefreshTokenStore,
+    postgres_session_store::PostgresSessionStore,
+    postgres_two_fa_code_store::PostgresTwoFACodeStore,
+};
+use crate::utils::shutdown::Shutdown;
+use chrono::Utc;
+use sqlx::PgPool;
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+// Stores whose rows carry an expiry time and have to be deleted once it has
+// passed, since nothing expires them on its own the way Redis does
+#[async_trait::async_trait]
+pub trait PurgeExpired: Send + Sync + 'static {
+    async fn purge_expired(&self, now: i64);
+}
+
+// Everything that's otherwise kept in Redis, all of it expiring
+#[derive(Clone)]
+pub struct PostgresTokenStores {
+    pub banned_token_store: Arc<PostgresBannedTokenStore>,
+    pub refresh_token_store: Arc<PostgresRefreshTokenStore>,
+    pub session_store: Arc<PostgresSessionStore>,
+    pub password_reset_token_store: Arc<PostgresPasswordResetTokenStore>,
+    pub email_verification_token_store: Arc<PostgresEmailVerificationTokenStore>,
+    pub two_fa_code_store: Arc<PostgresTwoFACodeStore>,
+    pub login_attempt_store: Arc<PostgresLoginAttemptStore>,
+}
+
+impl PostgresTokenStores {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            banned_token_store: Arc::new(PostgresBannedTokenStore::new(pool.clone())),
+            refresh_token_store: Arc::new(PostgresRefreshTokenStore::new(pool.clone())),
+            session_store: Arc::new(PostgresSessionStore::new(pool.clone())),
+            password_reset_token_store: Arc::new(PostgresPasswordResetTokenStore::new(
+                pool.clone(),
+            )),
+            email_verification_token_store: Arc::new(PostgresEmailVerificationTokenStore::new(
+                pool.clone(),
+            )),
+            two_fa_code_store: Arc::new(PostgresTwoFACodeStore::new(pool.clone())),
+            login_attempt_store: Arc::new(PostgresLoginAttemptStore::new(pool)),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl PurgeExpired for PostgresTokenStores {
+    #[tracing::instrument(name = "Purging expired rows from PostgreSQL", skip_all)]
+    async fn purge_expired(&self, now: i64) {
+        log_purged(
+            "banned tokens",
+            self.banned_token_store.purge_expired(now).await,
+        );
+        log_purged(
+            "refresh tokens",
+            self.refresh_token_store.purge_expired(now).await,
+        );
+        log_purged("sessions", self.session_store.purge_expired(now).await);
+        log_purged(
+            "password reset tokens",
+            self.password_reset_token_store.purge_expired(now).await,
+        );
+        log_purged(
+            "email verification tokens",
+            self.email_verification_token_store.purge_expired(now).await,
+        );
+        log_purged("2FA codes", self.two_fa_code_store.purge_expired(now).await);
+        log_purged(
+            "login attempts",
+            self.login_attempt_store.purge_expired(now).await,
+        );
+    }
+}
+
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteTokenStores;
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use super::{log_purged, PurgeExpired};
+    use crate::services::data_stores::{
+        sqlite_banned_token_store::SqliteBannedTokenStore,
+        sqlite_email_verification_token_store::SqliteEmailVerificationTokenStore,
+        sqlite_login_attempt_store::SqliteLoginAttemptStore,
+        sqlite_password_reset_token_store::SqlitePasswordResetTokenStore,
+        sqlite_refresh_token_store::SqliteRefreshTokenStore,
+        sqlite_session_store::SqliteSessionStore, sqlite_two_fa_code_store::SqliteTwoFACodeStore,
+    };
+    use sqlx::SqlitePool;
+    use std::sync::Arc;
+
+    // The same as `PostgresTokenStores`, next to the users in SQLite
+    #[derive(Clone)]
+    pub struct SqliteTokenStores {
+        pub banned_token_store: Arc<SqliteBannedTokenStore>,
+        pub refresh_token_store: Arc<SqliteRefreshTokenStore>,
+        pub session_store: Arc<SqliteSessionStore>,
+        pub password_reset_token_store: Arc<SqlitePasswordResetTokenStore>,
+        pub email_verification_token_store: Arc<SqliteEmailVerificationTokenStore>,
+        pub two_fa_code_store: Arc<SqliteTwoFACodeStore>,
+        pub login_attempt_store: Arc<SqliteLoginAttemptStore>,
+    }
+
+    impl SqliteTokenStores {
+        pub fn new(pool: SqlitePool) -> Self {
+            Self {
+                banned_token_store: Arc::new(SqliteBannedTokenStore::new(pool.clone())),
+                refresh_token_store: Arc::new(SqliteRefreshTokenStore::new(pool.clone())),
+                session_store: Arc::new(SqliteSessionStore::new(pool.clone())),
+                password_reset_token_store: Arc::new(SqlitePasswordResetTokenStore::new(
+                    pool.clone(),
+                )),
+                email_verification_token_store: Arc::new(SqliteEmailVerificationTokenStore::new(
+                    pool.clone(),
+                )),
+                two_fa_code_store: Arc::new(SqliteTwoFACodeStore::new(pool.clone())),
+                login_attempt_store: Arc::new(SqliteLoginAttemptStore::new(pool)),
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl PurgeExpired for SqliteTokenStores {
+        #[tracing::instrument(name = "Purging expired rows from SQLite", skip_all)]
+        async fn purge_expired(&self, now: i64) {
+            log_purged(
+                "banned tokens",
+                self.banned_token_store.purge_expired(now).await,
+            );
+            log_purged(
+                "refresh tokens",
+                self.refresh_token_store.purge_expired(now).await,
+            );
+            log_purged("sessions", self.session_store.purge_expired(now).await);
+            log_purged(
+                "password reset tokens",
+                self.password_reset_token_store.purge_expired(now).await,
+            );
+            log_purged(
+                "email verification tokens",
+                self.email_verification_token_store.purge_expired(now).await,
+            );
+            log_purged("2FA codes", self.two_fa_code_store.purge_expired(now).await);
+            log_purged(
+                "login attempts",
+                self.login_attempt_store.purge_expired(now).await,
+            );
+        }
+    }
+}
+
+// Expired rows are already ignored when read, purging them only keeps the
+// tables from growing forever. A purge that's under way when shutting down
+// is finished, no new one is started.
+pub fn spawn_purge_task(
+    stores: impl PurgeExpired,
+    period: Duration,
+    shutdown: &Shutdown,
+) -> JoinHandle<()> {
+    let stopped = shutdown.clone();
+    shutdown.spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = stopped.stopped() => break,
+            }
+            stores.purge_expired(Utc::now().timestamp()).await;
+        }
+    })
+}
+
+fn log_purged(what: &str, result: Result<u64, impl Debug>) {
+    match result {
+        Ok(count) => tracing::debug!("Purged {} expired {}", count, what),
+        Err(e) => tracing::error!("Failed to purge expired {}: {:?}", what, e),
+    }
+}
@@ -0,0 +1,63 @@
+use crate::domain::{AuthAPIError, Email};
+use crate::utils::auth::Claims;
+use secrecy::ExposeSecret;
+
+// Events are logged under this target, so they can be kept apart from the
+// rest of the logs, e.g. with `RUST_LOG=audit=info`
+pub const AUDIT_TARGET: &str = "audit";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdminAction {
+    ListUsers,
+    GetUser,
+    DisableUser,
+    EnableUser,
+    Require2fa,
+    Waive2fa,
+    ForcePasswordReset,
+    DeleteUser,
+}
+
+impl AdminAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ListUsers => "list_users",
+            Self::GetUser => "get_user",
+            Self::DisableUser => "disable_user",
+            Self::EnableUser => "enable_user",
+            Self::Require2fa => "require_2fa",
+            Self::Waive2fa => "waive_2fa",
+            Self::ForcePasswordReset => "force_password_reset",
+            Self::DeleteUser => "delete_user",
+        }
+    }
+}
+
+// Record who did what to which user, and whether it went through
+pub fn audit_admin_action<T>(
+    admin: &Claims,
+    action: AdminAction,
+    subject: Option<&Email>,
+    result: &Result<T, AuthAPIError>,
+) {
+    let subject = subject.map(|email| email.as_ref().expose_secret());
+    match result {
+        Ok(_) => tracing::info!(
+            target: AUDIT_TARGET,
+            admin = %admin.sub,
+            session = %admin.jti,
+            action = action.as_str(),
+            subject,
+            outcome = "success",
+        ),
+        Err(e) => tracing::warn!(
+            target: AUDIT_TARGET,
+            admin = %admin.sub,
+            session = %admin.jti,
+            action = action.as_str(),
+            subject,
+            outcome = "failure",
+            error = %e,
+        ),
+    }
+}
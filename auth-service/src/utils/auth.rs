@@ -1,34 +1,99 @@
-use super::constants::{JWT_COOKIE_NAME, JWT_SECRET};
-use crate::domain::{data_store::BannedTokenStore, Email};
-use crate::prelude::BannedTokenType;
-use axum_extra::extract::cookie::{Cookie, SameSite};
+use super::constants::JWT_KEYRING;
+use super::keys::JWT_ALGORITHM;
+use super::settings::settings;
+use crate::domain::{
+    types::{RECOVERY_CODE_ALPHABET, RECOVERY_CODE_LENGTH},
+    Email, RecoveryCode, RefreshTokenRecord, UserRoles,
+};
+use crate::prelude::{BannedTokenType, RefreshTokenType, SessionType};
+use axum_extra::extract::{cookie::Cookie, CookieJar};
 use chrono::Utc;
 use color_eyre::eyre::{eyre, Report, Result, WrapErr};
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Validation};
+use jsonwebtoken::{decode, decode_header, encode, Validation};
 use secrecy::ExposeSecret;
 use secrecy::SecretString;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-// Create cookie with a new JWT auth toke
+// Create cookie with a new JWT auth token for the given session, carrying
+// the user's roles as claims
 #[tracing::instrument(skip_all)]
-pub fn generate_auth_cookie(email: &Email) -> Result<Cookie<'static>> {
-    let token = generate_auth_token(email)?;
+pub fn generate_auth_cookie(
+    email: &Email,
+    session_id: &str,
+    roles: &UserRoles,
+) -> Result<Cookie<'static>> {
+    let token = generate_auth_token(email, session_id, roles)?;
     Ok(create_auth_cookie(token))
 }
 
 // Create cookie and set the value to the passed-in token string
 #[tracing::instrument(skip_all)]
 fn create_auth_cookie(token: String) -> Cookie<'static> {
-    let cookie = Cookie::build((JWT_COOKIE_NAME, token))
+    build_cookie(settings().cookies.jwt_name.clone(), token)
+}
+
+// Both cookies share the attributes configured in `CookieSettings`
+fn build_cookie(name: String, value: String) -> Cookie<'static> {
+    let cookies = &settings().cookies;
+    let mut cookie = Cookie::build((name, value))
         .path("/") // apply cookie to all URLs on the server
         .http_only(true) // prevent JavaScript from accessing the cookie
-        .same_site(SameSite::Lax) // send cookie with "same-site" requests, and with "cross-site" top-level navigations.
+        .same_site(cookies.same_site.into()) // Lax by default, see `CookieSettings`
+        .secure(cookies.secure)
         .build();
+    if let Some(domain) = &cookies.domain {
+        cookie.set_domain(domain.clone());
+    }
+
+    cookie
+}
+
+// Expire both auth cookies in the browser. The removal cookies carry the
+// same path and domain the cookies were set with, or browsers ignore them.
+pub fn remove_auth_cookies(jar: CookieJar) -> CookieJar {
+    let cookies = &settings().cookies;
+    jar.remove(build_cookie(cookies.jwt_name.clone(), String::new()))
+        .remove(build_cookie(cookies.refresh_name.clone(), String::new()))
+}
+
+// Create cookie with a new refresh token and persist the token in the store.
+// A `family_id` of `None` starts a new token family, as happens on login.
+#[tracing::instrument(skip_all)]
+pub async fn generate_refresh_cookie(
+    email: &Email,
+    family_id: Option<String>,
+    refresh_token_store: RefreshTokenType,
+) -> Result<Cookie<'static>> {
+    let token = generate_random_token();
+    let family_id = family_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    refresh_token_store
+        .add_refresh_token(
+            SecretString::new(token.clone().into_boxed_str()),
+            RefreshTokenRecord::new(email.clone(), family_id),
+        )
+        .await
+        .wrap_err("failed to store refresh token")?;
+
+    Ok(create_refresh_cookie(token))
+}
 
+// Create cookie and set the value to the passed-in refresh token string
+#[tracing::instrument(skip_all)]
+fn create_refresh_cookie(token: String) -> Cookie<'static> {
+    let mut cookie = build_cookie(settings().cookies.refresh_name.clone(), token);
+    cookie.set_max_age(time::Duration::seconds(refresh_token_ttl_seconds()));
     cookie
 }
 
+// Opaque random token, used for refresh and password reset tokens.
+// Only the store knows what a token maps to.
+pub fn generate_random_token() -> String {
+    let bytes: [u8; 32] = rand::random();
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
 #[derive(Debug, Error)]
 pub enum GenerateTokenError {
     #[error("token error")]
@@ -38,16 +103,26 @@ pub enum GenerateTokenError {
     UnexpectedError(#[from] Report),
 }
 
-// This value determines how long the JWT auth token is valid for
-pub const TOKEN_TTL_SECONDS: i64 = 600; // 10 minutes
+// How long the JWT auth token is valid for
+pub fn token_ttl_seconds() -> i64 {
+    // Bounded by `MAX_TOKEN_TTL_SECONDS`, so the cast can't overflow
+    settings().tokens.access_token_ttl_seconds as i64
+}
+
+// How long a refresh token is valid for
+pub fn refresh_token_ttl_seconds() -> i64 {
+    settings().tokens.refresh_token_ttl_seconds as i64
+}
 
 // Create JWT auth token
 #[tracing::instrument(skip_all)]
-fn generate_auth_token(email: &Email) -> Result<String> {
-    let delta = chrono::Duration::try_seconds(TOKEN_TTL_SECONDS)
-        .ok_or_else(|| Report::msg("TOKEN_TTL_SECONDS out of range for chrono::Duration"))?;
+fn generate_auth_token(email: &Email, session_id: &str, roles: &UserRoles) -> Result<String> {
+    let delta = chrono::Duration::try_seconds(token_ttl_seconds())
+        .ok_or_else(|| Report::msg("token TTL out of range for chrono::Duration"))?;
 
-    let exp = Utc::now()
+    let now = Utc::now();
+
+    let exp = now
         .checked_add_signed(delta)
         .ok_or_else(|| Report::msg("failed to compute JWT expiration timestamp (overflow?)"))?
         .timestamp();
@@ -56,19 +131,33 @@ fn generate_auth_token(email: &Email) -> Result<String> {
         .try_into()
         .map_err(|e| Report::msg(format!("JWT exp timestamp cannot fit into usize: {e}")))?;
 
+    let iat: usize = now
+        .timestamp()
+        .try_into()
+        .map_err(|e| Report::msg(format!("JWT iat timestamp cannot fit into usize: {e}")))?;
+
     let sub = email.as_ref().expose_secret().to_owned();
 
-    let claims = Claims { sub, exp };
+    let claims = Claims {
+        sub,
+        exp,
+        iat,
+        jti: session_id.to_owned(),
+        roles: roles.roles.clone(),
+        permissions: roles.permissions.clone(),
+    };
 
     create_token(&claims).wrap_err("failed to create JWT token")
 }
 
-// Check if JWT auth token is valid by decoding it using the JWT secret
+// Check if JWT auth token is valid by verifying it against the key named in its `kid` header
 #[tracing::instrument(skip_all)]
-pub async fn validate_token(token: &str, banned_token_store: BannedTokenType) -> Result<Claims> {
+pub async fn validate_token(
+    token: &str,
+    banned_token_store: BannedTokenType,
+    session_store: SessionType,
+) -> Result<Claims> {
     let is_banned = banned_token_store
-        .read()
-        .await
         .token_exists(&SecretString::new(token.to_owned().into_boxed_str()))
         .await
         .wrap_err("failed to check if token is banned")?;
@@ -77,54 +166,125 @@ pub async fn validate_token(token: &str, banned_token_store: BannedTokenType) ->
         return Err(eyre!("invalid token"));
     }
 
-    let data = decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
-        &Validation::default(),
-    )
-    .wrap_err("failed to decode/verify JWT")?;
+    let kid = decode_header(token)
+        .wrap_err("failed to decode JWT header")?
+        .kid
+        .ok_or_else(|| eyre!("JWT header is missing kid"))?;
+
+    let decoding_key = JWT_KEYRING
+        .decoding_key(&kid)
+        .ok_or_else(|| eyre!("unknown JWT signing key"))?;
+
+    let data = decode::<Claims>(token, decoding_key, &Validation::new(JWT_ALGORITHM))
+        .wrap_err("failed to decode/verify JWT")?;
+
+    // Tokens issued before the user's sessions were revoked (e.g. by a
+    // password reset) are rejected even though their signature is valid.
+    // Bans only have one-second granularity, so a token issued in the same
+    // second passes here; if it predates the ban its session is gone anyway.
+    let email = Email::parse(SecretString::new(data.claims.sub.clone().into_boxed_str()))
+        .map_err(|e| eyre!("invalid JWT subject: {e}"))?;
+
+    let banned_before = banned_token_store
+        .user_tokens_banned_before(&email)
+        .await
+        .wrap_err("failed to check if user tokens are banned")?;
+
+    if let Some(banned_before) = banned_before {
+        if (data.claims.iat as i64) < banned_before {
+            return Err(eyre!("invalid token"));
+        }
+    }
+
+    // The session must still exist, so revoking it logs out every token issued to it
+    let session = session_store
+        .get_session(&data.claims.jti)
+        .await
+        .wrap_err("failed to get session")?;
+
+    if session.email != email {
+        return Err(eyre!("invalid token"));
+    }
 
     Ok(data.claims)
 }
 
-// Create JWT auth token by encoding claims using the JWT secret
+// Create JWT auth token by signing claims with the active key of the keyring
 #[tracing::instrument(skip_all)]
 fn create_token(claims: &Claims) -> Result<String> {
-    encode(
-        &jsonwebtoken::Header::default(),
-        &claims,
-        &EncodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
-    )
-    .wrap_err("encoding failed")
+    encode(&JWT_KEYRING.header(), &claims, JWT_KEYRING.encoding_key()).wrap_err("encoding failed")
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,
     pub exp: usize,
+    pub iat: usize,
+    // Id of the session the token was issued to
+    pub jti: String,
+    // As granted when the token was issued, changes only apply to tokens
+    // issued after them. Tokens from before roles existed have none.
+    #[serde(default)]
+    pub roles: Vec<String>,
+    #[serde(default)]
+    pub permissions: Vec<String>,
 }
 
 pub fn generate_6_digit_code() -> u32 {
     rand::random_range(100_000..=999_999)
 }
 
+// Number of recovery codes handed out on 2FA enrollment or regeneration
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+pub fn generate_recovery_codes() -> Vec<RecoveryCode> {
+    let alphabet = RECOVERY_CODE_ALPHABET.as_bytes();
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let code: String = (0..RECOVERY_CODE_LENGTH)
+                .map(|_| alphabet[rand::random_range(0..alphabet.len())] as char)
+                .collect();
+            RecoveryCode::parse(SecretString::new(code.into_boxed_str()))
+                .expect("generated recovery code is valid")
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::get_redis_client;
-    use crate::prelude::RedisBannedTokenStore;
-    use crate::utils::constants::REDIS_HOST_NAME;
+    use crate::domain::{BannedTokenStore, Session, SessionStore};
+    use crate::get_redis_connection_manager;
+    use crate::prelude::{RedisBannedTokenStore, RedisSessionStore};
+    use crate::utils::keys::Keyring;
+    use axum_extra::extract::cookie::SameSite;
     use secrecy::SecretString;
     use std::sync::Arc;
-    use tokio::sync::RwLock;
 
-    fn configure_redis() -> redis::Connection {
-        get_redis_client(REDIS_HOST_NAME.to_owned())
-            .expect("Failed to get Redis client")
-            .get_connection()
+    async fn configure_redis() -> redis::aio::ConnectionManager {
+        get_redis_connection_manager(settings().stores.redis_host_name.clone())
+            .await
             .expect("Failed to get Redis connection")
     }
 
+    // Session store holding a fresh session for `email`, along with its id
+    async fn session_store_with_session(email: &Email) -> (SessionType, String) {
+        let session_store = Arc::new(RedisSessionStore::new(configure_redis().await));
+        let id = uuid::Uuid::new_v4().to_string();
+        session_store
+            .add_session(Session {
+                id: id.clone(),
+                email: email.clone(),
+                user_agent: None,
+                ip: "127.0.0.1".to_owned(),
+                created_at: Utc::now().timestamp(),
+                last_seen_at: Utc::now().timestamp(),
+            })
+            .await
+            .unwrap();
+        (session_store, id)
+    }
+
     #[test]
     fn test_6_digit_code_generator() {
         let code = generate_6_digit_code();
@@ -137,8 +297,8 @@ mod tests {
             "test@example.com".to_owned().into_boxed_str(),
         ))
         .unwrap();
-        let cookie = generate_auth_cookie(&email).unwrap();
-        assert_eq!(cookie.name(), JWT_COOKIE_NAME);
+        let cookie = generate_auth_cookie(&email, "session", &UserRoles::default()).unwrap();
+        assert_eq!(cookie.name(), settings().cookies.jwt_name);
         assert_eq!(cookie.value().split('.').count(), 3);
         assert_eq!(cookie.path(), Some("/"));
         assert_eq!(cookie.http_only(), Some(true));
@@ -149,21 +309,58 @@ mod tests {
     async fn test_create_auth_cookie() {
         let token = "test_token".to_owned();
         let cookie = create_auth_cookie(token.clone());
-        assert_eq!(cookie.name(), JWT_COOKIE_NAME);
+        assert_eq!(cookie.name(), settings().cookies.jwt_name);
         assert_eq!(cookie.value(), token);
         assert_eq!(cookie.path(), Some("/"));
         assert_eq!(cookie.http_only(), Some(true));
         assert_eq!(cookie.same_site(), Some(SameSite::Lax));
     }
 
+    #[tokio::test]
+    async fn test_create_refresh_cookie() {
+        let token = "test_refresh_token".to_owned();
+        let cookie = create_refresh_cookie(token.clone());
+        assert_eq!(cookie.name(), settings().cookies.refresh_name);
+        assert_eq!(cookie.value(), token);
+        assert_eq!(cookie.path(), Some("/"));
+        assert_eq!(cookie.http_only(), Some(true));
+        assert_eq!(
+            cookie.max_age(),
+            Some(time::Duration::seconds(refresh_token_ttl_seconds()))
+        );
+    }
+
+    #[test]
+    fn test_generate_recovery_codes() {
+        let codes = generate_recovery_codes();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+
+        let unique: std::collections::HashSet<_> = codes
+            .iter()
+            .map(|code| code.as_ref().expose_secret().to_owned())
+            .collect();
+        assert_eq!(unique.len(), RECOVERY_CODE_COUNT);
+    }
+
+    #[test]
+    fn test_generate_random_token() {
+        let token = generate_random_token();
+        assert_eq!(token.len(), 64);
+        assert_ne!(token, generate_random_token());
+    }
+
     #[tokio::test]
     async fn test_generate_auth_token() {
         let email = Email::parse(SecretString::new(
             "test@example.com".to_owned().into_boxed_str(),
         ))
         .unwrap();
-        let result = generate_auth_token(&email).unwrap();
+        let result = generate_auth_token(&email, "session", &UserRoles::default()).unwrap();
         assert_eq!(result.split('.').count(), 3);
+
+        let header = decode_header(&result).unwrap();
+        assert_eq!(header.alg, JWT_ALGORITHM);
+        assert_eq!(header.kid.as_deref(), Some(JWT_KEYRING.active_kid()));
     }
 
     #[tokio::test]
@@ -172,15 +369,24 @@ mod tests {
             "test@example.com".to_owned().into_boxed_str(),
         ))
         .unwrap();
-        let token = generate_auth_token(&email).unwrap();
-        let redis_conn = Arc::new(RwLock::new(configure_redis()));
+        let (session_store, session_id) = session_store_with_session(&email).await;
+        let roles = UserRoles {
+            roles: vec!["admin".to_owned()],
+            permissions: vec!["users:manage".to_owned()],
+        };
+        let token = generate_auth_token(&email, &session_id, &roles).unwrap();
+        let redis_conn = configure_redis().await;
         let result = validate_token(
             &token,
-            Arc::new(RwLock::new(RedisBannedTokenStore::new(redis_conn))),
+            Arc::new(RedisBannedTokenStore::new(redis_conn)),
+            session_store,
         )
         .await
         .unwrap();
         assert_eq!(result.sub, "test@example.com");
+        assert_eq!(result.jti, session_id);
+        assert_eq!(result.roles, roles.roles);
+        assert_eq!(result.permissions, roles.permissions);
 
         let exp = Utc::now()
             .checked_add_signed(chrono::Duration::try_minutes(9).expect("valid duration"))
@@ -190,13 +396,138 @@ mod tests {
         assert!(result.exp > exp as usize);
     }
 
+    #[tokio::test]
+    async fn test_validate_token_issued_without_roles() {
+        #[derive(Serialize)]
+        struct LegacyClaims {
+            sub: String,
+            exp: usize,
+            iat: usize,
+            jti: String,
+        }
+
+        let email = Email::parse(SecretString::new(
+            "test@example.com".to_owned().into_boxed_str(),
+        ))
+        .unwrap();
+        let (session_store, session_id) = session_store_with_session(&email).await;
+        let claims = LegacyClaims {
+            sub: "test@example.com".to_owned(),
+            exp: (Utc::now().timestamp() + 60) as usize,
+            iat: Utc::now().timestamp() as usize,
+            jti: session_id,
+        };
+        let token = encode(&JWT_KEYRING.header(), &claims, JWT_KEYRING.encoding_key()).unwrap();
+        let redis_conn = configure_redis().await;
+        let result = validate_token(
+            &token,
+            Arc::new(RedisBannedTokenStore::new(redis_conn)),
+            session_store,
+        )
+        .await
+        .unwrap();
+        assert!(result.roles.is_empty());
+        assert!(result.permissions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_with_unknown_signing_key() {
+        let email = Email::parse(SecretString::new(
+            "test@example.com".to_owned().into_boxed_str(),
+        ))
+        .unwrap();
+        let (session_store, session_id) = session_store_with_session(&email).await;
+        let other_keyring = Keyring::generate().unwrap();
+        let claims = Claims {
+            sub: "test@example.com".to_owned(),
+            exp: (Utc::now().timestamp() + 60) as usize,
+            iat: Utc::now().timestamp() as usize,
+            jti: session_id,
+            roles: Vec::new(),
+            permissions: Vec::new(),
+        };
+        let token = encode(
+            &other_keyring.header(),
+            &claims,
+            other_keyring.encoding_key(),
+        )
+        .unwrap();
+        let redis_conn = configure_redis().await;
+        let result = validate_token(
+            &token,
+            Arc::new(RedisBannedTokenStore::new(redis_conn)),
+            session_store,
+        )
+        .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_with_banned_user_tokens() {
+        let email = Email::parse(SecretString::new(
+            format!("{}@example.com", uuid::Uuid::new_v4()).into_boxed_str(),
+        ))
+        .unwrap();
+        let (session_store, session_id) = session_store_with_session(&email).await;
+        let token = generate_auth_token(&email, &session_id, &UserRoles::default()).unwrap();
+        let redis_conn = configure_redis().await;
+        let banned_token_store = Arc::new(RedisBannedTokenStore::new(redis_conn));
+
+        banned_token_store
+            .ban_user_tokens(&email, Utc::now().timestamp() + 1)
+            .await
+            .unwrap();
+
+        let result = validate_token(&token, banned_token_store, session_store).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_issued_in_the_second_of_the_ban() {
+        let email = Email::parse(SecretString::new(
+            format!("{}@example.com", uuid::Uuid::new_v4()).into_boxed_str(),
+        ))
+        .unwrap();
+        let redis_conn = configure_redis().await;
+        let banned_token_store = Arc::new(RedisBannedTokenStore::new(redis_conn));
+        banned_token_store
+            .ban_user_tokens(&email, Utc::now().timestamp())
+            .await
+            .unwrap();
+
+        // A login right after the ban gets a working token
+        let (session_store, session_id) = session_store_with_session(&email).await;
+        let token = generate_auth_token(&email, &session_id, &UserRoles::default()).unwrap();
+
+        let result = validate_token(&token, banned_token_store, session_store).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_with_revoked_session() {
+        let email = Email::parse(SecretString::new(
+            format!("{}@example.com", uuid::Uuid::new_v4()).into_boxed_str(),
+        ))
+        .unwrap();
+        let (session_store, session_id) = session_store_with_session(&email).await;
+        let token = generate_auth_token(&email, &session_id, &UserRoles::default()).unwrap();
+        let redis_conn = configure_redis().await;
+        let banned_token_store = Arc::new(RedisBannedTokenStore::new(redis_conn));
+
+        session_store.remove_session(&session_id).await.unwrap();
+
+        let result = validate_token(&token, banned_token_store, session_store).await;
+        assert!(result.is_err());
+    }
+
     #[tokio::test]
     async fn test_validate_token_with_invalid_token() {
         let token = "invalid_token".to_owned();
-        let redis_conn = Arc::new(RwLock::new(configure_redis()));
+        let redis_conn = configure_redis().await;
         let result = validate_token(
             &token,
-            Arc::new(RwLock::new(RedisBannedTokenStore::new(redis_conn))),
+            Arc::new(RedisBannedTokenStore::new(redis_conn.clone())),
+            Arc::new(RedisSessionStore::new(redis_conn)),
         )
         .await;
         assert!(result.is_err());
@@ -0,0 +1,188 @@
+use crate::utils::settings::settings;
+use axum::{
+    extract::{rejection::ExtensionRejection, ConnectInfo, FromRequestParts},
+    http::{request::Parts, HeaderMap},
+};
+use std::net::{IpAddr, SocketAddr};
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+const FORWARDED: &str = "forwarded";
+
+// The address of the client behind any trusted proxies, used for login
+// throttling and recorded with sessions. Without trusted proxies this is
+// simply the peer address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+impl<S: Send + Sync> FromRequestParts<S> for ClientIp {
+    type Rejection = ExtensionRejection;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let ConnectInfo(peer) = ConnectInfo::<SocketAddr>::from_request_parts(parts, state).await?;
+        Ok(Self(client_ip(
+            peer.ip(),
+            &parts.headers,
+            &settings().application.trusted_proxies,
+        )))
+    }
+}
+
+// Walk the forwarding chain from the nearest hop outwards and take the first
+// address that isn't one of our proxies. Anything further out was written by
+// the client and can't be believed.
+pub fn client_ip(peer: IpAddr, headers: &HeaderMap, trusted_proxies: &[IpAddr]) -> IpAddr {
+    let peer = peer.to_canonical();
+    if !trusted_proxies.contains(&peer) {
+        return peer;
+    }
+
+    let mut chain = forwarded_for(headers);
+    if chain.is_empty() {
+        chain = forwarded(headers);
+    }
+
+    let mut client = peer;
+    for hop in chain.iter().rev() {
+        // An address we can't read ends the chain we can trust
+        let Some(hop) = hop.map(|hop| hop.to_canonical()) else {
+            break;
+        };
+        client = hop;
+        if !trusted_proxies.contains(&hop) {
+            break;
+        }
+    }
+    client
+}
+
+// `X-Forwarded-For: client, proxy1, proxy2`, possibly over several headers
+fn forwarded_for(headers: &HeaderMap) -> Vec<Option<IpAddr>> {
+    headers
+        .get_all(X_FORWARDED_FOR)
+        .iter()
+        .flat_map(|value| value.to_str().unwrap_or_default().split(','))
+        .filter(|hop| !hop.trim().is_empty())
+        .map(parse_node)
+        .collect()
+}
+
+// RFC 7239 `Forwarded: for=192.0.2.60;proto=https, for="[2001:db8::1]:4711"`
+fn forwarded(headers: &HeaderMap) -> Vec<Option<IpAddr>> {
+    headers
+        .get_all(FORWARDED)
+        .iter()
+        .flat_map(|value| value.to_str().unwrap_or_default().split(','))
+        .filter(|element| !element.trim().is_empty())
+        .map(|element| {
+            element
+                .split(';')
+                .filter_map(|pair| pair.split_once('='))
+                .find(|(name, _)| name.trim().eq_ignore_ascii_case("for"))
+                .and_then(|(_, node)| parse_node(node))
+        })
+        .collect()
+}
+
+// An address, optionally quoted, bracketed or with a port
+fn parse_node(node: &str) -> Option<IpAddr> {
+    let node = node.trim().trim_matches('"');
+    node.parse::<IpAddr>()
+        .or_else(|_| node.parse::<SocketAddr>().map(|addr| addr.ip()))
+        .or_else(|_| {
+            node.trim_start_matches('[')
+                .trim_end_matches(']')
+                .parse::<IpAddr>()
+        })
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers(name: &'static str, values: &[&'static str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for value in values {
+            headers.append(name, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    fn ip(ip: &str) -> IpAddr {
+        ip.parse().unwrap()
+    }
+
+    #[test]
+    fn test_client_ip_without_trusted_proxy() {
+        let forged = headers(X_FORWARDED_FOR, &["192.0.2.1"]);
+
+        assert_eq!(client_ip(ip("10.0.0.1"), &forged, &[]), ip("10.0.0.1"));
+        assert_eq!(
+            client_ip(ip("198.51.100.7"), &forged, &[ip("10.0.0.1")]),
+            ip("198.51.100.7")
+        );
+        assert_eq!(
+            client_ip(ip("::ffff:198.51.100.7"), &HeaderMap::new(), &[]),
+            ip("198.51.100.7")
+        );
+    }
+
+    #[test]
+    fn test_client_ip_from_x_forwarded_for() {
+        let proxies = [ip("10.0.0.1"), ip("10.0.0.2")];
+
+        let single = headers(X_FORWARDED_FOR, &["192.0.2.1"]);
+        assert_eq!(
+            client_ip(ip("10.0.0.1"), &single, &proxies),
+            ip("192.0.2.1")
+        );
+
+        // Whatever the client put in front of its own address is ignored
+        let chain = headers(X_FORWARDED_FOR, &["203.0.113.9, 192.0.2.1", "10.0.0.2"]);
+        assert_eq!(client_ip(ip("10.0.0.1"), &chain, &proxies), ip("192.0.2.1"));
+
+        let ipv6 = headers(X_FORWARDED_FOR, &["2001:db8::1"]);
+        assert_eq!(
+            client_ip(ip("10.0.0.1"), &ipv6, &proxies),
+            ip("2001:db8::1")
+        );
+
+        // Without a readable address the proxy is all we know
+        let garbage = headers(X_FORWARDED_FOR, &["unknown"]);
+        assert_eq!(
+            client_ip(ip("10.0.0.1"), &garbage, &proxies),
+            ip("10.0.0.1")
+        );
+        assert_eq!(
+            client_ip(ip("10.0.0.1"), &HeaderMap::new(), &proxies),
+            ip("10.0.0.1")
+        );
+    }
+
+    #[test]
+    fn test_client_ip_from_forwarded() {
+        let proxies = [ip("10.0.0.1")];
+
+        let forwarded = headers(
+            FORWARDED,
+            &["for=203.0.113.9, for=\"[2001:db8::1]:4711\";proto=https"],
+        );
+        assert_eq!(
+            client_ip(ip("10.0.0.1"), &forwarded, &proxies),
+            ip("2001:db8::1")
+        );
+
+        let with_port = headers(FORWARDED, &["proto=https;For=\"192.0.2.1:1234\""]);
+        assert_eq!(
+            client_ip(ip("10.0.0.1"), &with_port, &proxies),
+            ip("192.0.2.1")
+        );
+
+        let obfuscated = headers(FORWARDED, &["for=_hidden"]);
+        assert_eq!(
+            client_ip(ip("10.0.0.1"), &obfuscated, &proxies),
+            ip("10.0.0.1")
+        );
+    }
+}
@@ -1,69 +1,69 @@
-use dotenvy::dotenv;
+use super::keys::Keyring;
+use super::settings::settings;
+use crate::app_state::UnverifiedLoginPolicy;
 use lazy_static::lazy_static;
-use secrecy::SecretString;
-use std::env as std_env;
 
-// Define a lazily evaluated static. lazy_static is needed because std_env::var is not a const function.
+// Define a lazily evaluated static. The keyring is built from the settings the
+// service was started with, which have already checked that the keys load.
 lazy_static! {
-    pub static ref JWT_SECRET: SecretString = set_token();
-    pub static ref DATABASE_URL: SecretString = set_db_url();
-    pub static ref REDIS_HOST_NAME: String = set_redis_host();
-    pub static ref POSTMARK_AUTH_TOKEN: SecretString = set_postmark_auth_token();
+    pub static ref JWT_KEYRING: Keyring = set_keyring();
 }
 
-fn set_token() -> SecretString {
-    dotenv().ok(); // Load environment variables
-    let secret = std_env::var(env::JWT_SECRET_ENV_VAR).expect("JWT_SECRET must be set.");
-    if secret.is_empty() {
-        panic!("JWT_SECRET must not be empty.");
+fn set_keyring() -> Keyring {
+    let jwt = &settings().jwt;
+    match (&jwt.keys_dir, &jwt.active_key_id) {
+        (Some(keys_dir), Some(active_kid)) => {
+            Keyring::from_dir(keys_dir, active_kid).expect("Failed to load JWT keys.")
+        }
+        _ => {
+            // Without a keys directory every restart invalidates all issued tokens,
+            // which is fine for local development and tests but not for production.
+            tracing::warn!("JWT_KEYS_DIR is not set, signing tokens with an ephemeral key");
+            Keyring::generate().expect("Failed to generate JWT signing key.")
+        }
     }
-    SecretString::new(secret.to_owned().into_boxed_str())
 }
 
-fn set_db_url() -> SecretString {
-    dotenv().ok(); // Load environment variables
-    let secret = std_env::var(env::DATABASE_URL_ENV_VAR).expect("DATABASE_URL must be set.");
-    if secret.is_empty() {
-        panic!("DATABASE_URL must not be empty.");
-    }
-    SecretString::new(secret.to_owned().into_boxed_str())
-}
-
-fn set_redis_host() -> String {
-    dotenv().ok();
-    std_env::var(env::REDIS_HOST_NAME_ENV_VAR).unwrap_or(DEFAULT_REDIS_HOSTNAME.to_owned())
-}
+// Path of the TOML config file, see `Settings`
+pub const CONFIG_FILE_ENV_VAR: &str = "AUTH_CONFIG_FILE";
+pub const DEFAULT_CONFIG_FILE: &str = "config.toml";
 
-fn set_postmark_auth_token() -> SecretString {
-    dotenv().ok();
-    SecretString::new(
-        std_env::var(env::POSTMARK_AUTH_TOKEN_ENV_VAR)
-            .expect("POSTMARK_AUTH_TOKEN must be set.")
-            .into_boxed_str(),
-    )
-}
+// Content type of RFC 7807 error responses, see `ErrorResponse`
+pub const PROBLEM_JSON_CONTENT_TYPE: &str = "application/problem+json";
 
-pub mod env {
-    pub const JWT_SECRET_ENV_VAR: &str = "JWT_SECRET";
-    pub const DATABASE_URL_ENV_VAR: &str = "DATABASE_URL";
-    pub const REDIS_HOST_NAME_ENV_VAR: &str = "REDIS_HOST_NAME";
-    pub const POSTMARK_AUTH_TOKEN_ENV_VAR: &str = "POSTMARK_AUTH_TOKEN";
-}
+// Every request body is a handful of short fields, anything bigger is
+// rejected before it's buffered
+pub const MAX_REQUEST_BODY_BYTES: usize = 16 * 1024;
 
 pub const JWT_COOKIE_NAME: &str = "jwt";
+pub const REFRESH_COOKIE_NAME: &str = "refresh_token";
+pub const DEFAULT_APP_ADDRESS: &str = "0.0.0.0:3000";
+pub const DEFAULT_ALLOWED_ORIGIN: &str = "http://localhost:8000";
 pub const DEFAULT_REDIS_HOSTNAME: &str = "127.0.0.1";
+pub const DEFAULT_POSTMARK_BASE_URL: &str = "https://api.postmarkapp.com/email";
+pub const DEFAULT_EMAIL_TIMEOUT_MILLISECONDS: u64 = 10_000;
+pub const DEFAULT_ACCESS_TOKEN_TTL_SECONDS: u64 = 600; // 10 minutes
+pub const DEFAULT_REFRESH_TOKEN_TTL_SECONDS: u64 = 1_209_600; // 14 days
+pub const DEFAULT_PASSWORD_RESET_TOKEN_TTL_SECONDS: u64 = 900; // 15 minutes
+pub const DEFAULT_EMAIL_VERIFICATION_TOKEN_TTL_SECONDS: u64 = 86_400; // 24 hours
+pub const DEFAULT_TWO_FA_CODE_TTL_SECONDS: u64 = 600; // 10 minutes
 
-pub mod prod {
-    pub const APP_ADDRESS: &str = "0.0.0.0:3000";
-    pub mod email_client {
-        use std::time::Duration;
+// Upper bound for every token TTL, so they fit comfortably in timestamps
+pub const MAX_TOKEN_TTL_SECONDS: u64 = 31_536_000; // 1 year
 
-        pub const BASE_URL: &str = "https://api.postmarkapp.com/email";
-        // If you created your own Postmark account, make sure to use your email address!
-        pub const SENDER: &str = "email@arshiaeskandari.com";
-        pub const TIMEOUT: Duration = std::time::Duration::from_secs(10);
-    }
-}
+// Accept TOTP codes from one 30 second step either side of the current one
+pub const DEFAULT_TOTP_DRIFT_STEPS: u8 = 1;
+
+pub const DEFAULT_UNVERIFIED_LOGIN_POLICY: UnverifiedLoginPolicy = UnverifiedLoginPolicy::Reject;
+pub const DEFAULT_LOGIN_MAX_ACCOUNT_FAILURES: u32 = 5;
+pub const DEFAULT_LOGIN_MAX_IP_FAILURES: u32 = 50;
+pub const DEFAULT_LOGIN_LOCKOUT_SECONDS: u64 = 900;
+pub const DEFAULT_PURGE_INTERVAL_SECONDS: u64 = 3600;
+pub const DEFAULT_HEALTH_CHECK_TIMEOUT_MILLISECONDS: u64 = 2_000;
+pub const DEFAULT_SERVICE_NAME: &str = "auth-service";
+pub const DEFAULT_READINESS_DELAY_SECONDS: u64 = 5;
+pub const DEFAULT_DRAIN_TIMEOUT_SECONDS: u64 = 20;
+pub const DEFAULT_TLS_RELOAD_INTERVAL_SECONDS: u64 = 10;
 
 pub mod test {
     pub const APP_ADDRESS: &str = "127.0.0.1:0";
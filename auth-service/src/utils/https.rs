@@ -0,0 +1,185 @@
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{
+        header::{HOST, SET_COOKIE},
+        uri::Authority,
+        HeaderMap, HeaderValue, StatusCode, Uri,
+    },
+    middleware::Next,
+    response::{IntoResponse, Redirect, Response},
+};
+use axum_extra::extract::cookie::Cookie;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+const X_FORWARDED_PROTO: &str = "x-forwarded-proto";
+
+// Decides whether a request reached us over HTTPS: always when we terminate
+// TLS ourselves, otherwise only when a trusted proxy says so
+#[derive(Debug, Clone)]
+pub struct HttpsPolicy {
+    tls: bool,
+    trusted_proxies: Arc<[IpAddr]>,
+}
+
+impl HttpsPolicy {
+    pub fn new(tls: bool, trusted_proxies: &[IpAddr]) -> Self {
+        Self {
+            tls,
+            trusted_proxies: trusted_proxies.into(),
+        }
+    }
+
+    pub fn is_https(&self, request: &Request) -> bool {
+        if self.tls {
+            return true;
+        }
+        let Some(ConnectInfo(peer)) = request.extensions().get::<ConnectInfo<SocketAddr>>() else {
+            return false;
+        };
+        if !self.trusted_proxies.contains(&peer.ip().to_canonical()) {
+            return false;
+        }
+        // Each proxy appends its own, the first is what the client used
+        request
+            .headers()
+            .get(X_FORWARDED_PROTO)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(',').next())
+            .is_some_and(|proto| proto.trim().eq_ignore_ascii_case("https"))
+    }
+}
+
+// Cookies are built without knowing how the request arrived, this makes
+// every cookie set in answer to an HTTPS request `Secure`
+pub async fn secure_cookies(
+    State(policy): State<HttpsPolicy>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let https = policy.is_https(&request);
+    let mut response = next.run(request).await;
+    if https {
+        mark_cookies_secure(response.headers_mut());
+    }
+    response
+}
+
+fn mark_cookies_secure(headers: &mut HeaderMap) {
+    let values: Vec<HeaderValue> = headers.get_all(SET_COOKIE).iter().cloned().collect();
+    if values.is_empty() {
+        return;
+    }
+
+    headers.remove(SET_COOKIE);
+    for value in values {
+        let secured = value
+            .to_str()
+            .ok()
+            .and_then(|value| Cookie::parse(value).ok())
+            .and_then(|mut cookie| {
+                cookie.set_secure(true);
+                HeaderValue::from_str(&cookie.to_string()).ok()
+            });
+        headers.append(SET_COOKIE, secured.unwrap_or(value));
+    }
+}
+
+// Served on `tls.redirect_address`, sends plain HTTP requests to the same
+// URL on the HTTPS port
+pub async fn redirect_to_https(
+    State(https_port): State<u16>,
+    headers: HeaderMap,
+    uri: Uri,
+) -> Response {
+    let host = headers.get(HOST).and_then(|host| host.to_str().ok());
+    match host.and_then(|host| https_location(host, https_port, &uri)) {
+        Some(location) => Redirect::permanent(&location).into_response(),
+        None => (StatusCode::BAD_REQUEST, "Missing or invalid Host header").into_response(),
+    }
+}
+
+fn https_location(host: &str, https_port: u16, uri: &Uri) -> Option<String> {
+    let host = host.parse::<Authority>().ok()?;
+    let port = match https_port {
+        443 => String::new(),
+        port => format!(":{port}"),
+    };
+    let path = uri.path_and_query().map_or("/", |path| path.as_str());
+    Some(format!("https://{}{}{}", host.host(), port, path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+
+    fn request(peer: &str, forwarded_proto: Option<&str>) -> Request {
+        let mut builder = Request::builder();
+        if let Some(proto) = forwarded_proto {
+            builder = builder.header(X_FORWARDED_PROTO, proto);
+        }
+        let mut request = builder.body(Body::empty()).unwrap();
+        let peer: SocketAddr = peer.parse().unwrap();
+        request.extensions_mut().insert(ConnectInfo(peer));
+        request
+    }
+
+    #[test]
+    fn test_is_https() {
+        let proxy: IpAddr = "10.0.0.1".parse().unwrap();
+        let behind_proxy = HttpsPolicy::new(false, &[proxy]);
+        let tls = HttpsPolicy::new(true, &[]);
+
+        assert!(tls.is_https(&request("192.0.2.1:1234", None)));
+        assert!(behind_proxy.is_https(&request("10.0.0.1:1234", Some("https"))));
+        assert!(behind_proxy.is_https(&request("[::ffff:10.0.0.1]:1234", Some("HTTPS, http"))));
+        assert!(!behind_proxy.is_https(&request("10.0.0.1:1234", Some("http"))));
+        assert!(!behind_proxy.is_https(&request("10.0.0.1:1234", None)));
+        // Anyone else could be lying about it
+        assert!(!behind_proxy.is_https(&request("192.0.2.1:1234", Some("https"))));
+    }
+
+    #[test]
+    fn test_mark_cookies_secure() {
+        let mut headers = HeaderMap::new();
+        headers.append(
+            SET_COOKIE,
+            HeaderValue::from_static("jwt=token; HttpOnly; SameSite=Lax; Path=/"),
+        );
+        headers.append(
+            SET_COOKIE,
+            HeaderValue::from_static("refresh=; Path=/; Max-Age=0"),
+        );
+
+        mark_cookies_secure(&mut headers);
+
+        let cookies: Vec<_> = headers
+            .get_all(SET_COOKIE)
+            .iter()
+            .map(|value| Cookie::parse(value.to_str().unwrap().to_owned()).unwrap())
+            .collect();
+        assert_eq!(cookies.len(), 2);
+        assert!(cookies.iter().all(|cookie| cookie.secure() == Some(true)));
+        assert_eq!(cookies[0].http_only(), Some(true));
+        assert_eq!(cookies[1].max_age(), Some(time::Duration::ZERO));
+    }
+
+    #[test]
+    fn test_https_location() {
+        let uri: Uri = "/login?next=%2Fapp".parse().unwrap();
+        assert_eq!(
+            https_location("example.com", 443, &uri).as_deref(),
+            Some("https://example.com/login?next=%2Fapp")
+        );
+        assert_eq!(
+            https_location("example.com:80", 3443, &uri).as_deref(),
+            Some("https://example.com:3443/login?next=%2Fapp")
+        );
+        assert_eq!(
+            https_location("[::1]:80", 443, &Uri::from_static("/")).as_deref(),
+            Some("https://[::1]/")
+        );
+        assert_eq!(https_location("not a host", 443, &uri), None);
+    }
+}
@@ -0,0 +1,195 @@
+use crate::domain::{AuthAPIError, ErrorCode, FieldError};
+use axum::{
+    body::Bytes,
+    extract::{FromRequest, Request},
+    http::{header::CONTENT_TYPE, HeaderMap, StatusCode},
+};
+use serde::de::DeserializeOwned;
+use serde_json::error::Category;
+
+// Drop-in for `axum::Json` on request bodies. Its rejections are plain text,
+// this reports them as `AuthAPIError`s like every other error, with the path
+// of the field that didn't deserialize.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonBody<T>(pub T);
+
+impl<T, S> FromRequest<S> for JsonBody<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = AuthAPIError;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        if !is_json_content_type(request.headers()) {
+            return Err(AuthAPIError::UnsupportedMediaType);
+        }
+
+        // Bounded by the router's `DefaultBodyLimit`
+        let bytes =
+            Bytes::from_request(request, state)
+                .await
+                .map_err(|rejection| match rejection.status() {
+                    StatusCode::PAYLOAD_TOO_LARGE => AuthAPIError::PayloadTooLarge,
+                    _ => AuthAPIError::MalformedBody,
+                })?;
+
+        parse(&bytes).map(JsonBody)
+    }
+}
+
+fn is_json_content_type(headers: &HeaderMap) -> bool {
+    let Some(content_type) = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return false;
+    };
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    match essence.split_once('/') {
+        Some(("application", subtype)) => subtype == "json" || subtype.ends_with("+json"),
+        _ => false,
+    }
+}
+
+fn parse<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, AuthAPIError> {
+    let mut deserializer = serde_json::Deserializer::from_slice(bytes);
+    let value = serde_path_to_error::deserialize(&mut deserializer).map_err(|e| {
+        let path = e.path().to_string();
+        let error = e.into_inner();
+        match error.classify() {
+            Category::Data => AuthAPIError::InvalidBody(field_error(&path, &error)),
+            Category::Syntax | Category::Eof | Category::Io => AuthAPIError::MalformedBody,
+        }
+    })?;
+
+    // Anything but whitespace after the value
+    deserializer
+        .end()
+        .map_err(|_| AuthAPIError::MalformedBody)?;
+    Ok(value)
+}
+
+// serde only says which kind of data error it was in the message
+fn field_error(path: &str, error: &serde_json::Error) -> FieldError {
+    let full = error.to_string();
+    // Positions are meaningless once the error is tied to a field
+    let message = full.split(" at line ").next().unwrap_or(&full);
+
+    if let Some(field) = message
+        .strip_prefix("missing field `")
+        .and_then(|rest| rest.strip_suffix('`'))
+    {
+        // The path points at the object the field is missing from
+        let field = match path {
+            "." => field.to_owned(),
+            parent => format!("{parent}.{field}"),
+        };
+        return FieldError {
+            field,
+            code: ErrorCode::MissingField,
+            message: message.to_owned(),
+        };
+    }
+
+    let code = if message.starts_with("invalid type") {
+        ErrorCode::InvalidType
+    } else {
+        ErrorCode::InvalidValue
+    };
+    FieldError {
+        field: path.to_owned(),
+        code,
+        message: message.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Request {
+        email: String,
+        #[serde(rename = "requires2FA")]
+        requires_2fa: bool,
+        devices: Option<Vec<Device>>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Device {
+        name: String,
+    }
+
+    fn invalid_body(body: &str) -> FieldError {
+        match parse::<Request>(body.as_bytes()) {
+            Err(AuthAPIError::InvalidBody(error)) => error,
+            other => panic!("Expected an invalid body for {body}, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_field_errors() {
+        let error = invalid_body(r#"{"requires2FA": true}"#);
+        assert_eq!(error.field, "email");
+        assert_eq!(error.code, ErrorCode::MissingField);
+        assert_eq!(error.message, "missing field `email`");
+
+        let error = invalid_body(r#"{"email": "a@b.com", "requires2FA": "yes"}"#);
+        assert_eq!(error.field, "requires2FA");
+        assert_eq!(error.code, ErrorCode::InvalidType);
+
+        let error = invalid_body(r#"{"email": "a@b.com", "requires2FA": true, "devices": [{}]}"#);
+        assert_eq!(error.field, "devices[0].name");
+        assert_eq!(error.code, ErrorCode::MissingField);
+    }
+
+    #[test]
+    fn test_malformed_body() {
+        for body in [
+            "",
+            "{\"email\": ",
+            "not json",
+            r#"{"email": "a@b.com", "requires2FA": true} {}"#,
+        ] {
+            assert!(
+                matches!(
+                    parse::<Request>(body.as_bytes()),
+                    Err(AuthAPIError::MalformedBody)
+                ),
+                "Failed for {body}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_is_json_content_type() {
+        let headers = |value: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(CONTENT_TYPE, HeaderValue::from_static(value));
+            headers
+        };
+
+        assert!(is_json_content_type(&headers("application/json")));
+        assert!(is_json_content_type(&headers(
+            "Application/JSON; charset=utf-8"
+        )));
+        assert!(is_json_content_type(&headers(
+            "application/merge-patch+json"
+        )));
+        assert!(!is_json_content_type(&headers("text/plain")));
+        assert!(!is_json_content_type(&headers(
+            "application/x-www-form-urlencoded"
+        )));
+        assert!(!is_json_content_type(&HeaderMap::new()));
+    }
+}
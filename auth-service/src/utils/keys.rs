@@ -0,0 +1,273 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use color_eyre::eyre::{eyre, Result, WrapErr};
+use ed25519_dalek::pkcs8::{DecodePrivateKey, DecodePublicKey, EncodePrivateKey};
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+    OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse,
+};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header};
+use std::collections::HashMap;
+use std::path::Path;
+
+// Every token is signed with EdDSA (Ed25519) so that other services can verify
+// it with the public keys published at `/.well-known/jwks.json`.
+pub const JWT_ALGORITHM: Algorithm = Algorithm::EdDSA;
+
+// Holds the key used to sign new tokens plus every key tokens may still be
+// signed with. During a rotation the previous key stays in the keyring as a
+// verification-only key until the tokens it signed have expired.
+pub struct Keyring {
+    active_kid: String,
+    encoding_key: EncodingKey,
+    decoding_keys: HashMap<String, DecodingKey>,
+    jwks: JwkSet,
+}
+
+impl Keyring {
+    pub fn new(active_kid: String, active_key: SigningKey) -> Result<Self> {
+        let der = active_key
+            .to_pkcs8_der()
+            .map_err(|e| eyre!("failed to encode signing key: {e}"))?;
+        let mut keyring = Self {
+            active_kid: active_kid.clone(),
+            encoding_key: EncodingKey::from_ed_der(der.as_bytes()),
+            decoding_keys: HashMap::new(),
+            jwks: JwkSet { keys: Vec::new() },
+        };
+        keyring.add_verification_key(active_kid, active_key.verifying_key())?;
+        Ok(keyring)
+    }
+
+    // Create a keyring with a single freshly generated key. Tokens signed with
+    // it stop validating as soon as the process exits.
+    pub fn generate() -> Result<Self> {
+        let kid = uuid::Uuid::new_v4().to_string();
+        let signing_key = SigningKey::from_bytes(&rand::random());
+        Self::new(kid, signing_key)
+    }
+
+    // Load every `<kid>.pem` file in `dir`. Private keys (PKCS#8) can sign and
+    // verify, public keys (SPKI) can only verify. The key named by
+    // `active_kid` must be a private key and is used for signing.
+    pub fn from_dir(dir: &Path, active_kid: &str) -> Result<Self> {
+        let mut signing_keys = HashMap::new();
+        let mut verifying_keys = HashMap::new();
+
+        let entries = std::fs::read_dir(dir)
+            .wrap_err_with(|| format!("failed to read JWT keys directory {}", dir.display()))?;
+
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("pem") {
+                continue;
+            }
+            let kid = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .ok_or_else(|| eyre!("invalid key file name {}", path.display()))?
+                .to_owned();
+            let pem = std::fs::read_to_string(&path)
+                .wrap_err_with(|| format!("failed to read key file {}", path.display()))?;
+
+            if pem.contains("PRIVATE KEY") {
+                let key = SigningKey::from_pkcs8_pem(&pem)
+                    .map_err(|e| eyre!("invalid private key {}: {e}", path.display()))?;
+                signing_keys.insert(kid, key);
+            } else {
+                let key = VerifyingKey::from_public_key_pem(&pem)
+                    .map_err(|e| eyre!("invalid public key {}: {e}", path.display()))?;
+                verifying_keys.insert(kid, key);
+            }
+        }
+
+        let active_key = signing_keys
+            .remove(active_kid)
+            .ok_or_else(|| eyre!("no private key found for active key id '{active_kid}'"))?;
+
+        let mut keyring = Self::new(active_kid.to_owned(), active_key)?;
+        for (kid, key) in signing_keys {
+            keyring.add_verification_key(kid, key.verifying_key())?;
+        }
+        for (kid, key) in verifying_keys {
+            keyring.add_verification_key(kid, key)?;
+        }
+
+        // Keep the JWKS output stable regardless of directory order
+        keyring
+            .jwks
+            .keys
+            .sort_by(|a, b| a.common.key_id.cmp(&b.common.key_id));
+
+        Ok(keyring)
+    }
+
+    pub fn add_verification_key(&mut self, kid: String, key: VerifyingKey) -> Result<()> {
+        let jwk = Jwk {
+            common: CommonParameters {
+                public_key_use: Some(PublicKeyUse::Signature),
+                key_algorithm: Some(KeyAlgorithm::EdDSA),
+                key_id: Some(kid.clone()),
+                ..Default::default()
+            },
+            algorithm: AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                key_type: OctetKeyPairType::OctetKeyPair,
+                curve: EllipticCurve::Ed25519,
+                x: URL_SAFE_NO_PAD.encode(key.to_bytes()),
+            }),
+        };
+        let decoding_key = DecodingKey::from_jwk(&jwk).wrap_err("failed to build decoding key")?;
+
+        self.jwks
+            .keys
+            .retain(|existing| existing.common.key_id.as_deref() != Some(kid.as_str()));
+        self.jwks.keys.push(jwk);
+        self.decoding_keys.insert(kid, decoding_key);
+        Ok(())
+    }
+
+    // Header for newly issued tokens, carrying the `kid` of the active key
+    pub fn header(&self) -> Header {
+        let mut header = Header::new(JWT_ALGORITHM);
+        header.kid = Some(self.active_kid.clone());
+        header
+    }
+
+    pub fn encoding_key(&self) -> &EncodingKey {
+        &self.encoding_key
+    }
+
+    pub fn decoding_key(&self, kid: &str) -> Option<&DecodingKey> {
+        self.decoding_keys.get(kid)
+    }
+
+    pub fn active_kid(&self) -> &str {
+        &self.active_kid
+    }
+
+    pub fn jwks(&self) -> &JwkSet {
+        &self.jwks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::pkcs8::{spki::der::pem::LineEnding, EncodePublicKey};
+    use jsonwebtoken::{decode, decode_header, encode, Validation};
+    use serde::{Deserialize, Serialize};
+    use std::path::PathBuf;
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct TestClaims {
+        sub: String,
+        exp: usize,
+    }
+
+    fn claims() -> TestClaims {
+        TestClaims {
+            sub: "test@example.com".to_owned(),
+            exp: (chrono::Utc::now().timestamp() + 60) as usize,
+        }
+    }
+
+    fn sign(keyring: &Keyring) -> String {
+        encode(&keyring.header(), &claims(), keyring.encoding_key()).unwrap()
+    }
+
+    fn verify(keyring: &Keyring, token: &str) -> bool {
+        let kid = decode_header(token).unwrap().kid.unwrap();
+        match keyring.decoding_key(&kid) {
+            Some(key) => decode::<TestClaims>(token, key, &Validation::new(JWT_ALGORITHM)).is_ok(),
+            None => false,
+        }
+    }
+
+    fn keys_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_private_key(dir: &Path, kid: &str) -> SigningKey {
+        let key = SigningKey::from_bytes(&rand::random());
+        let pem = key.to_pkcs8_pem(LineEnding::LF).unwrap();
+        std::fs::write(dir.join(format!("{kid}.pem")), pem.as_bytes()).unwrap();
+        key
+    }
+
+    #[test]
+    fn test_header_contains_active_kid() {
+        let keyring = Keyring::generate().unwrap();
+        let header = keyring.header();
+        assert_eq!(header.alg, JWT_ALGORITHM);
+        assert_eq!(header.kid.as_deref(), Some(keyring.active_kid()));
+    }
+
+    #[test]
+    fn test_generated_keyring_round_trip() {
+        let keyring = Keyring::generate().unwrap();
+        let token = sign(&keyring);
+        assert!(verify(&keyring, &token));
+    }
+
+    #[test]
+    fn test_token_from_other_keyring_is_rejected() {
+        let keyring = Keyring::generate().unwrap();
+        let other = Keyring::generate().unwrap();
+        assert!(!verify(&keyring, &sign(&other)));
+    }
+
+    #[test]
+    fn test_from_dir_keeps_retired_keys_for_verification() {
+        let dir = keys_dir();
+        write_private_key(&dir, "2026-01");
+        let retired = write_private_key(&dir, "2025-12");
+
+        let old_keyring = Keyring::new("2025-12".to_owned(), retired).unwrap();
+        let old_token = sign(&old_keyring);
+
+        let keyring = Keyring::from_dir(&dir, "2026-01").unwrap();
+        assert_eq!(keyring.active_kid(), "2026-01");
+        assert!(verify(&keyring, &old_token));
+        assert!(verify(&keyring, &sign(&keyring)));
+
+        let kids: Vec<_> = keyring
+            .jwks()
+            .keys
+            .iter()
+            .map(|jwk| jwk.common.key_id.clone().unwrap())
+            .collect();
+        assert_eq!(kids, vec!["2025-12", "2026-01"]);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_from_dir_accepts_public_keys() {
+        let dir = keys_dir();
+        write_private_key(&dir, "current");
+        let previous = SigningKey::from_bytes(&rand::random());
+        let public_pem = previous
+            .verifying_key()
+            .to_public_key_pem(LineEnding::LF)
+            .unwrap();
+        std::fs::write(dir.join("previous.pem"), public_pem).unwrap();
+
+        let keyring = Keyring::from_dir(&dir, "current").unwrap();
+        let old_token = sign(&Keyring::new("previous".to_owned(), previous).unwrap());
+        assert!(verify(&keyring, &old_token));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_from_dir_requires_private_active_key() {
+        let dir = keys_dir();
+        write_private_key(&dir, "current");
+
+        assert!(Keyring::from_dir(&dir, "missing").is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
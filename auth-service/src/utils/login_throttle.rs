@@ -0,0 +1,123 @@
+use crate::domain::{LoginAttemptKey, LoginAttempts};
+
+// An account can fail this many times before attempts start being delayed
+pub const FREE_ACCOUNT_FAILURES: u32 = 2;
+
+// How many failed logins are tolerated before an account or client IP is
+// locked out. IPs get a higher limit since many users can share one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoginThrottlePolicy {
+    pub max_account_failures: u32,
+    pub max_ip_failures: u32,
+    pub lockout_seconds: u64,
+}
+
+impl LoginThrottlePolicy {
+    // Seconds the caller has to wait before `key` may attempt to log in again.
+    // Past the free failures each account failure doubles the delay until the
+    // limit is reached and the key is locked out.
+    pub fn retry_after(
+        &self,
+        key: &LoginAttemptKey,
+        attempts: &LoginAttempts,
+        now: i64,
+    ) -> Option<u64> {
+        let max_failures = match key {
+            LoginAttemptKey::Account(_) => self.max_account_failures,
+            LoginAttemptKey::Ip(_) => self.max_ip_failures,
+        };
+
+        let delay = if attempts.failures >= max_failures {
+            self.lockout_seconds
+        } else if matches!(key, LoginAttemptKey::Account(_))
+            && attempts.failures > FREE_ACCOUNT_FAILURES
+        {
+            2u64.saturating_pow(attempts.failures - FREE_ACCOUNT_FAILURES - 1)
+                .min(self.lockout_seconds)
+        } else {
+            return None;
+        };
+
+        let elapsed = now.saturating_sub(attempts.last_failure_at).max(0) as u64;
+        (elapsed < delay).then(|| delay - elapsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Email;
+    use secrecy::SecretString;
+
+    const POLICY: LoginThrottlePolicy = LoginThrottlePolicy {
+        max_account_failures: 5,
+        max_ip_failures: 20,
+        lockout_seconds: 900,
+    };
+
+    fn account_key() -> LoginAttemptKey {
+        LoginAttemptKey::Account(
+            Email::parse(SecretString::new(
+                "test@example.com".to_owned().into_boxed_str(),
+            ))
+            .unwrap(),
+        )
+    }
+
+    fn attempts(failures: u32) -> LoginAttempts {
+        LoginAttempts {
+            failures,
+            last_failure_at: 1_000,
+        }
+    }
+
+    #[test]
+    fn test_free_failures_are_not_delayed() {
+        for failures in 0..=FREE_ACCOUNT_FAILURES {
+            assert_eq!(
+                POLICY.retry_after(&account_key(), &attempts(failures), 1_000),
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn test_account_delay_doubles() {
+        assert_eq!(
+            POLICY.retry_after(&account_key(), &attempts(3), 1_000),
+            Some(1)
+        );
+        assert_eq!(
+            POLICY.retry_after(&account_key(), &attempts(4), 1_000),
+            Some(2)
+        );
+        assert_eq!(
+            POLICY.retry_after(&account_key(), &attempts(4), 1_001),
+            Some(1)
+        );
+        assert_eq!(
+            POLICY.retry_after(&account_key(), &attempts(4), 1_002),
+            None
+        );
+    }
+
+    #[test]
+    fn test_account_locked_out_at_limit() {
+        assert_eq!(
+            POLICY.retry_after(&account_key(), &attempts(5), 1_100),
+            Some(800)
+        );
+        assert_eq!(
+            POLICY.retry_after(&account_key(), &attempts(5), 1_900),
+            None
+        );
+    }
+
+    #[test]
+    fn test_ip_only_locked_out_at_limit() {
+        let ip_key = LoginAttemptKey::Ip("127.0.0.1".parse().unwrap());
+
+        assert_eq!(POLICY.retry_after(&ip_key, &attempts(19), 1_000), None);
+        assert_eq!(POLICY.retry_after(&ip_key, &attempts(20), 1_000), Some(900));
+    }
+}
@@ -0,0 +1,216 @@
+use lazy_static::lazy_static;
+use prometheus::{
+    exponential_buckets, Encoder, HistogramOpts, HistogramTimer, HistogramVec, IntCounter,
+    IntCounterVec, Opts, Registry, TextEncoder,
+};
+
+// Everything is registered on our own registry rather than the crate's
+// default one, so `/metrics` only exposes what's defined here
+lazy_static! {
+    pub static ref REGISTRY: Registry = Registry::new_custom(Some("auth".to_owned()), None)
+        .expect("Failed to create metrics registry");
+    pub static ref SIGNUPS: IntCounter = register(IntCounter::new(
+        "signups_total",
+        "Accounts created through /signup"
+    ));
+    pub static ref LOGINS: IntCounterVec = register(IntCounterVec::new(
+        Opts::new("logins_total", "Login attempts by outcome"),
+        &["outcome"]
+    ));
+    pub static ref TWO_FA_VERIFICATIONS: IntCounterVec = register(IntCounterVec::new(
+        Opts::new("two_fa_verifications_total", "2FA verifications by outcome"),
+        &["outcome"]
+    ));
+    pub static ref LOGOUTS: IntCounter =
+        register(IntCounter::new("logouts_total", "Successful logouts"));
+    pub static ref TOKEN_VERIFICATIONS: IntCounterVec = register(IntCounterVec::new(
+        Opts::new(
+            "token_verifications_total",
+            "Tokens checked by /verify-token by outcome"
+        ),
+        &["outcome"]
+    ));
+    pub static ref TOKEN_REVOCATIONS: IntCounterVec = register(IntCounterVec::new(
+        Opts::new(
+            "token_revocations_total",
+            "Sessions and refresh token families revoked, by reason"
+        ),
+        &["reason"]
+    ));
+    // Argon2 is deliberately slow, so this is most of a login's latency
+    pub static ref PASSWORD_HASH_DURATION: HistogramVec = register(HistogramVec::new(
+        HistogramOpts::new(
+            "password_hash_duration_seconds",
+            "Time spent computing or verifying Argon2 hashes"
+        )
+        .buckets(exponential_buckets(0.001, 2.0, 12).unwrap()),
+        &["operation"]
+    ));
+    pub static ref STORE_DURATION: HistogramVec = register(HistogramVec::new(
+        HistogramOpts::new(
+            "store_operation_duration_seconds",
+            "Latency of data store operations by backend"
+        )
+        .buckets(exponential_buckets(0.0001, 2.0, 16).unwrap()),
+        &["backend", "store", "operation"]
+    ));
+    pub static ref EMAIL_SEND_DURATION: HistogramVec = register(HistogramVec::new(
+        HistogramOpts::new(
+            "email_send_duration_seconds",
+            "Time taken to hand an email to the provider"
+        )
+        .buckets(exponential_buckets(0.01, 2.0, 12).unwrap()),
+        &["provider", "outcome"]
+    ));
+}
+
+fn register<M>(metric: prometheus::Result<M>) -> M
+where
+    M: prometheus::core::Collector + Clone + 'static,
+{
+    let metric = metric.expect("Invalid metric definition");
+    REGISTRY
+        .register(Box::new(metric.clone()))
+        .expect("Metric registered twice");
+    metric
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginOutcome {
+    Success,
+    TwoFactorRequired,
+    IncorrectCredentials,
+    UserNotFound,
+    EmailNotVerified,
+    AccountDisabled,
+    PasswordResetRequired,
+    Throttled,
+}
+
+impl LoginOutcome {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Success => "success",
+            Self::TwoFactorRequired => "2fa_required",
+            Self::IncorrectCredentials => "incorrect_credentials",
+            Self::UserNotFound => "user_not_found",
+            Self::EmailNotVerified => "email_not_verified",
+            Self::AccountDisabled => "account_disabled",
+            Self::PasswordResetRequired => "password_reset_required",
+            Self::Throttled => "throttled",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RevocationReason {
+    // A single session logged out from another device
+    Session,
+    // Every session of a user, logged out everywhere on request
+    AllSessions,
+    // Every session of a user whose password was just reset
+    PasswordReset,
+    // A rotated refresh token was presented again
+    RefreshTokenReuse,
+    // Every session of a user an admin disabled, deleted or sent to reset
+    // their password
+    Admin,
+}
+
+impl RevocationReason {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Session => "session",
+            Self::AllSessions => "all_sessions",
+            Self::PasswordReset => "password_reset",
+            Self::RefreshTokenReuse => "refresh_token_reuse",
+            Self::Admin => "admin",
+        }
+    }
+}
+
+pub fn record_login(outcome: LoginOutcome) {
+    LOGINS.with_label_values(&[outcome.as_str()]).inc();
+}
+
+pub fn record_two_fa_verification(success: bool) {
+    TWO_FA_VERIFICATIONS
+        .with_label_values(&[outcome(success)])
+        .inc();
+}
+
+pub fn record_token_verification(valid: bool) {
+    let outcome = if valid { "valid" } else { "invalid" };
+    TOKEN_VERIFICATIONS.with_label_values(&[outcome]).inc();
+}
+
+pub fn record_revocation(reason: RevocationReason) {
+    TOKEN_REVOCATIONS
+        .with_label_values(&[reason.as_str()])
+        .inc();
+}
+
+// Observes the time until the returned timer is dropped
+pub fn time_store(backend: &str, store: &str, operation: &str) -> HistogramTimer {
+    STORE_DURATION
+        .with_label_values(&[backend, store, operation])
+        .start_timer()
+}
+
+pub fn time_password_hash(operation: &str) -> HistogramTimer {
+    PASSWORD_HASH_DURATION
+        .with_label_values(&[operation])
+        .start_timer()
+}
+
+pub fn observe_email_send(provider: &str, success: bool, seconds: f64) {
+    EMAIL_SEND_DURATION
+        .with_label_values(&[provider, outcome(success)])
+        .observe(seconds);
+}
+
+fn outcome(success: bool) -> &'static str {
+    if success {
+        "success"
+    } else {
+        "failure"
+    }
+}
+
+// Render every metric in the Prometheus text exposition format
+pub fn render() -> Result<String, prometheus::Error> {
+    // The statics register themselves on first use, so that metrics nothing
+    // has touched yet are still listed
+    lazy_static::initialize(&SIGNUPS);
+    lazy_static::initialize(&LOGINS);
+    lazy_static::initialize(&TWO_FA_VERIFICATIONS);
+    lazy_static::initialize(&LOGOUTS);
+    lazy_static::initialize(&TOKEN_VERIFICATIONS);
+    lazy_static::initialize(&TOKEN_REVOCATIONS);
+    lazy_static::initialize(&PASSWORD_HASH_DURATION);
+    lazy_static::initialize(&STORE_DURATION);
+    lazy_static::initialize(&EMAIL_SEND_DURATION);
+
+    let mut buffer = Vec::new();
+    TextEncoder::new().encode(&REGISTRY.gather(), &mut buffer)?;
+    Ok(String::from_utf8(buffer).expect("Prometheus text format is UTF-8"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_includes_recorded_metrics() {
+        record_login(LoginOutcome::TwoFactorRequired);
+        record_revocation(RevocationReason::RefreshTokenReuse);
+        drop(time_store("postgres", "user", "get_user"));
+
+        let output = render().unwrap();
+        assert!(output.contains(r#"auth_logins_total{outcome="2fa_required"}"#));
+        assert!(output.contains(r#"auth_token_revocations_total{reason="refresh_token_reuse"}"#));
+        assert!(output.contains(
+            r#"auth_store_operation_duration_seconds_count{backend="postgres",operation="get_user",store="user"}"#
+        ));
+    }
+}
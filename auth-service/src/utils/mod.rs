@@ -1,3 +1,14 @@
+pub mod audit;
 pub mod auth;
+pub mod client_ip;
 pub mod constants;
+pub mod https;
+pub mod json;
+pub mod keys;
+pub mod login_throttle;
+pub mod metrics;
+pub mod settings;
+pub mod shutdown;
+pub mod tls;
+pub mod totp;
 pub mod tracing;
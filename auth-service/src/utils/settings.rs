@@ -0,0 +1,846 @@
+use super::constants::*;
+use super::keys::Keyring;
+use super::login_throttle::LoginThrottlePolicy;
+use super::tls::load_certified_key;
+use crate::app_state::UnverifiedLoginPolicy;
+use crate::domain::Email;
+use axum::http::HeaderValue;
+use axum_extra::extract::cookie::SameSite;
+use config::{Config, ConfigError, Environment, File, FileFormat};
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+use thiserror::Error;
+
+// Everything the service can be configured with. Values are layered, each
+// source overriding the ones before it:
+//   1. the defaults below
+//   2. the TOML file named by `AUTH_CONFIG_FILE`, or `config.toml` if present
+//   3. `AUTH__<SECTION>__<KEY>` environment variables, e.g. `AUTH__EMAIL__SENDER`
+//   4. the flat environment variables listed in `ENV_VAR_KEYS`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub application: ApplicationSettings,
+    pub tokens: TokenSettings,
+    pub cookies: CookieSettings,
+    pub stores: StoreSettings,
+    pub email: EmailSettings,
+    pub jwt: JwtSettings,
+    pub login: LoginSettings,
+    pub admin: AdminSettings,
+    pub health: HealthSettings,
+    pub tracing: TracingSettings,
+    pub shutdown: ShutdownSettings,
+    pub tls: TlsSettings,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ApplicationSettings {
+    pub address: String,
+    // Origins allowed to make credentialed cross-origin requests
+    pub allowed_origins: Vec<String>,
+    // Reverse proxies whose `X-Forwarded-Proto` header is believed when
+    // deciding whether a request came in over HTTPS, and whose
+    // `X-Forwarded-For` or `Forwarded` header gives the client's IP
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+impl Default for ApplicationSettings {
+    fn default() -> Self {
+        Self {
+            address: DEFAULT_APP_ADDRESS.to_owned(),
+            allowed_origins: vec![DEFAULT_ALLOWED_ORIGIN.to_owned()],
+            trusted_proxies: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TokenSettings {
+    pub access_token_ttl_seconds: u64,
+    pub refresh_token_ttl_seconds: u64,
+    pub password_reset_token_ttl_seconds: u64,
+    pub email_verification_token_ttl_seconds: u64,
+    pub two_fa_code_ttl_seconds: u64,
+}
+
+impl Default for TokenSettings {
+    fn default() -> Self {
+        Self {
+            access_token_ttl_seconds: DEFAULT_ACCESS_TOKEN_TTL_SECONDS,
+            refresh_token_ttl_seconds: DEFAULT_REFRESH_TOKEN_TTL_SECONDS,
+            password_reset_token_ttl_seconds: DEFAULT_PASSWORD_RESET_TOKEN_TTL_SECONDS,
+            email_verification_token_ttl_seconds: DEFAULT_EMAIL_VERIFICATION_TOKEN_TTL_SECONDS,
+            two_fa_code_ttl_seconds: DEFAULT_TWO_FA_CODE_TTL_SECONDS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CookieSettings {
+    pub jwt_name: String,
+    pub refresh_name: String,
+    pub same_site: CookieSameSite,
+    pub secure: bool,
+    pub domain: Option<String>,
+}
+
+impl Default for CookieSettings {
+    fn default() -> Self {
+        Self {
+            jwt_name: JWT_COOKIE_NAME.to_owned(),
+            refresh_name: REFRESH_COOKIE_NAME.to_owned(),
+            same_site: CookieSameSite::Lax,
+            secure: false,
+            domain: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CookieSameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+impl From<CookieSameSite> for SameSite {
+    fn from(same_site: CookieSameSite) -> Self {
+        match same_site {
+            CookieSameSite::Strict => SameSite::Strict,
+            CookieSameSite::Lax => SameSite::Lax,
+            CookieSameSite::None => SameSite::None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct StoreSettings {
+    // Keep everything in memory, see `AppState::in_memory`
+    pub in_memory: bool,
+    pub database_url: Option<SecretString>,
+    // Keep everything in SQLite instead of Postgres and Redis, needs the
+    // `sqlite` feature
+    pub sqlite_database_url: Option<SecretString>,
+    pub redis_host_name: String,
+    // Keep tokens, sessions, 2FA codes and login attempts in Postgres, and
+    // don't connect to Redis at all
+    pub postgres_token_stores: bool,
+    pub purge_interval_seconds: u64,
+}
+
+impl Default for StoreSettings {
+    fn default() -> Self {
+        Self {
+            in_memory: false,
+            database_url: None,
+            sqlite_database_url: None,
+            redis_host_name: DEFAULT_REDIS_HOSTNAME.to_owned(),
+            postgres_token_stores: false,
+            purge_interval_seconds: DEFAULT_PURGE_INTERVAL_SECONDS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct EmailSettings {
+    pub provider: EmailProvider,
+    pub base_url: String,
+    pub sender: Option<String>,
+    pub timeout_milliseconds: u64,
+    pub postmark_auth_token: Option<SecretString>,
+}
+
+impl Default for EmailSettings {
+    fn default() -> Self {
+        Self {
+            provider: EmailProvider::Postmark,
+            base_url: DEFAULT_POSTMARK_BASE_URL.to_owned(),
+            sender: None,
+            timeout_milliseconds: DEFAULT_EMAIL_TIMEOUT_MILLISECONDS,
+            postmark_auth_token: None,
+        }
+    }
+}
+
+impl EmailSettings {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_milliseconds)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailProvider {
+    Postmark,
+    // Emails are only logged, see `MockEmailClient`
+    Log,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct JwtSettings {
+    pub keys_dir: Option<PathBuf>,
+    pub active_key_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LoginSettings {
+    pub unverified_login_policy: UnverifiedLoginPolicy,
+    pub totp_drift_steps: u8,
+    pub max_account_failures: u32,
+    pub max_ip_failures: u32,
+    pub lockout_seconds: u64,
+    // Answer signup, login and password reset requests the same way whether
+    // or not the account exists, see `AppState::enumeration_protection`
+    pub enumeration_protection: bool,
+}
+
+impl Default for LoginSettings {
+    fn default() -> Self {
+        Self {
+            unverified_login_policy: DEFAULT_UNVERIFIED_LOGIN_POLICY,
+            totp_drift_steps: DEFAULT_TOTP_DRIFT_STEPS,
+            max_account_failures: DEFAULT_LOGIN_MAX_ACCOUNT_FAILURES,
+            max_ip_failures: DEFAULT_LOGIN_MAX_IP_FAILURES,
+            lockout_seconds: DEFAULT_LOGIN_LOCKOUT_SECONDS,
+            enumeration_protection: false,
+        }
+    }
+}
+
+impl LoginSettings {
+    pub fn throttle_policy(&self) -> LoginThrottlePolicy {
+        LoginThrottlePolicy {
+            max_account_failures: self.max_account_failures,
+            max_ip_failures: self.max_ip_failures,
+            lockout_seconds: self.lockout_seconds,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AdminSettings {
+    // Users granted the admin role once their email is verified, both at
+    // startup and when they verify it, so that a fresh deployment has
+    // someone who can manage users
+    pub bootstrap_emails: Vec<String>,
+}
+
+impl AdminSettings {
+    // Validation has already rejected any entry that isn't an email
+    pub fn bootstrap_admins(&self) -> Vec<Email> {
+        self.bootstrap_emails
+            .iter()
+            .filter_map(|email| Email::parse(SecretString::from(email.as_str())).ok())
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct HealthSettings {
+    // How long each dependency may take to answer `/health/ready`
+    pub timeout_milliseconds: u64,
+    // Also check the email provider, off by default so that an email outage
+    // doesn't take every instance out of rotation
+    pub check_email_provider: bool,
+}
+
+impl Default for HealthSettings {
+    fn default() -> Self {
+        Self {
+            timeout_milliseconds: DEFAULT_HEALTH_CHECK_TIMEOUT_MILLISECONDS,
+            check_email_provider: false,
+        }
+    }
+}
+
+impl HealthSettings {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_milliseconds)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TracingSettings {
+    // Base URL of an OTLP/HTTP collector, e.g. `http://localhost:4318`.
+    // Spans are only exported when it's set.
+    pub otlp_endpoint: Option<String>,
+    // Name the exported spans are reported under
+    pub service_name: String,
+}
+
+impl Default for TracingSettings {
+    fn default() -> Self {
+        Self {
+            otlp_endpoint: None,
+            service_name: DEFAULT_SERVICE_NAME.to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ShutdownSettings {
+    // How long `/health/ready` fails before the listener is closed, giving
+    // load balancers time to stop routing new requests here
+    pub readiness_delay_seconds: u64,
+    // How long in-flight requests and background tasks get to finish once
+    // the listener is closed
+    pub drain_timeout_seconds: u64,
+}
+
+impl Default for ShutdownSettings {
+    fn default() -> Self {
+        Self {
+            readiness_delay_seconds: DEFAULT_READINESS_DELAY_SECONDS,
+            drain_timeout_seconds: DEFAULT_DRAIN_TIMEOUT_SECONDS,
+        }
+    }
+}
+
+impl ShutdownSettings {
+    pub fn readiness_delay(&self) -> Duration {
+        Duration::from_secs(self.readiness_delay_seconds)
+    }
+
+    pub fn drain_timeout(&self) -> Duration {
+        Duration::from_secs(self.drain_timeout_seconds)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TlsSettings {
+    // PEM certificate chain and private key. HTTPS is served when both are
+    // set, plain HTTP otherwise.
+    pub cert_path: Option<PathBuf>,
+    pub key_path: Option<PathBuf>,
+    // How often the files are checked for changes, so that a renewed
+    // certificate is picked up without a restart
+    pub reload_interval_seconds: u64,
+    // Also listen for plain HTTP here, redirecting every request to HTTPS
+    pub redirect_address: Option<String>,
+}
+
+impl Default for TlsSettings {
+    fn default() -> Self {
+        Self {
+            cert_path: None,
+            key_path: None,
+            reload_interval_seconds: DEFAULT_TLS_RELOAD_INTERVAL_SECONDS,
+            redirect_address: None,
+        }
+    }
+}
+
+impl TlsSettings {
+    // The certificate and key paths, if HTTPS is enabled
+    pub fn certificate(&self) -> Option<(&Path, &Path)> {
+        match (&self.cert_path, &self.key_path) {
+            (Some(cert_path), Some(key_path)) => Some((cert_path, key_path)),
+            _ => None,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.certificate().is_some()
+    }
+
+    pub fn reload_interval(&self) -> Duration {
+        Duration::from_secs(self.reload_interval_seconds)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum SettingsError {
+    #[error("failed to load configuration: {0}")]
+    Load(#[from] ConfigError),
+    #[error("invalid configuration: `{key}`: {reason}")]
+    Invalid { key: &'static str, reason: String },
+}
+
+fn invalid(key: &'static str, reason: impl Into<String>) -> SettingsError {
+    SettingsError::Invalid {
+        key,
+        reason: reason.into(),
+    }
+}
+
+// Environment variables the service was configured with before the config
+// file existed, still honoured so existing deployments keep working
+const ENV_VAR_KEYS: &[(&str, &str)] = &[
+    ("DATABASE_URL", "stores.database_url"),
+    ("SQLITE_DATABASE_URL", "stores.sqlite_database_url"),
+    ("REDIS_HOST_NAME", "stores.redis_host_name"),
+    ("IN_MEMORY_STORES", "stores.in_memory"),
+    ("POSTGRES_TOKEN_STORES", "stores.postgres_token_stores"),
+    ("PURGE_INTERVAL_SECONDS", "stores.purge_interval_seconds"),
+    ("POSTMARK_AUTH_TOKEN", "email.postmark_auth_token"),
+    ("JWT_KEYS_DIR", "jwt.keys_dir"),
+    ("JWT_ACTIVE_KEY_ID", "jwt.active_key_id"),
+    ("UNVERIFIED_LOGIN_POLICY", "login.unverified_login_policy"),
+    ("TOTP_DRIFT_STEPS", "login.totp_drift_steps"),
+    ("LOGIN_MAX_ACCOUNT_FAILURES", "login.max_account_failures"),
+    ("LOGIN_MAX_IP_FAILURES", "login.max_ip_failures"),
+    ("LOGIN_LOCKOUT_SECONDS", "login.lockout_seconds"),
+    // The standard OpenTelemetry variables
+    ("OTEL_EXPORTER_OTLP_ENDPOINT", "tracing.otlp_endpoint"),
+    ("OTEL_SERVICE_NAME", "tracing.service_name"),
+];
+
+impl Settings {
+    // Load the settings from the config file and the process environment
+    // (including `.env`)
+    pub fn load() -> Result<Self, SettingsError> {
+        dotenvy::dotenv().ok();
+        let env: HashMap<String, String> = std::env::vars().collect();
+        Self::from_sources(&env)
+    }
+
+    fn from_sources(env: &HashMap<String, String>) -> Result<Self, SettingsError> {
+        let config_file = match env.get(CONFIG_FILE_ENV_VAR) {
+            Some(path) => File::new(path, FileFormat::Toml),
+            None => File::new(DEFAULT_CONFIG_FILE, FileFormat::Toml).required(false),
+        };
+
+        let mut builder = Config::builder().add_source(config_file).add_source(
+            Environment::with_prefix("AUTH")
+                .separator("__")
+                .list_separator(",")
+                .with_list_parse_key("application.allowed_origins")
+                .with_list_parse_key("application.trusted_proxies")
+                .with_list_parse_key("admin.bootstrap_emails")
+                .try_parsing(true)
+                .source(Some(env.clone().into_iter().collect())),
+        );
+        for (var, key) in ENV_VAR_KEYS {
+            let value = env.get(*var).filter(|value| !value.is_empty()).cloned();
+            builder = builder.set_override_option(*key, value)?;
+        }
+
+        Ok(builder.build()?.try_deserialize()?)
+    }
+
+    // Catch mistakes on startup rather than on the first request that hits them
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        self.application
+            .address
+            .parse::<SocketAddr>()
+            .map_err(|e| invalid("application.address", e.to_string()))?;
+        for origin in &self.application.allowed_origins {
+            if !origin.starts_with("http://") && !origin.starts_with("https://") {
+                return Err(invalid(
+                    "application.allowed_origins",
+                    format!("contains '{origin}', which is not an http(s) origin"),
+                ));
+            }
+            HeaderValue::from_str(origin)
+                .map_err(|e| invalid("application.allowed_origins", format!("'{origin}': {e}")))?;
+        }
+
+        for (key, ttl) in [
+            (
+                "tokens.access_token_ttl_seconds",
+                self.tokens.access_token_ttl_seconds,
+            ),
+            (
+                "tokens.refresh_token_ttl_seconds",
+                self.tokens.refresh_token_ttl_seconds,
+            ),
+            (
+                "tokens.password_reset_token_ttl_seconds",
+                self.tokens.password_reset_token_ttl_seconds,
+            ),
+            (
+                "tokens.email_verification_token_ttl_seconds",
+                self.tokens.email_verification_token_ttl_seconds,
+            ),
+            (
+                "tokens.two_fa_code_ttl_seconds",
+                self.tokens.two_fa_code_ttl_seconds,
+            ),
+        ] {
+            if ttl == 0 || ttl > MAX_TOKEN_TTL_SECONDS {
+                return Err(invalid(
+                    key,
+                    format!("must be between 1 and {MAX_TOKEN_TTL_SECONDS} seconds"),
+                ));
+            }
+        }
+
+        if self.cookies.jwt_name.is_empty() || self.cookies.refresh_name.is_empty() {
+            return Err(invalid("cookies", "names must not be empty"));
+        }
+        if self.cookies.jwt_name == self.cookies.refresh_name {
+            return Err(invalid("cookies", "jwt_name and refresh_name must differ"));
+        }
+        // Browsers drop `SameSite=None` cookies that aren't also `Secure`.
+        // Over TLS every cookie is.
+        if self.cookies.same_site == CookieSameSite::None
+            && !self.cookies.secure
+            && !self.tls.enabled()
+        {
+            return Err(invalid(
+                "cookies.same_site",
+                "none requires cookies.secure or TLS",
+            ));
+        }
+
+        self.validate_stores()?;
+        self.validate_email()?;
+
+        if let Some(keys_dir) = &self.jwt.keys_dir {
+            let active_kid = self
+                .jwt
+                .active_key_id
+                .as_deref()
+                .ok_or_else(|| invalid("jwt.active_key_id", "must be set with jwt.keys_dir"))?;
+            Keyring::from_dir(keys_dir, active_kid)
+                .map_err(|e| invalid("jwt.keys_dir", format!("{e:#}")))?;
+        }
+
+        if self.login.max_account_failures == 0 || self.login.max_ip_failures == 0 {
+            return Err(invalid("login", "max failures must be at least 1"));
+        }
+
+        for email in &self.admin.bootstrap_emails {
+            Email::parse(SecretString::from(email.as_str()))
+                .map_err(|e| invalid("admin.bootstrap_emails", format!("'{email}': {e}")))?;
+        }
+
+        if self.health.timeout_milliseconds == 0 {
+            return Err(invalid("health.timeout_milliseconds", "must be at least 1"));
+        }
+
+        if let Some(endpoint) = &self.tracing.otlp_endpoint {
+            let url = reqwest::Url::parse(endpoint)
+                .map_err(|e| invalid("tracing.otlp_endpoint", e.to_string()))?;
+            if !matches!(url.scheme(), "http" | "https") {
+                return Err(invalid("tracing.otlp_endpoint", "must be an http(s) URL"));
+            }
+        }
+        if self.tracing.service_name.is_empty() {
+            return Err(invalid("tracing.service_name", "must not be empty"));
+        }
+
+        if self.shutdown.drain_timeout_seconds == 0 {
+            return Err(invalid(
+                "shutdown.drain_timeout_seconds",
+                "must be at least 1",
+            ));
+        }
+
+        self.validate_tls()?;
+
+        Ok(())
+    }
+
+    fn validate_tls(&self) -> Result<(), SettingsError> {
+        let tls = &self.tls;
+        if tls.cert_path.is_some() != tls.key_path.is_some() {
+            return Err(invalid(
+                "tls",
+                "cert_path and key_path must be set together",
+            ));
+        }
+        if let Some((cert_path, key_path)) = tls.certificate() {
+            load_certified_key(cert_path, key_path)
+                .map_err(|e| invalid("tls.cert_path", format!("{e:#}")))?;
+        }
+        if tls.reload_interval_seconds == 0 {
+            return Err(invalid("tls.reload_interval_seconds", "must be at least 1"));
+        }
+        if let Some(address) = &tls.redirect_address {
+            if !tls.enabled() {
+                return Err(invalid(
+                    "tls.redirect_address",
+                    "requires cert_path and key_path",
+                ));
+            }
+            address
+                .parse::<SocketAddr>()
+                .map_err(|e| invalid("tls.redirect_address", e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    fn validate_stores(&self) -> Result<(), SettingsError> {
+        if self.stores.in_memory {
+            return Ok(());
+        }
+        if self.stores.sqlite_database_url.is_some() {
+            if !cfg!(feature = "sqlite") {
+                return Err(invalid(
+                    "stores.sqlite_database_url",
+                    "is set but the service was built without the `sqlite` feature",
+                ));
+            }
+            if self.stores.postgres_token_stores {
+                return Err(invalid(
+                    "stores.postgres_token_stores",
+                    "can't be used with stores.sqlite_database_url",
+                ));
+            }
+        } else if !is_set(&self.stores.database_url) {
+            return Err(invalid("stores.database_url", "must be set"));
+        }
+        if self.stores.purge_interval_seconds == 0 {
+            return Err(invalid(
+                "stores.purge_interval_seconds",
+                "must be at least 1",
+            ));
+        }
+        Ok(())
+    }
+
+    fn validate_email(&self) -> Result<(), SettingsError> {
+        if self.stores.in_memory || self.email.provider == EmailProvider::Log {
+            return Ok(());
+        }
+        reqwest::Url::parse(&self.email.base_url)
+            .map_err(|e| invalid("email.base_url", e.to_string()))?;
+        let sender = self
+            .email
+            .sender
+            .as_deref()
+            .ok_or_else(|| invalid("email.sender", "must be set"))?;
+        Email::parse(SecretString::from(sender))
+            .map_err(|e| invalid("email.sender", e.to_string()))?;
+        if !is_set(&self.email.postmark_auth_token) {
+            return Err(invalid("email.postmark_auth_token", "must be set"));
+        }
+        if self.email.timeout_milliseconds == 0 {
+            return Err(invalid("email.timeout_milliseconds", "must be at least 1"));
+        }
+        Ok(())
+    }
+}
+
+fn is_set(secret: &Option<SecretString>) -> bool {
+    secret
+        .as_ref()
+        .is_some_and(|secret| !secret.expose_secret().is_empty())
+}
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+// Load and validate the settings once on startup, so that configuration
+// mistakes are reported before the service starts serving
+pub fn init_settings() -> Result<&'static Settings, SettingsError> {
+    let settings = Settings::load()?;
+    settings.validate()?;
+    Ok(SETTINGS.get_or_init(|| settings))
+}
+
+// The settings the service was started with. Tests and benchmarks don't call
+// `init_settings` and set up their own stores and email client, so they load
+// the settings on first use without validating them.
+pub fn settings() -> &'static Settings {
+    SETTINGS.get_or_init(|| Settings::load().unwrap_or_else(|e| panic!("{e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(vars: &[(&str, &str)]) -> HashMap<String, String> {
+        vars.iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    // Every test points at a config file that doesn't exist unless it needs one
+    fn load(vars: &[(&str, &str)]) -> Result<Settings, SettingsError> {
+        let mut env = env(vars);
+        env.entry(CONFIG_FILE_ENV_VAR.to_owned())
+            .or_insert_with(|| "tests/fixtures/empty.toml".to_owned());
+        let settings = Settings::from_sources(&env)?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    const REQUIRED: &[(&str, &str)] = &[
+        ("DATABASE_URL", "postgres://postgres@localhost:5432"),
+        ("POSTMARK_AUTH_TOKEN", "token"),
+        ("AUTH__EMAIL__SENDER", "sender@example.com"),
+    ];
+
+    #[test]
+    fn test_defaults() {
+        let settings = load(REQUIRED).unwrap();
+        assert_eq!(settings.application.address, DEFAULT_APP_ADDRESS);
+        assert_eq!(
+            settings.application.allowed_origins,
+            vec![DEFAULT_ALLOWED_ORIGIN]
+        );
+        assert_eq!(
+            settings.tokens.access_token_ttl_seconds,
+            DEFAULT_ACCESS_TOKEN_TTL_SECONDS
+        );
+        assert_eq!(settings.cookies.jwt_name, JWT_COOKIE_NAME);
+        assert_eq!(settings.email.provider, EmailProvider::Postmark);
+        assert_eq!(
+            settings.login.unverified_login_policy,
+            DEFAULT_UNVERIFIED_LOGIN_POLICY
+        );
+        assert!(!settings.health.check_email_provider);
+        assert!(!settings.login.enumeration_protection);
+        assert!(settings.admin.bootstrap_emails.is_empty());
+    }
+
+    #[test]
+    fn test_file_and_env_overrides() {
+        let settings = load(
+            &[
+                REQUIRED,
+                &[
+                    (CONFIG_FILE_ENV_VAR, "tests/fixtures/settings.toml"),
+                    (
+                        "AUTH__APPLICATION__ALLOWED_ORIGINS",
+                        "http://a.example.com,https://b.example.com",
+                    ),
+                    ("AUTH__COOKIES__SECURE", "true"),
+                    ("AUTH__APPLICATION__TRUSTED_PROXIES", "10.0.0.1,::1"),
+                    ("UNVERIFIED_LOGIN_POLICY", "allow"),
+                    ("AUTH__LOGIN__ENUMERATION_PROTECTION", "true"),
+                    (
+                        "AUTH__ADMIN__BOOTSTRAP_EMAILS",
+                        "admin@example.com,ops@example.com",
+                    ),
+                ],
+            ]
+            .concat(),
+        )
+        .unwrap();
+
+        // From the file
+        assert_eq!(settings.application.address, "127.0.0.1:4000");
+        assert_eq!(settings.tokens.access_token_ttl_seconds, 300);
+        assert_eq!(settings.cookies.same_site, CookieSameSite::Strict);
+        // From the environment
+        assert_eq!(
+            settings.application.allowed_origins,
+            vec!["http://a.example.com", "https://b.example.com"]
+        );
+        assert!(settings.cookies.secure);
+        assert_eq!(
+            settings.application.trusted_proxies,
+            vec![
+                "10.0.0.1".parse::<IpAddr>().unwrap(),
+                "::1".parse::<IpAddr>().unwrap()
+            ]
+        );
+        assert_eq!(
+            settings.login.unverified_login_policy,
+            UnverifiedLoginPolicy::Allow
+        );
+        assert!(settings.login.enumeration_protection);
+        assert_eq!(
+            settings.admin.bootstrap_emails,
+            vec!["admin@example.com", "ops@example.com"]
+        );
+    }
+
+    #[test]
+    fn test_missing_config_file_is_an_error() {
+        let result = load(&[REQUIRED, &[(CONFIG_FILE_ENV_VAR, "missing.toml")]].concat());
+        assert!(matches!(result, Err(SettingsError::Load(_))));
+    }
+
+    #[test]
+    fn test_invalid_value_is_an_error() {
+        let result = load(
+            &[
+                REQUIRED,
+                &[("AUTH__TOKENS__ACCESS_TOKEN_TTL_SECONDS", "soon")],
+            ]
+            .concat(),
+        );
+        assert!(matches!(result, Err(SettingsError::Load(_))));
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        let cases: &[(&[(&str, &str)], &str)] = &[
+            (
+                &[("AUTH__APPLICATION__ADDRESS", "localhost")],
+                "application.address",
+            ),
+            (
+                &[("AUTH__APPLICATION__ALLOWED_ORIGINS", "localhost:8000")],
+                "application.allowed_origins",
+            ),
+            (
+                &[("AUTH__TOKENS__REFRESH_TOKEN_TTL_SECONDS", "0")],
+                "tokens.refresh_token_ttl_seconds",
+            ),
+            (&[("AUTH__COOKIES__SAME_SITE", "none")], "cookies.same_site"),
+            (&[("AUTH__EMAIL__SENDER", "not an email")], "email.sender"),
+            (&[("POSTMARK_AUTH_TOKEN", "")], "email.postmark_auth_token"),
+            (&[("DATABASE_URL", "")], "stores.database_url"),
+            (&[("JWT_KEYS_DIR", "keys")], "jwt.active_key_id"),
+            (
+                &[("AUTH__ADMIN__BOOTSTRAP_EMAILS", "admin.example.com")],
+                "admin.bootstrap_emails",
+            ),
+            (
+                &[("AUTH__HEALTH__TIMEOUT_MILLISECONDS", "0")],
+                "health.timeout_milliseconds",
+            ),
+            (
+                &[("OTEL_EXPORTER_OTLP_ENDPOINT", "localhost:4318")],
+                "tracing.otlp_endpoint",
+            ),
+            (
+                &[("AUTH__SHUTDOWN__DRAIN_TIMEOUT_SECONDS", "0")],
+                "shutdown.drain_timeout_seconds",
+            ),
+            (&[("AUTH__TLS__CERT_PATH", "cert.pem")], "tls"),
+            (
+                &[
+                    ("AUTH__TLS__CERT_PATH", "missing/cert.pem"),
+                    ("AUTH__TLS__KEY_PATH", "missing/key.pem"),
+                ],
+                "tls.cert_path",
+            ),
+            (
+                &[("AUTH__TLS__REDIRECT_ADDRESS", "0.0.0.0:80")],
+                "tls.redirect_address",
+            ),
+        ];
+
+        for (vars, expected_key) in cases {
+            let vars = [REQUIRED, vars].concat();
+            match load(&vars) {
+                Err(SettingsError::Invalid { key, .. }) => assert_eq!(key, *expected_key),
+                other => panic!("expected `{expected_key}` to be invalid, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_log_provider_needs_no_postmark_settings() {
+        let settings = load(&[
+            ("DATABASE_URL", "postgres://postgres@localhost:5432"),
+            ("AUTH__EMAIL__PROVIDER", "log"),
+        ])
+        .unwrap();
+        assert_eq!(settings.email.provider, EmailProvider::Log);
+    }
+}
@@ -0,0 +1,105 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+
+// Coordinates shutting down. Draining fails readiness so that no new traffic
+// is routed here, stopping closes the listener and tells background tasks to
+// finish what they're doing.
+#[derive(Clone, Default)]
+pub struct Shutdown {
+    draining: Arc<AtomicBool>,
+    stop: CancellationToken,
+    tasks: TaskTracker,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn start_draining(&self) {
+        self.draining.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    pub fn stop(&self) {
+        self.start_draining();
+        self.stop.cancel();
+    }
+
+    // Resolves once `stop` has been called
+    pub async fn stopped(&self) {
+        self.stop.cancelled().await
+    }
+
+    // Background tasks spawned here are waited for on shutdown. They should
+    // return soon after `stopped` resolves.
+    pub fn spawn<F>(&self, task: F) -> tokio::task::JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.tasks.spawn(task)
+    }
+
+    pub async fn wait_for_tasks(&self) {
+        self.tasks.close();
+        self.tasks.wait().await
+    }
+}
+
+// Resolves on SIGINT (Ctrl+C) or SIGTERM, which is what orchestrators send
+pub async fn termination_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to listen for Ctrl+C");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_stop_waits_for_background_tasks() {
+        let shutdown = Shutdown::new();
+        let finished = Arc::new(AtomicBool::new(false));
+
+        let task_shutdown = shutdown.clone();
+        let task_finished = finished.clone();
+        shutdown.spawn(async move {
+            task_shutdown.stopped().await;
+            // Finishing up takes a while
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            task_finished.store(true, Ordering::SeqCst);
+        });
+
+        assert!(!shutdown.is_draining());
+        shutdown.stop();
+        assert!(shutdown.is_draining());
+        shutdown.wait_for_tasks().await;
+        assert!(finished.load(Ordering::SeqCst));
+    }
+}
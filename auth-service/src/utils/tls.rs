@@ -0,0 +1,288 @@
+use super::shutdown::Shutdown;
+use axum::serve::Listener;
+use color_eyre::eyre::{eyre, Result, WrapErr};
+use rustls::crypto::{ring, CryptoProvider};
+use rustls::pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer};
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+use rustls::ServerConfig;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_rustls::{server::TlsStream, TlsAcceptor};
+use tokio_util::either::Either;
+
+// A client that stalls the handshake only holds on to its own task, this
+// bounds how long for
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+// Completed handshakes waiting for the server to pick them up
+const ACCEPT_BACKLOG: usize = 128;
+
+fn crypto_provider() -> Arc<CryptoProvider> {
+    Arc::new(ring::default_provider())
+}
+
+// Read a PEM certificate chain and the private key it was issued for
+pub fn load_certified_key(cert_path: &Path, key_path: &Path) -> Result<CertifiedKey> {
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| eyre!("failed to read {}: {e}", cert_path.display()))?;
+    if certs.is_empty() {
+        return Err(eyre!("no certificates in {}", cert_path.display()));
+    }
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .map_err(|e| eyre!("failed to read {}: {e}", key_path.display()))?;
+
+    CertifiedKey::from_der(certs, key, &crypto_provider())
+        .wrap_err("the private key doesn't match the certificate")
+}
+
+// Hands rustls the certificate loaded from disk, swapping in a new one when
+// the files change so that renewing it doesn't need a restart
+#[derive(Debug)]
+pub struct ReloadingCertResolver {
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    current: RwLock<LoadedCert>,
+}
+
+#[derive(Debug)]
+struct LoadedCert {
+    key: Arc<CertifiedKey>,
+    modified: [Option<SystemTime>; 2],
+}
+
+impl ReloadingCertResolver {
+    pub fn new(cert_path: &Path, key_path: &Path) -> Result<Self> {
+        let modified = modified_times(cert_path, key_path);
+        let key = Arc::new(load_certified_key(cert_path, key_path)?);
+        Ok(Self {
+            cert_path: cert_path.to_owned(),
+            key_path: key_path.to_owned(),
+            current: RwLock::new(LoadedCert { key, modified }),
+        })
+    }
+
+    // Reload if either file was modified since it was last loaded. On failure
+    // the current certificate is kept and the reload retried next time, as
+    // happens when only one of the files has been replaced so far.
+    pub fn reload_if_changed(&self) -> Result<bool> {
+        let modified = modified_times(&self.cert_path, &self.key_path);
+        if self.current.read().unwrap().modified == modified {
+            return Ok(false);
+        }
+
+        let key = Arc::new(load_certified_key(&self.cert_path, &self.key_path)?);
+        *self.current.write().unwrap() = LoadedCert { key, modified };
+        Ok(true)
+    }
+}
+
+impl ResolvesServerCert for ReloadingCertResolver {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(self.current.read().unwrap().key.clone())
+    }
+}
+
+fn modified_times(cert_path: &Path, key_path: &Path) -> [Option<SystemTime>; 2] {
+    [cert_path, key_path].map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+}
+
+pub fn server_config(resolver: Arc<ReloadingCertResolver>) -> Result<Arc<ServerConfig>> {
+    let mut config = ServerConfig::builder_with_provider(crypto_provider())
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_cert_resolver(resolver);
+    // axum is built without HTTP/2 support
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(Arc::new(config))
+}
+
+// Check the certificate files for changes every `period` until shutting down
+pub fn spawn_reload_task(
+    resolver: Arc<ReloadingCertResolver>,
+    period: Duration,
+    shutdown: &Shutdown,
+) -> JoinHandle<()> {
+    let stopped = shutdown.clone();
+    shutdown.spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        // The first tick completes immediately, right after the initial load
+        interval.tick().await;
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = stopped.stopped() => break,
+            }
+            match resolver.reload_if_changed() {
+                Ok(true) => tracing::info!("Reloaded the TLS certificate"),
+                Ok(false) => {}
+                Err(e) => tracing::error!("Failed to reload the TLS certificate: {:#}", e),
+            }
+        }
+    })
+}
+
+// Accepts TCP connections and completes their TLS handshakes in the
+// background, so that a slow client doesn't hold up everyone else's
+pub struct TlsListener {
+    local_addr: SocketAddr,
+    connections: mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+}
+
+impl TlsListener {
+    pub fn new(listener: TcpListener, config: Arc<ServerConfig>) -> std::io::Result<Self> {
+        let local_addr = listener.local_addr()?;
+        let (sender, connections) = mpsc::channel(ACCEPT_BACKLOG);
+        tokio::spawn(accept_connections(
+            listener,
+            TlsAcceptor::from(config),
+            sender,
+        ));
+        Ok(Self {
+            local_addr,
+            connections,
+        })
+    }
+}
+
+// Runs until the `TlsListener` is dropped, which closes the socket
+async fn accept_connections(
+    listener: TcpListener,
+    acceptor: TlsAcceptor,
+    sender: mpsc::Sender<(TlsStream<TcpStream>, SocketAddr)>,
+) {
+    loop {
+        let (stream, remote_addr) = tokio::select! {
+            _ = sender.closed() => return,
+            result = listener.accept() => match result {
+                Ok(connection) => connection,
+                Err(e) => {
+                    // Usually running out of file descriptors, give some
+                    // connections time to close
+                    tracing::warn!("Failed to accept connection: {}", e);
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue;
+                }
+            },
+        };
+
+        let acceptor = acceptor.clone();
+        let sender = sender.clone();
+        tokio::spawn(async move {
+            match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                Ok(Ok(stream)) => {
+                    let _ = sender.send((stream, remote_addr)).await;
+                }
+                Ok(Err(e)) => tracing::debug!("TLS handshake with {} failed: {}", remote_addr, e),
+                Err(_) => tracing::debug!("TLS handshake with {} timed out", remote_addr),
+            }
+        });
+    }
+}
+
+impl Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.connections.recv().await {
+            Some(connection) => connection,
+            // The accept loop only stops once this listener is dropped
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> std::io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
+
+pub type ServerIo = Either<TcpStream, TlsStream<TcpStream>>;
+
+// What `Application` serves on, depending on whether TLS is configured
+pub enum ServerListener {
+    Plain(TcpListener),
+    Tls(TlsListener),
+}
+
+impl Listener for ServerListener {
+    type Io = ServerIo;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self {
+            Self::Plain(listener) => {
+                let (stream, remote_addr) = Listener::accept(listener).await;
+                (Either::Left(stream), remote_addr)
+            }
+            Self::Tls(listener) => {
+                let (stream, remote_addr) = listener.accept().await;
+                (Either::Right(stream), remote_addr)
+            }
+        }
+    }
+
+    fn local_addr(&self) -> std::io::Result<Self::Addr> {
+        match self {
+            Self::Plain(listener) => TcpListener::local_addr(listener),
+            Self::Tls(listener) => Listener::local_addr(listener),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_certificate(dir: &Path) -> (PathBuf, PathBuf, Vec<u8>) {
+        let generated = rcgen::generate_simple_self_signed(vec!["localhost".to_owned()]).unwrap();
+        let cert_path = dir.join("cert.pem");
+        let key_path = dir.join("key.pem");
+        std::fs::write(&cert_path, generated.cert.pem()).unwrap();
+        std::fs::write(&key_path, generated.key_pair.serialize_pem()).unwrap();
+        (cert_path, key_path, generated.cert.der().to_vec())
+    }
+
+    fn served_cert(resolver: &ReloadingCertResolver) -> Vec<u8> {
+        resolver.current.read().unwrap().key.cert[0].to_vec()
+    }
+
+    fn certs_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_mismatched_key_is_rejected() {
+        let (cert_path, _, _) = write_certificate(&certs_dir());
+        let (_, other_key_path, _) = write_certificate(&certs_dir());
+
+        assert!(load_certified_key(&cert_path, &other_key_path).is_err());
+    }
+
+    #[test]
+    fn test_reload_if_changed() {
+        let dir = certs_dir();
+        let (cert_path, key_path, first) = write_certificate(&dir);
+        let resolver = ReloadingCertResolver::new(&cert_path, &key_path).unwrap();
+
+        assert!(!resolver.reload_if_changed().unwrap());
+        assert_eq!(served_cert(&resolver), first);
+
+        // Halfway through replacing the files the old certificate is kept
+        let generated = rcgen::generate_simple_self_signed(vec!["localhost".to_owned()]).unwrap();
+        std::fs::write(&cert_path, generated.cert.pem()).unwrap();
+        assert!(resolver.reload_if_changed().is_err());
+        assert_eq!(served_cert(&resolver), first);
+
+        std::fs::write(&key_path, generated.key_pair.serialize_pem()).unwrap();
+        assert!(resolver.reload_if_changed().unwrap());
+        assert_eq!(served_cert(&resolver), generated.cert.der().to_vec());
+    }
+}
@@ -0,0 +1,134 @@
+use crate::domain::{Email, TotpSecret};
+use color_eyre::eyre::{eyre, Result};
+use secrecy::{ExposeSecret, SecretString};
+use totp_rs::{Algorithm, Secret, TOTP};
+
+// Authenticator apps only support the RFC 6238 defaults reliably, so codes are
+// always 6 digit HMAC-SHA1 codes over a 30 second step.
+pub const TOTP_DIGITS: usize = 6;
+pub const TOTP_STEP_SECONDS: u64 = 30;
+pub const TOTP_ISSUER: &str = "Auth Service";
+
+// 160 bit secret, the length recommended by RFC 4226
+pub fn generate_totp_secret() -> TotpSecret {
+    let bytes: [u8; 20] = rand::random();
+    let encoded = Secret::Raw(bytes.to_vec()).to_encoded().to_string();
+    TotpSecret::parse(SecretString::new(encoded.into_boxed_str()))
+        .expect("generated TOTP secret is valid base32")
+}
+
+// `otpauth://` URI that authenticator apps import, usually from a QR code
+pub fn provisioning_uri(secret: &TotpSecret, email: &Email) -> Result<String> {
+    Ok(totp(secret, 0, email.as_ref().expose_secret().to_owned())?.get_url())
+}
+
+// Check `code` against every step within `drift_steps` of `now` (a Unix
+// timestamp). Returns the step the code belongs to, so callers can refuse to
+// accept the same code twice.
+pub fn verify_totp_code(
+    secret: &TotpSecret,
+    code: &str,
+    now: u64,
+    drift_steps: u8,
+) -> Result<Option<u64>> {
+    if !is_totp_code(code) {
+        return Ok(None);
+    }
+
+    let totp = totp(secret, 0, String::new())?;
+    let current_step = now / TOTP_STEP_SECONDS;
+    let first_step = current_step.saturating_sub(drift_steps as u64);
+    let last_step = current_step + drift_steps as u64;
+
+    Ok((first_step..=last_step).find(|step| {
+        let expected = totp.generate(step * TOTP_STEP_SECONDS);
+        constant_time_eq(expected.as_bytes(), code.as_bytes())
+    }))
+}
+
+pub fn is_totp_code(code: &str) -> bool {
+    code.len() == TOTP_DIGITS && code.chars().all(|c| c.is_ascii_digit())
+}
+
+fn totp(secret: &TotpSecret, skew: u8, account_name: String) -> Result<TOTP> {
+    let bytes = Secret::Encoded(secret.as_ref().expose_secret().to_owned())
+        .to_bytes()
+        .map_err(|e| eyre!("invalid TOTP secret: {e:?}"))?;
+    TOTP::new(
+        Algorithm::SHA1,
+        TOTP_DIGITS,
+        skew,
+        TOTP_STEP_SECONDS,
+        bytes,
+        Some(TOTP_ISSUER.to_owned()),
+        account_name,
+    )
+    .map_err(|e| eyre!("failed to build TOTP: {e}"))
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Secret from the RFC 6238 test vectors ("12345678901234567890")
+    fn rfc_secret() -> TotpSecret {
+        TotpSecret::parse(SecretString::new(
+            "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ"
+                .to_owned()
+                .into_boxed_str(),
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_verify_totp_code_matches_rfc_vectors() {
+        // RFC 6238 lists 94287082 for T = 59; the 6 digit code is its last 6 digits
+        assert_eq!(
+            verify_totp_code(&rfc_secret(), "287082", 59, 0).unwrap(),
+            Some(1)
+        );
+        assert_eq!(
+            verify_totp_code(&rfc_secret(), "081804", 1111111109, 0).unwrap(),
+            Some(1111111109 / TOTP_STEP_SECONDS)
+        );
+    }
+
+    #[test]
+    fn test_verify_totp_code_respects_drift_window() {
+        let now = 1111111109 + TOTP_STEP_SECONDS;
+        assert_eq!(
+            verify_totp_code(&rfc_secret(), "081804", now, 0).unwrap(),
+            None
+        );
+        assert_eq!(
+            verify_totp_code(&rfc_secret(), "081804", now, 1).unwrap(),
+            Some(1111111109 / TOTP_STEP_SECONDS)
+        );
+    }
+
+    #[test]
+    fn test_verify_totp_code_rejects_malformed_codes() {
+        for code in ["", "28708", "2870822", "28708a"] {
+            assert_eq!(verify_totp_code(&rfc_secret(), code, 59, 1).unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn test_provisioning_uri() {
+        let secret = generate_totp_secret();
+        let email = Email::parse(SecretString::new(
+            "test@example.com".to_owned().into_boxed_str(),
+        ))
+        .unwrap();
+
+        let uri = provisioning_uri(&secret, &email).unwrap();
+
+        assert!(uri.starts_with("otpauth://totp/Auth%20Service:test%40example.com?"));
+        assert!(uri.contains(&format!("secret={}", secret.as_ref().expose_secret())));
+        assert!(uri.contains("issuer=Auth%20Service"));
+    }
+}
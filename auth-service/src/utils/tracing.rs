@@ -1,8 +1,14 @@
-use axum::{body::Body, extract::Request, response::Response};
+use super::settings::TracingSettings;
+use axum::{body::Body, extract::Request, http::HeaderMap, response::Response};
 use color_eyre::eyre::Result;
+use opentelemetry::{global, trace::TracerProvider};
+use opentelemetry_http::{HeaderExtractor, HeaderInjector};
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{propagation::TraceContextPropagator, trace::SdkTracerProvider, Resource};
 use std::time::Duration;
 use tracing::{Level, Span};
 use tracing_error::ErrorLayer;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
 use tracing_subscriber::prelude::*;
 use tracing_subscriber::{fmt, EnvFilter};
 
@@ -10,14 +16,34 @@ use tracing_subscriber::{fmt, EnvFilter};
 // This helps in tracking and correlating logs for individual requests.
 pub fn make_span_with_request_id(request: &Request<Body>) -> Span {
     let request_id = uuid::Uuid::new_v4();
-    tracing::span!(
+    let span = tracing::span!(
         Level::INFO,
         "[REQUEST]",
         method = tracing::field::display(request.method()),
         uri = tracing::field::display(request.uri()),
         version = tracing::field::debug(request.version()),
         request_id = tracing::field::display(request_id),
-    )
+    );
+
+    // Continue the caller's trace if it sent a `traceparent` header, otherwise
+    // the span starts a new one. This fails when no OpenTelemetry layer is
+    // installed, e.g. in tests, and there's no trace to continue then anyway.
+    let parent = global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(request.headers()))
+    });
+    let _ = span.set_parent(parent);
+    span
+}
+
+// Headers carrying the current span's trace context, to be added to outgoing
+// requests so that the services they reach join the same trace
+pub fn trace_context_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    let context = Span::current().context();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut HeaderInjector(&mut headers))
+    });
+    headers
 }
 
 // Logs an event indicating the start of a request.
@@ -52,7 +78,38 @@ pub fn on_response(response: &Response, latency: Duration, _span: &Span) {
     };
 }
 
-pub fn init_tracing() -> Result<()> {
+// Keeps the tracer provider alive. Dropping it exports the spans that are
+// still buffered.
+pub struct TracingGuard {
+    provider: SdkTracerProvider,
+}
+
+impl Drop for TracingGuard {
+    fn drop(&mut self) {
+        if let Err(e) = self.provider.shutdown() {
+            eprintln!("Failed to shut down the tracer provider: {e}");
+        }
+    }
+}
+
+// Spans are always given OpenTelemetry trace ids so that trace context can be
+// propagated, but they're only exported if an OTLP endpoint is configured
+pub fn tracer_provider(settings: &TracingSettings) -> Result<SdkTracerProvider> {
+    let resource = Resource::builder()
+        .with_service_name(settings.service_name.clone())
+        .build();
+    let mut builder = SdkTracerProvider::builder().with_resource(resource);
+    if let Some(endpoint) = &settings.otlp_endpoint {
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+            .build()?;
+        builder = builder.with_batch_exporter(exporter);
+    }
+    Ok(builder.build())
+}
+
+pub fn init_tracing(settings: &TracingSettings) -> Result<TracingGuard> {
     // Create a formatting layer for tracing output with a compact format
     let fmt_layer = fmt::layer().compact();
 
@@ -61,13 +118,85 @@ pub fn init_tracing() -> Result<()> {
     // If it fails, default to the "info" log level
     let filter_layer = EnvFilter::try_from_default_env().or_else(|_| EnvFilter::try_new("info"))?;
 
+    // W3C trace context (`traceparent` and `tracestate` headers) is used both
+    // for incoming requests and for the ones we make
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    let provider = tracer_provider(settings)?;
+    let otel_layer =
+        tracing_opentelemetry::layer().with_tracer(provider.tracer(settings.service_name.clone()));
+
     // Build the tracing subscriber registry with the formatting layer,
     // the filter layer, and the error layer for enhanced error reporting
     tracing_subscriber::registry()
         .with(filter_layer) // Add the filter layer to control log verbosity
         .with(fmt_layer) // Add the formatting layer for compact log output
+        .with(otel_layer) // Record spans with OpenTelemetry and export them
         .with(ErrorLayer::default()) // Add the error layer to capture error contexts
         .init(); // Initialize the tracing subscriber
 
-    Ok(())
+    Ok(TracingGuard { provider })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const TRACE_ID: &str = "0af7651916cd43dd8448eb211c80319c";
+
+    fn request_with_traceparent() -> Request<Body> {
+        Request::builder()
+            .uri("/verify-token")
+            .header("traceparent", format!("00-{TRACE_ID}-b7ad6b7169203331-01"))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    // A mock server stands in for the OTLP collector
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_request_span_continues_incoming_trace() {
+        let collector = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/traces"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&collector)
+            .await;
+
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        let provider = tracer_provider(&TracingSettings {
+            otlp_endpoint: Some(collector.uri()),
+            service_name: "auth-service-test".to_owned(),
+        })
+        .unwrap();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+
+        let headers = tracing::subscriber::with_default(subscriber, || {
+            make_span_with_request_id(&request_with_traceparent()).in_scope(trace_context_headers)
+        });
+
+        // Outgoing requests carry the caller's trace id with our span as parent
+        let traceparent = headers["traceparent"].to_str().unwrap();
+        assert!(traceparent.starts_with(&format!("00-{TRACE_ID}-")));
+        assert!(!traceparent.contains("b7ad6b7169203331"));
+
+        // The exporter uses a blocking HTTP client on its own thread
+        tokio::task::spawn_blocking(move || provider.force_flush())
+            .await
+            .unwrap()
+            .unwrap();
+
+        let trace_id = u128::from_str_radix(TRACE_ID, 16).unwrap().to_be_bytes();
+        let requests = collector.received_requests().await.unwrap();
+        assert!(requests
+            .iter()
+            .any(|request| request.body.windows(16).any(|bytes| bytes == trace_id)));
+    }
+
+    #[test]
+    fn test_no_trace_context_without_a_span() {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        assert!(trace_context_headers().is_empty());
+    }
 }
@@ -0,0 +1,351 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_macros::db_test;
+use auth_service::domain::{Email, ErrorCode, UserStore, ADMIN_ROLE, MANAGE_USERS_PERMISSION};
+use auth_service::dto::{AdminUserDetailsResponse, AdminUsersResponse};
+use auth_service::prelude::PostgresUserStore;
+use auth_service::ErrorResponse;
+use secrecy::SecretString;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+const PASSWORD: &str = "123DSDFdasd@@456789";
+
+fn login_body(email: &str) -> serde_json::Value {
+    serde_json::json!({ "email": email, "password": PASSWORD })
+}
+
+async fn signup(app: &TestApp, email: &str) {
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": PASSWORD,
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+}
+
+// Sign up a user holding the admin role and log in as them
+async fn login_admin(app: &TestApp) -> String {
+    let email = get_random_email();
+    signup(app, &email).await;
+    PostgresUserStore::new(app.pg_pool.clone())
+        .add_role(
+            &Email::parse(SecretString::from(email.clone())).unwrap(),
+            ADMIN_ROLE,
+        )
+        .await
+        .expect("Failed to grant role");
+
+    let response = app.post_login(&login_body(&email)).await;
+    assert_eq!(response.status().as_u16(), 200);
+    email
+}
+
+async fn error_code(response: reqwest::Response) -> ErrorCode {
+    response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse")
+        .code
+}
+
+async fn user_details(response: reqwest::Response) -> AdminUserDetailsResponse {
+    assert_eq!(response.status().as_u16(), 200);
+    response
+        .json::<AdminUserDetailsResponse>()
+        .await
+        .expect("Could not deserialize response body to AdminUserDetailsResponse")
+}
+
+#[db_test]
+async fn should_return_400_without_token_and_403_without_permission() {
+    let response = app.get_admin_users("").await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(error_code(response).await, ErrorCode::MissingToken);
+
+    let email = get_random_email();
+    signup(app, &email).await;
+    app.post_login(&login_body(&email)).await;
+
+    let response = app.get_admin_users("").await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(error_code(response).await, ErrorCode::Forbidden);
+
+    let response = app.delete_admin_user(&email).await;
+    assert_eq!(response.status().as_u16(), 403);
+    let response = app.get_admin_user(&email).await;
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn should_grant_admin_role_to_bootstrap_admin_once_verified() {
+    let email = get_random_email();
+    let app = TestApp::with_bootstrap_admin(&email).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    signup(&app, &email).await;
+
+    // Only a verified email proves the user owns it
+    app.post_login(&login_body(&email)).await;
+    let response = app.get_admin_users("").await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let token = app.get_token_from_last_email().await;
+    let response = app
+        .post_verify_email(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Roles are read into the token at login
+    app.post_login(&login_body(&email)).await;
+    let details = user_details(app.get_admin_user(&email).await).await;
+    assert_eq!(details.roles, vec![ADMIN_ROLE.to_owned()]);
+
+    app.clean_up().await;
+}
+
+#[db_test]
+async fn should_list_users_with_search_and_pagination() {
+    let admin = login_admin(app).await;
+    let tag = uuid::Uuid::new_v4().simple().to_string();
+    let mut tagged: Vec<String> = (0..3)
+        .map(|i| format!("user{i}-{tag}@example.com"))
+        .collect();
+    for email in &tagged {
+        signup(app, email).await;
+    }
+    tagged.sort();
+
+    let response = app.get_admin_users("").await;
+    assert_eq!(response.status().as_u16(), 200);
+    let page = response
+        .json::<AdminUsersResponse>()
+        .await
+        .expect("Could not deserialize response body to AdminUsersResponse");
+    assert_eq!(page.total, 4);
+    assert_eq!((page.limit, page.offset), (50, 0));
+    assert!(page.users.iter().any(|user| user.email == admin));
+
+    let response = app
+        .get_admin_users(&format!("email={}&limit=2&offset=1", tag.to_uppercase()))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let page = response
+        .json::<AdminUsersResponse>()
+        .await
+        .expect("Could not deserialize response body to AdminUsersResponse");
+    assert_eq!(page.total, 3);
+    let emails: Vec<_> = page.users.iter().map(|user| user.email.clone()).collect();
+    assert_eq!(emails, tagged[1..]);
+
+    let response = app.get_admin_users("limit=0&offset=-1").await;
+    assert_eq!(response.status().as_u16(), 400);
+    let body = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(body.code, ErrorCode::InvalidInput);
+    let fields: Vec<_> = body.errors.iter().map(|e| e.field.as_str()).collect();
+    assert_eq!(fields, ["limit", "offset"]);
+}
+
+#[db_test]
+async fn should_return_user_details_or_404() {
+    let admin = login_admin(app).await;
+
+    let details = user_details(app.get_admin_user(&admin).await).await;
+    assert_eq!(details.user.email, admin);
+    assert!(!details.user.disabled);
+    assert_eq!(details.roles, vec![ADMIN_ROLE.to_owned()]);
+    assert_eq!(
+        details.permissions,
+        vec![MANAGE_USERS_PERMISSION.to_owned()]
+    );
+
+    let response = app.get_admin_user(&get_random_email()).await;
+    assert_eq!(response.status().as_u16(), 404);
+    assert_eq!(error_code(response).await, ErrorCode::UserNotFound);
+
+    let response = app.get_admin_user("not-an-email").await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(error_code(response).await, ErrorCode::InvalidInput);
+}
+
+#[db_test]
+async fn should_block_login_while_disabled() {
+    login_admin(app).await;
+    let email = get_random_email();
+    signup(app, &email).await;
+
+    // A second device logged in as the user loses its session
+    let user_client = reqwest::Client::builder()
+        .cookie_store(true)
+        .build()
+        .unwrap();
+    let response = user_client
+        .post(format!("{}/login", &app.address))
+        .json(&login_body(&email))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+
+    let details = user_details(app.post_admin_user_action(&email, "disable").await).await;
+    assert!(details.user.disabled);
+
+    let response = user_client
+        .post(format!("{}/refresh", &app.address))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = user_client
+        .post(format!("{}/login", &app.address))
+        .json(&login_body(&email))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(error_code(response).await, ErrorCode::AccountDisabled);
+
+    let details = user_details(app.post_admin_user_action(&email, "enable").await).await;
+    assert!(!details.user.disabled);
+
+    let response = user_client
+        .post(format!("{}/login", &app.address))
+        .json(&login_body(&email))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[db_test]
+async fn should_require_and_waive_2fa() {
+    login_admin(app).await;
+    let email = get_random_email();
+    signup(app, &email).await;
+
+    let details = user_details(
+        app.post_admin_user_2fa(&email, &serde_json::json!({ "required": true }))
+            .await,
+    )
+    .await;
+    assert!(details.user.requires_2fa);
+
+    let details = user_details(
+        app.post_admin_user_2fa(&email, &serde_json::json!({ "required": false }))
+            .await,
+    )
+    .await;
+    assert!(!details.user.requires_2fa);
+
+    let response = app
+        .post_admin_user_2fa(
+            &get_random_email(),
+            &serde_json::json!({ "required": true }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[db_test]
+async fn should_force_password_reset() {
+    login_admin(app).await;
+    let email = get_random_email();
+    signup(app, &email).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let details = user_details(app.post_admin_user_action(&email, "password-reset").await).await;
+    assert!(details.user.password_reset_required);
+
+    let response = app.post_login(&login_body(&email)).await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(error_code(response).await, ErrorCode::PasswordResetRequired);
+
+    let token = app.get_token_from_last_email().await;
+    let new_password = "New123DSDFdasd@@456";
+    let response = app
+        .post_password_reset_confirm(&serde_json::json!({
+            "token": token,
+            "newPassword": new_password,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_login(&serde_json::json!({ "email": email, "password": new_password }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[db_test]
+async fn should_delete_user() {
+    login_admin(app).await;
+    let email = get_random_email();
+    signup(app, &email).await;
+
+    let response = app.delete_admin_user(&email).await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = app.get_admin_user(&email).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    let response = app.delete_admin_user(&email).await;
+    assert_eq!(response.status().as_u16(), 404);
+    assert_eq!(error_code(response).await, ErrorCode::UserNotFound);
+
+    // The email is free to sign up with again
+    signup(app, &email).await;
+}
+
+#[db_test]
+async fn should_invalidate_pending_tokens_of_deleted_user() {
+    login_admin(app).await;
+    let email = get_random_email();
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(3)
+        .mount(&app.email_server)
+        .await;
+
+    signup(app, &email).await;
+    let verification_token = app.get_token_from_last_email().await;
+    let response = app
+        .post_password_reset_request(&serde_json::json!({ "email": email }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let reset_token = app.get_token_from_last_email().await;
+
+    let response = app.delete_admin_user(&email).await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    // Neither token carries over to a new account with the same email
+    signup(app, &email).await;
+    let response = app
+        .post_password_reset_confirm(&serde_json::json!({
+            "token": reset_token,
+            "newPassword": "New123DSDFdasd@@456",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(error_code(response).await, ErrorCode::InvalidToken);
+
+    let response = app
+        .post_verify_email(&serde_json::json!({ "token": verification_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(error_code(response).await, ErrorCode::InvalidToken);
+}
@@ -0,0 +1,160 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::domain::ErrorCode;
+use auth_service::dto::{PasswordResetResponse, SignupResponse};
+use auth_service::ErrorResponse;
+use std::time::Duration;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+const PASSWORD: &str = "123DSDFdasd@@456789";
+const WRONG_PASSWORD: &str = "Wrong123DSDFdasd@@456";
+
+async fn mock_email_server(app: &TestApp) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+}
+
+async fn signup(app: &TestApp, email: &str, password: &str) -> reqwest::Response {
+    app.post_signup(&serde_json::json!({
+        "email": email,
+        "password": password,
+        "requires2FA": false
+    }))
+    .await
+}
+
+// Emails are sent after responding, so they may take a moment to arrive
+async fn wait_for_emails(app: &TestApp, count: usize) -> Vec<serde_json::Value> {
+    for _ in 0..50 {
+        let requests = app
+            .email_server
+            .received_requests()
+            .await
+            .expect("Request recording is disabled");
+        if requests.len() >= count {
+            return requests
+                .iter()
+                .map(|request| request.body_json().expect("Email body is not JSON"))
+                .collect();
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("Expected {count} emails to be sent");
+}
+
+#[tokio::test]
+async fn should_reject_unknown_user_like_a_wrong_password() {
+    let app = TestApp::with_enumeration_protection().await;
+    mock_email_server(&app).await;
+    let random_email = get_random_email();
+    assert_eq!(signup(&app, &random_email, PASSWORD).await.status(), 201);
+
+    let wrong_password = app
+        .post_login(&serde_json::json!({ "email": random_email, "password": WRONG_PASSWORD }))
+        .await;
+    let unknown_user = app
+        .post_login(&serde_json::json!({ "email": get_random_email(), "password": WRONG_PASSWORD }))
+        .await;
+
+    assert_eq!(wrong_password.status().as_u16(), 401);
+    assert_eq!(unknown_user.status().as_u16(), 401);
+    let wrong_password = wrong_password
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    let unknown_user = unknown_user
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(unknown_user.code, ErrorCode::IncorrectCredentials);
+    assert_eq!(unknown_user.code, wrong_password.code);
+    assert_eq!(unknown_user.detail, wrong_password.detail);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_answer_signup_with_a_taken_email_like_a_new_account() {
+    let app = TestApp::with_enumeration_protection().await;
+    mock_email_server(&app).await;
+    let random_email = get_random_email();
+
+    let created = signup(&app, &random_email, PASSWORD).await;
+    assert_eq!(created.status().as_u16(), 201);
+    let created = created
+        .json::<SignupResponse>()
+        .await
+        .expect("Could not deserialize response body to SignupResponse");
+    wait_for_emails(&app, 1).await;
+
+    let taken = signup(&app, &random_email, WRONG_PASSWORD).await;
+    assert_eq!(taken.status().as_u16(), 201);
+    let taken = taken
+        .json::<SignupResponse>()
+        .await
+        .expect("Could not deserialize response body to SignupResponse");
+    assert_eq!(taken, created);
+
+    // The owner is told instead
+    let emails = wait_for_emails(&app, 2).await;
+    assert_eq!(emails[1]["To"], random_email.as_str());
+    assert!(emails[1]["TextBody"]
+        .as_str()
+        .expect("Email has no text body")
+        .contains("already has one"));
+
+    // And the account is untouched
+    let response = app
+        .post_login(&serde_json::json!({ "email": random_email, "password": PASSWORD }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_send_password_reset_email_after_responding() {
+    let app = TestApp::with_enumeration_protection().await;
+    mock_email_server(&app).await;
+    let random_email = get_random_email();
+    assert_eq!(signup(&app, &random_email, PASSWORD).await.status(), 201);
+    wait_for_emails(&app, 1).await;
+
+    let unknown = app
+        .post_password_reset_request(&serde_json::json!({ "email": get_random_email() }))
+        .await;
+    let known = app
+        .post_password_reset_request(&serde_json::json!({ "email": random_email }))
+        .await;
+
+    assert_eq!(unknown.status().as_u16(), 200);
+    assert_eq!(known.status().as_u16(), 200);
+    assert_eq!(
+        unknown
+            .json::<PasswordResetResponse>()
+            .await
+            .expect("Could not deserialize response body to PasswordResetResponse"),
+        known
+            .json::<PasswordResetResponse>()
+            .await
+            .expect("Could not deserialize response body to PasswordResetResponse")
+    );
+
+    // Only the existing account gets an email, with a token that works
+    let emails = wait_for_emails(&app, 2).await;
+    assert_eq!(emails.len(), 2);
+    assert_eq!(emails[1]["To"], random_email.as_str());
+    let token = app.get_token_from_last_email().await;
+    let response = app
+        .post_password_reset_confirm(&serde_json::json!({
+            "token": token,
+            "newPassword": "New123DSDFdasd@@456",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
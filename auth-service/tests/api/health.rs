@@ -0,0 +1,135 @@
+use crate::helpers::TestApp;
+use auth_macros::db_test;
+use auth_service::app_state::UnverifiedLoginPolicy;
+use auth_service::dto::{HealthResponse, HealthStatus};
+use auth_service::prelude::{AppState, Application, HealthCheck};
+use auth_service::utils::constants::test;
+use auth_service::utils::settings::settings;
+use color_eyre::eyre::{eyre, Result};
+use std::sync::Arc;
+
+#[db_test]
+async fn should_return_200_when_live() {
+    let response = app.get_health_live().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let body = response
+        .json::<HealthResponse>()
+        .await
+        .expect("Could not deserialize response body to HealthResponse");
+    assert_eq!(body.status, HealthStatus::Up);
+    assert!(body.checks.is_empty());
+}
+
+#[db_test]
+async fn should_report_each_dependency_when_ready() {
+    let response = app.get_health_ready().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let body = response
+        .json::<HealthResponse>()
+        .await
+        .expect("Could not deserialize response body to HealthResponse");
+    assert_eq!(body.status, HealthStatus::Up);
+    for name in ["postgres", "redis"] {
+        let check = body.checks.get(name).expect("dependency not reported");
+        assert_eq!(check.status, HealthStatus::Up);
+        assert!(check.error.is_none());
+    }
+}
+
+struct StubHealthCheck {
+    name: &'static str,
+    outcome: Outcome,
+}
+
+enum Outcome {
+    Up,
+    Fail,
+    Hang,
+}
+
+#[async_trait::async_trait]
+impl HealthCheck for StubHealthCheck {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    async fn check(&self) -> Result<()> {
+        match self.outcome {
+            Outcome::Up => Ok(()),
+            Outcome::Fail => Err(eyre!("connection refused")),
+            Outcome::Hang => std::future::pending().await,
+        }
+    }
+}
+
+async fn spawn_app(app_state: AppState) -> String {
+    let app = Application::build(app_state, test::APP_ADDRESS)
+        .await
+        .expect("Failed to build app");
+    let address = format!("http://{}", app.address);
+    #[allow(clippy::let_underscore_future)]
+    let _ = tokio::spawn(app.run());
+    address
+}
+
+fn in_memory_app_state() -> AppState {
+    AppState::in_memory(
+        UnverifiedLoginPolicy::Allow,
+        settings().login.throttle_policy(),
+    )
+}
+
+#[tokio::test]
+async fn should_return_503_when_a_dependency_is_down() {
+    let app_state = in_memory_app_state().with_health_checks(vec![
+        Arc::new(StubHealthCheck {
+            name: "up",
+            outcome: Outcome::Up,
+        }),
+        Arc::new(StubHealthCheck {
+            name: "failing",
+            outcome: Outcome::Fail,
+        }),
+        Arc::new(StubHealthCheck {
+            name: "hanging",
+            outcome: Outcome::Hang,
+        }),
+    ]);
+    let address = spawn_app(app_state).await;
+
+    let response = reqwest::get(format!("{address}/health/ready"))
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status().as_u16(), 503);
+    let body = response
+        .json::<HealthResponse>()
+        .await
+        .expect("Could not deserialize response body to HealthResponse");
+    assert_eq!(body.status, HealthStatus::Down);
+
+    assert_eq!(body.checks["up"].status, HealthStatus::Up);
+
+    let failing = &body.checks["failing"];
+    assert_eq!(failing.status, HealthStatus::Down);
+    assert_eq!(failing.error.as_deref(), Some("connection refused"));
+
+    let hanging = &body.checks["hanging"];
+    assert_eq!(hanging.status, HealthStatus::Down);
+    assert!(hanging.error.as_deref().unwrap().starts_with("timed out"));
+    assert!(hanging.latency_ms >= settings().health.timeout_milliseconds);
+}
+
+// In-memory stores have nothing to check
+#[tokio::test]
+async fn should_be_ready_without_dependencies() {
+    let address = spawn_app(in_memory_app_state()).await;
+
+    let response = reqwest::get(format!("{address}/health/ready"))
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status().as_u16(), 200);
+}
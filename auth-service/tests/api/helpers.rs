@@ -1,12 +1,20 @@
-use auth_service::app_state::TwoFACodeType;
+use auth_service::app_state::{
+    BannedTokenType, EmailVerificationTokenType, HealthCheckType, LoginAttemptType,
+    PasswordResetTokenType, RefreshTokenType, SessionType, TwoFACodeType, UnverifiedLoginPolicy,
+};
 use auth_service::domain::Email;
 use auth_service::prelude::{
-    AppState, Application, PostgresUserStore, PostmarkEmailClient, RedisBannedTokenStore,
-    RedisTwoFACodeStore,
+    AppState, Application, HashmapLoginAttemptStore, PostgresRecoveryCodeStore, PostgresTotpStore,
+    PostgresUserStore, PostmarkEmailClient, RedisBannedTokenStore,
+    RedisEmailVerificationTokenStore, RedisPasswordResetTokenStore, RedisRefreshTokenStore,
+    RedisSessionStore, RedisTwoFACodeStore,
 };
+use auth_service::services::health_checks::{PostgresHealthCheck, RedisHealthCheck};
+use auth_service::services::purge_task::PostgresTokenStores;
 use auth_service::utils::constants::test;
-use auth_service::utils::constants::{DATABASE_URL, REDIS_HOST_NAME};
-use auth_service::{get_postgres_pool, get_redis_client};
+use auth_service::utils::login_throttle::LoginThrottlePolicy;
+use auth_service::utils::settings::settings;
+use auth_service::{get_postgres_pool, get_redis_connection_manager};
 use reqwest::cookie::Jar;
 use reqwest::Client;
 use secrecy::{ExposeSecret, SecretString};
@@ -14,7 +22,7 @@ use sqlx::postgres::{PgConnectOptions, PgConnection, PgPoolOptions};
 use sqlx::{Connection, Executor, PgPool};
 use std::str::FromStr;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use totp_rs::{Algorithm, Secret, TOTP};
 use uuid::Uuid;
 use wiremock::MockServer;
 
@@ -22,6 +30,7 @@ pub struct TestApp {
     pub address: String,
     pub cookie_jar: Arc<Jar>,
     pub two_fa_code_store: TwoFACodeType,
+    pub pg_pool: PgPool,
     pub http_client: reqwest::Client,
     pub email_server: MockServer,
     cleanup_called: bool,
@@ -39,31 +48,156 @@ impl Drop for TestApp {
     }
 }
 
-fn configure_redis() -> redis::Connection {
-    get_redis_client(REDIS_HOST_NAME.to_owned())
-        .expect("Failed to get Redis client")
-        .get_connection()
+async fn configure_redis() -> redis::aio::ConnectionManager {
+    get_redis_connection_manager(settings().stores.redis_host_name.clone())
+        .await
         .expect("Failed to get Redis connection")
 }
 
 impl TestApp {
+    // Most tests sign up and log in straight away, so they don't bother
+    // verifying the email address first.
     pub async fn new() -> Self {
+        Self::with_unverified_login_policy(UnverifiedLoginPolicy::Allow).await
+    }
+
+    pub async fn with_unverified_login_policy(
+        unverified_login_policy: UnverifiedLoginPolicy,
+    ) -> Self {
+        Self::build(
+            unverified_login_policy,
+            settings().login.throttle_policy(),
+            false,
+            false,
+            Vec::new(),
+        )
+        .await
+    }
+
+    pub async fn with_login_throttle_policy(login_throttle_policy: LoginThrottlePolicy) -> Self {
+        Self::build(
+            UnverifiedLoginPolicy::Allow,
+            login_throttle_policy,
+            false,
+            false,
+            Vec::new(),
+        )
+        .await
+    }
+
+    // Banned tokens and 2FA codes are kept in Postgres instead of Redis
+    pub async fn with_postgres_token_stores() -> Self {
+        Self::build(
+            UnverifiedLoginPolicy::Allow,
+            settings().login.throttle_policy(),
+            true,
+            false,
+            Vec::new(),
+        )
+        .await
+    }
+
+    pub async fn with_enumeration_protection() -> Self {
+        Self::build(
+            UnverifiedLoginPolicy::Allow,
+            settings().login.throttle_policy(),
+            false,
+            true,
+            Vec::new(),
+        )
+        .await
+    }
+
+    // Verifying `email` grants it the admin role
+    pub async fn with_bootstrap_admin(email: &str) -> Self {
+        Self::build(
+            UnverifiedLoginPolicy::Allow,
+            settings().login.throttle_policy(),
+            false,
+            false,
+            vec![Email::parse(SecretString::from(email.to_owned())).unwrap()],
+        )
+        .await
+    }
+
+    async fn build(
+        unverified_login_policy: UnverifiedLoginPolicy,
+        login_throttle_policy: LoginThrottlePolicy,
+        postgres_token_stores: bool,
+        enumeration_protection: bool,
+        bootstrap_admins: Vec<Email>,
+    ) -> Self {
         let (pg_pool, db_name) = configure_postgresql().await;
-        let redis_conn = Arc::new(RwLock::new(configure_redis()));
-        let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
-        let banned_token_store =
-            Arc::new(RwLock::new(RedisBannedTokenStore::new(redis_conn.clone())));
-        let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_conn)));
+        let user_store = Arc::new(PostgresUserStore::new(pg_pool.clone()));
+        let totp_store = Arc::new(PostgresTotpStore::new(pg_pool.clone()));
+        let recovery_code_store = Arc::new(PostgresRecoveryCodeStore::new(pg_pool.clone()));
+        let mut health_checks: Vec<HealthCheckType> =
+            vec![Arc::new(PostgresHealthCheck::new(pg_pool.clone()))];
+        // Like main, Redis isn't connected to at all with Postgres token stores
+        let (
+            banned_token_store,
+            refresh_token_store,
+            session_store,
+            password_reset_token_store,
+            email_verification_token_store,
+            two_fa_code_store,
+            login_attempt_store,
+        ): (
+            BannedTokenType,
+            RefreshTokenType,
+            SessionType,
+            PasswordResetTokenType,
+            EmailVerificationTokenType,
+            TwoFACodeType,
+            LoginAttemptType,
+        ) = if postgres_token_stores {
+            let stores = PostgresTokenStores::new(pg_pool.clone());
+            (
+                stores.banned_token_store,
+                stores.refresh_token_store,
+                stores.session_store,
+                stores.password_reset_token_store,
+                stores.email_verification_token_store,
+                stores.two_fa_code_store,
+                stores.login_attempt_store,
+            )
+        } else {
+            let redis_conn = configure_redis().await;
+            health_checks.push(Arc::new(RedisHealthCheck::new(redis_conn.clone())));
+            (
+                Arc::new(RedisBannedTokenStore::new(redis_conn.clone())),
+                Arc::new(RedisRefreshTokenStore::new(redis_conn.clone())),
+                Arc::new(RedisSessionStore::new(redis_conn.clone())),
+                Arc::new(RedisPasswordResetTokenStore::new(redis_conn.clone())),
+                Arc::new(RedisEmailVerificationTokenStore::new(redis_conn.clone())),
+                Arc::new(RedisTwoFACodeStore::new(redis_conn)),
+                // Every test logs in from the same IP, so failures aren't
+                // shared through Redis
+                Arc::new(HashmapLoginAttemptStore::new()),
+            )
+        };
         let email_server = MockServer::start().await; // New!
         let base_url = email_server.uri(); // New!
-        let email_client = Arc::new(RwLock::new(configure_postmark_email_client(base_url)));
+        let email_client = Arc::new(configure_postmark_email_client(base_url));
 
         let app_state = AppState::new(
             user_store,
-            banned_token_store.clone(),
+            banned_token_store,
+            refresh_token_store,
+            session_store,
+            password_reset_token_store,
+            email_verification_token_store,
             two_fa_code_store.clone(),
+            login_attempt_store,
+            totp_store,
+            recovery_code_store,
             email_client,
-        );
+            unverified_login_policy,
+            login_throttle_policy,
+        )
+        .with_health_checks(health_checks)
+        .with_enumeration_protection(enumeration_protection)
+        .with_bootstrap_admins(bootstrap_admins);
 
         let app = Application::build(app_state, test::APP_ADDRESS)
             .await
@@ -87,6 +221,7 @@ impl TestApp {
             cookie_jar,
             http_client,
             two_fa_code_store,
+            pg_pool,
             cleanup_called: false,
             db_name,
             email_server,
@@ -101,6 +236,38 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn get_jwks(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/.well-known/jwks.json", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_health_live(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/health/live", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_health_ready(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/health/ready", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_metrics(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/metrics", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn post_signup<Body>(&self, body: &Body) -> reqwest::Response
     where
         Body: serde::Serialize,
@@ -133,6 +300,114 @@ impl TestApp {
             .expect("Failed to logout")
     }
 
+    pub async fn post_refresh(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/refresh", &self.address))
+            .send()
+            .await
+            .expect("Failed to refresh")
+    }
+
+    pub async fn get_sessions(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/sessions", &self.address))
+            .send()
+            .await
+            .expect("Failed to list sessions")
+    }
+
+    pub async fn delete_session(&self, id: &str) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/sessions/{}", &self.address, id))
+            .send()
+            .await
+            .expect("Failed to revoke session")
+    }
+
+    pub async fn delete_sessions(&self) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/sessions", &self.address))
+            .send()
+            .await
+            .expect("Failed to revoke sessions")
+    }
+
+    pub async fn post_password_reset_request<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/password-reset/request", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to request password reset")
+    }
+
+    pub async fn post_password_reset_confirm<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/password-reset/confirm", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to confirm password reset")
+    }
+
+    pub async fn post_verify_email<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/verify-email", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to verify email")
+    }
+
+    pub async fn post_resend_verification_email<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/verify-email/resend", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to resend verification email")
+    }
+
+    pub async fn post_totp_enroll(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/2fa/totp/enroll", &self.address))
+            .send()
+            .await
+            .expect("Failed to enroll TOTP")
+    }
+
+    pub async fn post_totp_confirm<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/2fa/totp/confirm", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to confirm TOTP")
+    }
+
+    pub async fn post_regenerate_recovery_codes(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/2fa/recovery-codes", &self.address))
+            .send()
+            .await
+            .expect("Failed to regenerate recovery codes")
+    }
+
     pub async fn post_verify_2fa<Body>(&self, body: &Body) -> reqwest::Response
     where
         Body: serde::Serialize,
@@ -157,6 +432,93 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    // Send a body as is, for requests the typed helpers can't make
+    pub async fn get_admin_users(&self, query: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/admin/users?{}", &self.address, query))
+            .send()
+            .await
+            .expect("Failed to list users")
+    }
+
+    pub async fn get_admin_user(&self, email: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/admin/users/{}", &self.address, email))
+            .send()
+            .await
+            .expect("Failed to get user")
+    }
+
+    // `action` is one of disable, enable or password-reset
+    pub async fn post_admin_user_action(&self, email: &str, action: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!(
+                "{}/admin/users/{}/{}",
+                &self.address, email, action
+            ))
+            .send()
+            .await
+            .expect("Failed to run admin action")
+    }
+
+    pub async fn post_admin_user_2fa<Body>(&self, email: &str, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/admin/users/{}/2fa", &self.address, email))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to set user 2FA")
+    }
+
+    pub async fn delete_admin_user(&self, email: &str) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/admin/users/{}", &self.address, email))
+            .send()
+            .await
+            .expect("Failed to delete user")
+    }
+
+    pub async fn post_raw(
+        &self,
+        path: &str,
+        content_type: &str,
+        body: impl Into<reqwest::Body>,
+    ) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}{}", &self.address, path))
+            .header(reqwest::header::CONTENT_TYPE, content_type)
+            .body(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // Pull the token out of the last email the mock Postmark server received.
+    // Every token email reads "Your ... token is <token>. ..."
+    pub async fn get_token_from_last_email(&self) -> String {
+        let requests = self
+            .email_server
+            .received_requests()
+            .await
+            .expect("Request recording is disabled");
+        let body: serde_json::Value = requests
+            .last()
+            .expect("No email was sent")
+            .body_json()
+            .expect("Email body is not JSON");
+        body["TextBody"]
+            .as_str()
+            .expect("Email has no text body")
+            .split(" token is ")
+            .nth(1)
+            .and_then(|rest| rest.split('.').next())
+            .expect("Email has no token")
+            .to_owned()
+    }
+
     pub async fn clean_up(mut self) {
         self.cleanup_called = true;
 
@@ -168,8 +530,26 @@ pub fn get_random_email() -> String {
     format!("{}@example.com", Uuid::new_v4())
 }
 
+// Code an authenticator app would show `offset_steps` 30 second steps from now
+pub fn get_totp_code(secret: &str, offset_steps: i64) -> String {
+    let bytes = Secret::Encoded(secret.to_owned())
+        .to_bytes()
+        .expect("Invalid TOTP secret");
+    let totp = TOTP::new_unchecked(Algorithm::SHA1, 6, 0, 30, bytes, None, String::new());
+    let now = chrono::Utc::now().timestamp();
+    totp.generate((now + offset_steps * 30) as u64)
+}
+
+fn database_url() -> &'static SecretString {
+    settings()
+        .stores
+        .database_url
+        .as_ref()
+        .expect("DATABASE_URL must be set.")
+}
+
 async fn configure_postgresql() -> (PgPool, String) {
-    let base_db_url: &SecretString = &DATABASE_URL;
+    let base_db_url = database_url();
     let db_name = Uuid::new_v4().to_string();
 
     configure_database(base_db_url, &db_name).await;
@@ -214,7 +594,7 @@ async fn configure_database(base_db_url: &SecretString, db_name: &str) {
 }
 
 async fn delete_database(db_name: &str) {
-    let postgresql_conn_url: &SecretString = &DATABASE_URL;
+    let postgresql_conn_url = database_url();
 
     let connection_options = PgConnectOptions::from_str(postgresql_conn_url.expose_secret())
         .expect("Failed to parse PostgreSQL connection string");
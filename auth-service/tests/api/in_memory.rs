@@ -0,0 +1,107 @@
+use crate::helpers::get_random_email;
+use auth_service::app_state::UnverifiedLoginPolicy;
+use auth_service::domain::Email;
+use auth_service::dto::{SessionsResponse, TwoFactorAuthResponse};
+use auth_service::prelude::{AppState, Application};
+use auth_service::utils::constants::{test, JWT_COOKIE_NAME};
+use auth_service::utils::settings::settings;
+use secrecy::ExposeSecret;
+
+// The whole service runs without Postgres, Redis or Postmark
+#[tokio::test]
+async fn should_run_full_login_flow_in_memory() {
+    let app_state = AppState::in_memory(
+        UnverifiedLoginPolicy::Allow,
+        settings().login.throttle_policy(),
+    );
+    let two_fa_code_store = app_state.two_fa_code_store.clone();
+    let app = Application::build(app_state, test::APP_ADDRESS)
+        .await
+        .expect("Failed to build app");
+    let address = format!("http://{}", app.address);
+    #[allow(clippy::let_underscore_future)]
+    let _ = tokio::spawn(app.run());
+
+    let client = reqwest::Client::builder()
+        .cookie_store(true)
+        .build()
+        .unwrap();
+    let random_email = get_random_email();
+
+    let response = client
+        .post(format!("{address}/signup"))
+        .json(&serde_json::json!({
+            "email": random_email,
+            "password": "123DSDFdasd@@456789",
+            "requires2FA": true
+        }))
+        .send()
+        .await
+        .expect("Failed to signup");
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = client
+        .post(format!("{address}/login"))
+        .json(&serde_json::json!({
+            "email": random_email,
+            "password": "123DSDFdasd@@456789",
+        }))
+        .send()
+        .await
+        .expect("Failed to login");
+    assert_eq!(response.status().as_u16(), 206);
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+
+    let email = Email::parse(random_email.clone().into()).unwrap();
+    let (_, code) = two_fa_code_store.get_two_fa_code(&email).await.unwrap();
+
+    let response = client
+        .post(format!("{address}/verify-2fa"))
+        .json(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": code.as_ref().expose_secret(),
+        }))
+        .send()
+        .await
+        .expect("Failed to verify 2FA");
+    assert_eq!(response.status().as_u16(), 200);
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    let response = client
+        .get(format!("{address}/sessions"))
+        .send()
+        .await
+        .expect("Failed to list sessions");
+    assert_eq!(response.status().as_u16(), 200);
+    let sessions = response
+        .json::<SessionsResponse>()
+        .await
+        .expect("Could not deserialize response body to SessionsResponse")
+        .sessions;
+    assert_eq!(sessions.len(), 1);
+
+    let response = client
+        .post(format!("{address}/logout"))
+        .send()
+        .await
+        .expect("Failed to logout");
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = client
+        .post(format!("{address}/verify-token"))
+        .json(&serde_json::json!({ "token": token }))
+        .send()
+        .await
+        .expect("Failed to verify token");
+    assert_eq!(response.status().as_u16(), 401);
+}
@@ -0,0 +1,227 @@
+use crate::helpers::TestApp;
+use auth_macros::db_test;
+use auth_service::domain::ErrorCode;
+use auth_service::utils::constants::{MAX_REQUEST_BODY_BYTES, PROBLEM_JSON_CONTENT_TYPE};
+use auth_service::ErrorResponse;
+use serde_json::{json, Value};
+
+struct JsonRoute {
+    path: &'static str,
+    // Lacks the field named by `missing`
+    missing_field: Value,
+    missing: &'static str,
+    // Has a value of the wrong type for `wrong_type_field`
+    wrong_type: Value,
+    wrong_type_field: &'static str,
+}
+
+fn json_routes() -> Vec<JsonRoute> {
+    vec![
+        JsonRoute {
+            path: "/signup",
+            missing_field: json!({ "password": "123DSDFdasd@@456789", "requires2FA": false }),
+            missing: "email",
+            wrong_type: json!({
+                "email": "user@example.com",
+                "password": "123DSDFdasd@@456789",
+                "requires2FA": "yes"
+            }),
+            wrong_type_field: "requires2FA",
+        },
+        JsonRoute {
+            path: "/login",
+            missing_field: json!({ "email": "user@example.com" }),
+            missing: "password",
+            wrong_type: json!({ "email": 42, "password": "123DSDFdasd@@456789" }),
+            wrong_type_field: "email",
+        },
+        JsonRoute {
+            path: "/verify-2fa",
+            missing_field: json!({
+                "email": "user@example.com",
+                "loginAttemptId": "c9a2865b-467d-498b-93b8-634903ae68e0"
+            }),
+            missing: "2FACode",
+            wrong_type: json!({
+                "email": "user@example.com",
+                "loginAttemptId": "c9a2865b-467d-498b-93b8-634903ae68e0",
+                "2FACode": 302912
+            }),
+            wrong_type_field: "2FACode",
+        },
+        JsonRoute {
+            path: "/verify-token",
+            missing_field: json!({}),
+            missing: "token",
+            wrong_type: json!({ "token": ["abc"] }),
+            wrong_type_field: "token",
+        },
+        JsonRoute {
+            path: "/verify-email",
+            missing_field: json!({}),
+            missing: "token",
+            wrong_type: json!({ "token": true }),
+            wrong_type_field: "token",
+        },
+        JsonRoute {
+            path: "/verify-email/resend",
+            missing_field: json!({}),
+            missing: "email",
+            wrong_type: json!({ "email": null }),
+            wrong_type_field: "email",
+        },
+        JsonRoute {
+            path: "/password-reset/request",
+            missing_field: json!({}),
+            missing: "email",
+            wrong_type: json!({ "email": { "address": "user@example.com" } }),
+            wrong_type_field: "email",
+        },
+        JsonRoute {
+            path: "/password-reset/confirm",
+            missing_field: json!({ "token": "abc" }),
+            missing: "newPassword",
+            wrong_type: json!({ "token": 1, "newPassword": "New123DSDFdasd@@456" }),
+            wrong_type_field: "token",
+        },
+        JsonRoute {
+            path: "/2fa/totp/confirm",
+            missing_field: json!({}),
+            missing: "code",
+            wrong_type: json!({ "code": 123456 }),
+            wrong_type_field: "code",
+        },
+    ]
+}
+
+async fn problem(response: reqwest::Response, status: u16, route: &str) -> ErrorResponse {
+    assert_eq!(response.status().as_u16(), status, "Failed for {route}");
+    assert_eq!(
+        response.headers()["content-type"],
+        PROBLEM_JSON_CONTENT_TYPE,
+        "Failed for {route}"
+    );
+    let problem = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    // Still served for older clients
+    assert_eq!(problem.error, problem.detail, "Failed for {route}");
+    problem
+}
+
+#[db_test]
+async fn should_return_422_with_the_missing_field() {
+    for route in json_routes() {
+        let response = app
+            .post_raw(
+                route.path,
+                "application/json",
+                route.missing_field.to_string(),
+            )
+            .await;
+
+        let body = problem(response, 422, route.path).await;
+        assert_eq!(
+            body.code,
+            ErrorCode::InvalidBody,
+            "Failed for {}",
+            route.path
+        );
+        assert_eq!(body.errors.len(), 1, "Failed for {}", route.path);
+        assert_eq!(
+            body.errors[0].field, route.missing,
+            "Failed for {}",
+            route.path
+        );
+        assert_eq!(body.errors[0].code, ErrorCode::MissingField);
+    }
+}
+
+#[db_test]
+async fn should_return_422_with_the_field_of_the_wrong_type() {
+    for route in json_routes() {
+        let response = app
+            .post_raw(route.path, "application/json", route.wrong_type.to_string())
+            .await;
+
+        let body = problem(response, 422, route.path).await;
+        assert_eq!(
+            body.code,
+            ErrorCode::InvalidBody,
+            "Failed for {}",
+            route.path
+        );
+        assert_eq!(body.errors.len(), 1, "Failed for {}", route.path);
+        assert_eq!(
+            body.errors[0].field, route.wrong_type_field,
+            "Failed for {}",
+            route.path
+        );
+        assert_eq!(body.errors[0].code, ErrorCode::InvalidType);
+    }
+}
+
+#[db_test]
+async fn should_return_413_if_body_is_too_large() {
+    let oversized = json!({ "email": "a".repeat(MAX_REQUEST_BODY_BYTES) }).to_string();
+
+    for route in json_routes() {
+        let response = app
+            .post_raw(route.path, "application/json", oversized.clone())
+            .await;
+
+        let body = problem(response, 413, route.path).await;
+        assert_eq!(
+            body.code,
+            ErrorCode::PayloadTooLarge,
+            "Failed for {}",
+            route.path
+        );
+    }
+}
+
+#[db_test]
+async fn should_return_400_if_body_is_not_json() {
+    for route in json_routes() {
+        for malformed in [
+            "",
+            "{\"email\": ",
+            "email=user@example.com",
+            "{'email': 'user@example.com'}",
+        ] {
+            let response = app
+                .post_raw(route.path, "application/json", malformed)
+                .await;
+
+            let body = problem(response, 400, route.path).await;
+            assert_eq!(
+                body.code,
+                ErrorCode::MalformedBody,
+                "Failed for {} with {:?}",
+                route.path,
+                malformed
+            );
+        }
+    }
+}
+
+#[db_test]
+async fn should_return_415_if_content_type_is_not_json() {
+    for route in json_routes() {
+        for content_type in ["text/plain", "application/x-www-form-urlencoded"] {
+            let response = app
+                .post_raw(route.path, content_type, route.missing_field.to_string())
+                .await;
+
+            let body = problem(response, 415, route.path).await;
+            assert_eq!(
+                body.code,
+                ErrorCode::UnsupportedMediaType,
+                "Failed for {} with {}",
+                route.path,
+                content_type
+            );
+        }
+    }
+}
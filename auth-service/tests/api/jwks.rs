@@ -0,0 +1,62 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_macros::db_test;
+use auth_service::utils::{auth::Claims, constants::JWT_COOKIE_NAME};
+use jsonwebtoken::{decode, decode_header, jwk::JwkSet, DecodingKey, Validation};
+
+#[db_test]
+async fn should_return_jwks() {
+    let response = app.get_jwks().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let jwks = response
+        .json::<JwkSet>()
+        .await
+        .expect("Could not deserialize response body to JwkSet");
+
+    assert!(!jwks.keys.is_empty());
+    assert!(jwks.keys.iter().all(|jwk| jwk.common.key_id.is_some()));
+}
+
+#[db_test]
+async fn should_verify_issued_token_with_jwks() {
+    let random_email = get_random_email();
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "123DSDFdasd@@456789",
+        "requires2FA": false
+    });
+    app.post_signup(&signup_body).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "123DSDFdasd@@456789",
+    });
+    let response = app.post_login(&login_body).await;
+    let token = response
+        .cookies()
+        .find(|c| c.name() == JWT_COOKIE_NAME)
+        .map(|c| c.value().to_string())
+        .expect("jwt token doesn't exist");
+
+    let jwks = app
+        .get_jwks()
+        .await
+        .json::<JwkSet>()
+        .await
+        .expect("Could not deserialize response body to JwkSet");
+
+    let header = decode_header(&token).expect("Failed to decode JWT header");
+    let kid = header.kid.expect("JWT header is missing kid");
+    let jwk = jwks.find(&kid).expect("Signing key not published in JWKS");
+
+    let claims = decode::<Claims>(
+        &token,
+        &DecodingKey::from_jwk(jwk).expect("Invalid JWK"),
+        &Validation::new(header.alg),
+    )
+    .expect("Failed to verify JWT with published key")
+    .claims;
+
+    assert_eq!(claims.sub, random_email);
+}
@@ -1,7 +1,9 @@
 use crate::helpers::{get_random_email, TestApp};
 use auth_macros::db_test;
+use auth_service::domain::ErrorCode;
 use auth_service::dto::TwoFactorAuthResponse;
 use auth_service::utils::constants::JWT_COOKIE_NAME;
+use auth_service::ErrorResponse;
 use wiremock::matchers::{method, path};
 use wiremock::{Mock, ResponseTemplate};
 
@@ -155,3 +157,44 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
         "2FA required".to_owned()
     );
 }
+
+#[db_test]
+async fn should_return_401_if_incorrect_credentials_and_2fa_enabled() {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "123DSDFdasd@@456789",
+        "requires2FA": true
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    // No 2FA code should be emailed when the password is wrong
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "123DSDFdasd@@45678",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .code,
+        ErrorCode::IncorrectCredentials
+    );
+}
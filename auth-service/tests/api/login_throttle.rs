@@ -0,0 +1,167 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::domain::{Email, ErrorCode};
+use auth_service::dto::TwoFactorAuthResponse;
+use auth_service::utils::login_throttle::LoginThrottlePolicy;
+use auth_service::ErrorResponse;
+use secrecy::ExposeSecret;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+const PASSWORD: &str = "123DSDFdasd@@456789";
+const WRONG_PASSWORD: &str = "Wrong123DSDFdasd@@456";
+
+const POLICY: LoginThrottlePolicy = LoginThrottlePolicy {
+    max_account_failures: 3,
+    max_ip_failures: 100,
+    lockout_seconds: 60,
+};
+
+async fn signup(app: &TestApp, email: &str, requires_2fa: bool) {
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": PASSWORD,
+            "requires2FA": requires_2fa
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+}
+
+async fn login(app: &TestApp, email: &str, password: &str) -> reqwest::Response {
+    app.post_login(&serde_json::json!({ "email": email, "password": password }))
+        .await
+}
+
+async fn assert_too_many_attempts(response: reqwest::Response, lockout_seconds: u64) {
+    assert_eq!(response.status().as_u16(), 429);
+
+    let retry_after: u64 = response
+        .headers()
+        .get("retry-after")
+        .expect("No Retry-After header found")
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(retry_after > 0 && retry_after <= lockout_seconds);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .code,
+        ErrorCode::RateLimited
+    );
+}
+
+#[tokio::test]
+async fn should_lock_out_account_after_repeated_failures() {
+    let app = TestApp::with_login_throttle_policy(POLICY).await;
+    let random_email = get_random_email();
+    signup(&app, &random_email, false).await;
+
+    for _ in 0..POLICY.max_account_failures {
+        let response = login(&app, &random_email, WRONG_PASSWORD).await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    // Even the right password is refused while the account is locked
+    let response = login(&app, &random_email, PASSWORD).await;
+    assert_too_many_attempts(response, POLICY.lockout_seconds).await;
+
+    // Other accounts are unaffected
+    let other_email = get_random_email();
+    signup(&app, &other_email, false).await;
+    let response = login(&app, &other_email, PASSWORD).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reset_account_failures_after_successful_login() {
+    let app = TestApp::with_login_throttle_policy(POLICY).await;
+    let random_email = get_random_email();
+    signup(&app, &random_email, false).await;
+
+    for _ in 0..2 {
+        for _ in 0..POLICY.max_account_failures - 1 {
+            let response = login(&app, &random_email, WRONG_PASSWORD).await;
+            assert_eq!(response.status().as_u16(), 401);
+        }
+        let response = login(&app, &random_email, PASSWORD).await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_lock_out_ip_after_repeated_failures() {
+    let policy = LoginThrottlePolicy {
+        max_account_failures: 100,
+        max_ip_failures: 3,
+        lockout_seconds: 60,
+    };
+    let app = TestApp::with_login_throttle_policy(policy).await;
+
+    // Spread over several accounts, as in password spraying
+    for _ in 0..policy.max_ip_failures {
+        let response = login(&app, &get_random_email(), WRONG_PASSWORD).await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = login(&app, &get_random_email(), WRONG_PASSWORD).await;
+    assert_too_many_attempts(response, policy.lockout_seconds).await;
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_lock_out_account_after_repeated_2fa_failures() {
+    let app = TestApp::with_login_throttle_policy(POLICY).await;
+    let random_email = get_random_email();
+    signup(&app, &random_email, true).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    let response = login(&app, &random_email, PASSWORD).await;
+    assert_eq!(response.status().as_u16(), 206);
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+
+    let email = Email::parse(random_email.clone().into()).unwrap();
+    let (_, code) = app.two_fa_code_store.get_two_fa_code(&email).await.unwrap();
+    let code = code.as_ref().expose_secret().to_owned();
+    let wrong_code = if code == "999999" { "999998" } else { "999999" };
+
+    for _ in 0..POLICY.max_account_failures {
+        let response = app
+            .post_verify_2fa(&serde_json::json!({
+                "email": random_email,
+                "loginAttemptId": login_attempt_id,
+                "2FACode": wrong_code,
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": code,
+        }))
+        .await;
+    assert_too_many_attempts(response, POLICY.lockout_seconds).await;
+
+    app.clean_up().await;
+}
@@ -1,5 +1,6 @@
 use crate::helpers::{get_random_email, TestApp};
 use auth_macros::db_test;
+use auth_service::domain::ErrorCode;
 use auth_service::{utils::constants::JWT_COOKIE_NAME, ErrorResponse};
 use reqwest::Url;
 
@@ -14,8 +15,8 @@ async fn should_return_400_if_jwt_cookie_missing() {
             .json::<ErrorResponse>()
             .await
             .expect("Could not deserialize response body to ErrorResponse")
-            .error,
-        "Missing token".to_owned()
+            .code,
+        ErrorCode::MissingToken
     );
 }
 
@@ -38,8 +39,8 @@ async fn should_return_401_if_invalid_token() {
             .json::<ErrorResponse>()
             .await
             .expect("Could not deserialize response body to ErrorResponse")
-            .error,
-        "Invalid token".to_owned()
+            .code,
+        ErrorCode::InvalidToken
     );
 }
 
@@ -80,8 +81,8 @@ async fn should_return_200_if_valid_jwt_cookie() {
             .json::<ErrorResponse>()
             .await
             .expect("Could not deserialize response body to ErrorResponse")
-            .error,
-        "Invalid token".to_owned()
+            .code,
+        ErrorCode::InvalidToken
     );
 }
 
@@ -113,8 +114,8 @@ async fn should_return_400_if_logout_called_twice_in_a_row() {
             .json::<ErrorResponse>()
             .await
             .expect("Could not deserialize response body to ErrorResponse")
-            .error,
-        "Missing token".to_owned()
+            .code,
+        ErrorCode::MissingToken
     );
 }
 
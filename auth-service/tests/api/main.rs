@@ -1,7 +1,24 @@
+mod admin;
+mod enumeration_protection;
+mod health;
 mod helpers;
+mod in_memory;
+mod json_body;
+mod jwks;
 mod login;
+mod login_throttle;
 mod logout;
+mod metrics;
+mod password_reset;
+mod postgres_token_stores;
+mod recovery_codes;
+mod refresh;
 mod root;
+mod sessions;
+mod shutdown;
 mod signup;
+mod tls;
+mod totp;
 mod verify_2fa;
+mod verify_email;
 mod verify_token;
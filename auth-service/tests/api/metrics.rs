@@ -0,0 +1,83 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_macros::db_test;
+use auth_service::utils::constants::JWT_COOKIE_NAME;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+// The metrics are process wide and the tests run concurrently, so they can
+// only be checked for having been recorded at least once
+fn metric_value(metrics: &str, series: &str) -> f64 {
+    metrics
+        .lines()
+        .find_map(|line| line.strip_prefix(series)?.trim().parse().ok())
+        .unwrap_or_else(|| panic!("{series} not found in:\n{metrics}"))
+}
+
+#[db_test]
+async fn should_record_auth_events() {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let random_email = get_random_email();
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "123DSDFdasd@@456789",
+        "requires2FA": false
+    });
+    app.post_signup(&signup_body).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "123DSDFdasd@@456789",
+    });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let token = response
+        .cookies()
+        .find(|c| c.name() == JWT_COOKIE_NAME)
+        .map(|c| c.value().to_string())
+        .expect("jwt token doesn't exist");
+
+    let wrong_password = serde_json::json!({
+        "email": random_email,
+        "password": "wrongPassword@@123456",
+    });
+    app.post_login(&wrong_password).await;
+
+    app.post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+    app.post_logout().await;
+
+    let response = app.get_metrics().await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .headers()
+        .get("content-type")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .starts_with("text/plain"));
+    let metrics = response.text().await.unwrap();
+
+    for series in [
+        "auth_signups_total",
+        r#"auth_logins_total{outcome="success"}"#,
+        r#"auth_logins_total{outcome="incorrect_credentials"}"#,
+        r#"auth_token_verifications_total{outcome="valid"}"#,
+        "auth_logouts_total",
+        r#"auth_password_hash_duration_seconds_count{operation="hash"}"#,
+        r#"auth_password_hash_duration_seconds_count{operation="verify"}"#,
+        r#"auth_store_operation_duration_seconds_count{backend="postgres",operation="add_user",store="user"}"#,
+        r#"auth_store_operation_duration_seconds_count{backend="redis",operation="add_session",store="session"}"#,
+        r#"auth_email_send_duration_seconds_count{outcome="success",provider="postmark"}"#,
+    ] {
+        assert!(
+            metric_value(&metrics, series) >= 1.0,
+            "{series} not recorded"
+        );
+    }
+}
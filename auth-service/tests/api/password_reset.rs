@@ -0,0 +1,231 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_macros::db_test;
+use auth_service::domain::ErrorCode;
+use auth_service::dto::PasswordResetResponse;
+use auth_service::utils::constants::{JWT_COOKIE_NAME, REFRESH_COOKIE_NAME};
+use auth_service::ErrorResponse;
+use reqwest::Url;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+async fn signup(app: &TestApp, email: &str) {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "123DSDFdasd@@456789",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+}
+
+async fn mock_email_server(app: &TestApp, expected_calls: u64) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(expected_calls)
+        .mount(&app.email_server)
+        .await;
+}
+
+#[db_test]
+async fn should_return_422_if_malformed_input() {
+    let response = app
+        .post_password_reset_request(&serde_json::json!({}))
+        .await;
+    assert_eq!(response.status().as_u16(), 422);
+
+    let test_cases = [
+        serde_json::json!({ "token": "abc" }),
+        serde_json::json!({ "newPassword": "New123DSDFdasd@@456" }),
+    ];
+
+    for test_case in test_cases.iter() {
+        let response = app.post_password_reset_confirm(test_case).await;
+        assert_eq!(
+            response.status().as_u16(),
+            422,
+            "Failed for input: {:?}",
+            test_case
+        );
+    }
+}
+
+#[db_test]
+async fn should_return_200_without_email_if_user_not_found() {
+    mock_email_server(app, 0).await;
+
+    let response = app
+        .post_password_reset_request(&serde_json::json!({ "email": get_random_email() }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response
+            .json::<PasswordResetResponse>()
+            .await
+            .expect("Could not deserialize response body to PasswordResetResponse")
+            .message,
+        "If the account exists, a password reset email has been sent".to_owned()
+    );
+}
+
+#[db_test]
+async fn should_send_email_if_user_exists() {
+    let random_email = get_random_email();
+    signup(app, &random_email).await;
+    mock_email_server(app, 1).await;
+
+    let response = app
+        .post_password_reset_request(&serde_json::json!({ "email": random_email }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The lifetime comes from `tokens.password_reset_token_ttl_seconds`
+    let requests = app
+        .email_server
+        .received_requests()
+        .await
+        .expect("Request recording is disabled");
+    let body: serde_json::Value = requests
+        .last()
+        .expect("No email was sent")
+        .body_json()
+        .expect("Email body is not JSON");
+    assert!(body["TextBody"]
+        .as_str()
+        .expect("Email has no text body")
+        .ends_with("It expires in 15 minutes."));
+}
+
+#[db_test]
+async fn should_return_401_if_invalid_token() {
+    let body = serde_json::json!({
+        "token": "invalid",
+        "newPassword": "New123DSDFdasd@@456",
+    });
+
+    let response = app.post_password_reset_confirm(&body).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .code,
+        ErrorCode::InvalidToken
+    );
+}
+
+#[db_test]
+async fn should_return_400_if_new_password_invalid_and_keep_token() {
+    let random_email = get_random_email();
+    signup(app, &random_email).await;
+    mock_email_server(app, 1).await;
+
+    app.post_password_reset_request(&serde_json::json!({ "email": random_email }))
+        .await;
+    let token = app.get_token_from_last_email().await;
+
+    let response = app
+        .post_password_reset_confirm(&serde_json::json!({
+            "token": token,
+            "newPassword": "weak",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    let body = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(body.code, ErrorCode::InvalidInput);
+    assert_eq!(body.errors.len(), 1);
+    assert_eq!(body.errors[0].field, "newPassword");
+    assert_eq!(body.errors[0].code, ErrorCode::PasswordTooShort);
+
+    let response = app
+        .post_password_reset_confirm(&serde_json::json!({
+            "token": token,
+            "newPassword": "New123DSDFdasd@@456",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[db_test]
+async fn should_reset_password_and_revoke_sessions() {
+    let random_email = get_random_email();
+    signup(app, &random_email).await;
+    mock_email_server(app, 1).await;
+
+    let old_login_body = serde_json::json!({
+        "email": random_email,
+        "password": "123DSDFdasd@@456789",
+    });
+    let response = app.post_login(&old_login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let old_token = response
+        .cookies()
+        .find(|c| c.name() == JWT_COOKIE_NAME)
+        .map(|c| c.value().to_string())
+        .expect("jwt token doesn't exist");
+    let old_refresh_token = response
+        .cookies()
+        .find(|c| c.name() == REFRESH_COOKIE_NAME)
+        .map(|c| c.value().to_string())
+        .expect("refresh token doesn't exist");
+
+    app.post_password_reset_request(&serde_json::json!({ "email": random_email }))
+        .await;
+    let token = app.get_token_from_last_email().await;
+
+    let confirm_body = serde_json::json!({
+        "token": token,
+        "newPassword": "New123DSDFdasd@@456",
+    });
+    let response = app.post_password_reset_confirm(&confirm_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The token is single-use
+    let response = app.post_password_reset_confirm(&confirm_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Existing sessions are revoked
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": old_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}={}; HttpOnly; SameSite=Lax; Path=/",
+            REFRESH_COOKIE_NAME, old_refresh_token
+        ),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Only the new password works from now on
+    let response = app.post_login(&old_login_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "New123DSDFdasd@@456",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let new_token = response
+        .cookies()
+        .find(|c| c.name() == JWT_COOKIE_NAME)
+        .map(|c| c.value().to_string())
+        .expect("jwt token doesn't exist");
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": new_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
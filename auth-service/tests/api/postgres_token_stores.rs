@@ -0,0 +1,458 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::domain::types::{
+    Email, EmailVerificationToken, LoginAttemptId, LoginAttemptKey, LoginAttempts,
+    PasswordResetToken, RefreshTokenRecord, Session, TwoFACode,
+};
+use auth_service::domain::{
+    BannedTokenStore, EmailVerificationTokenStore, LoginAttemptStore, PasswordResetTokenStore,
+    RefreshTokenStore, SessionStore, TwoFACodeStore,
+};
+use auth_service::dto::{SessionsResponse, TwoFactorAuthResponse};
+use auth_service::prelude::{PostgresBannedTokenStore, PostgresTwoFACodeStore};
+use auth_service::services::purge_task::PostgresTokenStores;
+use auth_service::utils::constants::{JWT_COOKIE_NAME, MAX_TOKEN_TTL_SECONDS, REFRESH_COOKIE_NAME};
+use auth_service::utils::settings::settings;
+use chrono::Utc;
+use reqwest::Url;
+use secrecy::{ExposeSecret, SecretString};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+fn signup_body(email: &str, requires_2fa: bool) -> serde_json::Value {
+    serde_json::json!({
+        "email": email,
+        "password": "123DSDFdasd@@456789",
+        "requires2FA": requires_2fa
+    })
+}
+
+fn login_body(email: &str) -> serde_json::Value {
+    serde_json::json!({ "email": email, "password": "123DSDFdasd@@456789" })
+}
+
+async fn signup_and_login(app: &TestApp, email: &str) -> reqwest::Response {
+    app.post_signup(&signup_body(email, false)).await;
+    let response = app.post_login(&login_body(email)).await;
+    assert_eq!(response.status().as_u16(), 200);
+    response
+}
+
+fn get_cookie(response: &reqwest::Response, name: &str) -> String {
+    response
+        .cookies()
+        .find(|c| c.name() == name)
+        .map(|c| c.value().to_string())
+        .unwrap_or_else(|| panic!("{} cookie doesn't exist", name))
+}
+
+fn set_refresh_cookie(app: &TestApp, token: &str) {
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}={}; HttpOnly; SameSite=Lax; Path=/",
+            REFRESH_COOKIE_NAME, token
+        ),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+}
+
+#[tokio::test]
+async fn should_reject_token_after_logout() {
+    let app = TestApp::with_postgres_token_stores().await;
+    let random_email = get_random_email();
+
+    app.post_signup(&serde_json::json!({
+        "email": random_email,
+        "password": "123DSDFdasd@@456789",
+        "requires2FA": false
+    }))
+    .await;
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "123DSDFdasd@@456789",
+        }))
+        .await;
+    let token = response
+        .cookies()
+        .find(|c| c.name() == JWT_COOKIE_NAME)
+        .map(|c| c.value().to_string())
+        .expect("jwt token doesn't exist");
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_log_in_once_with_2fa_code() {
+    let app = TestApp::with_postgres_token_stores().await;
+    let random_email = get_random_email();
+
+    app.post_signup(&serde_json::json!({
+        "email": random_email,
+        "password": "123DSDFdasd@@456789",
+        "requires2FA": true
+    }))
+    .await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "123DSDFdasd@@456789",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 206);
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+
+    let email = Email::parse(SecretString::new(random_email.clone().into_boxed_str())).unwrap();
+    let (_, code) = app.two_fa_code_store.get_two_fa_code(&email).await.unwrap();
+
+    let body = serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": login_attempt_id,
+        "2FACode": code.as_ref().expose_secret(),
+    });
+
+    let response = app.post_verify_2fa(&body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_verify_2fa(&body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_rotate_refresh_tokens_and_revoke_reused_families() {
+    let app = TestApp::with_postgres_token_stores().await;
+    let random_email = get_random_email();
+    let response = signup_and_login(&app, &random_email).await;
+    let old_refresh_token = get_cookie(&response, REFRESH_COOKIE_NAME);
+
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 200);
+    let new_refresh_token = get_cookie(&response, REFRESH_COOKIE_NAME);
+    assert_ne!(old_refresh_token, new_refresh_token);
+
+    set_refresh_cookie(&app, &old_refresh_token);
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    set_refresh_cookie(&app, &new_refresh_token);
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_list_and_end_sessions() {
+    let app = TestApp::with_postgres_token_stores().await;
+    let random_email = get_random_email();
+    let response = signup_and_login(&app, &random_email).await;
+    let refresh_token = get_cookie(&response, REFRESH_COOKIE_NAME);
+
+    let sessions = app
+        .get_sessions()
+        .await
+        .json::<SessionsResponse>()
+        .await
+        .expect("Could not deserialize response body to SessionsResponse")
+        .sessions;
+    assert_eq!(sessions.len(), 1);
+    assert!(sessions[0].current);
+
+    let response = app.delete_sessions().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The cookies are cleared, but the refresh token is revoked as well
+    set_refresh_cookie(&app, &refresh_token);
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_redeem_email_verification_and_password_reset_tokens_once() {
+    let app = TestApp::with_postgres_token_stores().await;
+    let random_email = get_random_email();
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_signup(&signup_body(&random_email, false)).await;
+    let body = serde_json::json!({ "token": app.get_token_from_last_email().await });
+    let response = app.post_verify_email(&body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let response = app.post_verify_email(&body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.post_password_reset_request(&serde_json::json!({ "email": random_email }))
+        .await;
+    let body = serde_json::json!({
+        "token": app.get_token_from_last_email().await,
+        "newPassword": "New123DSDFdasd@@456",
+    });
+    let response = app.post_password_reset_confirm(&body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let response = app.post_password_reset_confirm(&body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_throttle_repeated_failed_logins() {
+    let app = TestApp::with_postgres_token_stores().await;
+    let random_email = get_random_email();
+    app.post_signup(&signup_body(&random_email, false)).await;
+    let wrong_password = serde_json::json!({
+        "email": random_email,
+        "password": "Wrong123DSDFdasd@@456",
+    });
+
+    // Failures are counted across requests, so the account is throttled at
+    // the latest once it reaches the limit
+    let mut status = 401;
+    for _ in 0..settings().login.max_account_failures {
+        status = app.post_login(&wrong_password).await.status().as_u16();
+        if status == 429 {
+            break;
+        }
+        assert_eq!(status, 401);
+    }
+    assert_eq!(status, 429);
+
+    let email = Email::parse(SecretString::from(random_email)).unwrap();
+    let attempts = PostgresTokenStores::new(app.pg_pool.clone())
+        .login_attempt_store
+        .get_attempts(&LoginAttemptKey::Account(email))
+        .await
+        .unwrap();
+    assert!(attempts.failures > 1);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_purge_expired_rows() {
+    let app = TestApp::with_postgres_token_stores().await;
+    let banned_token_store = PostgresBannedTokenStore::new(app.pg_pool.clone());
+    let two_fa_code_store = PostgresTwoFACodeStore::new(app.pg_pool.clone());
+    let random_email = get_random_email();
+
+    app.post_signup(&serde_json::json!({
+        "email": random_email,
+        "password": "123DSDFdasd@@456789",
+        "requires2FA": true
+    }))
+    .await;
+
+    let email = Email::parse(SecretString::new(random_email.into_boxed_str())).unwrap();
+    let token = SecretString::new("banned-token".to_owned().into_boxed_str());
+    banned_token_store.add_token(token.clone()).await.unwrap();
+    banned_token_store
+        .ban_user_tokens(&email, Utc::now().timestamp())
+        .await
+        .unwrap();
+    two_fa_code_store
+        .add_two_fa_code(
+            email.clone(),
+            LoginAttemptId::parse(SecretString::new(
+                "c9a2865b-467d-498b-93b8-634903ae68e0"
+                    .to_owned()
+                    .into_boxed_str(),
+            ))
+            .unwrap(),
+            TwoFACode::parse(SecretString::new("123456".to_owned().into_boxed_str())).unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // Nothing has expired yet
+    let now = Utc::now().timestamp();
+    assert_eq!(banned_token_store.purge_expired(now).await.unwrap(), 0);
+    assert_eq!(two_fa_code_store.purge_expired(now).await.unwrap(), 0);
+
+    let tomorrow = now + 24 * 60 * 60;
+    assert_eq!(banned_token_store.purge_expired(tomorrow).await.unwrap(), 2);
+    assert_eq!(two_fa_code_store.purge_expired(tomorrow).await.unwrap(), 1);
+
+    assert!(!banned_token_store.token_exists(&token).await.unwrap());
+    assert_eq!(
+        banned_token_store
+            .user_tokens_banned_before(&email)
+            .await
+            .unwrap(),
+        None
+    );
+    assert!(!two_fa_code_store.two_fa_code_exists(&email).await.unwrap());
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_purge_expired_tokens_sessions_and_login_attempts() {
+    let app = TestApp::with_postgres_token_stores().await;
+    let stores = PostgresTokenStores::new(app.pg_pool.clone());
+    let email = Email::parse(SecretString::from(get_random_email())).unwrap();
+    let now = Utc::now().timestamp();
+
+    let refresh_token = SecretString::from("refresh-token");
+    stores
+        .refresh_token_store
+        .add_refresh_token(
+            refresh_token.clone(),
+            RefreshTokenRecord::new(email.clone(), "family".to_owned()),
+        )
+        .await
+        .unwrap();
+    stores
+        .refresh_token_store
+        .revoke_family("family")
+        .await
+        .unwrap();
+    stores
+        .session_store
+        .add_session(Session {
+            id: "family".to_owned(),
+            email: email.clone(),
+            user_agent: None,
+            ip: "127.0.0.1".to_owned(),
+            created_at: now,
+            last_seen_at: now,
+        })
+        .await
+        .unwrap();
+    let reset_token = PasswordResetToken::parse(SecretString::from("reset-token")).unwrap();
+    stores
+        .password_reset_token_store
+        .add_token(reset_token.clone(), email.clone())
+        .await
+        .unwrap();
+    let verification_token =
+        EmailVerificationToken::parse(SecretString::from("verification-token")).unwrap();
+    stores
+        .email_verification_token_store
+        .add_token(verification_token.clone(), email.clone())
+        .await
+        .unwrap();
+    let key = LoginAttemptKey::Account(email.clone());
+    stores
+        .login_attempt_store
+        .record_failure(&key, 60)
+        .await
+        .unwrap();
+
+    // Nothing has expired yet
+    assert_eq!(
+        stores.refresh_token_store.purge_expired(now).await.unwrap(),
+        0
+    );
+    assert_eq!(stores.session_store.purge_expired(now).await.unwrap(), 0);
+    assert_eq!(
+        stores
+            .password_reset_token_store
+            .purge_expired(now)
+            .await
+            .unwrap(),
+        0
+    );
+    assert_eq!(
+        stores
+            .email_verification_token_store
+            .purge_expired(now)
+            .await
+            .unwrap(),
+        0
+    );
+    assert_eq!(
+        stores.login_attempt_store.purge_expired(now).await.unwrap(),
+        0
+    );
+
+    let later = now + MAX_TOKEN_TTL_SECONDS as i64 + 1;
+    assert_eq!(
+        stores
+            .refresh_token_store
+            .purge_expired(later)
+            .await
+            .unwrap(),
+        2
+    );
+    assert_eq!(stores.session_store.purge_expired(later).await.unwrap(), 1);
+    assert_eq!(
+        stores
+            .password_reset_token_store
+            .purge_expired(later)
+            .await
+            .unwrap(),
+        1
+    );
+    assert_eq!(
+        stores
+            .email_verification_token_store
+            .purge_expired(later)
+            .await
+            .unwrap(),
+        1
+    );
+    assert_eq!(
+        stores
+            .login_attempt_store
+            .purge_expired(later)
+            .await
+            .unwrap(),
+        1
+    );
+
+    assert!(stores
+        .refresh_token_store
+        .get_refresh_token(&refresh_token)
+        .await
+        .is_err());
+    assert!(!stores
+        .refresh_token_store
+        .is_family_revoked("family")
+        .await
+        .unwrap());
+    assert!(stores.session_store.get_session("family").await.is_err());
+    assert!(stores
+        .password_reset_token_store
+        .take_token(&reset_token)
+        .await
+        .is_err());
+    assert!(stores
+        .email_verification_token_store
+        .take_token(&verification_token)
+        .await
+        .is_err());
+    assert_eq!(
+        stores.login_attempt_store.get_attempts(&key).await.unwrap(),
+        LoginAttempts::default()
+    );
+
+    app.clean_up().await;
+}
@@ -0,0 +1,163 @@
+use crate::helpers::{get_random_email, get_totp_code, TestApp};
+use auth_macros::db_test;
+use auth_service::dto::{
+    ConfirmTotpResponse, RecoveryCodesResponse, SignupResponse, TotpEnrollmentResponse,
+    TwoFactorAuthResponse,
+};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+const PASSWORD: &str = "123DSDFdasd@@456789";
+
+async fn signup_and_login(app: &TestApp, email: &str) {
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": PASSWORD,
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app
+        .post_login(&serde_json::json!({ "email": email, "password": PASSWORD }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+// Turn on 2FA via TOTP and return the recovery codes handed out with it
+async fn enable_totp(app: &TestApp) -> Vec<String> {
+    let secret = app
+        .post_totp_enroll()
+        .await
+        .json::<TotpEnrollmentResponse>()
+        .await
+        .expect("Could not deserialize response body to TotpEnrollmentResponse")
+        .secret;
+
+    let response = app
+        .post_totp_confirm(&serde_json::json!({ "code": get_totp_code(&secret, 0) }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<ConfirmTotpResponse>()
+        .await
+        .expect("Could not deserialize response body to ConfirmTotpResponse")
+        .recovery_codes
+}
+
+async fn verify_2fa_with(app: &TestApp, email: &str, code: &str) -> u16 {
+    let login_attempt_id = app
+        .post_login(&serde_json::json!({ "email": email, "password": PASSWORD }))
+        .await
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+
+    app.post_verify_2fa(&serde_json::json!({
+        "email": email,
+        "loginAttemptId": login_attempt_id,
+        "2FACode": code,
+    }))
+    .await
+    .status()
+    .as_u16()
+}
+
+#[db_test]
+async fn should_return_400_if_not_logged_in() {
+    let response = app.post_regenerate_recovery_codes().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[db_test]
+async fn should_issue_recovery_codes_when_totp_enabled() {
+    signup_and_login(app, &get_random_email()).await;
+
+    let codes = enable_totp(app).await;
+
+    assert_eq!(codes.len(), 10);
+    for code in codes {
+        assert_eq!(code.len(), 11, "Unexpected code format: {code}");
+        assert_eq!(code.chars().nth(5), Some('-'));
+    }
+}
+
+#[db_test]
+async fn should_log_in_with_recovery_code_issued_at_signup() {
+    let random_email = get_random_email();
+    let codes = app
+        .post_signup(&serde_json::json!({
+            "email": random_email,
+            "password": PASSWORD,
+            "requires2FA": true
+        }))
+        .await
+        .json::<SignupResponse>()
+        .await
+        .expect("Could not deserialize response body to SignupResponse")
+        .recovery_codes
+        .expect("No recovery codes were issued");
+
+    // Email 2FA users can get in without the emailed code
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    assert_eq!(verify_2fa_with(app, &random_email, &codes[0]).await, 200);
+}
+
+#[db_test]
+async fn should_log_in_with_recovery_code_once() {
+    let random_email = get_random_email();
+    signup_and_login(app, &random_email).await;
+    let codes = enable_totp(app).await;
+
+    assert_eq!(verify_2fa_with(app, &random_email, &codes[0]).await, 200);
+    assert_eq!(verify_2fa_with(app, &random_email, &codes[0]).await, 401);
+
+    // Codes are accepted regardless of case and dashes
+    let code = codes[1].replace('-', "").to_lowercase();
+    assert_eq!(verify_2fa_with(app, &random_email, &code).await, 200);
+}
+
+#[db_test]
+async fn should_return_401_if_recovery_code_incorrect() {
+    let random_email = get_random_email();
+    signup_and_login(app, &random_email).await;
+    enable_totp(app).await;
+
+    assert_eq!(
+        verify_2fa_with(app, &random_email, "ABCDE-FGHJK").await,
+        401
+    );
+}
+
+#[db_test]
+async fn should_invalidate_old_codes_on_regenerate() {
+    let random_email = get_random_email();
+    signup_and_login(app, &random_email).await;
+    let old_codes = enable_totp(app).await;
+
+    let response = app.post_regenerate_recovery_codes().await;
+    assert_eq!(response.status().as_u16(), 200);
+    let new_codes = response
+        .json::<RecoveryCodesResponse>()
+        .await
+        .expect("Could not deserialize response body to RecoveryCodesResponse")
+        .recovery_codes;
+    assert_eq!(new_codes.len(), 10);
+
+    assert_eq!(
+        verify_2fa_with(app, &random_email, &old_codes[0]).await,
+        401
+    );
+    assert_eq!(
+        verify_2fa_with(app, &random_email, &new_codes[0]).await,
+        200
+    );
+}
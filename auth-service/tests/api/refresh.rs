@@ -0,0 +1,135 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_macros::db_test;
+use auth_service::domain::ErrorCode;
+use auth_service::{
+    utils::constants::{JWT_COOKIE_NAME, REFRESH_COOKIE_NAME},
+    ErrorResponse,
+};
+use reqwest::Url;
+
+async fn signup_and_login(app: &TestApp) -> reqwest::Response {
+    let random_email = get_random_email();
+    let user_request = serde_json::json!({
+        "email": random_email,
+        "password": "123DSDFdasd@@456789",
+        "requires2FA": false
+    });
+
+    app.post_signup(&user_request).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "123DSDFdasd@@456789",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    response
+}
+
+fn get_cookie(response: &reqwest::Response, name: &str) -> String {
+    response
+        .cookies()
+        .find(|c| c.name() == name)
+        .map(|c| c.value().to_string())
+        .unwrap_or_else(|| panic!("{} cookie doesn't exist", name))
+}
+
+fn set_refresh_cookie(app: &TestApp, token: &str) {
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}={}; HttpOnly; SameSite=Lax; Path=/",
+            REFRESH_COOKIE_NAME, token
+        ),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+}
+
+#[db_test]
+async fn should_return_400_if_refresh_cookie_missing() {
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .code,
+        ErrorCode::MissingToken
+    );
+}
+
+#[db_test]
+async fn should_return_401_if_invalid_refresh_token() {
+    set_refresh_cookie(app, "invalid");
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .code,
+        ErrorCode::InvalidToken
+    );
+}
+
+#[db_test]
+async fn should_return_200_and_rotate_refresh_token() {
+    let response = signup_and_login(app).await;
+    let refresh_token = get_cookie(&response, REFRESH_COOKIE_NAME);
+
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let new_refresh_token = get_cookie(&response, REFRESH_COOKIE_NAME);
+    let new_auth_token = get_cookie(&response, JWT_COOKIE_NAME);
+    assert_ne!(refresh_token, new_refresh_token);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": new_auth_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The rotated token keeps working
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[db_test]
+async fn should_revoke_family_if_refresh_token_reused() {
+    let response = signup_and_login(app).await;
+    let old_refresh_token = get_cookie(&response, REFRESH_COOKIE_NAME);
+
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 200);
+    let new_refresh_token = get_cookie(&response, REFRESH_COOKIE_NAME);
+
+    // Replaying the rotated token is rejected...
+    set_refresh_cookie(app, &old_refresh_token);
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // ...and takes the rest of the chain down with it
+    set_refresh_cookie(app, &new_refresh_token);
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[db_test]
+async fn should_return_401_if_refresh_after_logout() {
+    let response = signup_and_login(app).await;
+    let refresh_token = get_cookie(&response, REFRESH_COOKIE_NAME);
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    set_refresh_cookie(app, &refresh_token);
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+}
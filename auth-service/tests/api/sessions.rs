@@ -0,0 +1,180 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_macros::db_test;
+use auth_service::domain::ErrorCode;
+use auth_service::dto::SessionsResponse;
+use auth_service::ErrorResponse;
+
+const PASSWORD: &str = "123DSDFdasd@@456789";
+const OTHER_USER_AGENT: &str = "other-device/1.0";
+
+fn login_body(email: &str) -> serde_json::Value {
+    serde_json::json!({ "email": email, "password": PASSWORD })
+}
+
+async fn signup_and_login(app: &TestApp, email: &str) {
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": PASSWORD,
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app.post_login(&login_body(email)).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+// A second device with its own cookies, logged in as `email`
+async fn login_other_device(app: &TestApp, email: &str) -> reqwest::Client {
+    let client = reqwest::Client::builder()
+        .cookie_store(true)
+        .user_agent(OTHER_USER_AGENT)
+        .build()
+        .unwrap();
+    let response = client
+        .post(format!("{}/login", &app.address))
+        .json(&login_body(email))
+        .send()
+        .await
+        .expect("Failed to login");
+    assert_eq!(response.status().as_u16(), 200);
+    client
+}
+
+async fn get_sessions(app: &TestApp, client: &reqwest::Client) -> reqwest::Response {
+    client
+        .get(format!("{}/sessions", &app.address))
+        .send()
+        .await
+        .expect("Failed to list sessions")
+}
+
+async fn parse_sessions(response: reqwest::Response) -> SessionsResponse {
+    assert_eq!(response.status().as_u16(), 200);
+    response
+        .json::<SessionsResponse>()
+        .await
+        .expect("Could not deserialize response body to SessionsResponse")
+}
+
+#[db_test]
+async fn should_return_400_if_not_logged_in() {
+    let response = app.get_sessions().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[db_test]
+async fn should_list_sessions_of_user() {
+    let random_email = get_random_email();
+    signup_and_login(app, &random_email).await;
+    login_other_device(app, &random_email).await;
+
+    // Another user's sessions aren't listed
+    let other_email = get_random_email();
+    app.post_signup(&serde_json::json!({
+        "email": other_email,
+        "password": PASSWORD,
+        "requires2FA": false
+    }))
+    .await;
+    login_other_device(app, &other_email).await;
+
+    let sessions = parse_sessions(app.get_sessions().await).await.sessions;
+
+    assert_eq!(sessions.len(), 2);
+    assert_eq!(sessions.iter().filter(|s| s.current).count(), 1);
+    let other = sessions.iter().find(|s| !s.current).unwrap();
+    assert_eq!(other.user_agent.as_deref(), Some(OTHER_USER_AGENT));
+    assert_eq!(other.ip, "127.0.0.1");
+    assert!(other.created_at > 0 && other.last_seen_at >= other.created_at);
+}
+
+#[db_test]
+async fn should_revoke_other_session() {
+    let random_email = get_random_email();
+    signup_and_login(app, &random_email).await;
+    let other_device = login_other_device(app, &random_email).await;
+
+    let sessions = parse_sessions(app.get_sessions().await).await.sessions;
+    let other = sessions.iter().find(|s| !s.current).unwrap();
+
+    let response = app.delete_session(&other.id).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Both the other device's JWT and its refresh token stop working
+    let response = get_sessions(app, &other_device).await;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = other_device
+        .post(format!("{}/refresh", &app.address))
+        .send()
+        .await
+        .expect("Failed to refresh");
+    assert_eq!(response.status().as_u16(), 401);
+
+    // The current session is unaffected
+    assert_eq!(
+        parse_sessions(app.get_sessions().await)
+            .await
+            .sessions
+            .len(),
+        1
+    );
+}
+
+#[db_test]
+async fn should_return_404_if_session_not_found() {
+    let random_email = get_random_email();
+    signup_and_login(app, &random_email).await;
+
+    // Sessions of other users are reported the same as unknown ids
+    let other_email = get_random_email();
+    app.post_signup(&serde_json::json!({
+        "email": other_email,
+        "password": PASSWORD,
+        "requires2FA": false
+    }))
+    .await;
+    let other_device = login_other_device(app, &other_email).await;
+    let other_session = parse_sessions(get_sessions(app, &other_device).await)
+        .await
+        .sessions
+        .remove(0);
+
+    for id in ["unknown", other_session.id.as_str()] {
+        let response = app.delete_session(id).await;
+        assert_eq!(response.status().as_u16(), 404);
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .code,
+            ErrorCode::SessionNotFound
+        );
+    }
+
+    assert_eq!(
+        get_sessions(app, &other_device).await.status().as_u16(),
+        200
+    );
+}
+
+#[db_test]
+async fn should_log_out_everywhere() {
+    let random_email = get_random_email();
+    signup_and_login(app, &random_email).await;
+    let other_device = login_other_device(app, &random_email).await;
+
+    let response = app.delete_sessions().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The caller's cookies are cleared and every other session is revoked
+    assert_eq!(app.get_sessions().await.status().as_u16(), 400);
+    assert_eq!(
+        get_sessions(app, &other_device).await.status().as_u16(),
+        401
+    );
+    assert_eq!(app.post_refresh().await.status().as_u16(), 400);
+}
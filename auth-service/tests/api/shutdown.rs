@@ -0,0 +1,89 @@
+use auth_service::app_state::UnverifiedLoginPolicy;
+use auth_service::dto::{HealthResponse, HealthStatus};
+use auth_service::prelude::{AppState, Application, HealthCheck};
+use auth_service::utils::constants::test;
+use auth_service::utils::settings::{settings, ShutdownSettings};
+use color_eyre::eyre::Result;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+// Keeps `/health/ready` busy, so a request can be in flight while shutting down
+struct SlowHealthCheck(Duration);
+
+#[async_trait::async_trait]
+impl HealthCheck for SlowHealthCheck {
+    fn name(&self) -> &'static str {
+        "slow"
+    }
+
+    async fn check(&self) -> Result<()> {
+        tokio::time::sleep(self.0).await;
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn should_fail_readiness_and_drain_in_flight_requests() {
+    let app_state = AppState::in_memory(
+        UnverifiedLoginPolicy::Allow,
+        settings().login.throttle_policy(),
+    )
+    .with_health_checks(vec![Arc::new(SlowHealthCheck(Duration::from_millis(1500)))]);
+    let app = Application::build(app_state, test::APP_ADDRESS)
+        .await
+        .expect("Failed to build app");
+    let address = format!("http://{}", app.address);
+
+    let (trigger, signal) = oneshot::channel::<()>();
+    let shutdown_settings = ShutdownSettings {
+        readiness_delay_seconds: 1,
+        drain_timeout_seconds: 5,
+    };
+    let server = tokio::spawn(async move {
+        let signal = async {
+            let _ = signal.await;
+        };
+        app.run_until_stopped(signal, &shutdown_settings).await
+    });
+
+    let client = reqwest::Client::new();
+    let in_flight = tokio::spawn(client.get(format!("{address}/health/ready")).send());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    trigger.send(()).unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // New requests are still served during the readiness delay, but the
+    // instance reports that it's going away
+    let response = client
+        .get(format!("{address}/health/ready"))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 503);
+    let body = response
+        .json::<HealthResponse>()
+        .await
+        .expect("Could not deserialize response body to HealthResponse");
+    assert_eq!(body.status, HealthStatus::Draining);
+
+    // The listener closes while the first request is still running, which
+    // is allowed to finish
+    let response = in_flight
+        .await
+        .unwrap()
+        .expect("In-flight request was dropped");
+    assert_eq!(response.status().as_u16(), 200);
+
+    tokio::time::timeout(Duration::from_secs(5), server)
+        .await
+        .expect("Server didn't stop")
+        .unwrap()
+        .expect("Server failed");
+
+    let result = reqwest::Client::new()
+        .get(format!("{address}/health/live"))
+        .send()
+        .await;
+    assert!(result.is_err(), "Server still accepts connections");
+}
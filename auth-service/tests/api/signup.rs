@@ -1,7 +1,19 @@
 use crate::helpers::{get_random_email, TestApp};
 use auth_macros::db_test;
+use auth_service::app_state::UnverifiedLoginPolicy;
+use auth_service::domain::{
+    Email, ErrorCode, HashedPassword, User, UserPage, UserQuery, UserRoles, UserStore,
+    UserStoreError,
+};
 use auth_service::dto::SignupResponse;
-use auth_service::prelude::ErrorResponse;
+use auth_service::prelude::{AppState, Application, ErrorResponse, HashmapUserStore};
+use auth_service::utils::constants::{test, PROBLEM_JSON_CONTENT_TYPE};
+use auth_service::utils::settings::settings;
+use futures::future::join_all;
+use secrecy::SecretString;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Barrier;
 
 #[db_test]
 async fn should_return_422_if_malformed_input() {
@@ -49,11 +61,26 @@ async fn should_return_201_if_valid_input() {
 
     assert_eq!(response.status().as_u16(), 201);
 
+    let body = response
+        .json::<SignupResponse>()
+        .await
+        .expect("Could not deserialize response body to UserBody");
+    assert_eq!(body.message, "User created successfully!");
+    // Signing up with 2FA hands out recovery codes
+    assert_eq!(body.recovery_codes.map(|codes| codes.len()), Some(10));
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": get_random_email(),
+            "password": "123DSDFdasd@@456789",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
     let expected_response = SignupResponse {
         message: "User created successfully!".to_owned(),
+        recovery_codes: None,
     };
-
-    // Assert that we are getting the correct response body!
     assert_eq!(
         response
             .json::<SignupResponse>()
@@ -66,24 +93,36 @@ async fn should_return_201_if_valid_input() {
 #[db_test]
 async fn should_return_400_if_invalid_input() {
     let test_cases = [
-        serde_json::json!({
-            "email": "my email",
-            "password": "password123",
-            "requires2FA": true
-        }),
-        serde_json::json!({
-            "email": "your email",
-            "password": "pass",
-            "requires2FA": true
-        }),
-        serde_json::json!({
-            "email": "his email",
-            "password": "password",
-            "requires2FA": false
-        }),
+        (
+            serde_json::json!({
+                "email": "my email",
+                "password": "password123",
+                "requires2FA": true
+            }),
+            vec![
+                ("email", ErrorCode::EmailMissingAtSymbol),
+                ("password", ErrorCode::PasswordMissingUppercase),
+            ],
+        ),
+        (
+            serde_json::json!({
+                "email": get_random_email(),
+                "password": "Pa1!",
+                "requires2FA": true
+            }),
+            vec![("password", ErrorCode::PasswordTooShort)],
+        ),
+        (
+            serde_json::json!({
+                "email": "user@@domain.com",
+                "password": "123DSDFdasd@@456789",
+                "requires2FA": false
+            }),
+            vec![("email", ErrorCode::EmailInvalidFormat)],
+        ),
     ];
 
-    for test_case in test_cases.iter() {
+    for (test_case, expected_errors) in test_cases.iter() {
         let response = app.post_signup(test_case).await;
         assert_eq!(
             response.status().as_u16(),
@@ -91,14 +130,26 @@ async fn should_return_400_if_invalid_input() {
             "Failed for input: {:?}",
             test_case
         );
+        assert_eq!(
+            response.headers()["content-type"],
+            PROBLEM_JSON_CONTENT_TYPE
+        );
 
+        let body = response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse");
+        assert_eq!(body.status, 400);
+        assert_eq!(body.code, ErrorCode::InvalidInput);
+        let errors: Vec<_> = body
+            .errors
+            .iter()
+            .map(|error| (error.field.as_str(), error.code))
+            .collect();
         assert_eq!(
-            response
-                .json::<ErrorResponse>()
-                .await
-                .expect("Could not deserialize response body to ErrorResponse")
-                .error,
-            "Invalid credentials".to_owned()
+            &errors, expected_errors,
+            "Failed for input: {:?}",
+            test_case
         );
     }
 }
@@ -121,7 +172,147 @@ async fn should_return_409_if_email_already_exists() {
             .json::<ErrorResponse>()
             .await
             .expect("Could not deserialize response body to ErrorResponse")
-            .error,
-        "User already exists".to_owned()
+            .code,
+        ErrorCode::UserAlreadyExists
     );
 }
+
+#[db_test]
+async fn should_create_user_once_for_concurrent_duplicate_signups() {
+    let random_email = get_random_email();
+    let user_request = serde_json::json!({
+        "email": random_email,
+        "password": "123DSDFdasd@@456789",
+        "requires2FA": false
+    });
+
+    let responses = join_all((0..5).map(|_| app.post_signup(&user_request))).await;
+    let mut statuses: Vec<u16> = responses
+        .iter()
+        .map(|response| response.status().as_u16())
+        .collect();
+    statuses.sort();
+
+    assert_eq!(statuses, vec![201, 409, 409, 409, 409]);
+}
+
+const CONCURRENT_SIGNUPS: usize = 4;
+
+// Holds every signup until all of them have reached the store, which only
+// happens if signups don't wait on each other
+struct BarrierUserStore {
+    inner: HashmapUserStore,
+    barrier: Barrier,
+}
+
+#[async_trait::async_trait]
+impl UserStore for BarrierUserStore {
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError> {
+        self.barrier.wait().await;
+        self.inner.add_user(user).await
+    }
+
+    async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
+        self.inner.get_user(email).await
+    }
+
+    async fn validate_user(
+        &self,
+        email: Email,
+        password: &SecretString,
+    ) -> Result<(), UserStoreError> {
+        self.inner.validate_user(email, password).await
+    }
+
+    async fn update_password(
+        &self,
+        email: &Email,
+        password: HashedPassword,
+    ) -> Result<(), UserStoreError> {
+        self.inner.update_password(email, password).await
+    }
+
+    async fn mark_email_verified(&self, email: &Email) -> Result<(), UserStoreError> {
+        self.inner.mark_email_verified(email).await
+    }
+
+    async fn set_requires_2fa(
+        &self,
+        email: &Email,
+        requires_2fa: bool,
+    ) -> Result<(), UserStoreError> {
+        self.inner.set_requires_2fa(email, requires_2fa).await
+    }
+
+    async fn add_role(&self, email: &Email, role: &str) -> Result<(), UserStoreError> {
+        self.inner.add_role(email, role).await
+    }
+
+    async fn remove_role(&self, email: &Email, role: &str) -> Result<(), UserStoreError> {
+        self.inner.remove_role(email, role).await
+    }
+
+    async fn get_roles(&self, email: &Email) -> Result<UserRoles, UserStoreError> {
+        self.inner.get_roles(email).await
+    }
+
+    async fn list_users(&self, query: &UserQuery) -> Result<UserPage, UserStoreError> {
+        self.inner.list_users(query).await
+    }
+
+    async fn set_disabled(&self, email: &Email, disabled: bool) -> Result<(), UserStoreError> {
+        self.inner.set_disabled(email, disabled).await
+    }
+
+    async fn set_password_reset_required(
+        &self,
+        email: &Email,
+        required: bool,
+    ) -> Result<(), UserStoreError> {
+        self.inner
+            .set_password_reset_required(email, required)
+            .await
+    }
+
+    async fn delete_user(&self, email: &Email) -> Result<(), UserStoreError> {
+        self.inner.delete_user(email).await
+    }
+}
+
+#[tokio::test]
+async fn should_not_serialize_concurrent_signups() {
+    let mut app_state = AppState::in_memory(
+        UnverifiedLoginPolicy::Allow,
+        settings().login.throttle_policy(),
+    );
+    app_state.user_store = Arc::new(BarrierUserStore {
+        inner: HashmapUserStore::new(),
+        barrier: Barrier::new(CONCURRENT_SIGNUPS),
+    });
+    let app = Application::build(app_state, test::APP_ADDRESS)
+        .await
+        .expect("Failed to build app");
+    let address = format!("http://{}", app.address);
+    #[allow(clippy::let_underscore_future)]
+    let _ = tokio::spawn(app.run());
+
+    let client = reqwest::Client::new();
+    let signups = (0..CONCURRENT_SIGNUPS).map(|_| {
+        client
+            .post(format!("{address}/signup"))
+            .json(&serde_json::json!({
+                "email": get_random_email(),
+                "password": "123DSDFdasd@@456789",
+                "requires2FA": false
+            }))
+            .send()
+    });
+
+    let responses = tokio::time::timeout(Duration::from_secs(10), join_all(signups))
+        .await
+        .expect("Concurrent signups blocked each other");
+
+    for response in responses {
+        assert_eq!(response.expect("Failed to signup").status().as_u16(), 201);
+    }
+}
@@ -0,0 +1,161 @@
+use crate::helpers::get_random_email;
+use auth_service::app_state::UnverifiedLoginPolicy;
+use auth_service::prelude::{AppState, Application};
+use auth_service::utils::constants::test;
+use auth_service::utils::settings::{settings, TlsSettings};
+use reqwest::{redirect::Policy, tls::TlsInfo, Certificate};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+struct TestCert {
+    pem: String,
+    der: Vec<u8>,
+}
+
+// Self-signed for 127.0.0.1, written where `TlsSettings` points
+fn write_certificate(dir: &Path) -> TestCert {
+    let generated = rcgen::generate_simple_self_signed(vec!["127.0.0.1".to_owned()]).unwrap();
+    std::fs::write(dir.join("key.pem"), generated.key_pair.serialize_pem()).unwrap();
+    std::fs::write(dir.join("cert.pem"), generated.cert.pem()).unwrap();
+    TestCert {
+        pem: generated.cert.pem(),
+        der: generated.cert.der().to_vec(),
+    }
+}
+
+fn certs_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn tls_settings(dir: &Path) -> TlsSettings {
+    TlsSettings {
+        cert_path: Some(dir.join("cert.pem")),
+        key_path: Some(dir.join("key.pem")),
+        reload_interval_seconds: 1,
+        redirect_address: Some(test::APP_ADDRESS.to_owned()),
+    }
+}
+
+async fn spawn_app(tls: &TlsSettings) -> Application {
+    let app_state = AppState::in_memory(
+        UnverifiedLoginPolicy::Allow,
+        settings().login.throttle_policy(),
+    );
+    Application::build_with_tls(app_state, test::APP_ADDRESS, tls)
+        .await
+        .expect("Failed to build app")
+}
+
+fn client(trusted: &TestCert) -> reqwest::Client {
+    reqwest::Client::builder()
+        .add_root_certificate(Certificate::from_pem(trusted.pem.as_bytes()).unwrap())
+        .tls_info(true)
+        .redirect(Policy::none())
+        .build()
+        .unwrap()
+}
+
+fn peer_certificate(response: &reqwest::Response) -> Vec<u8> {
+    response
+        .extensions()
+        .get::<TlsInfo>()
+        .and_then(|info| info.peer_certificate())
+        .expect("No peer certificate")
+        .to_vec()
+}
+
+#[tokio::test]
+async fn should_serve_https_with_secure_cookies() {
+    let dir = certs_dir();
+    let cert = write_certificate(&dir);
+    let app = spawn_app(&tls_settings(&dir)).await;
+    let address = format!("https://{}", app.address);
+    #[allow(clippy::let_underscore_future)]
+    let _ = tokio::spawn(app.run());
+    let client = client(&cert);
+
+    let email = get_random_email();
+    let credentials = serde_json::json!({
+        "email": email,
+        "password": "123DSDFdasd@@456789",
+        "requires2FA": false
+    });
+    let response = client
+        .post(format!("{address}/signup"))
+        .json(&credentials)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 201);
+    assert_eq!(peer_certificate(&response), cert.der);
+
+    let response = client
+        .post(format!("{address}/login"))
+        .json(&credentials)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 200);
+    let cookies: Vec<_> = response.cookies().collect();
+    assert!(!cookies.is_empty());
+    assert!(cookies.iter().all(|cookie| cookie.secure()));
+}
+
+#[tokio::test]
+async fn should_serve_a_renewed_certificate_without_a_restart() {
+    let dir = certs_dir();
+    let first = write_certificate(&dir);
+    let app = spawn_app(&tls_settings(&dir)).await;
+    let address = format!("https://{}", app.address);
+    #[allow(clippy::let_underscore_future)]
+    let _ = tokio::spawn(app.run());
+
+    let response = client(&first)
+        .get(format!("{address}/health/live"))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(peer_certificate(&response), first.der);
+
+    let renewed = write_certificate(&dir);
+    tokio::time::sleep(Duration::from_millis(2500)).await;
+
+    // A new client, so that a new connection is made
+    let response = client(&renewed)
+        .get(format!("{address}/health/live"))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(peer_certificate(&response), renewed.der);
+}
+
+#[tokio::test]
+async fn should_redirect_http_to_https() {
+    let dir = certs_dir();
+    write_certificate(&dir);
+    let app = spawn_app(&tls_settings(&dir)).await;
+    let https_port = app.address.rsplit(':').next().unwrap().to_owned();
+    let redirect_address = app.redirect_address.clone().expect("No redirect listener");
+    #[allow(clippy::let_underscore_future)]
+    let _ = tokio::spawn(app.run());
+
+    let response = reqwest::Client::builder()
+        .redirect(Policy::none())
+        .build()
+        .unwrap()
+        .post(format!("http://{redirect_address}/login?next=%2Fapp"))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // 308 keeps the method and body
+    assert_eq!(response.status().as_u16(), 308);
+    let host = redirect_address.rsplit_once(':').unwrap().0;
+    assert_eq!(
+        response.headers()["location"],
+        format!("https://{host}:{https_port}/login?next=%2Fapp").as_str()
+    );
+}
@@ -0,0 +1,223 @@
+use crate::helpers::{get_random_email, get_totp_code, TestApp};
+use auth_macros::db_test;
+use auth_service::domain::types::Email;
+use auth_service::domain::ErrorCode;
+use auth_service::dto::{TotpEnrollmentResponse, TwoFactorAuthResponse};
+use auth_service::ErrorResponse;
+use secrecy::ExposeSecret;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+const PASSWORD: &str = "123DSDFdasd@@456789";
+
+// Sign up and log in, leaving the auth cookie in the app's cookie jar
+async fn signup_and_login(app: &TestApp, email: &str) {
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": PASSWORD,
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app
+        .post_login(&serde_json::json!({ "email": email, "password": PASSWORD }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+async fn enroll(app: &TestApp) -> TotpEnrollmentResponse {
+    let response = app.post_totp_enroll().await;
+    assert_eq!(response.status().as_u16(), 200);
+    response
+        .json::<TotpEnrollmentResponse>()
+        .await
+        .expect("Could not deserialize response body to TotpEnrollmentResponse")
+}
+
+async fn login_with_2fa(app: &TestApp, email: &str) -> String {
+    let response = app
+        .post_login(&serde_json::json!({ "email": email, "password": PASSWORD }))
+        .await;
+    assert_eq!(response.status().as_u16(), 206);
+    response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id
+}
+
+#[db_test]
+async fn should_return_400_if_not_logged_in() {
+    let response = app.post_totp_enroll().await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app
+        .post_totp_confirm(&serde_json::json!({ "code": "123456" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[db_test]
+async fn should_return_provisioning_uri_on_enroll() {
+    let random_email = get_random_email();
+    signup_and_login(app, &random_email).await;
+
+    let enrollment = enroll(app).await;
+
+    assert!(enrollment.otpauth_uri.starts_with("otpauth://totp/"));
+    assert!(enrollment
+        .otpauth_uri
+        .contains(&format!("secret={}", enrollment.secret)));
+    assert!(enrollment
+        .otpauth_uri
+        .contains(&random_email.replace('@', "%40")));
+}
+
+#[db_test]
+async fn should_return_400_if_confirmation_code_malformed() {
+    signup_and_login(app, &get_random_email()).await;
+    enroll(app).await;
+
+    for code in ["", "12345", "1234567", "abcdef"] {
+        let response = app
+            .post_totp_confirm(&serde_json::json!({ "code": code }))
+            .await;
+        assert_eq!(response.status().as_u16(), 400, "Failed for code: {code}");
+    }
+}
+
+#[db_test]
+async fn should_return_401_if_confirmation_code_incorrect() {
+    signup_and_login(app, &get_random_email()).await;
+    let enrollment = enroll(app).await;
+
+    // Well outside the drift window
+    let response = app
+        .post_totp_confirm(&serde_json::json!({ "code": get_totp_code(&enrollment.secret, 10) }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[db_test]
+async fn should_return_409_if_already_enabled() {
+    signup_and_login(app, &get_random_email()).await;
+    let enrollment = enroll(app).await;
+
+    let response = app
+        .post_totp_confirm(&serde_json::json!({ "code": get_totp_code(&enrollment.secret, 0) }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_totp_enroll().await;
+    assert_eq!(response.status().as_u16(), 409);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .code,
+        ErrorCode::TotpAlreadyEnabled
+    );
+}
+
+#[db_test]
+async fn should_log_in_with_totp_code_instead_of_email() {
+    let random_email = get_random_email();
+    signup_and_login(app, &random_email).await;
+    let enrollment = enroll(app).await;
+    let confirmation_code = get_totp_code(&enrollment.secret, 0);
+
+    let response = app
+        .post_totp_confirm(&serde_json::json!({ "code": confirmation_code }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Confirming turns 2FA on, and no code is emailed to TOTP users
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let login_attempt_id = login_with_2fa(app, &random_email).await;
+
+    // The code used for confirmation can't be replayed
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": confirmation_code,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Codes outside the drift window are rejected
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": get_totp_code(&enrollment.secret, 3),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // A code from the next step is within the default drift window
+    let code = get_totp_code(&enrollment.secret, 1);
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": code,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let login_attempt_id = login_with_2fa(app, &random_email).await;
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": code,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[db_test]
+async fn should_reject_stored_email_code_for_totp_users() {
+    let random_email = get_random_email();
+    signup_and_login(app, &random_email).await;
+    let enrollment = enroll(app).await;
+    let response = app
+        .post_totp_confirm(&serde_json::json!({ "code": get_totp_code(&enrollment.secret, 0) }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let login_attempt_id = login_with_2fa(app, &random_email).await;
+
+    // The login attempt still has an email code stored with it, but it is
+    // never sent and mustn't be a second way in
+    let email = Email::parse(random_email.clone().into()).unwrap();
+    let (_, stored_code) = app.two_fa_code_store.get_two_fa_code(&email).await.unwrap();
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": stored_code.as_ref().expose_secret(),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": get_totp_code(&enrollment.secret, 1),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
@@ -1,7 +1,6 @@
 use crate::helpers::{get_random_email, TestApp};
 use auth_macros::db_test;
 use auth_service::domain::types::{Email, LoginAttemptId, TwoFACode};
-use auth_service::domain::TwoFACodeStore;
 use secrecy::{ExposeSecret, SecretString};
 
 #[db_test]
@@ -85,8 +84,6 @@ async fn should_return_401_if_old_code() {
         TwoFACode::parse(SecretString::new("123456".to_owned().into_boxed_str())).unwrap();
 
     app.two_fa_code_store
-        .write()
-        .await
         .add_two_fa_code(random_email.clone(), login_attempt_id, two_fa_code)
         .await
         .unwrap();
@@ -110,6 +107,15 @@ async fn should_return_401_if_old_code() {
 #[db_test]
 async fn should_return_200_if_correct_code() {
     let random_email = Email::parse(get_random_email().into()).unwrap();
+    // The issued token carries the user's roles, so the user has to exist
+    let signup_response = app
+        .post_signup(&serde_json::json!({
+            "email": random_email.as_ref().expose_secret(),
+            "password": "123DSDFdasd@@456789",
+            "requires2FA": true
+        }))
+        .await;
+    assert_eq!(signup_response.status().as_u16(), 201);
     let login_attempt_id = LoginAttemptId::parse(SecretString::new(
         "c9a2865b-467d-498b-93b8-634903ae68e0"
             .to_owned()
@@ -120,8 +126,6 @@ async fn should_return_200_if_correct_code() {
         TwoFACode::parse(SecretString::new("123456".to_owned().into_boxed_str())).unwrap();
 
     app.two_fa_code_store
-        .write()
-        .await
         .add_two_fa_code(random_email.clone(), login_attempt_id, two_fa_code)
         .await
         .unwrap();
@@ -140,3 +144,46 @@ async fn should_return_200_if_correct_code() {
         test_case
     );
 }
+
+#[db_test]
+async fn should_not_issue_2fa_code_if_incorrect_password() {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "123DSDFdasd@@456789",
+        "requires2FA": true
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "123DSDFdasd@@45678",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let email = Email::parse(random_email.clone().into()).unwrap();
+    assert!(!app
+        .two_fa_code_store
+        .two_fa_code_exists(&email)
+        .await
+        .unwrap());
+
+    let test_case = serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": "c9a2865b-467d-498b-93b8-634903ae68e0",
+        "2FACode": "123456"
+    });
+
+    let response = app.post_verify_2fa(&test_case).await;
+    assert_eq!(
+        response.status().as_u16(),
+        401,
+        "Failed for input: {:?}",
+        test_case
+    );
+}
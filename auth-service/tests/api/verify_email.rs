@@ -0,0 +1,183 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_macros::db_test;
+use auth_service::app_state::UnverifiedLoginPolicy;
+use auth_service::domain::ErrorCode;
+use auth_service::dto::VerifyEmailResponse;
+use auth_service::ErrorResponse;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+fn signup_body(email: &str) -> serde_json::Value {
+    serde_json::json!({
+        "email": email,
+        "password": "123DSDFdasd@@456789",
+        "requires2FA": false
+    })
+}
+
+fn login_body(email: &str) -> serde_json::Value {
+    serde_json::json!({
+        "email": email,
+        "password": "123DSDFdasd@@456789",
+    })
+}
+
+async fn mock_email_server(app: &TestApp, expected_calls: u64) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(expected_calls)
+        .mount(&app.email_server)
+        .await;
+}
+
+#[db_test]
+async fn should_send_verification_email_on_signup() {
+    mock_email_server(app, 1).await;
+
+    let response = app.post_signup(&signup_body(&get_random_email())).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+}
+
+#[db_test]
+async fn should_return_201_if_verification_email_fails() {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_signup(&signup_body(&get_random_email())).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+}
+
+#[db_test]
+async fn should_return_422_if_malformed_input() {
+    let response = app.post_verify_email(&serde_json::json!({})).await;
+    assert_eq!(response.status().as_u16(), 422);
+
+    let response = app
+        .post_resend_verification_email(&serde_json::json!({}))
+        .await;
+    assert_eq!(response.status().as_u16(), 422);
+}
+
+#[db_test]
+async fn should_return_400_if_token_is_empty() {
+    let response = app
+        .post_verify_email(&serde_json::json!({ "token": " " }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[db_test]
+async fn should_return_401_if_invalid_token() {
+    let response = app
+        .post_verify_email(&serde_json::json!({ "token": "invalid" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .code,
+        ErrorCode::InvalidToken
+    );
+}
+
+#[db_test]
+async fn should_allow_unverified_login_if_policy_allows() {
+    let random_email = get_random_email();
+    app.post_signup(&signup_body(&random_email)).await;
+
+    let response = app.post_login(&login_body(&random_email)).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[db_test]
+async fn should_not_resend_email_if_already_verified_or_unknown() {
+    let random_email = get_random_email();
+    mock_email_server(app, 1).await;
+
+    app.post_signup(&signup_body(&random_email)).await;
+    let token = app.get_token_from_last_email().await;
+    let response = app
+        .post_verify_email(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    for email in [random_email, get_random_email()] {
+        let response = app
+            .post_resend_verification_email(&serde_json::json!({ "email": email }))
+            .await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+}
+
+#[tokio::test]
+async fn should_reject_unverified_login_until_email_verified() {
+    let app = TestApp::with_unverified_login_policy(UnverifiedLoginPolicy::Reject).await;
+    let random_email = get_random_email();
+    mock_email_server(&app, 2).await;
+
+    let response = app.post_signup(&signup_body(&random_email)).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app.post_login(&login_body(&random_email)).await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .code,
+        ErrorCode::EmailNotVerified
+    );
+
+    // A wrong password is reported as such, without revealing the account is unverified
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "Wrong123DSDFdasd@@456",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Resending replaces the lost email with a fresh token
+    let response = app
+        .post_resend_verification_email(&serde_json::json!({ "email": random_email }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let token = app.get_token_from_last_email().await;
+
+    let response = app
+        .post_verify_email(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response
+            .json::<VerifyEmailResponse>()
+            .await
+            .expect("Could not deserialize response body to VerifyEmailResponse")
+            .message,
+        "Email verified successfully".to_owned()
+    );
+
+    // The token is single-use
+    let response = app
+        .post_verify_email(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.post_login(&login_body(&random_email)).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
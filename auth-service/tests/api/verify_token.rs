@@ -1,6 +1,10 @@
 use crate::helpers::{get_random_email, TestApp};
 use auth_macros::db_test;
+use auth_service::domain::{Email, ErrorCode, UserStore, ADMIN_ROLE, MANAGE_USERS_PERMISSION};
+use auth_service::dto::VerifyTokenResponse;
+use auth_service::prelude::PostgresUserStore;
 use auth_service::{utils::constants::JWT_COOKIE_NAME, ErrorResponse};
+use secrecy::SecretString;
 
 #[db_test]
 async fn should_return_200_valid_token() {
@@ -35,6 +39,61 @@ async fn should_return_200_valid_token() {
     assert_eq!(verify_response.status().as_u16(), 200);
 }
 
+fn jwt_cookie(response: &reqwest::Response) -> String {
+    response
+        .cookies()
+        .find(|c| c.name() == JWT_COOKIE_NAME)
+        .map(|c| c.value().to_string())
+        .expect("jwt token doesn't exist")
+}
+
+async fn verify(app: &TestApp, token: &str) -> VerifyTokenResponse {
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    response
+        .json::<VerifyTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to VerifyTokenResponse")
+}
+
+#[db_test]
+async fn should_return_roles_granted_when_the_token_was_issued() {
+    let random_email = get_random_email();
+    let credentials = serde_json::json!({
+        "email": random_email,
+        "password": "123DSDFdasd@@456789",
+    });
+    app.post_signup(&serde_json::json!({
+        "email": random_email,
+        "password": "123DSDFdasd@@456789",
+        "requires2FA": false
+    }))
+    .await;
+
+    let token = jwt_cookie(&app.post_login(&credentials).await);
+    let claims = verify(app, &token).await;
+    assert_eq!(claims.email, random_email);
+    assert!(claims.roles.is_empty());
+    assert!(claims.permissions.is_empty());
+
+    let email = Email::parse(SecretString::from(random_email.clone())).unwrap();
+    PostgresUserStore::new(app.pg_pool.clone())
+        .add_role(&email, ADMIN_ROLE)
+        .await
+        .expect("Failed to grant role");
+
+    // Tokens already issued keep what they were issued with
+    assert!(verify(app, &token).await.roles.is_empty());
+
+    // Refreshing picks up the new role
+    let refreshed = jwt_cookie(&app.post_refresh().await);
+    let claims = verify(app, &refreshed).await;
+    assert_eq!(claims.roles, vec![ADMIN_ROLE.to_owned()]);
+    assert_eq!(claims.permissions, vec![MANAGE_USERS_PERMISSION.to_owned()]);
+}
+
 #[db_test]
 async fn should_return_401_if_invalid_token() {
     let login_body = serde_json::json!({
@@ -50,8 +109,8 @@ async fn should_return_401_if_invalid_token() {
             .json::<ErrorResponse>()
             .await
             .expect("Could not deserialize response body to ErrorResponse")
-            .error,
-        "Invalid token".to_owned()
+            .code,
+        ErrorCode::InvalidToken
     );
 }
 